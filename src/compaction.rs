@@ -0,0 +1,251 @@
+use crate::shared_types::roots;
+use crate::y_transaction::EncodingException;
+use lib0::any::Any;
+use pyo3::exceptions::PyValueError;
+use pyo3::PyResult;
+use yrs::block::Prelim;
+use yrs::types::text::Diff;
+use yrs::types::Value;
+use yrs::updates::decoder::Decode;
+use yrs::{
+    Array, ArrayPrelim, ArrayRef, Doc, Map, MapPrelim, MapRef, Options, ReadTxn, StateVector, Text,
+    TextPrelim, Transact, TransactionMut, Update, Xml, XmlElementPrelim, XmlFragment,
+    XmlFragmentPrelim, XmlFragmentRef, XmlNode, XmlTextPrelim, XmlTextRef,
+};
+
+/// Re-creates the current contents of all root types of a document (read through `src`) in a
+/// fresh document with a new client id, and returns its whole state encoded as a single lib0 v1
+/// update. The result holds no deleted content, nor any history of how the contents came to be.
+///
+/// Root types of a kind which hasn't been defined yet, subdocuments and shared types embedded in
+/// texts can't be re-created, so an error is returned if there are any.
+pub(crate) fn compact<T: ReadTxn>(src: &T, options: &Options) -> PyResult<Vec<u8>> {
+    let roots = roots(src)?;
+    if roots.iter().any(|(_, value)| value.is_none()) {
+        return Err(PyValueError::new_err(
+            "Root types received from remote peers have to be fetched with one of the `YDoc.get_*` methods before compacting the document.",
+        ));
+    }
+    let doc = Doc::with_options(Options {
+        offset_kind: options.offset_kind.clone(),
+        skip_gc: options.skip_gc,
+        ..Options::default()
+    });
+    // root types have to be defined before a transaction is started
+    let roots: Vec<(Value, Value)> = roots
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let value = value?;
+            let name = name.as_str();
+            let root = match &value {
+                Value::YArray(_) => Value::YArray(doc.get_or_insert_array(name)),
+                Value::YMap(_) => Value::YMap(doc.get_or_insert_map(name)),
+                Value::YXmlFragment(_) => Value::YXmlFragment(doc.get_or_insert_xml_fragment(name)),
+                Value::YXmlElement(_) => Value::YXmlElement(doc.get_or_insert_xml_element(name)),
+                Value::YXmlText(_) => Value::YXmlText(doc.get_or_insert_xml_text(name)),
+                _ => Value::YText(doc.get_or_insert_text(name)),
+            };
+            Some((value, root))
+        })
+        .collect();
+    let mut txn = doc.transact_mut();
+    for (from, to) in roots {
+        match (from, to) {
+            (Value::YText(from), Value::YText(to)) => copy_text(src, &from, &mut txn, &to)?,
+            (Value::YArray(from), Value::YArray(to)) => copy_array(src, &from, &mut txn, &to)?,
+            (Value::YMap(from), Value::YMap(to)) => copy_map(src, &from, &mut txn, &to)?,
+            (Value::YXmlFragment(from), Value::YXmlFragment(to)) => {
+                copy_xml_children(src, &from, &mut txn, &to)?
+            }
+            (Value::YXmlElement(from), Value::YXmlElement(to)) => {
+                copy_xml_attributes(src, &from, &mut txn, &to);
+                copy_xml_children(src, &from, &mut txn, &to)?;
+            }
+            (Value::YXmlText(from), Value::YXmlText(to)) => {
+                copy_xml_attributes(src, &from, &mut txn, &to);
+                copy_text(src, &from, &mut txn, &to)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(txn.encode_state_as_update_v1(&StateVector::default()))
+}
+
+/// Encodes the whole state of a document (read through `src`) as a single lib0 v1 update, with
+/// the content of all deleted items garbage collected. Unlike `compact`, the ids of items and
+/// deletions are preserved, so the result can still be merged with updates of other peers.
+pub(crate) fn collect_garbage<T: ReadTxn>(src: &T, options: &Options) -> PyResult<Vec<u8>> {
+    let update = Update::decode_v1(&src.encode_state_as_update_v1(&StateVector::default()))
+        .map_err(|e| EncodingException::new_err(e.to_string()))?;
+    let doc = Doc::with_options(Options {
+        offset_kind: options.offset_kind.clone(),
+        skip_gc: false,
+        ..Options::default()
+    });
+    // content deleted by an update is garbage collected once the transaction applying it commits
+    doc.transact_mut().apply_update(update);
+    let txn = doc.transact();
+    Ok(txn.encode_state_as_update_v1(&StateVector::default()))
+}
+
+/// A place within a shared collection where a copied value is inserted.
+trait Slot {
+    fn put<V: Prelim>(&self, txn: &mut TransactionMut, value: V) -> V::Return;
+}
+
+struct MapSlot<'a>(&'a MapRef, &'a str);
+
+impl<'a> Slot for MapSlot<'a> {
+    fn put<V: Prelim>(&self, txn: &mut TransactionMut, value: V) -> V::Return {
+        self.0.insert(txn, self.1, value)
+    }
+}
+
+struct ArraySlot<'a>(&'a ArrayRef);
+
+impl<'a> Slot for ArraySlot<'a> {
+    fn put<V: Prelim>(&self, txn: &mut TransactionMut, value: V) -> V::Return {
+        self.0.push_back(txn, value)
+    }
+}
+
+fn copy_value<T: ReadTxn, S: Slot>(
+    src: &T,
+    value: Value,
+    txn: &mut TransactionMut,
+    slot: S,
+) -> PyResult<()> {
+    match value {
+        Value::Any(any) => {
+            slot.put(txn, any);
+        }
+        Value::YText(from) => {
+            let to = slot.put(txn, TextPrelim::new(""));
+            copy_text(src, &from, txn, &to)?;
+        }
+        Value::YArray(from) => {
+            let to = slot.put(txn, ArrayPrelim::default());
+            copy_array(src, &from, txn, &to)?;
+        }
+        Value::YMap(from) => {
+            let to = slot.put(txn, MapPrelim::<Any>::new());
+            copy_map(src, &from, txn, &to)?;
+        }
+        Value::YXmlElement(from) => {
+            let to = slot.put(txn, XmlElementPrelim::empty(from.tag().clone()));
+            copy_xml_attributes(src, &from, txn, &to);
+            copy_xml_children(src, &from, txn, &to)?;
+        }
+        Value::YXmlText(from) => {
+            let to = slot.put(txn, XmlTextPrelim::new(""));
+            copy_xml_attributes(src, &from, txn, &to);
+            copy_text(src, &from, txn, &to)?;
+        }
+        Value::YXmlFragment(from) => {
+            let to: XmlFragmentRef = slot.put(txn, empty_fragment());
+            copy_xml_children(src, &from, txn, &to)?;
+        }
+        Value::YDoc(_) => {
+            // subdocuments keep their own contents, which can't be carried over to a fresh copy
+            return Err(PyValueError::new_err(
+                "Documents containing subdocuments can't be compacted.",
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn copy_map<T: ReadTxn>(
+    src: &T,
+    from: &MapRef,
+    txn: &mut TransactionMut,
+    to: &MapRef,
+) -> PyResult<()> {
+    for (key, value) in from.iter(src) {
+        copy_value(src, value, txn, MapSlot(to, key))?;
+    }
+    Ok(())
+}
+
+fn copy_array<T: ReadTxn>(
+    src: &T,
+    from: &ArrayRef,
+    txn: &mut TransactionMut,
+    to: &ArrayRef,
+) -> PyResult<()> {
+    for value in from.iter(src) {
+        copy_value(src, value, txn, ArraySlot(to))?;
+    }
+    Ok(())
+}
+
+/// Copies text chunks along with their formatting attributes and embedded values. Shared types
+/// embedded in a text can't be inserted through Ypy, so an error is returned if there are any.
+fn copy_text<T: ReadTxn, X: Text>(
+    src: &T,
+    from: &X,
+    txn: &mut TransactionMut,
+    to: &X,
+) -> PyResult<()> {
+    for Diff {
+        insert, attributes, ..
+    } in from.diff(src, |_| ())
+    {
+        let index = to.len(txn);
+        match (insert, attributes) {
+            (Value::Any(Any::String(chunk)), Some(attrs)) => {
+                to.insert_with_attributes(txn, index, &chunk, *attrs)
+            }
+            (Value::Any(Any::String(chunk)), None) => to.insert(txn, index, &chunk),
+            (Value::Any(embed), Some(attrs)) => {
+                to.insert_embed_with_attributes(txn, index, embed, *attrs);
+            }
+            (Value::Any(embed), None) => {
+                to.insert_embed(txn, index, embed);
+            }
+            _ => {
+                return Err(PyValueError::new_err(
+                    "Texts with embedded shared types can't be compacted.",
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+fn copy_xml_attributes<T: ReadTxn, X: Xml>(src: &T, from: &X, txn: &mut TransactionMut, to: &X) {
+    for (name, value) in from.attributes(src) {
+        to.insert_attribute(txn, name, value);
+    }
+}
+
+fn copy_xml_children<T: ReadTxn, F: XmlFragment, G: XmlFragment>(
+    src: &T,
+    from: &F,
+    txn: &mut TransactionMut,
+    to: &G,
+) -> PyResult<()> {
+    for child in from.children(src) {
+        match child {
+            XmlNode::Element(from) => {
+                let element = to.push_back(txn, XmlElementPrelim::empty(from.tag().clone()));
+                copy_xml_attributes(src, &from, txn, &element);
+                copy_xml_children(src, &from, txn, &element)?;
+            }
+            XmlNode::Text(from) => {
+                let text: XmlTextRef = to.push_back(txn, XmlTextPrelim::new(""));
+                copy_xml_attributes(src, &from, txn, &text);
+                copy_text(src, &from, txn, &text)?;
+            }
+            XmlNode::Fragment(from) => {
+                let fragment = to.push_back(txn, empty_fragment());
+                copy_xml_children(src, &from, txn, &fragment)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn empty_fragment() -> XmlFragmentPrelim<Vec<XmlTextPrelim<String>>, XmlTextPrelim<String>> {
+    XmlFragmentPrelim::new(Vec::new())
+}
@@ -1,46 +1,95 @@
-use std::{collections::HashMap, convert::TryFrom};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    ops::{Deref, DerefMut},
+};
 
 use lib0::any::Any;
-use pyo3::{exceptions::PyTypeError, PyErr, PyObject, PyResult, Python};
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    PyErr, PyObject, PyResult, Python,
+};
 
 use crate::shared_types::{CompatiblePyType, YPyType};
+use crate::y_xml::xml_into_json;
+use yrs::XmlNode;
 
 #[derive(Clone, Debug)]
-pub(crate) struct JsonBuilder(String);
+pub(crate) struct JsonBuilder {
+    buffer: String,
+    /// Whether XML nodes should be serialized as `{tag, attributes, children}` objects instead of
+    /// being rejected.
+    pub include_xml: bool,
+    /// Whether keys of maps should be emitted in sorted order, which makes the output deterministic.
+    pub sort_keys: bool,
+}
 
 impl JsonBuilder {
     pub fn new() -> Self {
-        JsonBuilder(String::new())
+        JsonBuilder {
+            buffer: String::new(),
+            include_xml: false,
+            sort_keys: false,
+        }
+    }
+
+    pub fn with_options(include_xml: bool, sort_keys: bool) -> Self {
+        JsonBuilder {
+            include_xml,
+            sort_keys,
+            ..Self::new()
+        }
     }
 
     pub fn append_json<T: JsonBuildable>(&mut self, buildable: &T) -> Result<(), T::JsonError> {
-        let buffer = &mut self.0;
-        buildable.build_json(buffer)
+        buildable.build_json(self)
+    }
+}
+
+impl Deref for JsonBuilder {
+    type Target = String;
+
+    fn deref(&self) -> &String {
+        &self.buffer
+    }
+}
+
+impl DerefMut for JsonBuilder {
+    fn deref_mut(&mut self) -> &mut String {
+        &mut self.buffer
     }
 }
 
 impl From<JsonBuilder> for String {
     fn from(json_builder: JsonBuilder) -> Self {
-        json_builder.0
+        json_builder.buffer
     }
 }
 
 pub(crate) trait JsonBuildable {
     type JsonError;
-    fn build_json(&self, buffer: &mut String) -> Result<(), Self::JsonError>;
+    fn build_json(&self, buffer: &mut JsonBuilder) -> Result<(), Self::JsonError>;
 }
 
 impl<'a> JsonBuildable for CompatiblePyType<'a> {
     type JsonError = PyErr;
 
-    fn build_json(&self, buffer: &mut String) -> Result<(), Self::JsonError> {
+    fn build_json(&self, buffer: &mut JsonBuilder) -> Result<(), Self::JsonError> {
         match self {
             CompatiblePyType::Bool(b) => {
                 let t: bool = b.extract().unwrap();
                 buffer.push_str(if t { "true" } else { "false" });
             }
             CompatiblePyType::Int(i) => buffer.push_str(&i.to_string()),
-            CompatiblePyType::Float(f) => buffer.push_str(&f.to_string()),
+            CompatiblePyType::Float(f) => {
+                let num: f64 = f.extract()?;
+                if !num.is_finite() {
+                    return Err(PyValueError::new_err(format!(
+                        "Non-finite floats cannot be converted to a JSON format: {num}"
+                    )));
+                }
+                buffer.push_str(&f.to_string())
+            }
             CompatiblePyType::String(s) => {
                 let string: String = s.extract().unwrap();
                 buffer.reserve(string.len() + 2);
@@ -84,11 +133,37 @@ impl<'a> JsonBuildable for CompatiblePyType<'a> {
 impl<'a> JsonBuildable for YPyType<'a> {
     type JsonError = PyErr;
 
-    fn build_json(&self, buffer: &mut String) -> Result<(), Self::JsonError> {
+    fn build_json(&self, buffer: &mut JsonBuilder) -> Result<(), Self::JsonError> {
+        let include_xml = buffer.include_xml;
+        let sort_keys = buffer.sort_keys;
         let json = match self {
             YPyType::Text(text) => Ok(text.borrow().to_json()),
-            YPyType::Array(array) => array.borrow().to_json(),
-            YPyType::Map(map) => map.borrow().to_json(),
+            YPyType::Array(array) => array.borrow().to_json(Some(include_xml), Some(sort_keys)),
+            YPyType::Map(map) => map.borrow().to_json(Some(include_xml), Some(sort_keys)),
+            YPyType::XmlElement(xml) if include_xml => {
+                let xml = &xml.borrow().0;
+                let node = XmlNode::Element(xml.inner.clone());
+                Ok(any_into_json(
+                    xml.with_transaction(|txn| xml_into_json(txn, &node)),
+                    sort_keys,
+                ))
+            }
+            YPyType::XmlText(xml) if include_xml => {
+                let xml = &xml.borrow().0;
+                let node = XmlNode::Text(xml.inner.clone());
+                Ok(any_into_json(
+                    xml.with_transaction(|txn| xml_into_json(txn, &node)),
+                    sort_keys,
+                ))
+            }
+            YPyType::XmlFragment(xml) if include_xml => {
+                let xml = &xml.borrow().0;
+                let node = XmlNode::Fragment(xml.inner.clone());
+                Ok(any_into_json(
+                    xml.with_transaction(|txn| xml_into_json(txn, &node)),
+                    sort_keys,
+                ))
+            }
             xml => Err(PyTypeError::new_err(format!(
                 "XML elements cannot be converted to a JSON format: {xml}"
             ))),
@@ -98,10 +173,49 @@ impl<'a> JsonBuildable for YPyType<'a> {
     }
 }
 
+fn any_into_json(any: Any, sort_keys: bool) -> String {
+    let mut json = String::new();
+    write_any(&any, sort_keys, &mut json);
+    json
+}
+
+/// Writes a JSON representation of `any` into `buf`, with keys of nested maps written in sorted
+/// order if `sort_keys` is set.
+fn write_any(any: &Any, sort_keys: bool, buf: &mut String) {
+    match any {
+        Any::Map(map) if sort_keys => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            buf.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i != 0 {
+                    buf.push(',');
+                }
+                Any::String(key.as_str().into()).to_json(buf);
+                buf.push(':');
+                write_any(value, sort_keys, buf);
+            }
+            buf.push('}');
+        }
+        Any::Array(array) if sort_keys => {
+            buf.push('[');
+            for (i, value) in array.iter().enumerate() {
+                if i != 0 {
+                    buf.push(',');
+                }
+                write_any(value, sort_keys, buf);
+            }
+            buf.push(']');
+        }
+        other => other.to_json(buf),
+    }
+}
+
 impl JsonBuildable for Any {
     type JsonError = PyErr;
-    fn build_json(&self, buffer: &mut String) -> Result<(), Self::JsonError> {
-        self.to_json(buffer);
+    fn build_json(&self, buffer: &mut JsonBuilder) -> Result<(), Self::JsonError> {
+        let sort_keys = buffer.sort_keys;
+        write_any(self, sort_keys, buffer);
         Ok(())
     }
 }
@@ -109,16 +223,21 @@ impl JsonBuildable for Any {
 impl JsonBuildable for HashMap<String, PyObject> {
     type JsonError = PyErr;
 
-    fn build_json(&self, buffer: &mut String) -> Result<(), Self::JsonError> {
+    fn build_json(&self, buffer: &mut JsonBuilder) -> Result<(), Self::JsonError> {
         buffer.push_str("{");
+        let mut entries: Vec<_> = self.iter().collect();
+        if buffer.sort_keys {
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        }
         let res: PyResult<()> = Python::with_gil(|py| {
-            for (i, (k, py_obj)) in self.iter().enumerate() {
+            for (i, (k, py_obj)) in entries.into_iter().enumerate() {
                 let value: CompatiblePyType = py_obj.extract(py)?;
                 if i != 0 {
                     buffer.push_str(",");
                 }
+                buffer.push_str("\"");
                 buffer.push_str(k);
-                buffer.push_str(":");
+                buffer.push_str("\":");
                 value.build_json(buffer)?;
             }
             Ok(())
@@ -133,7 +252,7 @@ impl JsonBuildable for HashMap<String, PyObject> {
 impl JsonBuildable for Vec<PyObject> {
     type JsonError = PyErr;
 
-    fn build_json(&self, buffer: &mut String) -> Result<(), Self::JsonError> {
+    fn build_json(&self, buffer: &mut JsonBuilder) -> Result<(), Self::JsonError> {
         buffer.push_str("[");
         let res: PyResult<()> = Python::with_gil(|py| {
             self.iter().enumerate().try_for_each(|(i, object)| {
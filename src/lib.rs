@@ -1,11 +1,16 @@
 use pyo3::prelude::*;
 use pyo3::wrap_pyfunction;
+mod compaction;
 mod json_builder;
+mod partial_update;
 mod shared_types;
 mod type_conversions;
+mod update_buffer;
+mod update_info;
 mod y_array;
 mod y_doc;
 mod y_map;
+mod y_sticky_index;
 mod y_text;
 mod y_transaction;
 mod y_xml;
@@ -13,18 +18,21 @@ use crate::y_doc::*;
 
 /// Python bindings for Y.rs
 #[pymodule]
-pub fn y_py(_py: Python, m: &PyModule) -> PyResult<()> {
+pub fn y_py(py: Python, m: &PyModule) -> PyResult<()> {
     m.add("__version__", env!("CARGO_PKG_VERSION"))?;
 
     // Data Types
     m.add_class::<y_doc::YDoc>()?;
     m.add_class::<y_transaction::YTransaction>()?;
+    m.add_class::<y_doc::YBatch>()?;
     m.add_class::<y_text::YText>()?;
     m.add_class::<y_array::YArray>()?;
     m.add_class::<y_map::YMap>()?;
     m.add_class::<y_xml::YXmlText>()?;
     m.add_class::<y_xml::YXmlElement>()?;
     m.add_class::<y_xml::YXmlFragment>()?;
+    m.add_class::<y_sticky_index::YStickyIndex>()?;
+    m.add_class::<update_buffer::UpdateBuffer>()?;
     // Events
     m.add_class::<y_text::YTextEvent>()?;
     m.add_class::<y_array::YArrayEvent>()?;
@@ -35,6 +43,20 @@ pub fn y_py(_py: Python, m: &PyModule) -> PyResult<()> {
     // Functions
     m.add_wrapped(wrap_pyfunction!(encode_state_vector))?;
     m.add_wrapped(wrap_pyfunction!(encode_state_as_update))?;
+    m.add_wrapped(wrap_pyfunction!(encode_state_as_update_b64))?;
     m.add_wrapped(wrap_pyfunction!(apply_update))?;
+    m.add_wrapped(wrap_pyfunction!(apply_update_b64))?;
+    m.add_wrapped(wrap_pyfunction!(diff_docs))?;
+    m.add_wrapped(wrap_pyfunction!(docs_equal))?;
+    m.add_wrapped(wrap_pyfunction!(update_info::update_info))?;
+    m.add_wrapped(wrap_pyfunction!(update_info::validate_update))?;
+    m.add_wrapped(wrap_pyfunction!(type_conversions::register_encoder))?;
+    m.add_wrapped(wrap_pyfunction!(type_conversions::register_decoder))?;
+
+    // Exceptions
+    m.add(
+        "TransactionError",
+        py.get_type::<y_transaction::TransactionError>(),
+    )?;
     Ok(())
 }
@@ -0,0 +1,313 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use lib0::decoding::{Cursor, Read};
+use lib0::encoding::Write;
+use lib0::error::Error;
+use yrs::block::{
+    ClientID, ItemContent, BLOCK_GC_REF_NUMBER, BLOCK_SKIP_REF_NUMBER, HAS_ORIGIN, HAS_PARENT_SUB,
+    HAS_RIGHT_ORIGIN,
+};
+use yrs::updates::decoder::{Decode, Decoder, DecoderV1};
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
+use yrs::{DeleteSet, OffsetKind, ID};
+
+/// Restricts a `diff` update to the structs belonging to root types with given `names`, with
+/// both updates encoded using lib0 v1 encoding. The `full` state of the document the diff was
+/// taken from is needed to tell which root each struct belongs to, since structs placed next to
+/// their neighbours don't carry their parent.
+///
+/// Structs of other roots are replaced with garbage collected placeholders of the same length,
+/// so that clock ranges of each client stay contiguous and the result can be integrated without
+/// waiting for them. The delete set is passed on unchanged.
+pub(crate) fn filter_roots(
+    full: &[u8],
+    diff: &[u8],
+    names: &HashSet<&str>,
+) -> Result<Vec<u8>, Error> {
+    let mut index = RootIndex::new(&read_update(full)?.0);
+    let (clients, delete_set) = read_update(diff)?;
+    let mut encoder = EncoderV1::new();
+    encoder.write_var(clients.len());
+    for (client, clock, blocks) in clients {
+        encoder.write_var(blocks.len());
+        encoder.write_client(client);
+        encoder.write_var(clock);
+        let mut clock = clock;
+        for block in blocks {
+            let len = block.len();
+            let root = index.root_of(&ID::new(client, clock));
+            match &block {
+                Block::Item { .. }
+                    if root.as_deref().map_or(false, |name| names.contains(name)) =>
+                {
+                    block.encode(&mut encoder)
+                }
+                Block::Skip(_) => block.encode(&mut encoder),
+                _ => Block::Gc(len).encode(&mut encoder),
+            }
+            clock += len;
+        }
+    }
+    delete_set.encode(&mut encoder);
+    Ok(encoder.to_vec())
+}
+
+/// Returns the names of root types that structs of an `update` (encoded using lib0 v1 encoding)
+/// are placed in. Every root type holding any content, even deleted one, has at least one struct
+/// placed at its start, which carries the name of its root type explicitly.
+pub(crate) fn root_names(update: &[u8]) -> Result<HashSet<Rc<str>>, Error> {
+    let (clients, _) = read_update(update)?;
+    Ok(clients
+        .into_iter()
+        .flat_map(|(_, _, blocks)| blocks)
+        .filter_map(|block| match block {
+            Block::Item {
+                parent: Some(Parent::Root(name)),
+                ..
+            } => Some(name),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Structs of a single client: its id, the clock of the first struct and the structs themselves.
+pub(crate) type ClientStructs = (ClientID, u32, Vec<Block>);
+
+/// Reads all structs of an update, in the order they were encoded, followed by its delete set.
+/// Counts read from the update aren't trusted to preallocate memory, as it may be malformed.
+pub(crate) fn read_update(update: &[u8]) -> Result<(Vec<ClientStructs>, DeleteSet), Error> {
+    let mut decoder = DecoderV1::new(Cursor::new(update));
+    let client_count: u32 = decoder.read_var()?;
+    let mut clients = Vec::new();
+    for _ in 0..client_count {
+        let len: u32 = decoder.read_var()?;
+        let client = decoder.read_client()?;
+        let clock: u32 = decoder.read_var()?;
+        let blocks = (0..len)
+            .map(|_| Block::decode(&mut decoder))
+            .collect::<Result<_, _>>()?;
+        clients.push((client, clock, blocks));
+    }
+    let delete_set = DeleteSet::decode(&mut decoder)?;
+    Ok((clients, delete_set))
+}
+
+/// A single struct of an update, following the layout used by yrs when encoding updates.
+enum Block {
+    Gc(u32),
+    Skip(u32),
+    Item {
+        info: u8,
+        origin: Option<ID>,
+        right_origin: Option<ID>,
+        // only encoded when it can't be copied from the origins
+        parent: Option<Parent>,
+        parent_sub: Option<String>,
+        content: ItemContent,
+    },
+}
+
+/// An explicitly encoded parent of a struct: either a root type or a type nested in an item.
+enum Parent {
+    Root(Rc<str>),
+    Item(ID),
+}
+
+impl Block {
+    fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, Error> {
+        match decoder.read_info()? {
+            BLOCK_SKIP_REF_NUMBER => Ok(Block::Skip(decoder.read_var()?)),
+            BLOCK_GC_REF_NUMBER => Ok(Block::Gc(decoder.read_len()?)),
+            info => {
+                let origin = (info & HAS_ORIGIN != 0)
+                    .then(|| decoder.read_left_id())
+                    .transpose()?;
+                let right_origin = (info & HAS_RIGHT_ORIGIN != 0)
+                    .then(|| decoder.read_right_id())
+                    .transpose()?;
+                let (parent, parent_sub) = if info & (HAS_ORIGIN | HAS_RIGHT_ORIGIN) == 0 {
+                    let parent = if decoder.read_parent_info()? {
+                        Parent::Root(decoder.read_string()?.into())
+                    } else {
+                        Parent::Item(decoder.read_left_id()?)
+                    };
+                    let parent_sub = (info & HAS_PARENT_SUB != 0)
+                        .then(|| decoder.read_string().map(String::from))
+                        .transpose()?;
+                    (Some(parent), parent_sub)
+                } else {
+                    (None, None)
+                };
+                let content = ItemContent::decode(decoder, info)?;
+                Ok(Block::Item {
+                    info,
+                    origin,
+                    right_origin,
+                    parent,
+                    parent_sub,
+                    content,
+                })
+            }
+        }
+    }
+
+    fn encode<E: Encoder>(&self, encoder: &mut E) {
+        match self {
+            Block::Skip(len) => {
+                encoder.write_info(BLOCK_SKIP_REF_NUMBER);
+                encoder.write_var(*len);
+            }
+            Block::Gc(len) => {
+                encoder.write_info(BLOCK_GC_REF_NUMBER);
+                encoder.write_len(*len);
+            }
+            Block::Item {
+                info,
+                origin,
+                right_origin,
+                parent,
+                parent_sub,
+                content,
+            } => {
+                encoder.write_info(*info);
+                if let Some(origin) = origin {
+                    encoder.write_left_id(origin);
+                }
+                if let Some(right_origin) = right_origin {
+                    encoder.write_right_id(right_origin);
+                }
+                match parent {
+                    Some(Parent::Root(name)) => {
+                        encoder.write_parent_info(true);
+                        encoder.write_string(name);
+                    }
+                    Some(Parent::Item(id)) => {
+                        encoder.write_parent_info(false);
+                        encoder.write_left_id(id);
+                    }
+                    None => {}
+                }
+                if let Some(parent_sub) = parent_sub {
+                    encoder.write_string(parent_sub);
+                }
+                content.encode(encoder);
+            }
+        }
+    }
+
+    fn len(&self) -> u32 {
+        match self {
+            Block::Gc(len) | Block::Skip(len) => *len,
+            Block::Item { content, .. } => content.len(OffsetKind::Utf16),
+        }
+    }
+}
+
+/// Tells which root type each struct of a document belongs to.
+struct RootIndex {
+    clients: HashMap<ClientID, Vec<Range>>,
+}
+
+/// A clock range taken by a single struct, together with what its root can be found from.
+struct Range {
+    clock: u32,
+    len: u32,
+    /// Ids of structs belonging to the same root: the origin, the right origin and the parent
+    /// item, in order of preference. Neighbours may have been garbage collected since, in which
+    /// case the next one is followed.
+    links: Vec<ID>,
+    // resolved lazily and cached, as the same chains of neighbours are walked repeatedly
+    root: Option<Option<Rc<str>>>,
+}
+
+/// A struct whose root is being looked for, with the number of its links followed so far.
+type Visit = ((ClientID, usize), usize);
+
+impl RootIndex {
+    fn new(clients: &[ClientStructs]) -> Self {
+        let clients = clients
+            .iter()
+            .map(|(client, clock, blocks)| {
+                let mut clock = *clock;
+                let ranges = blocks
+                    .iter()
+                    .map(|block| {
+                        let (links, root) = match block {
+                            Block::Item {
+                                parent: Some(Parent::Root(name)),
+                                ..
+                            } => (Vec::new(), Some(Some(name.clone()))),
+                            Block::Item {
+                                origin,
+                                right_origin,
+                                parent,
+                                ..
+                            } => {
+                                let parent = match parent {
+                                    Some(Parent::Item(id)) => Some(*id),
+                                    _ => None,
+                                };
+                                let links = [*origin, *right_origin, parent];
+                                (links.into_iter().flatten().collect(), None)
+                            }
+                            // garbage collected structs don't tell where they were placed
+                            _ => (Vec::new(), None),
+                        };
+                        let range = Range {
+                            clock,
+                            len: block.len(),
+                            links,
+                            root,
+                        };
+                        clock += range.len;
+                        range
+                    })
+                    .collect();
+                (*client, ranges)
+            })
+            .collect();
+        RootIndex { clients }
+    }
+
+    fn find(&self, id: &ID) -> Option<(ClientID, usize)> {
+        let ranges = self.clients.get(&id.client)?;
+        let i = ranges.partition_point(|r| r.clock + r.len <= id.clock);
+        let range = ranges.get(i)?;
+        (range.clock <= id.clock).then_some((id.client, i))
+    }
+
+    /// Returns the name of a root type containing the struct with given `id`, if it's known.
+    fn root_of(&mut self, id: &ID) -> Option<Rc<str>> {
+        let mut stack = Vec::new();
+        let mut root = self.visit(id, &mut stack);
+        while let Some(&((client, i), followed)) = stack.last() {
+            let link = self.clients[&client][i].links.get(followed).copied();
+            match link {
+                Some(id) if root.is_none() => {
+                    stack.last_mut().unwrap().1 += 1;
+                    root = self.visit(&id, &mut stack);
+                }
+                _ => {
+                    stack.pop();
+                    self.clients.get_mut(&client).unwrap()[i].root = Some(root.clone());
+                }
+            }
+        }
+        root
+    }
+
+    /// Returns the root of the struct with given `id` if it's already known. Otherwise the struct
+    /// is pushed onto the `stack`, for its links to be followed.
+    fn visit(&mut self, id: &ID, stack: &mut Vec<Visit>) -> Option<Rc<str>> {
+        let (client, i) = self.find(id)?;
+        let range = &mut self.clients.get_mut(&client).unwrap()[i];
+        if let Some(root) = &range.root {
+            return root.clone();
+        }
+        // marks the range as visited, so that malformed, cyclic links can't loop forever
+        range.root = Some(None);
+        stack.push(((client, i), 0));
+        None
+    }
+}
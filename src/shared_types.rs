@@ -1,26 +1,46 @@
 use crate::{
+    partial_update::root_names,
+    type_conversions::{ToPython, WithDocToPython},
     y_array::YArray,
     y_doc::YDocInner,
     y_map::YMap,
     y_text::YText,
-    y_transaction::YTransactionInner,
+    y_transaction::{EncodingException, YTransactionInner},
     y_xml::{YXmlElement, YXmlFragment, YXmlText},
 };
 use pyo3::create_exception;
+use pyo3::pyclass::CompareOp;
 use pyo3::types as pytypes;
-use pyo3::{exceptions::PyException, prelude::*};
+use pyo3::{
+    exceptions::{PyException, PyIndexError, PyTypeError},
+    prelude::*,
+};
 use std::{
     cell::RefCell,
     fmt::Display,
     ops::{Deref, DerefMut},
     rc::Rc,
 };
-use yrs::types::TypeRef;
-use yrs::SubscriptionId;
+use yrs::types::{
+    Branch, BranchPtr, DeepObservable, Path, PathSegment, TypePtr, TypeRef, Value, TYPE_REFS_ARRAY,
+    TYPE_REFS_MAP, TYPE_REFS_TEXT, TYPE_REFS_XML_ELEMENT, TYPE_REFS_XML_FRAGMENT,
+    TYPE_REFS_XML_TEXT,
+};
+use yrs::TransactionMut;
+use yrs::{
+    Array, Assoc, IndexScope, Observable, ReadTxn, StateVector, StickyIndex, SubscriptionId,
+    XmlFragment, XmlNode,
+};
 
 // Common errors
 create_exception!(y_py, PreliminaryObservationException, PyException, "Occurs when an observer is attached to a Y type that is not integrated into a YDoc. Y types can only be observed once they have been added to a YDoc.");
 create_exception!(y_py, IntegratedOperationException, PyException, "Occurs when a method requires a type to be integrated (embedded into a YDoc), but is called on a preliminary type.");
+create_exception!(
+    y_py,
+    DeletedTypeException,
+    PyException,
+    "Occurs when a method modifies a Y type which has been removed from its YDoc."
+);
 
 /// Creates a default error with a common message string for throwing a `PyErr`.
 pub(crate) trait DefaultPyErr {
@@ -44,12 +64,214 @@ impl DefaultPyErr for IntegratedOperationException {
     }
 }
 
-#[pyclass]
-#[derive(Clone, Copy)]
-pub struct ShallowSubscription(pub SubscriptionId);
-#[pyclass]
-#[derive(Clone, Copy)]
-pub struct DeepSubscription(pub SubscriptionId);
+impl DefaultPyErr for DeletedTypeException {
+    fn default_message() -> PyErr {
+        DeletedTypeException::new_err(
+            "This type has been removed from its YDoc and can no longer be modified.",
+        )
+    }
+}
+
+/// Creates an error raised when copying an integrated shared type called `type_name`.
+pub(crate) fn integrated_copy_error(type_name: &str) -> PyErr {
+    PyTypeError::new_err(format!(
+        "Cannot copy an integrated {type_name}, as it's bound to its YDoc. Only preliminary instances can be copied."
+    ))
+}
+
+/// Converts a (possibly negative) insertion `index` into a position within a sequence of a given
+/// `len`, described as `target` in the error raised for an index out of range. Like with
+/// `list.insert`, negative indexes are counted from the end, so `-1` inserts before the last
+/// element and `len` appends.
+pub(crate) fn insert_position(index: isize, len: usize, target: &str) -> PyResult<u32> {
+    let position = if index < 0 {
+        len as isize + index
+    } else {
+        index
+    };
+    if (0..=len as isize).contains(&position) {
+        Ok(position as u32)
+    } else {
+        Err(PyIndexError::new_err(format!(
+            "Cannot insert at index {index} into {target} of length {len} (valid range: -{len}..={len})."
+        )))
+    }
+}
+
+/// Deep-copies a Python `value` using `copy.deepcopy` with a given `memo` dictionary.
+pub(crate) fn deepcopy(value: &PyObject, memo: &PyAny) -> PyResult<PyObject> {
+    let py = memo.py();
+    let deepcopy = py.import("copy")?.getattr("deepcopy")?;
+    Ok(deepcopy.call1((value, memo))?.into())
+}
+
+/// Cancels a subscription with a given id on the shared type it was created for.
+type Unobserve = Rc<dyn Fn(SubscriptionId)>;
+
+/// Subscription returned by `observe`. Can be used as a context manager, in which case the
+/// callback is cancelled when leaving the `with` block. Subscriptions are compared and hashed by
+/// their ids, which are unique among subscriptions of the same shared type.
+#[pyclass(unsendable)]
+#[derive(Clone)]
+pub struct ShallowSubscription(pub SubscriptionId, pub(crate) Unobserve);
+
+impl ShallowSubscription {
+    pub fn new<T: Observable + 'static>(id: SubscriptionId, target: T) -> Self {
+        ShallowSubscription(id, Rc::new(move |id| target.unobserve(id)))
+    }
+
+    /// Cancels the callback of this subscription.
+    pub fn unobserve(&self) {
+        (self.1)(self.0)
+    }
+}
+
+#[pymethods]
+impl ShallowSubscription {
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyObject {
+        let py = other.py();
+        match (op, other.extract::<PyRef<Self>>()) {
+            (CompareOp::Eq, Ok(other)) => (self.0 == other.0).into_py(py),
+            (CompareOp::Ne, Ok(other)) => (self.0 != other.0).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        self.0 as u64
+    }
+
+    fn __repr__(&self) -> String {
+        format!("ShallowSubscription({})", self.0)
+    }
+
+    fn __exit__(
+        &self,
+        _exception_type: Option<&PyAny>,
+        _exception_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> bool {
+        (self.1)(self.0);
+        false
+    }
+}
+
+/// Subscription returned by `observe_deep` and `observe_changes`. Can be used as a context
+/// manager, in which case the callback is cancelled when leaving the `with` block. Subscriptions
+/// are compared and hashed by their ids, which are unique among subscriptions of the same shared
+/// type.
+#[pyclass(unsendable)]
+#[derive(Clone)]
+pub struct DeepSubscription(pub SubscriptionId, pub(crate) Unobserve);
+
+impl DeepSubscription {
+    pub fn new<T: DeepObservable + Clone + 'static>(id: SubscriptionId, target: T) -> Self {
+        DeepSubscription(id, Rc::new(move |id| target.clone().unobserve_deep(id)))
+    }
+
+    /// Cancels the callback of this subscription.
+    pub fn unobserve(&self) {
+        (self.1)(self.0)
+    }
+}
+
+#[pymethods]
+impl DeepSubscription {
+    fn __enter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+
+    fn __richcmp__(&self, other: &PyAny, op: CompareOp) -> PyObject {
+        let py = other.py();
+        match (op, other.extract::<PyRef<Self>>()) {
+            (CompareOp::Eq, Ok(other)) => (self.0 == other.0).into_py(py),
+            (CompareOp::Ne, Ok(other)) => (self.0 != other.0).into_py(py),
+            _ => py.NotImplemented(),
+        }
+    }
+
+    fn __hash__(&self) -> u64 {
+        self.0 as u64
+    }
+
+    fn __repr__(&self) -> String {
+        format!("DeepSubscription({})", self.0)
+    }
+
+    fn __exit__(
+        &self,
+        _exception_type: Option<&PyAny>,
+        _exception_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> bool {
+        self.unobserve();
+        false
+    }
+}
+
+/// A Python callback passed to `observe` or `observe_deep`. When created with `weak` set, only a
+/// weak reference to the callback is kept, so that observing doesn't keep the callback (or a
+/// bound method's instance) alive. Once the callback is collected, its subscription is cancelled
+/// the next time an event is emitted.
+#[derive(Clone)]
+pub struct ObserverCallback {
+    callback: PyObject,
+    weak: bool,
+    cancel: Rc<RefCell<Option<Box<dyn Fn()>>>>,
+}
+
+impl ObserverCallback {
+    pub fn new(py: Python, callback: PyObject, weak: Option<bool>) -> PyResult<Self> {
+        let weak = weak.unwrap_or(false);
+        let callback = if weak {
+            // a bound method is created anew on each attribute access, so a plain weak reference
+            // to it would die right away
+            let weakref = py.import("weakref")?;
+            let is_method: bool = py
+                .import("inspect")?
+                .call_method1("ismethod", (callback.clone_ref(py),))?
+                .extract()?;
+            let reference = if is_method { "WeakMethod" } else { "ref" };
+            weakref.call_method1(reference, (callback,))?.into()
+        } else {
+            callback
+        };
+        Ok(ObserverCallback {
+            callback,
+            weak,
+            cancel: Rc::default(),
+        })
+    }
+
+    /// Sets what cancels the subscription once a weakly referenced callback is collected.
+    pub fn cancel_with<F: Fn() + 'static>(&self, cancel: F) {
+        if self.weak {
+            self.cancel.borrow_mut().replace(Box::new(cancel));
+        }
+    }
+
+    /// Calls the callback with given `args`. If the callback has been collected in the meantime,
+    /// its subscription is cancelled instead.
+    pub fn call1(&self, py: Python, args: impl IntoPy<Py<pytypes::PyTuple>>) -> PyResult<PyObject> {
+        if !self.weak {
+            return self.callback.call1(py, args);
+        }
+        let callback = self.callback.call0(py)?;
+        if callback.is_none(py) {
+            let cancel = self.cancel.borrow_mut().take();
+            if let Some(cancel) = cancel {
+                cancel();
+            }
+            Ok(callback)
+        } else {
+            callback.call1(py, args)
+        }
+    }
+}
 
 #[derive(FromPyObject)]
 pub enum SubId {
@@ -87,6 +309,32 @@ impl<I, P> SharedType<I, P> {
     }
 }
 
+impl<T: AsRef<Branch>, P> SharedType<TypeWithDoc<T>, P> {
+    /// Returns `false` if this type has been integrated and then removed from its document.
+    pub fn is_alive(&self) -> bool {
+        match self {
+            SharedType::Integrated(v) => v.is_alive(),
+            SharedType::Prelim(_) => true,
+        }
+    }
+
+    /// Returns an error if this type has been integrated and then removed from its document.
+    pub fn guard_alive<Txn: ReadTxn>(&self, txn: &Txn) -> PyResult<()> {
+        match self {
+            SharedType::Integrated(v) => v.guard_alive(txn),
+            SharedType::Prelim(_) => Ok(()),
+        }
+    }
+
+    /// Returns a logical identifier of this type, or `None` if it hasn't been integrated yet.
+    pub fn id(&self) -> PyObject {
+        match self {
+            SharedType::Integrated(v) => v.id(),
+            SharedType::Prelim(_) => Python::with_gil(|py| py.None()),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum YPyType<'a> {
     Text(&'a PyCell<YText>),
@@ -119,6 +367,25 @@ impl<'a> YPyType<'a> {
             YPyType::XmlFragment(_) => TypeRef::XmlFragment,
         }
     }
+
+    /// Returns a string tag describing the kind of this shared type, as reported by `type_of`.
+    pub fn type_tag(&self) -> &'static str {
+        match self {
+            YPyType::Text(_) => "text",
+            YPyType::Array(_) => "array",
+            YPyType::Map(_) => "map",
+            YPyType::XmlElement(_) => "xml_element",
+            YPyType::XmlText(_) => "xml_text",
+            YPyType::XmlFragment(_) => "xml_fragment",
+        }
+    }
+}
+
+/// Contents of a shared type captured for the event emitted right away by
+/// `observe(..., emit_initial=True)`. Such an event isn't backed by any transaction.
+#[derive(Clone)]
+pub struct InitialState<D> {
+    pub delta: Vec<D>,
 }
 
 #[derive(Clone)]
@@ -138,9 +405,38 @@ impl<T> TypeWithDoc<T> {
         txn
     }
 
+    /// Runs `f` with a read-only transaction of this type's document. If the document is in the
+    /// middle of committing (e.g. when called from an observer callback), the committing
+    /// transaction is reused.
     pub fn with_transaction<F, R>(&self, f: F) -> R
     where
-        F: FnOnce(&YTransactionInner) -> R,
+        F: FnOnce(&TransactionMut<'static>) -> R,
+    {
+        let committing = self.doc.borrow().committing_transaction();
+        if let Some(txn) = committing {
+            return f(unsafe { &*txn });
+        }
+        let txn = self.get_transaction();
+        let txn = txn.borrow();
+        f(&txn)
+    }
+
+    /// Runs `f` with a given `txn` if it's the transaction currently open on this type's document,
+    /// or with a read-only transaction of this type's own document otherwise.
+    pub fn with_transaction_from<F, R>(&self, txn: &YTransactionInner, f: F) -> R
+    where
+        F: FnOnce(&TransactionMut<'static>) -> R,
+    {
+        if self.doc.borrow().owns_transaction(txn) {
+            f(txn)
+        } else {
+            self.with_transaction(f)
+        }
+    }
+
+    pub fn with_transaction_mut<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut YTransactionInner) -> R,
     {
         let txn = self.get_transaction();
         let mut txn = txn.borrow_mut();
@@ -163,12 +459,237 @@ impl<T> DerefMut for TypeWithDoc<T> {
     }
 }
 
+impl<T: AsRef<Branch>> TypeWithDoc<T> {
+    /// Returns the branch backing this type. yrs keeps the lengths it stores up to date as the
+    /// contents change, so they can be read without starting a transaction.
+    pub fn branch(&self) -> &Branch {
+        self.inner.as_ref()
+    }
+
+    /// Returns a list of keys and indexes forming a path from a root type of the document down to
+    /// this type, in the same format as event paths, or `None` if this type has been removed from
+    /// its document.
+    ///
+    /// The path is built by following parent pointers up to the root type, so it takes time
+    /// proportional to the depth of this type (and to the number of its siblings at each level,
+    /// which have to be counted to find its index).
+    pub fn path(&self) -> PyObject {
+        self.with_transaction(|txn| {
+            let path = path_from_root(txn, branch_ptr(&self.inner));
+            Python::with_gil(|py| path.map_or(py.None(), |path| path.into_py(py)))
+        })
+    }
+
+    /// Returns a shared type directly containing this one, or `None` for root types and types which
+    /// have been removed from their document.
+    pub fn parent_type(&self) -> PyResult<PyObject> {
+        let parent = parent_branch(self.branch())
+            .filter(|parent| has_defined_kind(*parent) && !is_removed(self.branch()));
+        Python::with_gil(|py| match parent {
+            Some(parent) => Value::from(parent).with_doc_into_py(self.doc.clone(), py),
+            None => Ok(py.None()),
+        })
+    }
+
+    /// Returns `true` unless this type has been removed from its document, either directly or along
+    /// with one of its ancestors. Root types are always alive.
+    pub fn is_alive(&self) -> bool {
+        !is_removed(self.branch())
+    }
+
+    /// Returns an error if this type has been removed from its document. The contents of removed
+    /// types may have already been garbage collected, so they must not be modified. Only the
+    /// ancestors of this type are checked, so the cost doesn't depend on the size of the document.
+    pub fn guard_alive<Txn: ReadTxn>(&self, _txn: &Txn) -> PyResult<()> {
+        if is_removed(self.branch()) {
+            Err(DeletedTypeException::default_message())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns an identifier of this type which is the same on every peer: a name of a root type or
+    /// a `(client, clock)` pair of the block which has created a nested type.
+    pub fn id(&self) -> PyObject {
+        if let Some(item) = self.branch().item {
+            let id = item.id;
+            return Python::with_gil(|py| (id.client, id.clock).into_py(py));
+        }
+        self.with_transaction(|txn| {
+            // a sticky index placed at the very beginning of a root type is scoped by its name
+            let index = StickyIndex::at(txn, branch_ptr(&self.inner), 0, Assoc::Before);
+            Python::with_gil(|py| match index.as_ref().map(StickyIndex::scope) {
+                Some(IndexScope::Root(name)) => name.to_string().into_py(py),
+                _ => py.None(),
+            })
+        })
+    }
+}
+
+/// Returns the branch of a shared type directly containing a given nested `branch`, or `None` if
+/// it's a root type.
+fn parent_branch(branch: &Branch) -> Option<BranchPtr> {
+    match &branch.item?.parent {
+        TypePtr::Branch(parent) => Some(*parent),
+        _ => None,
+    }
+}
+
+/// Checks if a given `branch` has been removed from its document, either directly or along with
+/// one of its ancestors, by following parent pointers up to the root type.
+fn is_removed(branch: &Branch) -> bool {
+    let mut item = branch.item;
+    while let Some(current) = item {
+        if current.is_deleted() {
+            return true;
+        }
+        item = match &current.parent {
+            TypePtr::Branch(parent) => parent.item,
+            _ => None,
+        };
+    }
+    false
+}
+
+/// Builds a path from a root type down to a `target` branch by following parent pointers, or
+/// returns `None` if the target has been removed. Map entries are identified by their keys, while
+/// elements of arrays and XML nodes by their indexes, which are found by counting siblings.
+fn path_from_root<T: ReadTxn>(txn: &T, target: BranchPtr) -> Option<Path> {
+    let mut path = Path::new();
+    let mut current = target;
+    while let Some(item) = current.item {
+        if item.is_deleted() {
+            return None;
+        }
+        let parent = match &item.parent {
+            TypePtr::Branch(parent) => *parent,
+            _ => return None,
+        };
+        let segment = match &item.parent_sub {
+            Some(key) => PathSegment::Key(key.clone()),
+            None => PathSegment::Index(child_index(txn, parent, current)?),
+        };
+        path.push_front(segment);
+        current = parent;
+    }
+    Some(path)
+}
+
+/// Returns the index of a `child` branch among the elements of a `parent` array or XML node.
+fn child_index<T: ReadTxn>(txn: &T, parent: BranchPtr, child: BranchPtr) -> Option<u32> {
+    if !has_defined_kind(parent) {
+        return None;
+    }
+    let index = match Value::from(parent) {
+        Value::YArray(array) => array
+            .iter(txn)
+            .position(|value| value_branch(&value) == Some(child)),
+        Value::YXmlElement(xml) => xml_child_index(txn, &xml, child),
+        Value::YXmlFragment(xml) => xml_child_index(txn, &xml, child),
+        _ => None,
+    };
+    index.map(|index| index as u32)
+}
+
+/// Returns the names of all root types of a document, together with their values, or `None` in
+/// place of root types whose kind is still undefined. Remote updates create such root types until
+/// they're fetched with one of the `YDoc.get_*` methods.
+///
+/// `ReadTxn::root_refs` panics when it reaches a root type of undefined kind, so it's only used
+/// once the document is known to have none. Root types of undefined kind always hold content, so
+/// they're found by reading the root names out of the document state. When there are any, empty
+/// root types are left out, as only root types holding content can be found this way.
+pub(crate) fn roots<T: ReadTxn>(txn: &T) -> PyResult<Vec<(String, Option<Value>)>> {
+    let update = txn.encode_state_as_update_v1(&StateVector::default());
+    let names = root_names(&update).map_err(|e| EncodingException::new_err(e.to_string()))?;
+    let roots: Vec<(String, Option<Value>)> = names
+        .into_iter()
+        .map(|name| {
+            let value = root_branch(txn, &name)
+                .filter(|branch| has_defined_kind(*branch))
+                .map(Value::from);
+            (name.to_string(), value)
+        })
+        .collect();
+    if roots.iter().all(|(_, value)| value.is_some()) {
+        Ok(txn
+            .root_refs()
+            .map(|(name, value)| (name.to_string(), Some(value)))
+            .collect())
+    } else {
+        Ok(roots)
+    }
+}
+
+/// Returns the names and values of root types of a document, skipping root types whose kind is
+/// still undefined.
+pub(crate) fn typed_roots<T: ReadTxn>(txn: &T) -> PyResult<Vec<(String, Value)>> {
+    Ok(roots(txn)?
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        .collect())
+}
+
+/// Returns a branch of a root type called `name`, if there's any. Unlike `ReadTxn::root_refs`, this
+/// doesn't convert it into a `Value`, so it works for root types of undefined kind as well.
+pub(crate) fn root_branch<T: ReadTxn>(txn: &T, name: &str) -> Option<BranchPtr> {
+    let path = Path::from([PathSegment::Key(name.into())]);
+    txn.store().get_type_from_path(&path)
+}
+
+/// Checks if a `branch` has got its kind defined, i.e. it can be converted into a `Value`. Only root
+/// types may have an undefined kind.
+pub(crate) fn has_defined_kind(branch: BranchPtr) -> bool {
+    matches!(
+        branch.type_ref(),
+        TYPE_REFS_ARRAY
+            | TYPE_REFS_MAP
+            | TYPE_REFS_TEXT
+            | TYPE_REFS_XML_ELEMENT
+            | TYPE_REFS_XML_FRAGMENT
+            | TYPE_REFS_XML_TEXT
+    )
+}
+
+/// Returns a branch of a given `value`, unless it's not a shared type.
+fn value_branch(value: &Value) -> Option<BranchPtr> {
+    match value {
+        Value::YText(v) => Some(branch_ptr(v)),
+        Value::YArray(v) => Some(branch_ptr(v)),
+        Value::YMap(v) => Some(branch_ptr(v)),
+        Value::YXmlElement(v) => Some(branch_ptr(v)),
+        Value::YXmlText(v) => Some(branch_ptr(v)),
+        Value::YXmlFragment(v) => Some(branch_ptr(v)),
+        Value::Any(_) | Value::YDoc(_) => None,
+    }
+}
+
+fn branch_ptr<B: AsRef<Branch>>(shared_ref: &B) -> BranchPtr {
+    BranchPtr::from(shared_ref.as_ref())
+}
+
+fn xml_child_index<T: ReadTxn, X: XmlFragment>(
+    txn: &T,
+    xml: &X,
+    child: BranchPtr,
+) -> Option<usize> {
+    (0..xml.len(txn)).position(|i| {
+        let branch = match xml.get(txn, i) {
+            Some(XmlNode::Element(v)) => branch_ptr(&v),
+            Some(XmlNode::Fragment(v)) => branch_ptr(&v),
+            Some(XmlNode::Text(v)) => branch_ptr(&v),
+            None => return false,
+        };
+        branch == child
+    })
+}
+
 impl<'a> Display for YPyType<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let info = match self {
             YPyType::Text(t) => t.borrow().__str__(),
-            YPyType::Array(a) => a.borrow().__str__(),
-            YPyType::Map(m) => m.borrow().__str__(),
+            YPyType::Array(a) => a.borrow().__str__().map_err(|_| std::fmt::Error)?,
+            YPyType::Map(m) => m.borrow().__str__().map_err(|_| std::fmt::Error)?,
             YPyType::XmlElement(xml) => xml.borrow().__str__(),
             YPyType::XmlText(xml) => xml.borrow().__str__(),
             YPyType::XmlFragment(xml) => xml.borrow().__str__(),
@@ -1,7 +1,9 @@
 use lib0::any::Any;
 use pyo3::create_exception;
 use pyo3::exceptions::PyException;
+use pyo3::exceptions::PyOverflowError;
 use pyo3::exceptions::PyTypeError;
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::types as pytypes;
 use pyo3::types::PyList;
@@ -14,12 +16,13 @@ use std::rc::Rc;
 use yrs::block::Unused;
 use yrs::block::{ItemContent, Prelim};
 use yrs::types::Events;
+use yrs::types::ToJson;
 use yrs::types::{Attrs, Branch, BranchPtr, Change, Delta, Value};
 use yrs::ArrayRef;
 use yrs::MapRef;
 use yrs::TextRef;
 use yrs::TransactionMut;
-use yrs::{Array, Map, Text};
+use yrs::{Array, GetString, Map, ReadTxn, Text, XmlNode};
 
 use crate::shared_types::CompatiblePyType;
 use crate::shared_types::TypeWithDoc;
@@ -28,9 +31,10 @@ use crate::y_array::YArray;
 use crate::y_array::YArrayEvent;
 use crate::y_doc::WithDoc;
 use crate::y_doc::YDocInner;
-use crate::y_map::YMapEvent;
-use crate::y_text::YTextEvent;
-use crate::y_xml::{YXmlEvent, YXmlTextEvent};
+use crate::y_map::{YMap, YMapEvent};
+use crate::y_text::{YText, YTextEvent};
+use crate::y_transaction::YTransactionInner;
+use crate::y_xml::{xml_into_json, YXmlEvent, YXmlTextEvent};
 
 create_exception!(y_py, MultipleIntegrationError, PyException, "A Ypy data type instance cannot be integrated into multiple YDocs or the same YDoc multiple times");
 
@@ -59,31 +63,179 @@ where
     }
 }
 
-impl<'a> TryFrom<&'a PyAny> for CompatiblePyType<'a> {
-    type Error = PyErr;
+thread_local! {
+    /// Encoders registered with `register_encoder`, in registration order.
+    static ENCODERS: RefCell<Vec<(Py<pytypes::PyType>, PyObject)>> = RefCell::new(Vec::new());
+    /// Decoders registered with `register_decoder`, in registration order.
+    static DECODERS: RefCell<Vec<PyObject>> = RefCell::new(Vec::new());
+}
 
-    fn try_from(py_any: &'a PyAny) -> Result<Self, Self::Error> {
+/// Registers an `encoder` for values of a given Python `type` (including its subclasses), which
+/// otherwise couldn't be stored in a shared type. The encoder is called with such a value and must
+/// return a value that can be stored: a `bool`, `int`, `float`, `str`, `None`, `list`, `dict` or
+/// a shared type. Registering another encoder for the same type replaces the previous one.
+///
+/// Values are stored as their encoded primitive form, so reading them back returns that form
+/// unless a decoder was registered with `register_decoder`.
+///
+/// Registered encoders are local to the thread that registered them.
+///
+/// Example:
+///
+/// ```python
+/// from decimal import Decimal
+/// from y_py import YDoc, register_encoder
+///
+/// register_encoder(Decimal, str)
+/// doc = YDoc()
+/// map = doc.get_map('prices')
+/// with doc.begin_transaction() as txn:
+///     map.set(txn, 'total', Decimal('9.99'))
+/// assert map['total'] == '9.99'
+/// ```
+#[pyfunction]
+pub fn register_encoder(r#type: Py<pytypes::PyType>, encoder: PyObject) {
+    ENCODERS.with(|encoders| {
+        let mut encoders = encoders.borrow_mut();
+        encoders.retain(|(t, _)| !t.is(&r#type));
+        encoders.push((r#type, encoder));
+    })
+}
+
+/// Registers a `decoder` applied to values read back from shared types. Decoders are called with
+/// every primitive value read (nested values before the collections containing them) and should
+/// return values they don't recognize unchanged. When multiple decoders are registered, each one
+/// receives the result of the previous one. An exception raised by a decoder is propagated to the
+/// code reading the value.
+///
+/// Registered decoders are local to the thread that registered them.
+///
+/// Example:
+///
+/// ```python
+/// from decimal import Decimal
+/// from y_py import register_decoder
+///
+/// def decode_decimal(value):
+///     if isinstance(value, dict) and '$decimal' in value:
+///         return Decimal(value['$decimal'])
+///     return value
+///
+/// register_decoder(decode_decimal)
+/// ```
+#[pyfunction]
+pub fn register_decoder(decoder: PyObject) {
+    DECODERS.with(|decoders| decoders.borrow_mut().push(decoder))
+}
+
+/// Returns an encoder registered for the type of a given `value`, if any.
+fn find_encoder(value: &PyAny) -> PyResult<Option<PyObject>> {
+    let py = value.py();
+    // Clone the registry, so that encoders may be (de)registered while types are being checked.
+    let encoders: Vec<(Py<pytypes::PyType>, PyObject)> = ENCODERS.with(|encoders| {
+        encoders
+            .borrow()
+            .iter()
+            .map(|(t, encoder)| (t.clone_ref(py), encoder.clone_ref(py)))
+            .collect()
+    });
+    for (t, encoder) in encoders {
+        if value.is_instance(t.as_ref(py))? {
+            return Ok(Some(encoder));
+        }
+    }
+    Ok(None)
+}
+
+/// Runs a given `value` read from a shared type through all registered decoders. If a decoder
+/// fails, its error is returned.
+fn decode(py: Python, value: PyObject) -> PyResult<PyObject> {
+    let decoders: Vec<PyObject> = DECODERS.with(|decoders| {
+        decoders
+            .borrow()
+            .iter()
+            .map(|decoder| decoder.clone_ref(py))
+            .collect()
+    });
+    decoders
+        .into_iter()
+        .try_fold(value, |value, decoder| decoder.call1(py, (value,)))
+}
+
+/// Converts a primitive `value` read from a shared type into a Python object. The value itself, as
+/// well as all values nested in it, are passed through registered decoders on the way.
+pub(crate) fn decode_any(py: Python, value: Any) -> PyResult<PyObject> {
+    let value = match value {
+        Any::Array(items) => {
+            let items = items
+                .iter()
+                .map(|item| decode_any(py, item.clone()))
+                .collect::<PyResult<Vec<PyObject>>>()?;
+            PyList::new(py, items).into()
+        }
+        Any::Map(entries) => {
+            let dict = pytypes::PyDict::new(py);
+            for (key, value) in entries.iter() {
+                dict.set_item(key, decode_any(py, value.clone())?)?;
+            }
+            dict.into()
+        }
+        other => other.into_py(py),
+    };
+    decode(py, value)
+}
+
+impl<'a> CompatiblePyType<'a> {
+    /// Converts values of types natively supported by Ypy, without consulting registered encoders.
+    fn try_from_native(py_any: &'a PyAny) -> Option<Self> {
+        // `bool` is a subclass of `int`, so it has to be checked first to keep `True` and `False`
+        // from being stored as numbers.
         if let Ok(b) = py_any.downcast::<pytypes::PyBool>() {
-            Ok(Self::Bool(b))
+            Some(Self::Bool(b))
         } else if let Ok(i) = py_any.downcast::<pytypes::PyInt>() {
-            Ok(Self::Int(i))
+            Some(Self::Int(i))
         } else if py_any.is_none() {
-            Ok(Self::None)
+            Some(Self::None)
         } else if let Ok(f) = py_any.downcast::<pytypes::PyFloat>() {
-            Ok(Self::Float(f))
+            Some(Self::Float(f))
         } else if let Ok(s) = py_any.downcast::<pytypes::PyString>() {
-            Ok(Self::String(s))
+            Some(Self::String(s))
         } else if let Ok(list) = py_any.downcast::<pytypes::PyList>() {
-            Ok(Self::List(list))
+            Some(Self::List(list))
         } else if let Ok(dict) = py_any.downcast::<pytypes::PyDict>() {
-            Ok(Self::Dict(dict))
+            Some(Self::Dict(dict))
         } else if let Ok(v) = YPyType::try_from(py_any) {
-            Ok(Self::YType(v))
+            Some(Self::YType(v))
         } else {
-            Err(PyTypeError::new_err(format!(
-                "Cannot integrate this type into a YDoc: {py_any}"
-            )))
+            None
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a PyAny> for CompatiblePyType<'a> {
+    type Error = PyErr;
+
+    fn try_from(py_any: &'a PyAny) -> Result<Self, Self::Error> {
+        if let Some(v) = Self::try_from_native(py_any) {
+            return Ok(v);
         }
+        if let Some(encoder) = find_encoder(py_any)? {
+            let py = py_any.py();
+            let encoded = encoder.call1(py, (py_any,))?.into_ref(py);
+            return Self::try_from_native(encoded).ok_or_else(|| {
+                PyTypeError::new_err(format!(
+                    "Encoder registered for {} returned a value that cannot be integrated into a YDoc: {encoded}",
+                    py_any.get_type()
+                ))
+            });
+        }
+        // Values of foreign numeric types (e.g. numpy scalars) are rejected rather than guessed at,
+        // since converting them implicitly could silently change their meaning.
+        Err(PyTypeError::new_err(format!(
+            "Cannot integrate a value of {} into a YDoc: {py_any}. Convert it into a built-in type \
+             or register an encoder for it with `register_encoder`.",
+            py_any.get_type()
+        )))
     }
 }
 
@@ -93,20 +245,22 @@ impl<'a> FromPyObject<'a> for CompatiblePyType<'a> {
     }
 }
 
+/// Converts values read from a document into Python objects. Conversion fails if a primitive value
+/// is rejected by one of the decoders registered with `register_decoder`.
 pub trait WithDocToPython {
-    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyObject;
+    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyResult<PyObject>;
 }
 
 impl WithDocToPython for Delta {
-    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyObject {
+    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyResult<PyObject> {
         let result = pytypes::PyDict::new(py);
         match self {
             Delta::Inserted(value, attrs) => {
-                let value = value.clone().with_doc_into_py(doc.clone(), py);
+                let value = value.clone().with_doc_into_py(doc.clone(), py)?;
                 result.set_item("insert", value).unwrap();
 
                 if let Some(attrs) = attrs {
-                    let attrs = attrs.with_doc_into_py(doc.clone(), py);
+                    let attrs = attrs.with_doc_into_py(doc.clone(), py)?;
                     result.set_item("attributes", attrs).unwrap();
                 }
             }
@@ -114,7 +268,7 @@ impl WithDocToPython for Delta {
                 result.set_item("retain", len).unwrap();
 
                 if let Some(attrs) = attrs {
-                    let attrs = attrs.with_doc_into_py(doc.clone(), py);
+                    let attrs = attrs.with_doc_into_py(doc.clone(), py)?;
                     result.set_item("attributes", attrs).unwrap();
                 }
             }
@@ -122,31 +276,31 @@ impl WithDocToPython for Delta {
                 result.set_item("delete", len).unwrap();
             }
         }
-        result.into()
+        Ok(result.into())
     }
 }
 
 impl WithDocToPython for &Attrs {
-    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyObject {
+    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyResult<PyObject> {
         let o = pytypes::PyDict::new(py);
         for (key, value) in self.iter() {
             let key = key.as_ref();
-            let value = Value::Any(value.clone()).with_doc_into_py(doc.clone(), py);
+            let value = Value::Any(value.clone()).with_doc_into_py(doc.clone(), py)?;
             o.set_item(key, value).unwrap();
         }
-        o.into()
+        Ok(o.into())
     }
 }
 
 impl WithDocToPython for &Change {
-    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyObject {
+    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyResult<PyObject> {
         let result = pytypes::PyDict::new(py);
         match self {
             Change::Added(values) => {
                 let values: Vec<PyObject> = values
                     .iter()
                     .map(|v| v.clone().with_doc_into_py(doc.clone(), py))
-                    .collect();
+                    .collect::<PyResult<_>>()?;
                 result.set_item("insert", values).unwrap();
             }
             Change::Removed(len) => {
@@ -156,7 +310,101 @@ impl WithDocToPython for &Change {
                 result.set_item("retain", len).unwrap();
             }
         }
-        result.into()
+        Ok(result.into())
+    }
+}
+
+/// Returns an error if a shared type about to be inserted into a document is already integrated.
+/// Preliminary ones are integrated in place - the Python object passed by the caller becomes
+/// the integrated shared type - so each of them can only be inserted once.
+pub(crate) fn guard_prelim(y_type: &YPyType) -> PyResult<()> {
+    if y_type.is_prelim() {
+        Ok(())
+    } else {
+        Err(MultipleIntegrationError::new_err(format!(
+            "Cannot integrate a nested Ypy object because is already integrated into a YDoc: {y_type}"
+        )))
+    }
+}
+
+/// Maximum number of elements or entries shown by `__repr__` of shared collections.
+pub(crate) const REPR_MAX_ITEMS: usize = 10;
+
+/// Maximum number of characters shown by `__repr__` of texts.
+pub(crate) const REPR_MAX_CHARS: usize = 100;
+
+/// Joins `parts` - representations of up to `REPR_MAX_ITEMS + 1` leading elements of a collection -
+/// between `open` and `close` brackets. The elements past the limit are replaced with an ellipsis.
+pub(crate) fn truncated_repr(mut parts: Vec<String>, open: &str, close: &str) -> String {
+    if parts.len() > REPR_MAX_ITEMS {
+        parts.truncate(REPR_MAX_ITEMS);
+        parts.push("...".to_string());
+    }
+    format!("{open}{}{close}", parts.join(", "))
+}
+
+/// Returns a `value` which has just been inserted into a shared type if it's a shared type that
+/// has been integrated by the insertion, or `None` otherwise.
+pub(crate) fn integrated_or_none(py: Python, value: &PyObject) -> PyObject {
+    match value.extract::<CompatiblePyType>(py) {
+        Ok(CompatiblePyType::YType(y_type)) if !y_type.is_prelim() => value.clone_ref(py),
+        _ => py.None(),
+    }
+}
+
+/// Turns a `value` read from a shared type into a snapshot if `materialize` is set, so that it no
+/// longer follows changes of its document: nested arrays and maps become lists and dictionaries,
+/// as parsed from their `to_json`, while texts and XML nodes become strings. Other values, and any
+/// value when `materialize` isn't set, are returned unchanged.
+pub(crate) fn materialize_value(
+    py: Python,
+    value: PyObject,
+    materialize: bool,
+) -> PyResult<PyObject> {
+    if !materialize {
+        return Ok(value);
+    }
+    match YPyType::try_from(value.as_ref(py)) {
+        Ok(YPyType::Array(_) | YPyType::Map(_)) => {
+            let json = value.call_method0(py, "to_json")?;
+            Ok(py.import("json")?.call_method1("loads", (json,))?.into())
+        }
+        Ok(_) => Ok(value.as_ref(py).str()?.into()),
+        Err(_) => Ok(value),
+    }
+}
+
+/// Converts a `value` read through `txn` into a snapshot of its contents, the same way
+/// `materialize_value` does. Meant for values being removed from their parent, as shared types
+/// nested in them can no longer be read once they're deleted. Subdocuments outlive their removal,
+/// so they're returned as they are.
+pub(crate) fn value_into_snapshot<T: ReadTxn>(
+    txn: &T,
+    value: Value,
+    doc: Rc<RefCell<YDocInner>>,
+    py: Python,
+) -> PyResult<PyObject> {
+    match value {
+        Value::Any(v) => decode_any(py, v),
+        Value::YArray(v) => {
+            let items = v
+                .iter(txn)
+                .map(|item| value_into_snapshot(txn, item, doc.clone(), py))
+                .collect::<PyResult<Vec<PyObject>>>()?;
+            Ok(PyList::new(py, items).into())
+        }
+        Value::YMap(v) => {
+            let dict = pytypes::PyDict::new(py);
+            for (key, value) in v.iter(txn) {
+                dict.set_item(key, value_into_snapshot(txn, value, doc.clone(), py)?)?;
+            }
+            Ok(dict.into())
+        }
+        Value::YText(v) => Ok(v.get_string(txn).into_py(py)),
+        Value::YXmlElement(v) => Ok(v.get_string(txn).into_py(py)),
+        Value::YXmlText(v) => Ok(v.get_string(txn).into_py(py)),
+        Value::YXmlFragment(v) => Ok(v.get_string(txn).into_py(py)),
+        doc_value @ Value::YDoc(_) => doc_value.with_doc_into_py(doc, py),
     }
 }
 
@@ -313,14 +561,27 @@ impl<'a> TryFrom<CompatiblePyType<'a>> for Any {
             CompatiblePyType::Bool(b) => Ok(Any::Bool(b.extract()?)),
             CompatiblePyType::String(s) => Ok(Any::String(s.extract::<String>()?.into_boxed_str())),
             CompatiblePyType::Int(i) => {
-                let num: i64 = i.extract()?;
-                if num > MAX_JS_NUMBER {
+                let num: i64 = i.extract().map_err(|_| {
+                    PyOverflowError::new_err(format!(
+                        "Cannot integrate an int outside of the 64-bit signed range into a YDoc: {i}"
+                    ))
+                })?;
+                if !(-MAX_JS_NUMBER..=MAX_JS_NUMBER).contains(&num) {
                     Ok(Any::BigInt(num))
                 } else {
                     Ok(Any::Number(num as f64))
                 }
             }
-            CompatiblePyType::Float(f) => Ok(Any::Number(f.extract()?)),
+            CompatiblePyType::Float(f) => {
+                let num: f64 = f.extract()?;
+                if num.is_finite() {
+                    Ok(Any::Number(num))
+                } else {
+                    Err(PyValueError::new_err(format!(
+                        "Cannot integrate a non-finite float into a YDoc: {num}"
+                    )))
+                }
+            }
             CompatiblePyType::List(l) => {
                 let result: PyResult<Vec<Any>> = l
                     .into_iter()
@@ -376,6 +637,12 @@ impl<'a> TryFrom<&'a PyAny> for YPyType<'a> {
             Ok(YPyType::Array(array))
         } else if let Ok(map) = value.extract() {
             Ok(YPyType::Map(map))
+        } else if let Ok(xml) = value.extract() {
+            Ok(YPyType::XmlElement(xml))
+        } else if let Ok(xml) = value.extract() {
+            Ok(YPyType::XmlText(xml))
+        } else if let Ok(xml) = value.extract() {
+            Ok(YPyType::XmlFragment(xml))
         } else {
             Err(pyo3::exceptions::PyValueError::new_err(format!(
                 "Could not extract a Ypy type from this object: {value}"
@@ -417,9 +684,9 @@ impl ToPython for Any {
 }
 
 impl WithDocToPython for Value {
-    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyObject {
-        match self {
-            Value::Any(v) => v.into_py(py),
+    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyResult<PyObject> {
+        Ok(match self {
+            Value::Any(v) => decode_any(py, v)?,
             Value::YText(v) => v.with_doc(doc).into_py(py),
             Value::YArray(v) => v.with_doc(doc).into_py(py),
             Value::YMap(v) => v.with_doc(doc).into_py(py),
@@ -427,10 +694,148 @@ impl WithDocToPython for Value {
             Value::YXmlText(v) => v.with_doc(doc).into_py(py),
             Value::YXmlFragment(v) => v.with_doc(doc).into_py(py),
             Value::YDoc(_) => py.None(),
+        })
+    }
+}
+
+/// Converts a value read from a shared type into its JSON-like representation. By default XML nodes
+/// are represented as strings, like `ToJson` does. With `include_xml` they are represented as
+/// objects instead (see `xml_into_json`).
+pub(crate) fn value_into_json<T: ReadTxn>(txn: &T, value: &Value, include_xml: bool) -> Any {
+    if !include_xml {
+        return value.to_json(txn);
+    }
+    match value {
+        Value::YArray(v) => {
+            let items: Vec<Any> = v
+                .iter(txn)
+                .map(|item| value_into_json(txn, &item, include_xml))
+                .collect();
+            Any::Array(items.into_boxed_slice())
+        }
+        Value::YMap(v) => {
+            let entries: HashMap<String, Any> = v
+                .iter(txn)
+                .map(|(key, value)| (key.to_string(), value_into_json(txn, &value, include_xml)))
+                .collect();
+            Any::Map(Box::new(entries))
         }
+        Value::YXmlElement(v) => xml_into_json(txn, &XmlNode::Element(v.clone())),
+        Value::YXmlText(v) => xml_into_json(txn, &XmlNode::Text(v.clone())),
+        Value::YXmlFragment(v) => xml_into_json(txn, &XmlNode::Fragment(v.clone())),
+        other => other.to_json(txn),
     }
 }
 
+/// Deep-copies a value read from a shared type into a Python object. Nested shared types become
+/// new preliminary instances, detached from the document they were read from.
+pub(crate) fn value_into_prelim<T: ReadTxn>(
+    txn: &T,
+    value: Value,
+    py: Python,
+) -> PyResult<PyObject> {
+    match value {
+        Value::Any(v) => decode_any(py, v),
+        Value::YText(v) => Ok(YText(SharedType::Prelim(v.get_string(txn))).into_py(py)),
+        Value::YArray(v) => {
+            let items = v
+                .iter(txn)
+                .map(|item| value_into_prelim(txn, item, py))
+                .collect::<PyResult<Vec<PyObject>>>()?;
+            Ok(YArray(SharedType::Prelim(items)).into_py(py))
+        }
+        Value::YMap(v) => {
+            let entries = v
+                .iter(txn)
+                .map(|(key, value)| Ok((key.to_string(), value_into_prelim(txn, value, py)?)))
+                .collect::<PyResult<HashMap<String, PyObject>>>()?;
+            Ok(YMap(SharedType::Prelim(entries)).into_py(py))
+        }
+        other => Err(PyTypeError::new_err(format!(
+            "Cannot copy a value of type {} into a preliminary type",
+            value_type_tag(&other)
+        ))),
+    }
+}
+
+/// Deep-copies a Python object, turning both preliminary and integrated shared types into new
+/// preliminary instances. Integrated types are read through `txn` when it belongs to their
+/// document. Other values are returned unchanged.
+pub(crate) fn py_into_prelim(txn: &YTransactionInner, value: &PyAny) -> PyResult<PyObject> {
+    let py = value.py();
+    match YPyType::try_from(value) {
+        Ok(YPyType::Text(v)) => match &v.try_borrow()?.0 {
+            SharedType::Integrated(text) => text.with_transaction_from(txn, |t| {
+                value_into_prelim(t, Value::YText(text.inner.clone()), py)
+            }),
+            SharedType::Prelim(s) => Ok(YText(SharedType::Prelim(s.clone())).into_py(py)),
+        },
+        Ok(YPyType::Array(v)) => match &v.try_borrow()?.0 {
+            SharedType::Integrated(array) => array.with_transaction_from(txn, |t| {
+                value_into_prelim(t, Value::YArray(array.inner.clone()), py)
+            }),
+            SharedType::Prelim(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| py_into_prelim(txn, item.as_ref(py)))
+                    .collect::<PyResult<Vec<PyObject>>>()?;
+                Ok(YArray(SharedType::Prelim(items)).into_py(py))
+            }
+        },
+        Ok(YPyType::Map(v)) => match &v.try_borrow()?.0 {
+            SharedType::Integrated(map) => map.with_transaction_from(txn, |t| {
+                value_into_prelim(t, Value::YMap(map.inner.clone()), py)
+            }),
+            SharedType::Prelim(entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), py_into_prelim(txn, value.as_ref(py))?)))
+                    .collect::<PyResult<HashMap<String, PyObject>>>()?;
+                Ok(YMap(SharedType::Prelim(entries)).into_py(py))
+            }
+        },
+        Ok(other) => Err(PyTypeError::new_err(format!(
+            "Cannot copy a value of type {} into a preliminary type",
+            other.type_tag()
+        ))),
+        Err(_) => Ok(value.into()),
+    }
+}
+
+/// All string tags describing kinds of values stored in shared types.
+pub(crate) const VALUE_KINDS: [&str; 8] = [
+    "primitive",
+    "text",
+    "array",
+    "map",
+    "xml_element",
+    "xml_text",
+    "xml_fragment",
+    "doc",
+];
+
+/// Returns a string tag describing the kind of a value stored in a shared type, without
+/// converting it into a Python object.
+pub(crate) fn value_type_tag(value: &Value) -> &'static str {
+    match value {
+        Value::Any(_) => "primitive",
+        Value::YText(_) => "text",
+        Value::YArray(_) => "array",
+        Value::YMap(_) => "map",
+        Value::YXmlElement(_) => "xml_element",
+        Value::YXmlText(_) => "xml_text",
+        Value::YXmlFragment(_) => "xml_fragment",
+        Value::YDoc(_) => "doc",
+    }
+}
+
+/// Returns a string tag describing the kind of a value stored in a preliminary shared type.
+pub(crate) fn py_type_tag(value: &PyAny) -> &'static str {
+    YPyType::try_from(value)
+        .map(|v| v.type_tag())
+        .unwrap_or("primitive")
+}
+
 pub(crate) fn events_into_py(
     txn: &TransactionMut,
     events: &Events,
@@ -454,3 +859,41 @@ pub(crate) fn events_into_py(
         PyList::new(py, py_events).into()
     })
 }
+
+/// Aggregates deep events into a single dictionary keyed by the path of each changed shared type,
+/// relative to the observed one. Path segments are joined with `/`, so changes made to the observed
+/// type itself are stored under an empty key. Each entry holds a `delta` (for sequence types)
+/// and/or `keys` (for maps and XML attributes) describing the changes.
+pub(crate) fn events_into_changes(
+    txn: &TransactionMut,
+    events: &Events,
+    doc: Rc<RefCell<YDocInner>>,
+) -> PyResult<PyObject> {
+    Python::with_gil(|py| {
+        let changes = pytypes::PyDict::new(py);
+        let events = events_into_py(txn, events, doc);
+        for event in events.as_ref(py).iter()? {
+            let event = event?;
+            let path = event
+                .call_method0("path")?
+                .iter()?
+                .map(|segment| Ok(segment?.str()?.to_string()))
+                .collect::<PyResult<Vec<String>>>()?
+                .join("/");
+            changes.set_item(path, event_changes(event)?)?;
+        }
+        Ok(changes.into())
+    })
+}
+
+/// Returns a dictionary with the `delta` and/or `keys` of a given Python `event`, depending on
+/// which of them are available for its shared type.
+pub(crate) fn event_changes(event: &PyAny) -> PyResult<&pytypes::PyDict> {
+    let change = pytypes::PyDict::new(event.py());
+    for attr in ["delta", "keys"] {
+        if event.hasattr(attr)? {
+            change.set_item(attr, event.getattr(attr)?)?;
+        }
+    }
+    Ok(change)
+}
@@ -0,0 +1,64 @@
+use std::os::raw::{c_int, c_void};
+
+use pyo3::exceptions::PyIndexError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use pyo3::{ffi, AsPyPointer};
+
+/// A read-only buffer holding an encoded update, exposed through Python's buffer protocol. It can
+/// be passed wherever a bytes-like object is accepted (e.g. `socket.send`, `memoryview` or
+/// `apply_update`) without copying the encoded payload into a new `bytes` object.
+#[pyclass(unsendable)]
+pub struct UpdateBuffer(Vec<u8>);
+
+#[pymethods]
+impl UpdateBuffer {
+    pub fn __len__(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn __getitem__(&self, index: isize) -> PyResult<u8> {
+        let len = self.0.len() as isize;
+        let normalized = if index < 0 { len + index } else { index };
+        if (0..len).contains(&normalized) {
+            Ok(self.0[normalized as usize])
+        } else {
+            Err(PyIndexError::new_err("UpdateBuffer index out of range"))
+        }
+    }
+
+    unsafe fn __getbuffer__(
+        slf: &PyCell<Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        let this = slf.borrow();
+        // also rejects requests for a writable buffer and takes a reference to `slf`, which keeps
+        // the underlying memory alive for as long as the view is in use
+        let result = ffi::PyBuffer_FillInfo(
+            view,
+            slf.as_ptr(),
+            this.0.as_ptr() as *mut c_void,
+            this.0.len() as ffi::Py_ssize_t,
+            1,
+            flags,
+        );
+        if result == -1 {
+            Err(PyErr::fetch(slf.py()))
+        } else {
+            Ok(())
+        }
+    }
+
+    unsafe fn __releasebuffer__(&self, _view: *mut ffi::Py_buffer) {}
+}
+
+/// Converts an encoded `update` into a Python `bytes` object or, unless `copy` is set, into an
+/// `UpdateBuffer` sharing its memory.
+pub(crate) fn update_into_py(py: Python, update: Vec<u8>, copy: bool) -> PyObject {
+    if copy {
+        PyBytes::new(py, &update).into()
+    } else {
+        UpdateBuffer(update).into_py(py)
+    }
+}
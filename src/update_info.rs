@@ -0,0 +1,51 @@
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::partial_update::read_update;
+use crate::y_transaction::EncodingException;
+
+/// Describes an `update` encoded using lib0 v1 encoding without applying it to a document. Returns
+/// a dictionary with its `byte_size`, the number of structs (blocks of inserted, deleted or
+/// garbage collected content) it carries as `struct_count` and the number of clients, whose
+/// changes it contains, as `client_count`. Raises an `EncodingException` if the update is
+/// malformed.
+///
+/// This is meant for relays, which need to enforce size limits or collect telemetry before
+/// forwarding updates, without the cost of integrating them into a scratch document.
+///
+/// Example:
+///
+/// ```python
+/// from y_py import YDoc, encode_state_as_update, update_info
+///
+/// doc = YDoc()
+/// with doc.begin_transaction() as txn:
+///     doc.get_text('text').extend(txn, 'hello')
+/// info = update_info(encode_state_as_update(doc))
+/// assert info['client_count'] == 1 and info['struct_count'] == 1
+/// ```
+#[pyfunction]
+pub fn update_info(update: Vec<u8>) -> PyResult<PyObject> {
+    let (clients, _) =
+        read_update(&update).map_err(|e| EncodingException::new_err(e.to_string()))?;
+    let struct_count: usize = clients.iter().map(|(_, _, blocks)| blocks.len()).sum();
+    Python::with_gil(|py| {
+        let info = PyDict::new(py);
+        info.set_item("byte_size", update.len())?;
+        info.set_item("struct_count", struct_count)?;
+        info.set_item("client_count", clients.len())?;
+        Ok(info.into())
+    })
+}
+
+/// Checks whether an `update` encoded using lib0 v1 encoding is well-formed, by decoding both its
+/// structs and delete set without applying it to a document. Unlike `update_info`, this never
+/// raises for malformed input and returns `False` instead, so that payloads of untrusted clients
+/// can be rejected before they are persisted.
+///
+/// Only the structure of the update is checked: a well-formed update may still depend on changes,
+/// which the document it's applied to doesn't have yet.
+#[pyfunction]
+pub fn validate_update(update: Vec<u8>) -> bool {
+    read_update(&update).is_ok()
+}
@@ -1,27 +1,32 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::convert::{TryFrom, TryInto};
 use std::rc::Rc;
 
 use crate::json_builder::JsonBuilder;
 use crate::shared_types::{
-    CompatiblePyType, DeepSubscription, DefaultPyErr, PreliminaryObservationException,
-    ShallowSubscription, SubId, TypeWithDoc,
+    deepcopy, insert_position, integrated_copy_error, CompatiblePyType, DeepSubscription,
+    DefaultPyErr, InitialState, IntegratedOperationException, ObserverCallback,
+    PreliminaryObservationException, ShallowSubscription, SubId, TypeWithDoc, YPyType,
 };
-use crate::type_conversions::{events_into_py, WithDocToPython};
-use crate::y_doc::{WithDoc, YDocInner};
-use crate::y_transaction::{YTransaction, YTransactionInner};
+use crate::type_conversions::{
+    decode_any, events_into_changes, events_into_py, guard_prelim, integrated_or_none,
+    materialize_value, py_type_tag, truncated_repr, value_into_json, value_into_prelim,
+    value_into_snapshot, value_type_tag, WithDocToPython, REPR_MAX_ITEMS,
+};
+use crate::y_doc::{with_committing_transaction, WithDoc, YDocInner};
+use crate::y_transaction::{origin_into_py, YTransaction, YTransactionInner};
 
 use super::shared_types::SharedType;
 use crate::type_conversions::ToPython;
 use lib0::any::Any;
-use pyo3::exceptions::PyIndexError;
+use pyo3::exceptions::{PyIndexError, PyValueError};
 
 use crate::type_conversions::PyObjectWrapper;
 use pyo3::prelude::*;
-use pyo3::types::{PyList, PySlice, PySliceIndices};
+use pyo3::types::{PyDict, PyList, PySlice, PySliceIndices};
 use yrs::types::array::ArrayEvent;
-use yrs::types::{DeepObservable, ToJson};
-use yrs::{Array, ArrayRef, Assoc, Observable, SubscriptionId, TransactionMut};
+use yrs::types::{Change, DeepObservable, ToJson, Value};
+use yrs::{Array, ArrayRef, Assoc, GetString, Observable, SubscriptionId, TransactionMut};
 
 /// A collection used to store data in an indexed sequence structure. This type is internally
 /// implemented as a double linked list, which may squash values inserted directly one after another
@@ -69,135 +74,301 @@ impl YArray {
     /// Preliminary instances can be nested into other shared data types such as `YArray` and `YMap`.
     /// Once a preliminary instance has been inserted this way, it becomes integrated into Ypy
     /// document store and cannot be nested again: attempt to do so will result in an exception.
+    /// The inserted object itself is integrated in place, so it can be used for further edits.
     #[getter]
     pub fn prelim(&self) -> bool {
         matches!(&self.0, SharedType::Prelim(_))
     }
 
-    /// Returns a number of elements stored within this instance of `YArray`.
-    pub fn __len__(&self) -> usize {
+    /// Returns a shallow copy of a preliminary `YArray`. Integrated instances are bound to their
+    /// document, so they can't be copied.
+    pub fn __copy__(&self) -> PyResult<Self> {
         match &self.0 {
-            SharedType::Integrated(v) => v.with_transaction(|txn| v.len(txn)) as usize,
-            SharedType::Prelim(v) => v.len(),
+            SharedType::Prelim(items) => Ok(YArray(SharedType::prelim(items.clone()))),
+            SharedType::Integrated(_) => Err(integrated_copy_error("YArray")),
         }
     }
 
-    /// Returns a number of elements stored within this instance of `YArray` using a provided transaction.
-    fn _len(&self, txn: &YTransactionInner) -> usize {
+    /// Returns a deep copy of a preliminary `YArray`. Integrated instances are bound to their
+    /// document, so they can't be copied.
+    pub fn __deepcopy__(&self, memo: &PyAny) -> PyResult<Self> {
         match &self.0 {
-            SharedType::Integrated(v) => v.len(txn) as usize,
+            SharedType::Prelim(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| deepcopy(item, memo))
+                    .collect::<PyResult<_>>()?;
+                Ok(YArray(SharedType::prelim(items)))
+            }
+            SharedType::Integrated(_) => Err(integrated_copy_error("YArray")),
+        }
+    }
+
+    /// Returns `False` if this `YArray` has been removed from its document, either directly or
+    /// along with one of its ancestors. Removed types can no longer be modified.
+    pub fn is_alive(&self) -> bool {
+        self.0.is_alive()
+    }
+
+    /// Returns `True` if this `YArray` has been removed from its document. See `is_alive`.
+    pub fn is_deleted(&self) -> bool {
+        !self.is_alive()
+    }
+
+    /// Returns an identifier of this `YArray` which is the same on every peer: the name of a root
+    /// type or a `(client, clock)` pair identifying the update which has created a nested one.
+    /// Preliminary instances have no identifier and return `None`.
+    pub fn id(&self) -> PyObject {
+        self.0.id()
+    }
+
+    /// Returns a shared type directly containing this `YArray`, or `None` for root types,
+    /// preliminary instances and instances which have been removed from their document.
+    #[getter]
+    pub fn parent(&self) -> PyResult<PyObject> {
+        match &self.0 {
+            SharedType::Integrated(v) => v.parent_type(),
+            SharedType::Prelim(_) => Ok(Python::with_gil(|py| py.None())),
+        }
+    }
+
+    /// Returns a list of keys and indexes forming a path from the root type of a document down to
+    /// this `YArray`, in the same format as event paths. Returns `None` if this instance has been
+    /// removed from its document. Preliminary instances have no path.
+    pub fn path(&self) -> PyResult<PyObject> {
+        match &self.0 {
+            SharedType::Integrated(v) => Ok(v.path()),
+            SharedType::Prelim(_) => Err(IntegratedOperationException::default_message()),
+        }
+    }
+
+    /// Returns a number of elements stored within this instance of `YArray`. The length is read
+    /// straight from the document, without starting a transaction.
+    pub fn __len__(&self) -> usize {
+        match &self.0 {
+            SharedType::Integrated(v) => v.branch().len() as usize,
             SharedType::Prelim(v) => v.len(),
         }
     }
 
-    pub fn __str__(&self) -> String {
+    /// Returns a number of elements stored within this `YArray`, optionally read through a given
+    /// `txn`. Raises a `TransactionError` if `txn` has already been committed.
+    pub fn length(&self, txn: Option<PyRef<YTransaction>>) -> PyResult<usize> {
+        match txn {
+            Some(txn) => Ok(self._len(&txn.get_active_inner()?.borrow())),
+            None => Ok(self.__len__()),
+        }
+    }
+
+    pub fn __str__(&self) -> PyResult<String> {
         match &self.0 {
             SharedType::Integrated(y_array) => {
                 let any = y_array.with_transaction(|txn| y_array.to_json(txn));
-                let py_values = Python::with_gil(|py| any.into_py(py));
-                py_values.to_string()
+                let py_values = Python::with_gil(|py| decode_any(py, any))?;
+                Ok(py_values.to_string())
             }
             SharedType::Prelim(py_contents) => {
                 let py_values = Python::with_gil(|py| py_contents.clone().into_py(py));
-                py_values.to_string()
+                Ok(py_values.to_string())
             }
         }
     }
 
-    pub fn __repr__(&self) -> String {
-        format!("YArray({})", self.__str__())
+    /// Returns a representation of this `YArray`, which - unlike `__str__` - only shows its first
+    /// `10` elements, followed by an ellipsis if there are more of them.
+    pub fn __repr__(&self) -> PyResult<String> {
+        Python::with_gil(|py| {
+            let repr = |value: PyObject| value.as_ref(py).repr().map(|r| r.to_string());
+            let parts: Vec<String> = match &self.0 {
+                SharedType::Integrated(array) => array.with_transaction(|txn| {
+                    array
+                        .iter(txn)
+                        .take(REPR_MAX_ITEMS + 1)
+                        .map(|value| repr(decode_any(py, value.to_json(txn))?))
+                        .collect::<PyResult<Vec<String>>>()
+                })?,
+                SharedType::Prelim(items) => items
+                    .iter()
+                    .take(REPR_MAX_ITEMS + 1)
+                    .map(|value| repr(value.clone_ref(py)))
+                    .collect::<PyResult<Vec<String>>>()?,
+            };
+            Ok(format!("YArray({})", truncated_repr(parts, "[", "]")))
+        })
     }
 
     /// Converts an underlying contents of this `YArray` instance into their JSON representation.
-    pub fn to_json(&self) -> PyResult<String> {
-        let mut json_builder = JsonBuilder::new();
+    /// Nested XML nodes are serialized as `{"tag", "attributes", "children"}` objects if
+    /// `include_xml` is set, otherwise they are either stringified (when integrated) or rejected.
+    /// Keys of nested maps are emitted in sorted order if `sort_keys` is set.
+    pub fn to_json(&self, include_xml: Option<bool>, sort_keys: Option<bool>) -> PyResult<String> {
+        let include_xml = include_xml.unwrap_or(false);
+        let mut json_builder = JsonBuilder::with_options(include_xml, sort_keys.unwrap_or(false));
         match &self.0 {
-            SharedType::Integrated(array) => {
-                array.with_transaction(|txn| json_builder.append_json(&array.to_json(txn)))
-            }
+            SharedType::Integrated(array) => array.with_transaction(|txn| {
+                let value = Value::YArray(array.inner.clone());
+                json_builder.append_json(&value_into_json(txn, &value, include_xml))
+            }),
             SharedType::Prelim(py_vec) => json_builder.append_json(py_vec),
         }?;
         Ok(json_builder.into())
     }
 
-    /// Adds a single item to the provided index in the array.
-    pub fn insert(&mut self, txn: &mut YTransaction, index: u32, item: PyObject) -> PyResult<()> {
-        txn.transact(|txn| self._insert(txn, index, item))?
+    /// Adds a single item to the provided index in the array. Negative indexes are counted from
+    /// the end of the array. If `item` is a preliminary shared type, it's returned once integrated,
+    /// so that it can be edited further. Otherwise `None` is returned.
+    pub fn insert(
+        &mut self,
+        txn: &mut YTransaction,
+        index: isize,
+        item: PyObject,
+    ) -> PyResult<PyObject> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._insert(txn, index, item.clone()))
+        })??;
+        Ok(Python::with_gil(|py| integrated_or_none(py, &item)))
     }
 
-    fn _insert(&mut self, txn: &mut YTransactionInner, index: u32, item: PyObject) -> PyResult<()> {
+    fn _insert(
+        &mut self,
+        txn: &mut YTransactionInner,
+        index: isize,
+        item: PyObject,
+    ) -> PyResult<()> {
+        let index = insert_position(index, self._len(txn), "an array")?;
         match &mut self.0 {
-            SharedType::Integrated(array) if array.len(txn) >= index => {
+            SharedType::Integrated(array) => {
+                guard_prelim_item(&item)?;
                 array.insert(txn, index, PyObjectWrapper::new(item, array.doc.clone()));
-                Ok(())
             }
-            SharedType::Prelim(vec) if vec.len() >= index as usize => {
-                vec.insert(index as usize, item);
-                Ok(())
-            }
-            _ => Err(PyIndexError::default_message()),
+            SharedType::Prelim(vec) => vec.insert(index as usize, item),
         }
+        Ok(())
+    }
+
+    /// Inserts an `item` right before the first element equal to `anchor`. Raises `ValueError` if
+    /// there's no such element.
+    pub fn insert_before(
+        &mut self,
+        txn: &mut YTransaction,
+        anchor: PyObject,
+        item: PyObject,
+    ) -> PyResult<()> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._insert_relative(txn, anchor, item, 0))
+        })?
+    }
+
+    /// Inserts an `item` right after the first element equal to `anchor`. Raises `ValueError` if
+    /// there's no such element.
+    pub fn insert_after(
+        &mut self,
+        txn: &mut YTransaction,
+        anchor: PyObject,
+        item: PyObject,
+    ) -> PyResult<()> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._insert_relative(txn, anchor, item, 1))
+        })?
+    }
+
+    fn _insert_relative(
+        &mut self,
+        txn: &mut YTransactionInner,
+        anchor: PyObject,
+        item: PyObject,
+        offset: u32,
+    ) -> PyResult<()> {
+        let index = Python::with_gil(|py| self.position(txn, anchor.as_ref(py)))?
+            .ok_or_else(|| PyValueError::new_err(format!("{anchor} is not in the YArray.")))?;
+        self._insert(txn, (index + offset) as isize, item)
     }
 
     /// Inserts a given range of `items` into this `YArray` instance, starting at given `index`.
+    /// Negative indexes are counted from the end of the array. When `items` is an integrated
+    /// `YArray` or `YText`, its contents are copied directly, with nested shared types inserted as
+    /// detached copies.
     pub fn insert_range(
         &mut self,
         txn: &mut YTransaction,
-        index: u32,
+        index: isize,
         items: PyObject,
     ) -> PyResult<()> {
-        txn.transact(|txn| self._insert_range(txn, index, items))?
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._insert_range(txn, index, items))
+        })?
     }
 
     fn _insert_range(
         &mut self,
         txn: &mut YTransactionInner,
-        index: u32,
+        index: isize,
         items: PyObject,
     ) -> PyResult<()> {
-        let items = Self::py_iter(items)?;
+        let index = insert_position(index, self._len(txn), "an array")?;
+        let items = self.source_items(txn, items)?;
         match &mut self.0 {
-            SharedType::Integrated(array) if array.len(txn) >= index => {
+            SharedType::Integrated(array) => {
                 Self::insert_multiple_at(&array.inner, txn, array.doc.clone(), index, items)?;
-                Ok(())
             }
-            SharedType::Prelim(vec) if vec.len() >= index as usize => {
-                let mut j = index;
-                for el in items {
-                    vec.insert(j as usize, el);
-                    j += 1;
-                }
-                Ok(())
+            SharedType::Prelim(vec) => {
+                let index = index as usize;
+                vec.splice(index..index, items);
             }
-            _ => Err(PyIndexError::default_message()),
         }
+        Ok(())
     }
 
-    /// Appends a range of `items` at the end of this `YArray` instance.
+    /// Appends a range of `items` at the end of this `YArray` instance. Like with `insert_range`,
+    /// nested shared types of an integrated `YArray` source are copied rather than moved.
     pub fn extend(&mut self, txn: &mut YTransaction, items: PyObject) -> PyResult<()> {
-        txn.transact(|txn| self._extend(txn, items))?
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._extend(txn, items))
+        })?
     }
     fn _extend(&mut self, txn: &mut YTransactionInner, items: PyObject) -> PyResult<()> {
-        let index = self._len(txn) as u32;
+        let index = self._len(txn) as isize;
         self._insert_range(txn, index, items)
     }
 
-    /// Adds a single item to the end of the array
-    pub fn append(&mut self, txn: &mut YTransaction, item: PyObject) -> PyResult<()> {
-        txn.transact(|txn| self._append(txn, item))
+    /// Adds a single item to the end of the array. If `item` is a preliminary shared type, it's
+    /// returned once integrated, so that it can be edited further. Otherwise `None` is returned.
+    pub fn append(&mut self, txn: &mut YTransaction, item: PyObject) -> PyResult<PyObject> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._append(txn, item.clone()))
+        })??;
+        Ok(Python::with_gil(|py| integrated_or_none(py, &item)))
     }
 
-    fn _append(&mut self, txn: &mut YTransactionInner, item: PyObject) {
+    fn _append(&mut self, txn: &mut YTransactionInner, item: PyObject) -> PyResult<()> {
         match &mut self.0 {
             SharedType::Integrated(array) => {
+                guard_prelim_item(&item)?;
                 array.push_back(txn, PyObjectWrapper::new(item, array.doc.clone()));
             }
             SharedType::Prelim(vec) => vec.push(item),
         }
+        Ok(())
     }
     /// Removes the element that the given index from the list.
     pub fn delete(&mut self, txn: &mut YTransaction, index: u32) -> PyResult<()> {
-        txn.transact(|txn| self._delete(txn, index))?
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._delete(txn, index))
+        })?
     }
 
     fn _delete(&mut self, txn: &mut YTransactionInner, index: u32) -> PyResult<()> {
@@ -214,6 +385,60 @@ impl YArray {
         }
     }
 
+    /// Removes the first element equal to `value`, as compared by Python `==`, the same way as
+    /// `list.remove` does. Raises `ValueError` if there's no such element.
+    pub fn remove(&mut self, txn: &mut YTransaction, value: PyObject) -> PyResult<()> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._remove(txn, value))
+        })?
+    }
+
+    fn _remove(&mut self, txn: &mut YTransactionInner, value: PyObject) -> PyResult<()> {
+        let index = Python::with_gil(|py| self.position(txn, value.as_ref(py)))?
+            .ok_or_else(|| PyValueError::new_err(format!("{value} is not in the YArray.")))?;
+        self._delete(txn, index)
+    }
+
+    /// Removes the element at the given `index` (the last one by default) and returns it.
+    /// Negative indexes are counted from the end of the array. Removed shared types can't be read
+    /// anymore, so they're returned as snapshots: lists, dictionaries or strings, like with
+    /// `materialize` set on `first` and `last`.
+    pub fn pop(&mut self, txn: &mut YTransaction, index: Option<isize>) -> PyResult<PyObject> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._pop(txn, index.unwrap_or(-1)))
+        })?
+    }
+
+    fn _pop(&mut self, txn: &mut YTransactionInner, index: isize) -> PyResult<PyObject> {
+        let len = self._len(txn);
+        if len == 0 {
+            return Err(PyIndexError::new_err("Cannot pop from an empty array."));
+        }
+        let index = if index < 0 {
+            len as isize + index
+        } else {
+            index
+        };
+        if !(0..len as isize).contains(&index) {
+            return Err(PyIndexError::default_message());
+        }
+        let index = index as u32;
+        match &mut self.0 {
+            SharedType::Integrated(v) => {
+                let value = v.get(txn, index).unwrap();
+                let value =
+                    Python::with_gil(|py| value_into_snapshot(&*txn, value, v.doc.clone(), py))?;
+                v.remove(txn, index);
+                Ok(value)
+            }
+            SharedType::Prelim(v) => Ok(v.remove(index as usize)),
+        }
+    }
+
     /// Deletes a range of items of given `length` from current `YArray` instance,
     /// starting from given `index`.
     pub fn delete_range(
@@ -222,7 +447,11 @@ impl YArray {
         index: u32,
         length: u32,
     ) -> PyResult<()> {
-        txn.transact(|txn| self._delete_range(txn, index, length))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._delete_range(txn, index, length))
+        })?
     }
 
     fn _delete_range(&mut self, txn: &mut YTransactionInner, index: u32, length: u32) {
@@ -234,18 +463,82 @@ impl YArray {
         }
     }
 
-    /// Moves the element from the index source to target.
+    /// Removes `delete_count` elements starting at a given `index` and inserts a range of `items`
+    /// in their place, returning the removed elements as a list, with shared types among them turned
+    /// into snapshots the same way `pop` does. Like JavaScript's `Array.splice`,
+    /// negative indexes are counted from the end of the array and `delete_count` is clamped to the
+    /// number of elements following `index`. Both operations happen within the same transaction.
+    pub fn splice(
+        &mut self,
+        txn: &mut YTransaction,
+        index: isize,
+        delete_count: u32,
+        items: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._splice(txn, index, delete_count, items))
+        })?
+    }
+
+    fn _splice(
+        &mut self,
+        txn: &mut YTransactionInner,
+        index: isize,
+        delete_count: u32,
+        items: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        let len = self._len(txn);
+        let index = insert_position(index, len, "an array")?;
+        let delete_count = delete_count.min(len as u32 - index);
+        let items = match items {
+            Some(items) => Self::py_iter(items)?,
+            None => Vec::default(),
+        };
+        Python::with_gil(|py| match &mut self.0 {
+            SharedType::Integrated(array) => {
+                let removed: Vec<PyObject> = array
+                    .inner
+                    .iter(txn)
+                    .skip(index as usize)
+                    .take(delete_count as usize)
+                    .map(|value| value_into_snapshot(&*txn, value, array.doc.clone(), py))
+                    .collect::<PyResult<_>>()?;
+                if delete_count > 0 {
+                    array.remove_range(txn, index, delete_count);
+                }
+                Self::insert_multiple_at(&array.inner, txn, array.doc.clone(), index, items)?;
+                Ok(removed.into_py(py))
+            }
+            SharedType::Prelim(vec) => {
+                let (start, end) = (index as usize, (index + delete_count) as usize);
+                let removed: Vec<PyObject> = vec.splice(start..end, items).collect();
+                Ok(removed.into_py(py))
+            }
+        })
+    }
+
+    /// Moves the element found at `source` index in front of the element found at `target` index.
+    /// Both indexes refer to the state of the array before the move, so moving an element to
+    /// either `source` or `source + 1` leaves the array unchanged, while moving it to the length of
+    /// the array places it at the end. Preliminary and integrated arrays follow the same semantics.
     pub fn move_to(&mut self, txn: &mut YTransaction, source: u32, target: u32) -> PyResult<()> {
-        txn.transact(|txn| self._move_to(txn, source, target))?
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._move_to(txn, source, target))
+        })?
     }
 
     fn _move_to(&mut self, txn: &mut YTransactionInner, source: u32, target: u32) -> PyResult<()> {
+        let len = self._len(txn) as u32;
+        if source >= len || target > len {
+            return Err(PyIndexError::default_message());
+        }
         match &mut self.0 {
-            SharedType::Integrated(v) => {
-                v.move_to(txn, source, target);
-                Ok(())
-            }
-            SharedType::Prelim(v) if source < v.len() as u32 && target < v.len() as u32 => {
+            SharedType::Integrated(v) => v.move_to(txn, source, target),
+            SharedType::Prelim(v) => {
                 if source < target {
                     let el = v.remove(source as usize);
                     v.insert((target - 1) as usize, el);
@@ -253,10 +546,9 @@ impl YArray {
                     let el = v.remove(source as usize);
                     v.insert(target as usize, el);
                 }
-                Ok(())
             }
-            _ => Err(PyIndexError::default_message()),
         }
+        Ok(())
     }
 
     /// Moves all elements found within `start`..`end` indexes range (both side inclusive) into
@@ -283,7 +575,11 @@ impl YArray {
         end: u32,
         target: u32,
     ) -> PyResult<()> {
-        txn.transact(|txn| self._move_range_to(txn, start, end, target))?
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._move_range_to(txn, start, end, target))
+        })?
     }
 
     fn _move_range_to(
@@ -327,6 +623,63 @@ impl YArray {
         }
     }
 
+    /// Reverses the order of elements of this `YArray` in place. Elements of integrated arrays are
+    /// moved rather than deleted and inserted again, so they keep their identity.
+    pub fn reverse(&mut self, txn: &mut YTransaction) -> PyResult<()> {
+        txn.transact(|txn| self.0.guard_alive(&*txn).and_then(|_| self._reverse(txn)))?
+    }
+
+    fn _reverse(&mut self, txn: &mut YTransactionInner) -> PyResult<()> {
+        match &mut self.0 {
+            SharedType::Integrated(v) => {
+                let len = v.len(txn) as usize;
+                reorder(v, txn, (0..len).rev().collect());
+            }
+            SharedType::Prelim(v) => v.reverse(),
+        }
+        Ok(())
+    }
+
+    /// Sorts elements of this `YArray` in place, following the semantics of Python's `sorted`:
+    /// elements are compared using `<`, optionally applied to results of a `key` function, and the
+    /// sort is stable. Elements of integrated arrays are moved rather than deleted and inserted
+    /// again, so they keep their identity.
+    pub fn sort(
+        &mut self,
+        txn: &mut YTransaction,
+        key: Option<PyObject>,
+        reverse: Option<bool>,
+    ) -> PyResult<()> {
+        let reverse = reverse.unwrap_or(false);
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._sort(txn, key, reverse))
+        })?
+    }
+
+    fn _sort(
+        &mut self,
+        txn: &mut YTransactionInner,
+        key: Option<PyObject>,
+        reverse: bool,
+    ) -> PyResult<()> {
+        let values: Vec<PyObject> = match &self.0 {
+            SharedType::Integrated(v) => Python::with_gil(|py| {
+                v.iter(&*txn)
+                    .map(|value| value.with_doc_into_py(v.doc.clone(), py))
+                    .collect::<PyResult<_>>()
+            })?,
+            SharedType::Prelim(v) => v.clone(),
+        };
+        let order = Python::with_gil(|py| sort_order(py, &values, key, reverse))?;
+        match &mut self.0 {
+            SharedType::Integrated(v) => reorder(v, txn, order),
+            SharedType::Prelim(v) => *v = order.into_iter().map(|i| values[i].clone()).collect(),
+        }
+        Ok(())
+    }
+
     pub fn __getitem__(&self, index: Index) -> PyResult<PyObject> {
         // Apply index to the Array type
         match index {
@@ -335,6 +688,47 @@ impl YArray {
         }
     }
 
+    /// Returns the first element of this `YArray`, or `default` (`None` unless specified) if it's
+    /// empty. If `materialize` is set, a nested shared type is returned as a snapshot instead of
+    /// a live instance.
+    pub fn first(
+        &self,
+        default: Option<PyObject>,
+        materialize: Option<bool>,
+    ) -> PyResult<PyObject> {
+        Python::with_gil(|py| match self.boundary_element(false)? {
+            Some(value) => materialize_value(py, value, materialize.unwrap_or(false)),
+            None => Ok(default.unwrap_or_else(|| py.None())),
+        })
+    }
+
+    /// Returns the last element of this `YArray`, or `default` (`None` unless specified) if it's
+    /// empty. If `materialize` is set, a nested shared type is returned as a snapshot instead of
+    /// a live instance.
+    pub fn last(&self, default: Option<PyObject>, materialize: Option<bool>) -> PyResult<PyObject> {
+        Python::with_gil(|py| match self.boundary_element(true)? {
+            Some(value) => materialize_value(py, value, materialize.unwrap_or(false)),
+            None => Ok(default.unwrap_or_else(|| py.None())),
+        })
+    }
+
+    /// Returns a string tag describing the kind of value stored at a given `index`: `"map"`,
+    /// `"array"`, `"text"`, `"xml_element"`, `"xml_text"`, `"xml_fragment"`, `"doc"` or
+    /// `"primitive"` for any other value. Negative indexes are counted from the end of the array.
+    /// The value itself is not converted into a Python object.
+    pub fn type_of(&self, index: isize) -> PyResult<&'static str> {
+        let index = self.normalize_index(index);
+        let tag = match &self.0 {
+            SharedType::Integrated(v) => v
+                .with_transaction(|txn| v.get(txn, index))
+                .map(|value| value_type_tag(&value)),
+            SharedType::Prelim(v) => v
+                .get(index as usize)
+                .map(|value| Python::with_gil(|py| py_type_tag(value.as_ref(py)))),
+        };
+        tag.ok_or_else(PyIndexError::default_message)
+    }
+
     /// Returns an iterator that can be used to traverse over the values stored withing this
     /// instance of `YArray`.
     ///
@@ -350,59 +744,177 @@ impl YArray {
     ///     print(item)
     ///     
     /// ```
-    pub fn __iter__(&self) -> PyObject {
-        Python::with_gil(|py| {
-            let list: PyObject = match &self.0 {
-                SharedType::Integrated(arr) => {
-                    arr.with_transaction(|txn| arr.to_json(txn).into_py(py))
-                }
-                SharedType::Prelim(arr) => arr.clone().into_py(py),
-            };
-            let any = list.as_ref(py);
-            any.iter().unwrap().into_py(py)
-        })
+    pub fn __iter__(&self) -> YArrayIterator {
+        YArrayIterator::new(self)
     }
 
     /// Subscribes to all operations happening over this instance of `YArray`. All changes are
     /// batched and eventually triggered during transaction commit phase.
+    /// If `emit_initial` is set, `f` is called right away with an event inserting the current
+    /// contents of the array, before any change happens.
     /// Returns a `SubscriptionId` which can be used to cancel the callback with `unobserve`.
-    pub fn observe(&mut self, f: PyObject) -> PyResult<ShallowSubscription> {
+    pub fn observe(
+        &mut self,
+        f: PyObject,
+        emit_initial: Option<bool>,
+        weak: Option<bool>,
+    ) -> PyResult<ShallowSubscription> {
         match &mut self.0 {
             SharedType::Integrated(array) => {
+                if emit_initial.unwrap_or(false) {
+                    let event = YArrayEvent::initial(array);
+                    Python::with_gil(|py| f.call1(py, (event,)))?;
+                }
+                let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+                let callback = f.clone();
                 let doc = array.doc.clone();
                 let sub: SubscriptionId = array
                     .inner
                     .observe(move |txn, e| {
                         Python::with_gil(|py| {
                             let event = YArrayEvent::new(e, txn, doc.clone());
-                            if let Err(err) = f.call1(py, (event,)) {
+                            if let Err(err) =
+                                with_committing_transaction(&doc, txn, || f.call1(py, (event,)))
+                            {
+                                err.restore(py)
+                            }
+                        })
+                    })
+                    .into();
+                let subscription = ShallowSubscription::new(sub, array.inner.clone());
+                callback.cancel_with({
+                    let subscription = subscription.clone();
+                    move || subscription.unobserve()
+                });
+                Ok(subscription)
+            }
+            SharedType::Prelim(_) => Err(PreliminaryObservationException::default_message()),
+        }
+    }
+    /// Subscribes to changes overlapping a range of this `YArray` between `start` (inclusive) and
+    /// `end` (exclusive) indexes. The callback is only called for transactions which inserted or
+    /// removed elements within that range, and the `delta` of the events it receives only describes
+    /// these changes, preceded by a retain up to where they begin.
+    ///
+    /// The range moves along with the elements it spans: changes made in front of it shift it,
+    /// elements inserted within it extend it and removed ones shrink it. Elements inserted right at
+    /// the `start` of the range are considered to be within it, while those inserted at its `end`
+    /// are not.
+    pub fn observe_range(
+        &mut self,
+        start: u32,
+        end: u32,
+        f: PyObject,
+        weak: Option<bool>,
+    ) -> PyResult<ShallowSubscription> {
+        if start > end {
+            return Err(PyValueError::new_err(format!(
+                "Range start {start} is greater than its end {end}."
+            )));
+        }
+        match &mut self.0 {
+            SharedType::Integrated(array) => {
+                let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+                let callback = f.clone();
+                let doc = array.doc.clone();
+                let range = Cell::new((start, end));
+                let sub: SubscriptionId = array
+                    .inner
+                    .observe(move |txn, e| {
+                        let (delta, moved) = range_delta(e.delta(txn), range.get());
+                        range.set(moved);
+                        if delta.is_empty() {
+                            return;
+                        }
+                        Python::with_gil(|py| {
+                            let event =
+                                YArrayEvent::new(e, txn, doc.clone()).with_range_delta(delta);
+                            if let Err(err) =
+                                with_committing_transaction(&doc, txn, || f.call1(py, (event,)))
+                            {
                                 err.restore(py)
                             }
                         })
                     })
                     .into();
-                Ok(ShallowSubscription(sub))
+                let subscription = ShallowSubscription::new(sub, array.inner.clone());
+                callback.cancel_with({
+                    let subscription = subscription.clone();
+                    move || subscription.unobserve()
+                });
+                Ok(subscription)
             }
             SharedType::Prelim(_) => Err(PreliminaryObservationException::default_message()),
         }
     }
+
     /// Observes YArray events and events of all child elements.
-    pub fn observe_deep(&mut self, f: PyObject) -> PyResult<DeepSubscription> {
+    pub fn observe_deep(&mut self, f: PyObject, weak: Option<bool>) -> PyResult<DeepSubscription> {
         match &mut self.0 {
             SharedType::Integrated(array) => {
+                let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+                let callback = f.clone();
                 let doc = array.doc.clone();
                 let sub: SubscriptionId = array
                     .inner
                     .observe_deep(move |txn, events| {
                         Python::with_gil(|py| {
                             let events = events_into_py(txn, events, doc.clone());
-                            if let Err(err) = f.call1(py, (events,)) {
+                            if let Err(err) =
+                                with_committing_transaction(&doc, txn, || f.call1(py, (events,)))
+                            {
                                 err.restore(py)
                             }
                         })
                     })
                     .into();
-                Ok(DeepSubscription(sub))
+                let subscription = DeepSubscription::new(sub, array.inner.clone());
+                callback.cancel_with({
+                    let subscription = subscription.clone();
+                    move || subscription.unobserve()
+                });
+                Ok(subscription)
+            }
+            SharedType::Prelim(_) => Err(PreliminaryObservationException::default_message()),
+        }
+    }
+
+    /// Observes changes made to this `YArray` and all of its nested shared types. Instead of a list
+    /// of events, the callback receives a single dictionary per transaction, keyed by the
+    /// `/`-separated path of each changed shared type relative to this one. Each value describes
+    /// the changes with a `delta` and/or `keys` entry, following the format of the corresponding
+    /// event type. Returns a `SubscriptionId` which can be used to cancel the callback with
+    /// `unobserve`.
+    pub fn observe_changes(
+        &mut self,
+        f: PyObject,
+        weak: Option<bool>,
+    ) -> PyResult<DeepSubscription> {
+        match &mut self.0 {
+            SharedType::Integrated(array) => {
+                let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+                let callback = f.clone();
+                let doc = array.doc.clone();
+                let sub: SubscriptionId = array
+                    .inner
+                    .observe_deep(move |txn, events| {
+                        Python::with_gil(|py| {
+                            let result = with_committing_transaction(&doc, txn, || {
+                                let changes = events_into_changes(txn, events, doc.clone())?;
+                                f.call1(py, (changes,))
+                            });
+                            if let Err(err) = result {
+                                err.restore(py)
+                            }
+                        })
+                    })
+                    .into();
+                let subscription = DeepSubscription::new(sub, array.inner.clone());
+                callback.cancel_with({
+                    let subscription = subscription.clone();
+                    move || subscription.unobserve()
+                });
+                Ok(subscription)
             }
             SharedType::Prelim(_) => Err(PreliminaryObservationException::default_message()),
         }
@@ -413,8 +925,8 @@ impl YArray {
         match &mut self.0 {
             SharedType::Integrated(arr) => {
                 match subscription_id {
-                    SubId::Shallow(ShallowSubscription(id)) => arr.unobserve(id),
-                    SubId::Deep(DeepSubscription(id)) => arr.unobserve_deep(id),
+                    SubId::Shallow(ShallowSubscription(id, _)) => arr.unobserve(id),
+                    SubId::Deep(DeepSubscription(id, _)) => arr.unobserve_deep(id),
                 }
                 Ok(())
             }
@@ -424,15 +936,65 @@ impl YArray {
 }
 
 impl YArray {
+    /// Returns a number of elements stored within this instance of `YArray` using a provided
+    /// transaction.
+    fn _len(&self, txn: &TransactionMut) -> usize {
+        match &self.0 {
+            SharedType::Integrated(v) => v.len(txn) as usize,
+            SharedType::Prelim(v) => v.len(),
+        }
+    }
+
+    /// Returns the index of the first element equal to a given `value`, as compared by Python `==`.
+    fn position(&self, txn: &TransactionMut, value: &PyAny) -> PyResult<Option<u32>> {
+        match &self.0 {
+            SharedType::Integrated(array) => {
+                for (index, item) in array.iter(txn).enumerate() {
+                    let item = item.with_doc_into_py(array.doc.clone(), value.py())?;
+                    if value.eq(item)? {
+                        return Ok(Some(index as u32));
+                    }
+                }
+            }
+            SharedType::Prelim(vec) => {
+                for (index, item) in vec.iter().enumerate() {
+                    if value.eq(item)? {
+                        return Ok(Some(index as u32));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+
     /// Gets a single element from a YArray.
+    /// Returns either the first or the `last` element of this `YArray`, reading both the length
+    /// and the element within a single transaction.
+    fn boundary_element(&self, last: bool) -> PyResult<Option<PyObject>> {
+        match &self.0 {
+            SharedType::Integrated(v) => {
+                let value = v.with_transaction(|txn| match v.len(txn) {
+                    0 => None,
+                    len => v.get(txn, if last { len - 1 } else { 0 }),
+                });
+                value
+                    .map(|value| Python::with_gil(|py| value.with_doc_into_py(v.doc.clone(), py)))
+                    .transpose()
+            }
+            SharedType::Prelim(v) => Ok(if last {
+                v.last().cloned()
+            } else {
+                v.first().cloned()
+            }),
+        }
+    }
+
     fn get_element(&self, index: u32) -> PyResult<PyObject> {
         match &self.0 {
             SharedType::Integrated(v) => {
                 let value = v.with_transaction(|txn| v.get(txn, index));
                 if let Some(value) = value {
-                    Ok(Python::with_gil(|py| {
-                        value.with_doc_into_py(v.doc.clone(), py)
-                    }))
+                    Python::with_gil(|py| value.with_doc_into_py(v.doc.clone(), py))
                 } else {
                     Err(PyIndexError::default_message())
                 }
@@ -447,60 +1009,46 @@ impl YArray {
         }
     }
 
-    /// Creates a new YArray from a range of values specified in a PySlice
+    /// Returns a list of values from a range specified in a PySlice. Nested shared types of an
+    /// integrated array are returned as live instances, same as when accessed by a single index.
     fn get_range(&self, slice: &PySlice) -> PyResult<PyObject> {
         let PySliceIndices {
-            start, stop, step, ..
-        } = slice.indices(self.__len__().try_into().unwrap()).unwrap();
-        match &self.0 {
-            SharedType::Integrated(arr) => Python::with_gil(|py| {
-                arr.with_transaction(|txn| {
-                    if step < 0 {
-                        let step = step.unsigned_abs();
-                        let (start, stop) = ((stop + 1) as usize, (start + 1) as usize);
-                        let values: Vec<PyObject> = arr
-                            .inner
-                            .iter(txn)
-                            .enumerate()
-                            .skip(start)
-                            .step_by(step)
-                            .take_while(|(i, _)| i < &stop)
-                            .map(|(_, el)| el.with_doc_into_py(arr.doc.clone(), py))
-                            .collect();
-                        let values: Vec<PyObject> = values.into_iter().rev().collect();
-                        Ok(values.into_py(py))
-                    } else {
-                        let (start, stop, step) = (start as usize, stop as usize, step as usize);
-                        let values: Vec<PyObject> = arr
-                            .inner
-                            .iter(txn)
-                            .enumerate()
-                            .skip(start)
-                            .step_by(step)
-                            .take_while(|(i, _)| i < &stop)
-                            .map(|(_, el)| el.with_doc_into_py(arr.doc.clone(), py))
-                            .collect();
-                        Ok(values.into_py(py))
-                    }
-                })
-            }),
-            SharedType::Prelim(arr) => Python::with_gil(|py| {
-                if step < 0 {
-                    let step = step.unsigned_abs();
-                    let (start, stop) = ((stop + 1) as usize, (start + 1) as usize);
-                    let list =
-                        PyList::new(py, arr[start..stop].iter().rev().step_by(step).cloned());
-                    Ok(list.into())
-                } else {
-                    let step = step as usize;
-                    let (start, stop) = (start as usize, stop as usize);
-                    let list = PyList::new(py, arr[start..stop].iter().step_by(step).cloned());
-                    Ok(list.into())
-                }
-            }),
+            start,
+            step,
+            slicelength,
+            ..
+        } = slice.indices(self.__len__().try_into().unwrap())?;
+        if slicelength == 0 {
+            return Ok(Python::with_gil(|py| PyList::empty(py).into()));
         }
+        // Walk the selected elements in ascending order and reverse them afterwards if needed.
+        let (first, last) = (start, start + (slicelength - 1) * step);
+        let (first, last) = (first.min(last) as usize, first.max(last) as usize);
+        let step = step.unsigned_abs();
+        Python::with_gil(|py| {
+            let mut values: Vec<PyObject> = match &self.0 {
+                SharedType::Integrated(arr) => arr.with_transaction(|txn| {
+                    arr.inner
+                        .iter(txn)
+                        .skip(first)
+                        .take(last - first + 1)
+                        .step_by(step)
+                        .map(|el| el.with_doc_into_py(arr.doc.clone(), py))
+                        .collect::<PyResult<_>>()
+                })?,
+                SharedType::Prelim(arr) => {
+                    arr[first..=last].iter().step_by(step).cloned().collect()
+                }
+            };
+            if start as usize != first {
+                values.reverse();
+            }
+            Ok(values.into_py(py))
+        })
     }
 
+    /// Converts a (possibly negative) insertion `index` into a position within an array of a given
+    /// `len`. Valid positions range from `-len` to `len` (inclusive).
     fn normalize_index(&self, index: isize) -> u32 {
         if index < 0 {
             (self.__len__() as isize + index) as u32
@@ -541,6 +1089,7 @@ impl YArray {
                     iter.next_if(|element| matches!(element, Ok(CompatiblePyType::YType(_))))
                 {
                     if let CompatiblePyType::YType(y_type) = y_type? {
+                        guard_prelim(&y_type)?;
                         let wrapped = PyObjectWrapper::new(y_type.into(), doc.clone());
                         dst.insert(txn, index, wrapped);
                         index += 1
@@ -551,6 +1100,49 @@ impl YArray {
         })
     }
 
+    /// Collects the elements of a range of `items` to insert. Integrated `YArray` and `YText`
+    /// sources are read straight from their documents, with nested shared types turned into new
+    /// preliminary copies. Other sources are iterated over in Python.
+    fn source_items(&self, txn: &YTransactionInner, items: PyObject) -> PyResult<Vec<PyObject>> {
+        Python::with_gil(|py| match YPyType::try_from(items.as_ref(py)) {
+            Ok(YPyType::Array(cell)) => {
+                let source = match cell.try_borrow() {
+                    Ok(source) => Self::copy_integrated(txn, &source.0, py),
+                    // the only array already borrowed here is this one, extended with itself
+                    Err(_) => Self::copy_integrated(txn, &self.0, py),
+                };
+                source.unwrap_or_else(|| Self::py_iter(items.clone_ref(py)))
+            }
+            Ok(YPyType::Text(cell)) => match &cell.try_borrow()?.0 {
+                SharedType::Integrated(text) => Ok(text
+                    .with_transaction_from(txn, |t| text.inner.get_string(t))
+                    .chars()
+                    .map(|c| c.to_string().into_py(py))
+                    .collect()),
+                SharedType::Prelim(_) => Self::py_iter(items.clone_ref(py)),
+            },
+            _ => Self::py_iter(items.clone_ref(py)),
+        })
+    }
+
+    /// Copies the elements of an integrated `array`, returning `None` for preliminary ones.
+    fn copy_integrated(
+        txn: &YTransactionInner,
+        array: &SharedType<TypeWithDoc<ArrayRef>, Vec<PyObject>>,
+        py: Python,
+    ) -> Option<PyResult<Vec<PyObject>>> {
+        match array {
+            SharedType::Integrated(array) => Some(array.with_transaction_from(txn, |t| {
+                array
+                    .inner
+                    .iter(t)
+                    .map(|value| value_into_prelim(t, value, py))
+                    .collect()
+            })),
+            SharedType::Prelim(_) => None,
+        }
+    }
+
     fn py_iter(iterable: PyObject) -> PyResult<Vec<PyObject>> {
         Python::with_gil(|py| {
             iterable.as_ref(py).iter().and_then(|iterable| {
@@ -566,6 +1158,63 @@ impl YArray {
         })
     }
 }
+
+pub enum InnerYArrayIterator {
+    Integrated(TypeWithDoc<std::vec::IntoIter<Value>>),
+    Prelim(std::vec::IntoIter<PyObject>),
+}
+
+/// Iterator over the elements of a `YArray`. Elements are read within a single transaction once
+/// the iteration starts, since looking up each one by its index would take quadratic time, but
+/// they are only converted into Python objects as they are yielded. Nested shared types are
+/// yielded as live instances, while changes made to the array itself after the iteration has
+/// started are not seen by the iterator.
+#[pyclass(unsendable)]
+pub struct YArrayIterator(InnerYArrayIterator);
+
+impl YArrayIterator {
+    fn new(array: &YArray) -> Self {
+        match &array.0 {
+            SharedType::Integrated(arr) => {
+                let values: Vec<Value> = arr.with_transaction(|txn| arr.iter(txn).collect());
+                YArrayIterator(InnerYArrayIterator::Integrated(TypeWithDoc::new(
+                    values.into_iter(),
+                    arr.doc.clone(),
+                )))
+            }
+            SharedType::Prelim(arr) => {
+                YArrayIterator(InnerYArrayIterator::Prelim(arr.clone().into_iter()))
+            }
+        }
+    }
+}
+
+impl Iterator for YArrayIterator {
+    type Item = PyResult<PyObject>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            InnerYArrayIterator::Integrated(iter) => {
+                let value = iter.inner.next()?;
+                Some(Python::with_gil(|py| {
+                    value.with_doc_into_py(iter.doc.clone(), py)
+                }))
+            }
+            InnerYArrayIterator::Prelim(iter) => iter.next().map(Ok),
+        }
+    }
+}
+
+#[pymethods]
+impl YArrayIterator {
+    fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
+        slf
+    }
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<PyObject>> {
+        slf.next().transpose()
+    }
+}
+
 #[derive(FromPyObject)]
 pub enum Index<'a> {
     Int(isize),
@@ -580,6 +1229,9 @@ pub struct YArrayEvent {
     txn: *const TransactionMut<'static>,
     target: Option<PyObject>,
     delta: Option<PyObject>,
+    initial: Option<InitialState<Change>>,
+    /// Changes restricted to the range observed with `YArray.observe_range`.
+    range_delta: Option<Vec<Change>>,
 }
 
 impl YArrayEvent {
@@ -594,6 +1246,42 @@ impl YArrayEvent {
             txn,
             target: None,
             delta: None,
+            initial: None,
+            range_delta: None,
+        }
+    }
+
+    fn with_range_delta(mut self, delta: Vec<Change>) -> Self {
+        self.range_delta = Some(delta);
+        self
+    }
+
+    /// Creates an event inserting the current contents of a given `array`.
+    fn initial(array: &TypeWithDoc<ArrayRef>) -> Self {
+        let values: Vec<Value> = array.with_transaction(|txn| array.iter(txn).collect());
+        let delta = if values.is_empty() {
+            Vec::new()
+        } else {
+            vec![Change::Added(values)]
+        };
+        let target =
+            Python::with_gil(|py| array.inner.clone().with_doc(array.doc.clone()).into_py(py));
+        YArrayEvent {
+            inner: std::ptr::null(),
+            doc: array.doc.clone(),
+            txn: std::ptr::null(),
+            target: Some(target),
+            delta: None,
+            initial: Some(InitialState { delta }),
+            range_delta: None,
+        }
+    }
+
+    fn deltas(&self) -> &[Change] {
+        match (&self.initial, &self.range_delta) {
+            (Some(initial), _) => &initial.delta,
+            (None, Some(delta)) => delta,
+            (None, None) => self.inner().delta(self.txn()),
         }
     }
 
@@ -623,17 +1311,40 @@ impl YArrayEvent {
         }
     }
 
-    fn __repr__(&mut self) -> String {
+    fn __repr__(&mut self) -> PyResult<String> {
         let target = self.target();
-        let delta = self.delta();
+        let delta = self.delta()?;
         let path = self.path();
-        format!("YArrayEvent(target={target}, delta={delta}, path={path})")
+        Ok(format!(
+            "YArrayEvent(target={target}, delta={delta}, path={path})"
+        ))
     }
 
     /// Returns an array of keys and indexes creating a path from root type down to current instance
     /// of shared type (accessible via `target` getter).
     pub fn path(&self) -> PyObject {
-        Python::with_gil(|py| self.inner().path().into_py(py))
+        match &self.initial {
+            // events of shallow observers are relative to their target, so their path is empty
+            Some(_) => Python::with_gil(|py| PyList::empty(py).into()),
+            None => Python::with_gil(|py| self.inner().path().into_py(py)),
+        }
+    }
+
+    /// Returns the origin of the transaction which has made the changes, or `None` if it has no
+    /// origin, e.g. `apply_update(doc, update, origin="remote")` reports `"remote"`. Initial events
+    /// emitted by `observe(..., emit_initial=True)` have no origin.
+    #[getter]
+    pub fn origin(&self) -> PyObject {
+        Python::with_gil(|py| match &self.initial {
+            Some(_) => py.None(),
+            None => origin_into_py(self.txn().origin(), py),
+        })
+    }
+
+    /// Returns `"array"` - the kind of the `target` shared type, as reported by `type_of`.
+    #[getter]
+    pub fn kind(&self) -> &'static str {
+        "array"
     }
 
     /// Returns a list of text changes made over corresponding `YArray` collection within
@@ -643,18 +1354,118 @@ impl YArrayEvent {
     /// - { delete: number }
     /// - { retain: number }
     #[getter]
-    pub fn delta(&mut self) -> PyObject {
+    pub fn delta(&mut self) -> PyResult<PyObject> {
         if let Some(delta) = &self.delta {
-            delta.clone()
+            Ok(delta.clone())
         } else {
             let delta: PyObject = Python::with_gil(|py| {
-                let delta = self.inner().delta(self.txn()).iter().map(|change| {
-                    Python::with_gil(|py| change.with_doc_into_py(self.doc.clone(), py))
-                });
-                PyList::new(py, delta).into()
-            });
+                let delta = self
+                    .deltas()
+                    .iter()
+                    .map(|change| change.with_doc_into_py(self.doc.clone(), py))
+                    .collect::<PyResult<Vec<_>>>()?;
+                PyResult::Ok(PyList::new(py, delta).into())
+            })?;
             self.delta = Some(delta.clone());
-            delta
+            Ok(delta)
+        }
+    }
+}
+
+/// Restricts an array `delta` to the changes overlapping a given `range` of indexes (from before
+/// the changes were made). Returns these changes, positioned within the array as it is after the
+/// changes, together with the range moved accordingly.
+fn range_delta(delta: &[Change], range: (u32, u32)) -> (Vec<Change>, (u32, u32)) {
+    let (start, end) = range;
+    let (mut moved_start, mut moved_end) = range;
+    let mut result = Vec::new();
+    // positions in the array from before and after the changes, and the position in the latter
+    // up to which the result describes it
+    let (mut old, mut new, mut described) = (0, 0, 0);
+    for change in delta {
+        match change {
+            Change::Retain(len) => {
+                old += len;
+                new += len;
+            }
+            Change::Added(values) => {
+                let len = values.len() as u32;
+                if old < start {
+                    moved_start += len;
+                    moved_end += len;
+                } else if old < end {
+                    moved_end += len;
+                    if new > described {
+                        result.push(Change::Retain(new - described));
+                    }
+                    result.push(change.clone());
+                    described = new + len;
+                }
+                new += len;
+            }
+            Change::Removed(len) => {
+                let before = start.min(old + len).saturating_sub(old);
+                let within = end.min(old + len).saturating_sub(start.max(old));
+                moved_start -= before;
+                moved_end -= before + within;
+                if within > 0 {
+                    if new > described {
+                        result.push(Change::Retain(new - described));
+                    }
+                    result.push(Change::Removed(within));
+                    described = new;
+                }
+                old += len;
+            }
+        }
+    }
+    (result, (moved_start, moved_end))
+}
+
+/// Checks that an `item` about to be inserted isn't an already integrated shared type.
+fn guard_prelim_item(item: &PyObject) -> PyResult<()> {
+    Python::with_gil(|py| match item.extract::<CompatiblePyType>(py) {
+        Ok(CompatiblePyType::YType(y_type)) => guard_prelim(&y_type),
+        _ => Ok(()),
+    })
+}
+
+/// Returns indexes of `values` in the order in which Python's `sorted` would arrange them when
+/// called with the same `key` and `reverse` arguments. The indexes are sorted by `sorted` itself,
+/// so comparisons which fail or don't form a total order (e.g. involving `NaN`) behave the same.
+fn sort_order(
+    py: Python,
+    values: &[PyObject],
+    key: Option<PyObject>,
+    reverse: bool,
+) -> PyResult<Vec<usize>> {
+    let keys = match key {
+        Some(key) => values
+            .iter()
+            .map(|value| key.call1(py, (value,)))
+            .collect::<PyResult<Vec<_>>>()?,
+        None => values.to_vec(),
+    };
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("key", PyList::new(py, keys).getattr("__getitem__")?)?;
+    kwargs.set_item("reverse", reverse)?;
+    let indexes: Vec<usize> = (0..values.len()).collect();
+    py.import("builtins")?
+        .getattr("sorted")?
+        .call((indexes,), Some(kwargs))?
+        .extract()
+}
+
+/// Rearranges elements of an integrated `array`, so that the element found at index `order[i]`
+/// ends up at index `i`. Only the elements which are out of place are moved.
+fn reorder(array: &ArrayRef, txn: &mut TransactionMut, order: Vec<usize>) {
+    let mut current: Vec<usize> = (0..order.len()).collect();
+    for (target, source) in order.into_iter().enumerate() {
+        let index = current.iter().position(|&i| i == source).unwrap();
+        if index != target {
+            array.move_to(txn, index as u32, target as u32);
+            let moved = current.remove(index);
+            current.insert(target, moved);
         }
     }
 }
@@ -1,26 +1,59 @@
 use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::rc::Weak;
 
+use crate::compaction::{collect_garbage, compact};
+use crate::json_builder::JsonBuilder;
+use crate::partial_update::filter_roots;
+use crate::shared_types::DeepSubscription;
+use crate::shared_types::{has_defined_kind, root_branch, typed_roots, CompatiblePyType, YPyType};
+use crate::type_conversions::{
+    event_changes, events_into_py, guard_prelim, value_into_json, PyObjectWrapper, WithDocToPython,
+};
 use crate::y_array::YArray;
 use crate::y_map::YMap;
 use crate::y_text::YText;
+use crate::y_transaction::EncodingException;
+use crate::y_transaction::PendingUpdate;
+use crate::y_transaction::TransactionError;
 use crate::y_transaction::YTransaction;
 use crate::y_transaction::YTransactionInner;
+use crate::y_transaction::{decode_state_vector, origin_into_py};
 use crate::y_xml::YXmlElement;
 use crate::y_xml::YXmlFragment;
 use crate::y_xml::YXmlText;
+use lib0::any::Any;
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
+use pyo3::types::IntoPyDict;
 use pyo3::types::PyBytes;
+use pyo3::types::PyList;
 use pyo3::types::PyTuple;
-use yrs::updates::encoder::Encode;
+use yrs::block::Prelim;
+use yrs::types::map::MapPrelim;
+use yrs::types::BranchPtr;
+use yrs::types::{DeepObservable, Events, Value};
+use yrs::types::{
+    TYPE_REFS_ARRAY, TYPE_REFS_MAP, TYPE_REFS_TEXT, TYPE_REFS_XML_ELEMENT, TYPE_REFS_XML_FRAGMENT,
+    TYPE_REFS_XML_TEXT,
+};
+use yrs::updates::decoder::Decode;
+use yrs::updates::encoder::{Encode, Encoder, EncoderV1};
+use yrs::Array;
 use yrs::Doc;
+use yrs::GetString;
+use yrs::Map;
 use yrs::OffsetKind;
 use yrs::Options;
+use yrs::ReadTxn;
+use yrs::Snapshot;
+use yrs::StateVector;
 use yrs::SubscriptionId;
 use yrs::Transact;
 use yrs::TransactionCleanupEvent;
 use yrs::TransactionMut;
+use yrs::Update;
 
 pub trait WithDoc<T> {
     fn with_doc(self, doc: Rc<RefCell<YDocInner>>) -> T;
@@ -30,11 +63,16 @@ pub trait WithTransaction {
 
     fn with_transaction<F, R>(&self, f: F) -> R
     where
-        F: FnOnce(&YTransactionInner) -> R,
+        F: FnOnce(&TransactionMut<'static>) -> R,
     {
+        let doc = self.get_doc();
+        let committing = doc.borrow().committing;
+        if let Some(txn) = committing {
+            return f(unsafe { &*txn });
+        }
         let txn = self.get_transaction();
-        let mut txn = txn.borrow_mut();
-        f(&mut txn)
+        let txn = txn.borrow();
+        f(&txn)
     }
 
     fn get_transaction(&self) -> Rc<RefCell<YTransactionInner>> {
@@ -44,49 +82,117 @@ pub trait WithTransaction {
     }
 }
 
+thread_local! {
+    /// A document standing in for the parent of root documents being destroyed, as `Doc::destroy`
+    /// requires a parent transaction even though it's never written to for them.
+    static DETACHED: Doc = Doc::new();
+}
+
+/// Error message raised when a transaction can't be started, because another one is active.
+const TRANSACTION_STARTED: &str =
+    "Transaction already started! Commit it first or, if it has been \
+     leaked, release it with `YDoc.abort_transaction()`.";
+
 pub struct YDocInner {
     doc: Doc,
     txn: Option<Weak<RefCell<YTransactionInner>>>,
+    /// Transaction which is being committed while its observer callbacks are running.
+    committing: Option<*const TransactionMut<'static>>,
+    /// Updates applied to this document which couldn't be integrated yet.
+    pending: PendingUpdate,
 }
 
 impl YDocInner {
+    /// Returns a transaction which is currently being committed, if any. Reads performed from
+    /// within observer callbacks must go through it, as the document store is already borrowed.
+    pub fn committing_transaction(&self) -> Option<*const TransactionMut<'static>> {
+        self.committing
+    }
+
+    /// Returns a transaction of this document which hasn't been committed yet, if any. A transaction
+    /// that is currently borrowed (e.g. because it's in the middle of committing) is considered
+    /// to be still in flight.
+    fn pending_transaction(&self) -> Option<Rc<RefCell<YTransactionInner>>> {
+        let txn = self.txn.as_ref()?.upgrade()?;
+        let in_flight = txn.try_borrow().map_or(true, |txn| !txn.committed);
+        in_flight.then_some(txn)
+    }
+
+    /// Checks if a given `txn` is the transaction currently open on this document.
+    pub fn owns_transaction(&self, txn: &YTransactionInner) -> bool {
+        self.txn
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .map_or(false, |current| std::ptr::eq(current.as_ptr(), txn))
+    }
+
     pub fn has_transaction(&self) -> bool {
-        if let Some(weak_txn) = &self.txn {
-            if let Some(txn) = weak_txn.upgrade() {
-                return !txn.borrow().committed;
-            }
-        }
-        false
+        self.committing.is_some() || self.pending_transaction().is_some()
     }
 
     pub fn begin_transaction(&mut self) -> Rc<RefCell<YTransactionInner>> {
-        // Check if we think we still have a transaction
-        if let Some(weak_txn) = &self.txn {
-            // And if it's actually around
-            if let Some(txn) = weak_txn.upgrade() {
-                if !txn.borrow().committed {
-                    return txn;
-                }
-            }
+        // Reuse a transaction which is still around
+        if let Some(txn) = self.pending_transaction() {
+            return txn;
         }
         // HACK: get rid of lifetime
         let txn = unsafe {
             std::mem::transmute::<TransactionMut, TransactionMut<'static>>(self.doc.transact_mut())
         };
-        let txn = YTransactionInner::new(txn);
+        self.track_transaction(txn)
+    }
+
+    /// Starts a new transaction tagged with a given `origin`, which can be read by observers of
+    /// the changes made within it. Unlike `begin_transaction`, a transaction which is still around
+    /// can't be reused, since its origin has already been set.
+    pub fn begin_transaction_with_origin(
+        &mut self,
+        origin: &str,
+    ) -> PyResult<Rc<RefCell<YTransactionInner>>> {
+        if self.has_transaction() {
+            return Err(TransactionError::new_err(TRANSACTION_STARTED));
+        }
+        // HACK: get rid of lifetime
+        let txn = unsafe {
+            std::mem::transmute::<TransactionMut, TransactionMut<'static>>(
+                self.doc.transact_mut_with(origin),
+            )
+        };
+        Ok(self.track_transaction(txn))
+    }
+
+    /// Registers `txn` as the transaction currently open on this document. Its lifetime must be
+    /// erased by the caller, as it would otherwise keep the document borrowed.
+    fn track_transaction(
+        &mut self,
+        txn: TransactionMut<'static>,
+    ) -> Rc<RefCell<YTransactionInner>> {
+        let txn = YTransactionInner::new(txn, self.pending.clone());
         let txn = Rc::new(RefCell::new(txn));
         self.txn = Some(Rc::downgrade(&txn));
         txn
     }
 
-    pub fn commit_transaction(&mut self) {
-        if let Some(weak_txn) = &self.txn {
-            if let Some(txn) = weak_txn.upgrade() {
-                let mut txn = txn.borrow_mut();
-                txn.commit();
-            }
+    /// Detaches a pending transaction from this document, so that it can be committed without
+    /// keeping the document borrowed.
+    pub fn take_transaction(&mut self) -> Option<Rc<RefCell<YTransactionInner>>> {
+        self.txn.take().and_then(|txn| txn.upgrade())
+    }
+
+    /// Returns the unit in which lengths and offsets of texts stored in this document are measured.
+    pub fn offset_kind(&self) -> OffsetKind {
+        self.doc.options().offset_kind
+    }
+
+    /// Returns an error if this document garbage collects deleted blocks, which makes it
+    /// impossible to restore its past states from snapshots.
+    pub fn guard_snapshots(&self) -> PyResult<()> {
+        if !self.doc.options().skip_gc {
+            return Err(PyValueError::new_err(
+                "Snapshots require a document created with `skip_gc=True`.",
+            ));
         }
-        self.txn = None;
+        Ok(())
     }
 
     pub fn transact_mut<F, R>(&self, f: F) -> R
@@ -97,11 +203,33 @@ impl YDocInner {
         let txn = unsafe {
             std::mem::transmute::<TransactionMut, TransactionMut<'static>>(self.doc.transact_mut())
         };
-        let mut txn = YTransactionInner::new(txn);
+        let mut txn = YTransactionInner::new(txn, self.pending.clone());
         f(&mut txn)
     }
 }
 
+/// Runs `f` - usually a Python observer callback - with `txn` registered as the transaction being
+/// committed by a given `doc`. While it's running, reads performed by shared types of that
+/// document reuse `txn` instead of trying to start a new transaction.
+pub(crate) fn with_committing_transaction<F, R>(
+    doc: &Rc<RefCell<YDocInner>>,
+    txn: &TransactionMut,
+    f: F,
+) -> R
+where
+    F: FnOnce() -> R,
+{
+    // HACK: get rid of lifetime
+    let txn = unsafe { std::mem::transmute::<&TransactionMut, &TransactionMut<'static>>(txn) };
+    let previous = doc
+        .borrow_mut()
+        .committing
+        .replace(txn as *const TransactionMut);
+    let result = f();
+    doc.borrow_mut().committing = previous;
+    result
+}
+
 /// A Ypy document type. Documents are most important units of collaborative resources management.
 /// All shared collections live within a scope of their corresponding documents. All updates are
 /// generated on per document basis (rather than individual shared type). All operations on shared
@@ -126,14 +254,43 @@ impl YDocInner {
 pub struct YDoc(Rc<RefCell<YDocInner>>);
 
 impl YDoc {
+    /// Commits a pending transaction of this document, if there's any. The document is not
+    /// borrowed while committing, so that observer callbacks can read from it.
+    fn commit_transaction(&self) {
+        let batched = self
+            .0
+            .borrow()
+            .pending_transaction()
+            .map_or(false, |txn| txn.borrow().batches > 0);
+        if batched {
+            return;
+        }
+        let txn = self.0.borrow_mut().take_transaction();
+        if let Some(txn) = txn {
+            txn.borrow_mut().commit();
+        }
+    }
+
     pub fn guard_store(&self) -> PyResult<()> {
         if self.0.borrow().has_transaction() {
-            return Err(pyo3::exceptions::PyAssertionError::new_err(
-                "Transaction already started!",
-            ));
+            return Err(TransactionError::new_err(TRANSACTION_STARTED));
         }
         Ok(())
     }
+
+    /// Checks that a root type called `name`, if it exists, is of an `expected` kind when `strict`
+    /// is set. Roots, which haven't got their kind defined yet, can be fetched as any kind.
+    fn guard_root_type(&self, name: &str, expected: &str, strict: Option<bool>) -> PyResult<()> {
+        if !strict.unwrap_or(false) {
+            return Ok(());
+        }
+        match self.root_type(name) {
+            Some(kind) if kind != expected && kind != "undefined" => Err(PyTypeError::new_err(
+                format!("Root type '{name}' is of kind '{kind}', not '{expected}'."),
+            )),
+            _ => Ok(()),
+        }
+    }
 }
 
 #[pymethods]
@@ -141,11 +298,17 @@ impl YDoc {
     /// Creates a new Ypy document. If `client_id` parameter was passed it will be used as this
     /// document globally unique identifier (it's up to caller to ensure that requirement).
     /// Otherwise it will be assigned a randomly generated number.
+    ///
+    /// `auto_load` and `should_load` are the subdocument loading flags used by providers: when
+    /// `auto_load` is set, peers load this document as soon as it's shared with them, while
+    /// `should_load` tells whether it should be synchronized now.
     #[new]
     pub fn new(
         client_id: Option<u64>,
         offset_kind: Option<String>,
         skip_gc: Option<bool>,
+        auto_load: Option<bool>,
+        should_load: Option<bool>,
     ) -> PyResult<Self> {
         let mut options = Options::default();
         if let Some(client_id) = client_id {
@@ -170,20 +333,78 @@ impl YDoc {
             options.skip_gc = skip_gc;
         }
 
+        if let Some(auto_load) = auto_load {
+            options.auto_load = auto_load;
+        }
+
+        if let Some(should_load) = should_load {
+            options.should_load = should_load;
+        }
+
         let inner = YDocInner {
             doc: Doc::with_options(options),
             txn: None,
+            committing: None,
+            pending: PendingUpdate::default(),
         };
 
         Ok(YDoc(Rc::new(RefCell::new(inner))))
     }
 
+    /// Creates a new document configured with given options (see `YDoc()`) and applies an
+    /// `update` encoded using lib0 v1 encoding to it, e.g. to load a persisted document. Raises an
+    /// `EncodingException` if the update is malformed. Parts of the update depending on changes it
+    /// doesn't contain are kept pending (see `pending_update`).
+    #[staticmethod]
+    pub fn from_update(
+        update: Vec<u8>,
+        client_id: Option<u64>,
+        offset_kind: Option<String>,
+        skip_gc: Option<bool>,
+        auto_load: Option<bool>,
+        should_load: Option<bool>,
+    ) -> PyResult<Self> {
+        let doc = Self::new(client_id, offset_kind, skip_gc, auto_load, should_load)?;
+        let txn = doc.0.borrow_mut().begin_transaction();
+        YTransaction::new(txn).apply_v1(update)?;
+        doc.commit_transaction();
+        Ok(doc)
+    }
+
     /// Gets globally unique identifier of this `YDoc` instance.
     #[getter]
     pub fn client_id(&self) -> u64 {
         self.0.borrow().doc.client_id()
     }
 
+    /// Returns a set of ids of all clients, whose changes (including deleted ones) are present in
+    /// this document's store, as recorded by its state vector. Ids of peers, whose updates are
+    /// still pending integration, are not included.
+    pub fn clients(&self) -> HashSet<u64> {
+        let state = self.with_transaction(|txn| txn.state_vector());
+        state.iter().map(|(&client, _)| client).collect()
+    }
+
+    /// Returns true if this document is loaded automatically by peers it's shared with as a
+    /// subdocument.
+    #[getter]
+    pub fn auto_load(&self) -> bool {
+        self.0.borrow().doc.options().auto_load
+    }
+
+    /// Returns true if this document should be synchronized by providers now.
+    #[getter]
+    pub fn should_load(&self) -> bool {
+        self.0.borrow().doc.options().should_load
+    }
+
+    /// Returns true if this document has a transaction that hasn't been committed yet. While it's
+    /// active, root types can't be retrieved from the document.
+    #[getter]
+    pub fn has_pending_transaction(&self) -> bool {
+        self.0.borrow().has_transaction()
+    }
+
     /// Returns a new transaction for this document. Ypy shared data types execute their
     /// operations in a context of a given transaction. Each document can have only one active
     /// transaction at the time - subsequent attempts will cause exception to be thrown.
@@ -204,15 +425,69 @@ impl YDoc {
         YTransaction::new(self.0.borrow_mut().begin_transaction())
     }
 
-    pub fn transact(&mut self, callback: PyObject) -> PyResult<PyObject> {
-        let txn = YTransaction::new(self.0.borrow_mut().begin_transaction());
+    /// Returns a context manager, which holds a single transaction open for the whole `with`
+    /// block and commits it - firing observer callbacks once for all the changes made within the
+    /// block - when the block exits. The transaction is passed to the block as its target.
+    ///
+    /// Batches are reentrant: a batch entered within another one (or while a transaction is
+    /// already open) shares its transaction and leaves committing it to the outermost batch (or
+    /// whoever has opened the transaction).
+    /// Transactions returned by `begin_transaction` and used by `transact` within a batch are the
+    /// batched transaction too, so their commits are deferred until the batch exits.
+    ///
+    /// Example:
+    ///
+    /// ```python
+    /// from y_py import YDoc
+    ///
+    /// doc = YDoc()
+    /// array = doc.get_array('array')
+    /// with doc.batch() as txn:
+    ///     for i in range(100):
+    ///         with doc.begin_transaction() as inner:
+    ///             array.append(inner, i)
+    /// # observers of `array` are called once here
+    /// ```
+    pub fn batch(&self) -> YBatch {
+        YBatch {
+            doc: self.0.clone(),
+            txn: None,
+            owner: false,
+        }
+    }
+
+    /// Forcibly releases a transaction of this document which hasn't been committed, e.g. because
+    /// it has been leaked by a code path which never committed it. yrs doesn't support rollbacks,
+    /// so changes made within that transaction are committed. Any further use of the released
+    /// transaction object raises an error.
+    ///
+    /// Returns `True` if there was a transaction to release.
+    pub fn abort_transaction(&self) -> bool {
+        let txn = self.0.borrow_mut().take_transaction();
+        match txn {
+            Some(txn) if !txn.borrow().committed => {
+                txn.borrow_mut().commit();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Runs a `callback` within a new transaction, which is committed once the callback returns.
+    /// If an `origin` is provided, the transaction is tagged with it, so that the callback (see
+    /// `YTransaction.origin`) and observers of the changes it makes can read it.
+    pub fn transact(&mut self, callback: PyObject, origin: Option<&str>) -> PyResult<PyObject> {
+        let txn = match origin {
+            Some(origin) => self.0.borrow_mut().begin_transaction_with_origin(origin)?,
+            None => self.0.borrow_mut().begin_transaction(),
+        };
+        let txn = YTransaction::new(txn);
         let result = Python::with_gil(|py| {
             let args = PyTuple::new(py, vec![txn.into_py(py)]);
             callback.call(py, args, None)
         });
         // Make transaction commit after callback returns
-        let mut doc = self.0.borrow_mut();
-        doc.commit_transaction();
+        self.commit_transaction();
         result
     }
 
@@ -222,9 +497,10 @@ impl YDoc {
     /// If there was no instance with this name before, it will be created and then returned.
     ///
     /// If there was an instance with this name, but it was of different type, it will be projected
-    /// onto `YMap` instance.
-    pub fn get_map(&mut self, name: &str) -> PyResult<YMap> {
+    /// onto `YMap` instance, unless `strict` is set, in which case a `TypeError` is raised instead.
+    pub fn get_map(&mut self, name: &str, strict: Option<bool>) -> PyResult<YMap> {
         self.guard_store()?;
+        self.guard_root_type(name, "map", strict)?;
         Ok(self
             .0
             .borrow()
@@ -239,9 +515,10 @@ impl YDoc {
     /// If there was no instance with this name before, it will be created and then returned.
     ///
     /// If there was an instance with this name, but it was of different type, it will be projected
-    /// onto `YXmlElement` instance.
-    pub fn get_xml_element(&mut self, name: &str) -> PyResult<YXmlElement> {
+    /// onto `YXmlElement` instance, unless `strict` is set, in which case a `TypeError` is raised instead.
+    pub fn get_xml_element(&mut self, name: &str, strict: Option<bool>) -> PyResult<YXmlElement> {
         self.guard_store()?;
+        self.guard_root_type(name, "xml_element", strict)?;
         Ok(self
             .0
             .borrow()
@@ -256,9 +533,10 @@ impl YDoc {
     /// If there was no instance with this name before, it will be created and then returned.
     ///
     /// If there was an instance with this name, but it was of different type, it will be projected
-    /// onto `YXmlText` instance.
-    pub fn get_xml_text(&mut self, name: &str) -> PyResult<YXmlText> {
+    /// onto `YXmlText` instance, unless `strict` is set, in which case a `TypeError` is raised instead.
+    pub fn get_xml_text(&mut self, name: &str, strict: Option<bool>) -> PyResult<YXmlText> {
         self.guard_store()?;
+        self.guard_root_type(name, "xml_text", strict)?;
         Ok(self
             .0
             .borrow()
@@ -273,9 +551,10 @@ impl YDoc {
     /// If there was no instance with this name before, it will be created and then returned.
     ///
     /// If there was an instance with this name, but it was of different type, it will be projected
-    /// onto `YXmlFragment` instance.
-    pub fn get_xml_fragment(&mut self, name: &str) -> PyResult<YXmlFragment> {
+    /// onto `YXmlFragment` instance, unless `strict` is set, in which case a `TypeError` is raised instead.
+    pub fn get_xml_fragment(&mut self, name: &str, strict: Option<bool>) -> PyResult<YXmlFragment> {
         self.guard_store()?;
+        self.guard_root_type(name, "xml_fragment", strict)?;
         Ok(self
             .0
             .borrow()
@@ -290,9 +569,10 @@ impl YDoc {
     /// If there was no instance with this name before, it will be created and then returned.
     ///
     /// If there was an instance with this name, but it was of different type, it will be projected
-    /// onto `YArray` instance.
-    pub fn get_array(&mut self, name: &str) -> PyResult<YArray> {
+    /// onto `YArray` instance, unless `strict` is set, in which case a `TypeError` is raised instead.
+    pub fn get_array(&mut self, name: &str, strict: Option<bool>) -> PyResult<YArray> {
         self.guard_store()?;
+        self.guard_root_type(name, "array", strict)?;
         Ok(self
             .0
             .borrow()
@@ -307,9 +587,10 @@ impl YDoc {
     /// If there was no instance with this name before, it will be created and then returned.
     ///
     /// If there was an instance with this name, but it was of different type, it will be projected
-    /// onto `YText` instance.
-    pub fn get_text(&mut self, name: &str) -> PyResult<YText> {
+    /// onto `YText` instance, unless `strict` is set, in which case a `TypeError` is raised instead.
+    pub fn get_text(&mut self, name: &str, strict: Option<bool>) -> PyResult<YText> {
         self.guard_store()?;
+        self.guard_root_type(name, "text", strict)?;
         Ok(self
             .0
             .borrow()
@@ -318,15 +599,524 @@ impl YDoc {
             .with_doc(self.0.clone()))
     }
 
-    /// Subscribes a callback to a `YDoc` lifecycle event.
-    pub fn observe_after_transaction(&mut self, callback: PyObject) -> SubscriptionId {
+    /// Returns the kind of a root type called `name`, as reported by `YArray.type_of`, or `None` if
+    /// there's no such root type. Unlike the `get_*` methods, this never creates a root type.
+    ///
+    /// Root types are created untyped when they're first introduced by an update received from
+    /// a remote peer, since updates don't carry their kinds. Until they're fetched with one of the
+    /// `get_*` methods, their kind is reported as `"undefined"`.
+    pub fn root_type(&self, name: &str) -> Option<&'static str> {
+        self.with_transaction(|txn| root_kind(txn, name))
+    }
+
+    /// Attaches a preliminary `YText`, `YArray` or `YMap` to this document as a new root type
+    /// called `name`, copying its contents in a transaction of its own. Like inserting it into
+    /// another shared type, this integrates the `prelim` object in place, which is also returned.
+    ///
+    /// Raises a `ValueError` if a root type called `name` already exists, a `TypeError` if
+    /// `prelim` isn't a shared type and a `MultipleIntegrationError` if it's already integrated.
+    ///
+    /// Example:
+    ///
+    /// ```python
+    /// from y_py import YDoc, YArray
+    ///
+    /// template = YArray([1, 2, 3])
+    /// doc = YDoc()
+    /// array = doc.integrate('array', template)
+    /// assert array is template and not array.prelim
+    /// assert doc.get_array('array').to_json() == '[1,2,3]'
+    /// ```
+    pub fn integrate(&mut self, name: &str, prelim: PyObject) -> PyResult<PyObject> {
+        self.guard_store()?;
+        Python::with_gil(|py| {
+            let y_type = match prelim.extract::<CompatiblePyType>(py)? {
+                CompatiblePyType::YType(y_type) => y_type,
+                _ => {
+                    return Err(PyTypeError::new_err(
+                        "Only preliminary YText, YArray and YMap instances can be integrated.",
+                    ))
+                }
+            };
+            guard_prelim(&y_type)?;
+            let branch = {
+                let inner = self.0.borrow();
+                if root_kind(&inner.doc.transact(), name).is_some() {
+                    return Err(PyValueError::new_err(format!(
+                        "Root type '{name}' already exists."
+                    )));
+                }
+                match y_type {
+                    YPyType::Text(_) => {
+                        BranchPtr::from(inner.doc.get_or_insert_text(name).as_ref())
+                    }
+                    YPyType::Array(_) => {
+                        BranchPtr::from(inner.doc.get_or_insert_array(name).as_ref())
+                    }
+                    YPyType::Map(_) => BranchPtr::from(inner.doc.get_or_insert_map(name).as_ref()),
+                    // XML types are never preliminary, so they are rejected by `guard_prelim`
+                    _ => unreachable!(),
+                }
+            };
+            let txn = self.0.borrow_mut().begin_transaction();
+            PyObjectWrapper::new(prelim.clone_ref(py), self.0.clone())
+                .integrate(&mut txn.borrow_mut(), branch);
+            self.commit_transaction();
+            Ok(prelim)
+        })
+    }
+
+    /// Encodes the whole state of this document into a single update using lib0 v1 encoding, with
+    /// the content of deleted items replaced by lightweight tombstones. Unlike `compact`, the
+    /// result keeps the history of this document, so it can be merged with updates of other peers.
+    ///
+    /// Documents created with `skip_gc=False` (the default) collect deleted content whenever a
+    /// transaction commits, so for them the result matches `encode_state_as_update`. Documents
+    /// created with `skip_gc=True` keep their deleted content, so that past states can be restored
+    /// from snapshots. This method lets them be persisted without it, while the document itself
+    /// is left untouched - a document restored from the result can't be used with snapshots taken
+    /// before.
+    ///
+    /// Deleted content can't be garbage collected in place, as shared types and observers of this
+    /// document keep pointing into its store. To compact a document before persisting it, persist
+    /// the result of this method instead and restore the document from it later on.
+    pub fn encode_state_gc(&self) -> PyResult<Py<PyBytes>> {
+        let options = self.0.borrow().doc.options().clone();
+        let update = self.with_transaction(|txn| collect_garbage(txn, &options))?;
+        Ok(Python::with_gil(|py| PyBytes::new(py, &update).into()))
+    }
+
+    /// Captures the current state of this document as a binary snapshot. Snapshots can be used
+    /// later on to restore the document (or its individual shared types) as it was at the time
+    /// of capture. Only available for documents created with `skip_gc=True`.
+    ///
+    /// Example:
+    ///
+    /// ```python
+    /// from y_py import YDoc
+    ///
+    /// doc = YDoc(skip_gc=True)
+    /// text = doc.get_text('name')
+    /// with doc.begin_transaction() as txn:
+    ///     text.extend(txn, 'hello')
+    /// snapshot = doc.snapshot()
+    /// with doc.begin_transaction() as txn:
+    ///     text.extend(txn, ' world')
+    /// assert text.to_string_at(snapshot) == 'hello'
+    /// ```
+    pub fn snapshot(&self) -> PyResult<Py<PyBytes>> {
+        self.0.borrow().guard_snapshots()?;
+        let snapshot = self.with_transaction(|txn| txn.snapshot().encode_v1());
+        Ok(Python::with_gil(|py| PyBytes::new(py, &snapshot).into()))
+    }
+
+    /// Encodes the state of this document at the time given `snapshot` was captured into an
+    /// update payload using lib0 v1 encoding. Applying that update to an empty document recreates
+    /// the historical document state.
+    pub fn encode_state_from_snapshot(&self, snapshot: Vec<u8>) -> PyResult<Py<PyBytes>> {
+        self.0.borrow().guard_snapshots()?;
+        let snapshot = Snapshot::decode_v1(snapshot.as_slice())
+            .map_err(|e| EncodingException::new_err(e.to_string()))?;
+        let mut encoder = EncoderV1::new();
+        self.with_transaction(|txn| txn.encode_state_from_snapshot(&snapshot, &mut encoder))
+            .map_err(|e| EncodingException::new_err(e.to_string()))?;
+        Ok(Python::with_gil(|py| {
+            PyBytes::new(py, &encoder.to_vec()).into()
+        }))
+    }
+
+    /// Re-encodes the currently visible contents of this document into a single, minimal update
+    /// using lib0 v1 encoding. Deleted content and editing history are dropped, and the contents
+    /// are written by a freshly generated client id.
+    ///
+    /// The result shares no history with this document: applying it to a peer which already holds
+    /// this document's updates duplicates the contents instead of merging them. It's meant to
+    /// bootstrap new documents (e.g. when archiving or resetting a long-lived document), not to
+    /// synchronize existing peers incrementally.
+    pub fn compact(&self) -> PyResult<Py<PyBytes>> {
+        let options = self.0.borrow().doc.options().clone();
+        let update = self.with_transaction(|txn| compact(txn, &options))?;
+        Ok(Python::with_gil(|py| PyBytes::new(py, &update).into()))
+    }
+
+    /// Works like `encode_state_as_update`, but only includes changes made to the root types with
+    /// given `names`, including the shared types nested in them. It's meant for peers that only
+    /// synchronize a part of a document.
+    ///
+    /// Update contents can't be split by root types without breaking the sequence of changes made
+    /// by each client, so changes of other roots are replaced with garbage collected placeholders.
+    /// A peer that applies the result can never receive these changes afterwards, as it
+    /// considers them already integrated: it has to keep synchronizing the same set of roots. The
+    /// deletions of all roots are included. Which roots changes belong to is determined from
+    /// the whole document state, so the cost of encoding is the same as encoding everything.
+    ///
+    /// Example:
+    ///
+    /// ```python
+    /// from y_py import YDoc, apply_update
+    ///
+    /// doc = YDoc()
+    /// settings, body = doc.get_map('settings'), doc.get_text('body')
+    /// with doc.begin_transaction() as txn:
+    ///     settings.set(txn, 'theme', 'dark')
+    ///     body.extend(txn, 'a long text')
+    ///
+    /// remote_doc = YDoc()
+    /// apply_update(remote_doc, doc.encode_roots_as_update(['settings']))
+    /// assert dict(remote_doc.get_map('settings')) == {'theme': 'dark'}
+    /// assert str(remote_doc.get_text('body')) == ''
+    /// ```
+    pub fn encode_roots_as_update(
+        &self,
+        names: Vec<String>,
+        vector: Option<Vec<u8>>,
+    ) -> PyResult<Py<PyBytes>> {
+        let sv = decode_state_vector(vector)?;
+        let (full, diff) = self.with_transaction(|txn| {
+            (
+                txn.encode_state_as_update_v1(&StateVector::default()),
+                txn.encode_state_as_update_v1(&sv),
+            )
+        });
+        let names = names.iter().map(String::as_str).collect();
+        let update = filter_roots(&full, &diff, &names)
+            .map_err(|e| EncodingException::new_err(e.to_string()))?;
+        Ok(Python::with_gil(|py| PyBytes::new(py, &update).into()))
+    }
+
+    /// Returns the part of updates applied to this document, which couldn't be integrated yet
+    /// because the updates it depends on haven't been applied. It's encoded using lib0 v1 encoding.
+    /// Pending content is integrated automatically once the missing updates are applied. Returns
+    /// `None` if all applied updates have been integrated.
+    ///
+    /// This is useful to diagnose documents which don't converge, e.g. when an applied update
+    /// doesn't seem to change anything.
+    pub fn pending_update(&self) -> Option<Py<PyBytes>> {
+        let pending = self.0.borrow().pending.borrow().clone()?;
+        Some(Python::with_gil(|py| PyBytes::new(py, &pending).into()))
+    }
+
+    /// Checks if some of the updates applied to this document couldn't be integrated yet, because
+    /// the updates they depend on haven't been applied.
+    pub fn has_pending(&self) -> bool {
+        self.0.borrow().pending.borrow().is_some()
+    }
+
+    /// Describes gaps which prevent the pending update of this document from being integrated.
+    /// Returns a dictionary mapping client ids to `(start, end)` ranges of clocks, where `start` is
+    /// the clock of the next update expected from that client and `end` is the clock right after
+    /// the last pending update of that client. Updates within that range have to be applied (some
+    /// of them may already be pending) before the pending update can be integrated.
+    pub fn missing_update_ranges(&self) -> PyResult<HashMap<u64, (u32, u32)>> {
+        let pending = match self.0.borrow().pending.borrow().clone() {
+            Some(pending) => pending,
+            None => return Ok(HashMap::new()),
+        };
+        let pending = Update::decode_v1(&pending)
+            .map_err(|e| EncodingException::new_err(e.to_string()))?
+            .state_vector();
+        let state = self.with_transaction(|txn| txn.state_vector());
+        Ok(pending
+            .iter()
+            .map(|(&client, &end)| (client, (state.get(&client), end)))
+            .filter(|(_, (start, end))| start < end)
+            .collect())
+    }
+
+    /// Applies an `update` (encoded using lib0 v1 encoding) in a transaction of its own and returns
+    /// all events it triggered on root types, which existed before the update was applied, and
+    /// their nested shared types. Each event is serialized into a dictionary with the `root` type
+    /// name, the `path` to the changed shared type within it and `delta` and/or `keys` describing
+    /// the changes, following the format of the corresponding event type. Root types received
+    /// from earlier updates, which haven't been fetched with one of the `get_*` methods yet, can't
+    /// be observed and are skipped.
+    ///
+    /// This is meant for testing: observers are only registered for the time of applying the
+    /// update.
+    pub fn apply_update_collecting_events(&self, update: Vec<u8>) -> PyResult<PyObject> {
+        self.guard_store()?;
+        let roots: Vec<(String, Value)> = {
+            let doc = self.0.borrow();
+            let txn = doc.doc.transact();
+            typed_roots(&txn)?
+        };
+        let collected: Rc<RefCell<Vec<PyObject>>> = Rc::default();
+        let subscriptions: Vec<DeepSubscription> = roots
+            .into_iter()
+            .filter_map(|(name, root)| {
+                let doc = self.0.clone();
+                let collected = collected.clone();
+                let callback = move |txn: &TransactionMut, events: &Events| {
+                    Python::with_gil(|py| {
+                        let result = with_committing_transaction(&doc, txn, || -> PyResult<()> {
+                            let events = events_into_py(txn, events, doc.clone());
+                            for event in events.as_ref(py).iter()? {
+                                let event = event?;
+                                let serialized = event_changes(event)?;
+                                serialized.set_item("root", &name)?;
+                                serialized.set_item("path", event.call_method0("path")?)?;
+                                collected.borrow_mut().push(serialized.into());
+                            }
+                            Ok(())
+                        });
+                        if let Err(err) = result {
+                            err.restore(py)
+                        }
+                    })
+                };
+                match root {
+                    Value::YText(mut v) => {
+                        Some(DeepSubscription::new(v.observe_deep(callback).into(), v))
+                    }
+                    Value::YArray(mut v) => {
+                        Some(DeepSubscription::new(v.observe_deep(callback).into(), v))
+                    }
+                    Value::YMap(mut v) => {
+                        Some(DeepSubscription::new(v.observe_deep(callback).into(), v))
+                    }
+                    Value::YXmlElement(mut v) => {
+                        Some(DeepSubscription::new(v.observe_deep(callback).into(), v))
+                    }
+                    Value::YXmlText(mut v) => {
+                        Some(DeepSubscription::new(v.observe_deep(callback).into(), v))
+                    }
+                    Value::YXmlFragment(mut v) => {
+                        Some(DeepSubscription::new(v.observe_deep(callback).into(), v))
+                    }
+                    Value::Any(_) | Value::YDoc(_) => None,
+                }
+            })
+            .collect();
+
+        let txn = self.0.borrow_mut().begin_transaction();
+        let result = txn.borrow_mut().apply_v1(&update);
+        self.commit_transaction();
+        for subscription in subscriptions {
+            subscription.unobserve();
+        }
+        result?;
+
+        let collected = collected.take();
+        Ok(Python::with_gil(|py| PyList::new(py, collected).into()))
+    }
+
+    /// Converts the contents of all root types of this document into a single JSON object, keyed
+    /// by root type names. Root texts are serialized as strings, arrays as lists and maps as
+    /// objects, the same way their own `to_json` methods would.
+    ///
+    /// If `include_xml` is set, XML nodes are serialized as `{"tag", "attributes", "children"}`
+    /// objects. Otherwise they are stringified. If `sort_keys` is set, keys of all maps are emitted
+    /// in sorted order, which makes the output deterministic.
+    ///
+    /// Example:
+    ///
+    /// ```python
+    /// import json
+    /// from y_py import YDoc
+    ///
+    /// doc = YDoc()
+    /// text = doc.get_text('text')
+    /// array = doc.get_array('array')
+    /// with doc.begin_transaction() as txn:
+    ///     text.extend(txn, 'hello')
+    ///     array.append(txn, 1)
+    /// assert json.loads(doc.to_json()) == {'text': 'hello', 'array': [1]}
+    /// ```
+    pub fn to_json(&self, include_xml: Option<bool>, sort_keys: Option<bool>) -> PyResult<String> {
+        let include_xml = include_xml.unwrap_or(false);
+        let sort_keys = sort_keys.unwrap_or(false);
+        self.with_transaction(|txn| roots_into_json(txn, include_xml, sort_keys))
+    }
+
+    /// Returns a list of `(path, value)` pairs for all leaf values stored in this document. Nested
+    /// maps and arrays - both shared types and plain dicts and lists - are descended into, so that
+    /// every path starts with a root type name followed by the keys and indexes leading to a value.
+    /// Texts and XML nodes are reported as their string representation. Root types and map keys
+    /// are visited in sorted order.
+    ///
+    /// Example:
+    ///
+    /// ```python
+    /// from y_py import YDoc
+    ///
+    /// doc = YDoc()
+    /// with doc.begin_transaction() as txn:
+    ///     doc.get_map('map').set(txn, 'tags', ['a', 'b'])
+    /// assert doc.walk() == [(['map', 'tags', 0], 'a'), (['map', 'tags', 1], 'b')]
+    /// ```
+    pub fn walk(&self) -> PyResult<PyObject> {
+        let doc = self.0.clone();
+        self.with_transaction(|txn| {
+            let mut roots = typed_roots(txn)?;
+            roots.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Python::with_gil(|py| {
+                let mut walker = Walker::new(doc, py);
+                for (name, value) in roots {
+                    walker.walk_child(txn, name.into_py(py), value)?;
+                }
+                Ok(PyList::new(py, walker.leaves).into())
+            })
+        })
+    }
+
+    /// Retrieves a value stored under a given `path` - a root type name followed by map keys
+    /// (strings) and array indexes (integers, negative ones are counted from the end), the same
+    /// kind of paths as returned by `walk`. Nested shared types are returned as `YMap`, `YArray`
+    /// etc. If nothing is stored under that path, the `default` value (`None` unless specified) is
+    /// returned. Raises a `TypeError` if a segment doesn't match the kind of value it's applied to.
+    ///
+    /// Example:
+    ///
+    /// ```python
+    /// from y_py import YDoc, YMap
+    ///
+    /// doc = YDoc()
+    /// settings = doc.get_map('settings')
+    /// with doc.begin_transaction() as txn:
+    ///     settings.set(txn, 'editor', YMap({'theme': 'dark'}))
+    /// assert doc.get_path(['settings', 'editor', 'theme']) == 'dark'
+    /// assert doc.get_path(['settings', 'font', 'size'], 12) == 12
+    /// ```
+    pub fn get_path(&self, path: Vec<PathKey>, default: Option<PyObject>) -> PyResult<PyObject> {
+        let (name, rest) = split_root(&path)?;
+        let value = self.with_transaction(|txn| -> PyResult<Option<Value>> {
+            let mut current = resolve_root(txn, name, rest.first());
+            for key in rest {
+                current = match current {
+                    Some(value) => path_child(txn, &value, key)?,
+                    None => return Ok(None),
+                };
+            }
+            Ok(current)
+        })?;
+        Python::with_gil(|py| match value {
+            Some(value) => value.with_doc_into_py(self.0.clone(), py),
+            None => Ok(default.unwrap_or_else(|| py.None())),
+        })
+    }
+
+    /// Stores a `value` under a given `path` (see `get_path`) within a transaction `txn`. The root
+    /// type has to exist already, while missing intermediate maps are created along the way. The
+    /// last segment is either a map key to set or an array index to replace - an index equal to
+    /// the length of the array appends the `value` instead. Raises a `KeyError` or an `IndexError`
+    /// if an intermediate array entry is missing, since arrays are never created implicitly.
+    ///
+    /// Returns the integrated shared type if `value` was a preliminary one, `None` otherwise.
+    ///
+    /// Example:
+    ///
+    /// ```python
+    /// from y_py import YDoc
+    ///
+    /// doc = YDoc()
+    /// doc.get_map('settings')
+    /// with doc.begin_transaction() as txn:
+    ///     doc.set_path(txn, ['settings', 'editor', 'theme'], 'dark')
+    /// assert doc.get_path(['settings', 'editor', 'theme']) == 'dark'
+    /// ```
+    pub fn set_path(
+        &self,
+        txn: &mut YTransaction,
+        path: Vec<PathKey>,
+        value: PyObject,
+    ) -> PyResult<PyObject> {
+        let (name, rest) = split_root(&path)?;
+        let (last, parents) = rest.split_last().ok_or_else(|| {
+            PyValueError::new_err("path must point to a value within a root type")
+        })?;
+        let container = txn.transact(|txn| -> PyResult<Value> {
+            let mut current = resolve_root(&*txn, name, Some(parents.first().unwrap_or(last)))
+                .ok_or_else(|| PyKeyError::new_err(format!("root type '{name}' doesn't exist")))?;
+            for (i, key) in parents.iter().enumerate() {
+                let next = parents.get(i + 1).unwrap_or(last);
+                current = match (path_child(&*txn, &current, key)?, &current, key, next) {
+                    (Some(value), ..) => value,
+                    (None, Value::YMap(map), PathKey::Key(key), PathKey::Key(_)) => {
+                        let prelim = MapPrelim::<Any>::from(HashMap::new());
+                        Value::YMap(map.insert(txn, key.as_str(), prelim))
+                    }
+                    (None, .., PathKey::Key(key), _) => {
+                        return Err(PyKeyError::new_err(key.clone()))
+                    }
+                    (None, .., PathKey::Index(index), _) => {
+                        return Err(PyIndexError::new_err(format!(
+                            "array index {index} out of range"
+                        )))
+                    }
+                };
+            }
+            Ok(current)
+        })??;
+        let doc = self.0.clone();
+        match (container, last) {
+            (Value::YMap(map), PathKey::Key(key)) => map.with_doc(doc).set(txn, key, value),
+            (Value::YArray(array), PathKey::Index(index)) => {
+                let mut array = array.with_doc(doc);
+                let len = array.__len__() as isize;
+                if *index == len {
+                    return array.append(txn, value);
+                }
+                let index = if *index < 0 { len + index } else { *index };
+                if !(0..len).contains(&index) {
+                    return Err(PyIndexError::new_err(format!(
+                        "array index {last} out of range"
+                    )));
+                }
+                // insert before deleting, so that an invalid value leaves the array unchanged
+                let integrated = array.insert(txn, index, value)?;
+                array.delete(txn, index as u32 + 1)?;
+                Ok(integrated)
+            }
+            (container, key) => Err(path_type_error(&container, key)),
+        }
+    }
+
+    /// Subscribes a callback to a `YDoc` lifecycle event. If `skip_empty` is set, the callback
+    /// isn't called for transactions which haven't changed the document, e.g. ones started only
+    /// to read its contents.
+    pub fn observe_after_transaction(
+        &mut self,
+        callback: PyObject,
+        skip_empty: Option<bool>,
+    ) -> SubscriptionId {
+        let skip_empty = skip_empty.unwrap_or(false);
+        // The document owns this callback, so it only keeps a weak reference back to it.
+        let doc = Rc::downgrade(&self.0);
         self.0
             .borrow()
             .doc
             .observe_transaction_cleanup(move |txn, event| {
+                let empty = event.before_state == event.after_state && event.delete_set.is_empty();
+                if skip_empty && empty {
+                    return;
+                }
                 Python::with_gil(|py| {
                     let event = AfterTransactionEvent::new(event, txn);
-                    if let Err(err) = callback.call1(py, (event,)) {
+                    let result = match doc.upgrade() {
+                        Some(doc) => {
+                            with_committing_transaction(&doc, txn, || callback.call1(py, (event,)))
+                        }
+                        None => callback.call1(py, (event,)),
+                    };
+                    if let Err(err) = result {
+                        err.restore(py)
+                    }
+                })
+            })
+            .unwrap()
+            .into()
+    }
+
+    /// Subscribes a callback to be called when this `YDoc` gets destroyed. The callback receives
+    /// the destroyed document. Returns a `SubscriptionId` that identifies the subscription.
+    pub fn observe_destroy(&mut self, callback: PyObject) -> SubscriptionId {
+        self.0
+            .borrow()
+            .doc
+            .observe_destroy(move |_, doc| {
+                Python::with_gil(|py| {
+                    let doc = YDoc::from(doc.clone());
+                    if let Err(err) = callback.call1(py, (doc,)) {
                         err.restore(py)
                     }
                 })
@@ -334,12 +1124,55 @@ impl YDoc {
             .unwrap()
             .into()
     }
+
+    /// Destroys this `YDoc` together with all of its subdocuments, triggering callbacks registered
+    /// with `observe_destroy`. Destroyed document should no longer be used.
+    ///
+    /// Destroying is meant for subdocuments: within a transaction of its parent document, a
+    /// destroyed subdocument is replaced with a fresh, unloaded one. A root document has no parent
+    /// to be removed from, so destroying it only triggers the callbacks of itself and of its
+    /// subdocuments.
+    pub fn destroy(&mut self) -> PyResult<()> {
+        self.guard_store()?;
+        let mut doc = self.0.borrow().doc.clone();
+        match doc.parent_doc() {
+            Some(parent) => {
+                let mut parent_txn = parent
+                    .try_transact_mut()
+                    .map_err(|_| TransactionError::new_err(TRANSACTION_STARTED))?;
+                doc.destroy(&mut parent_txn);
+            }
+            None => {
+                // `Doc::destroy` only writes to the parent transaction when there's a parent
+                DETACHED.with(|detached| doc.destroy(&mut detached.transact_mut()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WithTransaction for YDoc {
+    fn get_doc(&self) -> Rc<RefCell<YDocInner>> {
+        self.0.clone()
+    }
+}
+
+impl From<Doc> for YDoc {
+    fn from(doc: Doc) -> Self {
+        YDoc(Rc::new(RefCell::new(YDocInner {
+            doc,
+            txn: None,
+            committing: None,
+            pending: PendingUpdate::default(),
+        })))
+    }
 }
 
 /// Encodes a state vector of a given Ypy document into its binary representation using lib0 v1
 /// encoding. State vector is a compact representation of updates performed on a given document and
 /// can be used by `encode_state_as_update` on remote peer to generate a delta update payload to
-/// synchronize changes between peers.
+/// synchronize changes between peers. If `txn` is provided, the state is read through it instead
+/// of the document's current transaction.
 ///
 /// Example:
 ///
@@ -357,16 +1190,19 @@ impl YDoc {
 /// apply_update(local_doc, remote_delta)
 /// ```
 #[pyfunction]
-pub fn encode_state_vector(doc: &mut YDoc) -> PyObject {
-    let txn = doc.0.borrow_mut().begin_transaction();
-    let txn = YTransaction::new(txn);
-    txn.state_vector_v1()
+pub fn encode_state_vector(
+    doc: &mut YDoc,
+    txn: Option<PyRef<YTransaction>>,
+) -> PyResult<Py<PyBytes>> {
+    Ok(resolve_transaction(doc, txn)?.state_vector_v1())
 }
 
 /// Encodes all updates that have happened since a given version `vector` into a compact delta
 /// representation using lib0 v1 encoding. If `vector` parameter has not been provided, generated
 /// delta payload will contain all changes of a current Ypy document, working effectively as its
-/// state snapshot.
+/// state snapshot. If `txn` is provided, the state is read through it instead of the document's
+/// current transaction. If `copy` is `False`, an `UpdateBuffer` is returned instead of `bytes`
+/// (see `YTransaction.diff_v1`).
 ///
 /// Example:
 ///
@@ -384,13 +1220,26 @@ pub fn encode_state_vector(doc: &mut YDoc) -> PyObject {
 /// apply_update(local_doc, remote_delta)
 /// ```
 #[pyfunction]
-pub fn encode_state_as_update(doc: &mut YDoc, vector: Option<Vec<u8>>) -> PyResult<PyObject> {
-    let txn = doc.0.borrow_mut().begin_transaction();
-    YTransaction::new(txn).diff_v1(vector)
+pub fn encode_state_as_update(
+    doc: &mut YDoc,
+    vector: Option<Vec<u8>>,
+    txn: Option<PyRef<YTransaction>>,
+    copy: Option<bool>,
+) -> PyResult<PyObject> {
+    resolve_transaction(doc, txn)?.diff_v1(vector, copy)
 }
 
 /// Applies delta update generated by the remote document replica to a current document. This
-/// method assumes that a payload maintains lib0 v1 encoding format.
+/// method assumes that a payload maintains lib0 v1 encoding format. If `txn` is provided, the
+/// update is applied within it instead of the document's current transaction.
+///
+/// If an `origin` is provided, the update is applied in a new transaction tagged with it, so that
+/// observers can recognize the changes (see the `origin` of events), e.g. to avoid sending them
+/// back to the peer they came from. An origin can't be combined with `txn`.
+///
+/// Returns `True` if the update has been fully integrated. Otherwise some of the updates it depends
+/// on haven't been applied yet, so its remaining content is kept pending until they arrive (see
+/// `YDoc.pending_update`).
 ///
 /// Example:
 ///
@@ -408,19 +1257,350 @@ pub fn encode_state_as_update(doc: &mut YDoc, vector: Option<Vec<u8>>) -> PyResu
 /// apply_update(local_doc, remote_delta)
 /// ```
 #[pyfunction]
-pub fn apply_update(doc: &mut YDoc, diff: Vec<u8>) -> PyResult<()> {
-    let txn = doc.0.borrow_mut().begin_transaction();
-    YTransaction::new(txn).apply_v1(diff)?;
+pub fn apply_update(
+    doc: &mut YDoc,
+    diff: Vec<u8>,
+    txn: Option<PyRef<YTransaction>>,
+    origin: Option<&str>,
+) -> PyResult<bool> {
+    match (txn, origin) {
+        (Some(_), Some(_)) => Err(PyValueError::new_err(
+            "An origin can't be assigned to an already started transaction.",
+        )),
+        (None, Some(origin)) => {
+            let txn = doc.0.borrow_mut().begin_transaction_with_origin(origin)?;
+            YTransaction::new(txn).apply_v1(diff)
+        }
+        (txn, None) => resolve_transaction(doc, txn)?.apply_v1(diff),
+    }
+}
+
+/// Works like `encode_state_as_update`, but returns the update as a base64-encoded `str`, which
+/// can be sent over transports unable to carry raw bytes (e.g. JSON).
+#[pyfunction]
+pub fn encode_state_as_update_b64(
+    doc: &mut YDoc,
+    vector: Option<Vec<u8>>,
+    txn: Option<PyRef<YTransaction>>,
+) -> PyResult<String> {
+    let update = encode_state_as_update(doc, vector, txn, None)?;
+    Python::with_gil(|py| {
+        py.import("base64")?
+            .call_method1("b64encode", (update,))?
+            .call_method1("decode", ("ascii",))?
+            .extract()
+    })
+}
+
+/// Works like `apply_update`, but accepts the update as a base64-encoded `str`, as produced by
+/// `encode_state_as_update_b64`. Raises a `ValueError` if `diff` isn't valid base64.
+#[pyfunction]
+pub fn apply_update_b64(
+    doc: &mut YDoc,
+    diff: &str,
+    txn: Option<PyRef<YTransaction>>,
+    origin: Option<&str>,
+) -> PyResult<bool> {
+    let diff: Vec<u8> = Python::with_gil(|py| {
+        let kwargs = [("validate", true)].into_py_dict(py);
+        py.import("base64")?
+            .call_method("b64decode", (diff,), Some(kwargs))
+            .map_err(|e| PyValueError::new_err(format!("Update is not valid base64: {e}")))?
+            .extract()
+    })?;
+    apply_update(doc, diff, txn, origin)
+}
+
+/// Computes a delta update which, once applied to `old_doc`, brings it to the state of `new_doc`.
+/// Both documents are expected to be versions of the same logical document, where `new_doc` has
+/// seen every change of `old_doc`. A `ValueError` is raised if `old_doc` contains changes of some
+/// client which `new_doc` doesn't know about, as the resulting update couldn't reproduce `new_doc`.
+///
+/// Example:
+///
+/// ```python
+/// from y_py import YDoc, apply_update, diff_docs, encode_state_as_update
+///
+/// old_doc = YDoc()
+/// new_doc = YDoc()
+/// apply_update(new_doc, encode_state_as_update(old_doc))
+/// # ... modify new_doc
+///
+/// apply_update(old_doc, diff_docs(old_doc, new_doc))
+/// ```
+#[pyfunction]
+pub fn diff_docs(old_doc: &YDoc, new_doc: &YDoc) -> PyResult<Py<PyBytes>> {
+    let old_state = old_doc.with_transaction(|txn| txn.state_vector());
+    let new_state = new_doc.with_transaction(|txn| txn.state_vector());
+    if let Some((client, clock)) = old_state
+        .iter()
+        .find(|&(client, &clock)| clock > new_state.get(client))
+    {
+        return Err(PyValueError::new_err(format!(
+            "`old_doc` contains changes of client {client} up to clock {clock}, which `new_doc` \
+             has only seen up to clock {}.",
+            new_state.get(client)
+        )));
+    }
+    let update = new_doc.with_transaction(|txn| txn.encode_diff_v1(&old_state));
+    Ok(Python::with_gil(|py| PyBytes::new(py, &update).into()))
+}
+
+/// Checks if two documents are in sync: returns `True` only if both have seen exactly the same
+/// changes (their state vectors are equal) and the visible contents of all their root types,
+/// including XML nodes, are the same. Formatting attributes of texts aren't compared. Unlike
+/// comparing encoded updates, this doesn't depend on client ids or the order in which changes
+/// were applied.
+///
+/// Example:
+///
+/// ```python
+/// from y_py import YDoc, apply_update, docs_equal, encode_state_as_update
+///
+/// a = YDoc()
+/// b = YDoc()
+/// # ... modify a
+/// apply_update(b, encode_state_as_update(a))
+/// assert docs_equal(a, b)
+/// ```
+#[pyfunction]
+pub fn docs_equal(a: &YDoc, b: &YDoc) -> PyResult<bool> {
+    let a_state = a.with_transaction(|txn| txn.state_vector());
+    let b_state = b.with_transaction(|txn| txn.state_vector());
+    if a_state != b_state {
+        return Ok(false);
+    }
+    let a_json = a.with_transaction(|txn| roots_into_json(txn, true, true))?;
+    let b_json = b.with_transaction(|txn| roots_into_json(txn, true, true))?;
+    Ok(a_json == b_json)
+}
+
+/// Serializes all root types of a document, keyed by their names, into a JSON string. Root types
+/// of a kind which hasn't been defined yet are left out.
+pub(crate) fn roots_into_json<T: ReadTxn>(
+    txn: &T,
+    include_xml: bool,
+    sort_keys: bool,
+) -> PyResult<String> {
+    let roots: HashMap<String, Any> = typed_roots(txn)?
+        .into_iter()
+        .map(|(name, value)| {
+            let json = value_into_json(txn, &value, include_xml);
+            (name, json)
+        })
+        .collect();
+    let mut json_builder = JsonBuilder::with_options(include_xml, sort_keys);
+    json_builder.append_json(&Any::Map(Box::new(roots)))?;
+    Ok(json_builder.into())
+}
+
+/// Collects `(path, value)` pairs of leaf values for `YDoc.walk`.
+struct Walker<'py> {
+    doc: Rc<RefCell<YDocInner>>,
+    py: Python<'py>,
+    path: Vec<PyObject>,
+    leaves: Vec<PyObject>,
+}
+
+impl<'py> Walker<'py> {
+    fn new(doc: Rc<RefCell<YDocInner>>, py: Python<'py>) -> Self {
+        Walker {
+            doc,
+            py,
+            path: Vec::new(),
+            leaves: Vec::new(),
+        }
+    }
+
+    fn walk_child<T: ReadTxn>(&mut self, txn: &T, segment: PyObject, value: Value) -> PyResult<()> {
+        self.path.push(segment);
+        self.walk(txn, value)?;
+        self.path.pop();
+        Ok(())
+    }
+
+    fn walk<T: ReadTxn>(&mut self, txn: &T, value: Value) -> PyResult<()> {
+        let py = self.py;
+        let leaf = match value {
+            Value::YMap(map) => {
+                let mut entries: Vec<_> = map.iter(txn).collect();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                for (key, value) in entries {
+                    self.walk_child(txn, key.into_py(py), value)?;
+                }
+                return Ok(());
+            }
+            Value::YArray(array) => {
+                for (index, value) in array.iter(txn).enumerate() {
+                    self.walk_child(txn, index.into_py(py), value)?;
+                }
+                return Ok(());
+            }
+            Value::Any(Any::Map(entries)) => {
+                let mut entries: Vec<_> = (*entries).into_iter().collect();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                for (key, value) in entries {
+                    self.walk_child(txn, key.into_py(py), Value::Any(value))?;
+                }
+                return Ok(());
+            }
+            Value::Any(Any::Array(items)) => {
+                for (index, value) in items.into_vec().into_iter().enumerate() {
+                    self.walk_child(txn, index.into_py(py), Value::Any(value))?;
+                }
+                return Ok(());
+            }
+            Value::YText(text) => text.get_string(txn).into_py(py),
+            Value::YXmlElement(xml) => xml.get_string(txn).into_py(py),
+            Value::YXmlText(xml) => xml.get_string(txn).into_py(py),
+            Value::YXmlFragment(xml) => xml.get_string(txn).into_py(py),
+            value => value.with_doc_into_py(self.doc.clone(), py)?,
+        };
+        let path = PyList::new(py, &self.path);
+        self.leaves.push((path, leaf).into_py(py));
+        Ok(())
+    }
+}
+
+/// A segment of a path used by `YDoc.get_path` and `YDoc.set_path`.
+#[derive(FromPyObject)]
+pub enum PathKey {
+    Key(String),
+    Index(isize),
+}
+
+impl std::fmt::Display for PathKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathKey::Key(key) => write!(f, "'{key}'"),
+            PathKey::Index(index) => write!(f, "{index}"),
+        }
+    }
+}
+
+/// Splits a `path` into the name of the root type it starts at and the remaining segments.
+fn split_root(path: &[PathKey]) -> PyResult<(&str, &[PathKey])> {
+    match path.split_first() {
+        Some((PathKey::Key(name), rest)) => Ok((name.as_str(), rest)),
+        Some((PathKey::Index(_), _)) => Err(PyTypeError::new_err(
+            "path must start with the name of a root type",
+        )),
+        None => Err(PyValueError::new_err("path must not be empty")),
+    }
+}
+
+/// Returns a root type called `name`. Its kind is decided by the `next` path segment, which
+/// mirrors the way `YDoc.get_map` and `YDoc.get_array` project root types.
+fn resolve_root<T: ReadTxn>(txn: &T, name: &str, next: Option<&PathKey>) -> Option<Value> {
+    match next {
+        Some(PathKey::Key(_)) => txn.get_map(name).map(Value::YMap),
+        Some(PathKey::Index(_)) => txn.get_array(name).map(Value::YArray),
+        None => root_branch(txn, name)
+            .filter(|branch| has_defined_kind(*branch))
+            .map(Value::from),
+    }
+}
 
-    Ok(())
+/// Returns the kind of a root type called `name`, if there's any. Unlike `root_refs`, this doesn't
+/// panic on root types which haven't got their kind defined yet.
+fn root_kind<T: ReadTxn>(txn: &T, name: &str) -> Option<&'static str> {
+    let root = root_branch(txn, name)?;
+    Some(match root.type_ref() {
+        TYPE_REFS_ARRAY => "array",
+        TYPE_REFS_MAP => "map",
+        TYPE_REFS_TEXT => "text",
+        TYPE_REFS_XML_ELEMENT => "xml_element",
+        TYPE_REFS_XML_FRAGMENT => "xml_fragment",
+        TYPE_REFS_XML_TEXT => "xml_text",
+        _ => "undefined",
+    })
+}
+
+/// Returns a value stored within a `parent` map or array under a given `key`, if there's any.
+fn path_child<T: ReadTxn>(txn: &T, parent: &Value, key: &PathKey) -> PyResult<Option<Value>> {
+    match (parent, key) {
+        (Value::YMap(map), PathKey::Key(key)) => Ok(map.get(txn, key)),
+        (Value::YArray(array), PathKey::Index(index)) => {
+            let len = array.len(txn) as isize;
+            let index = if *index < 0 { len + index } else { *index };
+            Ok(if (0..len).contains(&index) {
+                array.get(txn, index as u32)
+            } else {
+                None
+            })
+        }
+        (parent, key) => Err(path_type_error(parent, key)),
+    }
+}
+
+/// Returns an error describing a path segment `key`, which can't be applied to a `parent` value.
+fn path_type_error(parent: &Value, key: &PathKey) -> PyErr {
+    let message = match parent {
+        Value::YMap(_) => format!("expected a key to look up in a map, found {key}"),
+        Value::YArray(_) => format!("expected an index to look up in an array, found {key}"),
+        _ => format!("cannot look up {key} in a value which isn't a map or an array"),
+    };
+    PyTypeError::new_err(message)
+}
+
+/// Returns a transaction used by module-level functions: either the one passed explicitly by
+/// the caller or the current transaction of a given `doc`.
+fn resolve_transaction(doc: &YDoc, txn: Option<PyRef<YTransaction>>) -> PyResult<YTransaction> {
+    match txn {
+        Some(txn) => Ok(YTransaction::new(txn.get_active_inner()?)),
+        None => Ok(YTransaction::new(doc.0.borrow_mut().begin_transaction())),
+    }
+}
+
+/// Context manager returned by `YDoc.batch`.
+#[pyclass(unsendable)]
+pub struct YBatch {
+    doc: Rc<RefCell<YDocInner>>,
+    txn: Option<Rc<RefCell<YTransactionInner>>>,
+    /// Whether this batch has started its transaction, rather than joining one already open.
+    owner: bool,
+}
+
+#[pymethods]
+impl YBatch {
+    fn __enter__(&mut self) -> PyResult<YTransaction> {
+        if self.txn.is_some() {
+            return Err(TransactionError::new_err("Batch has already been entered!"));
+        }
+        self.owner = self.doc.borrow().pending_transaction().is_none();
+        let txn = self.doc.borrow_mut().begin_transaction();
+        txn.borrow_mut().batches += 1;
+        self.txn = Some(txn.clone());
+        Ok(YTransaction::new(txn))
+    }
+
+    /// Commits the batched transaction, unless it was already open when this batch was entered -
+    /// then committing it is left to whoever has opened it.
+    fn __exit__(
+        &mut self,
+        _exception_type: Option<&PyAny>,
+        _exception_value: Option<&PyAny>,
+        _traceback: Option<&PyAny>,
+    ) -> bool {
+        if let Some(txn) = self.txn.take() {
+            let mut txn = txn.borrow_mut();
+            txn.batches -= 1;
+            // the transaction may have been released with `YDoc.abort_transaction`
+            if self.owner && txn.batches == 0 && !txn.committed {
+                txn.commit();
+            }
+        }
+        false
+    }
 }
 
 #[pyclass(unsendable)]
 pub struct AfterTransactionEvent {
-    before_state: PyObject,
-    after_state: PyObject,
-    delete_set: PyObject,
-    update: PyObject,
+    before_state: Py<PyBytes>,
+    after_state: Py<PyBytes>,
+    delete_set: Py<PyBytes>,
+    update: Py<PyBytes>,
+    origin: PyObject,
 }
 
 impl AfterTransactionEvent {
@@ -428,18 +1608,21 @@ impl AfterTransactionEvent {
         // Convert all event data into Python objects eagerly, so that we don't have to hold
         // on to the transaction.
         let before_state = event.before_state.encode_v1();
-        let before_state: PyObject = Python::with_gil(|py| PyBytes::new(py, &before_state).into());
+        let before_state: Py<PyBytes> =
+            Python::with_gil(|py| PyBytes::new(py, &before_state).into());
         let after_state = event.after_state.encode_v1();
-        let after_state: PyObject = Python::with_gil(|py| PyBytes::new(py, &after_state).into());
+        let after_state: Py<PyBytes> = Python::with_gil(|py| PyBytes::new(py, &after_state).into());
         let delete_set = event.delete_set.encode_v1();
-        let delete_set: PyObject = Python::with_gil(|py| PyBytes::new(py, &delete_set).into());
+        let delete_set: Py<PyBytes> = Python::with_gil(|py| PyBytes::new(py, &delete_set).into());
         let update = txn.encode_update_v1();
-        let update = Python::with_gil(|py| PyBytes::new(py, &update).into());
+        let update: Py<PyBytes> = Python::with_gil(|py| PyBytes::new(py, &update).into());
+        let origin = Python::with_gil(|py| origin_into_py(txn.origin(), py));
         AfterTransactionEvent {
             before_state,
             after_state,
             delete_set,
             update,
+            origin,
         }
     }
 }
@@ -448,21 +1631,27 @@ impl AfterTransactionEvent {
 impl AfterTransactionEvent {
     /// Returns a current shared type instance, that current event changes refer to.
     #[getter]
-    pub fn before_state(&mut self) -> PyObject {
+    pub fn before_state(&mut self) -> Py<PyBytes> {
         self.before_state.clone()
     }
 
     #[getter]
-    pub fn after_state(&mut self) -> PyObject {
+    pub fn after_state(&mut self) -> Py<PyBytes> {
         self.after_state.clone()
     }
 
     #[getter]
-    pub fn delete_set(&mut self) -> PyObject {
+    pub fn delete_set(&mut self) -> Py<PyBytes> {
         self.delete_set.clone()
     }
 
-    pub fn get_update(&self) -> PyObject {
+    pub fn get_update(&self) -> Py<PyBytes> {
         self.update.clone()
     }
+
+    /// Returns the origin of the committed transaction, or `None` if it has no origin.
+    #[getter]
+    pub fn origin(&self) -> PyObject {
+        self.origin.clone()
+    }
 }
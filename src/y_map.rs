@@ -1,25 +1,32 @@
-use pyo3::exceptions::{PyKeyError, PyTypeError};
+use pyo3::exceptions::{PyKeyError, PyTypeError, PyValueError};
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
 
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::mem::ManuallyDrop;
 use std::ops::DerefMut;
 use std::rc::Rc;
 
+use lib0::any::Any;
 use yrs::types::map::{MapEvent, MapIter};
-use yrs::types::{DeepObservable, ToJson};
+use yrs::types::{DeepObservable, EntryChange, ToJson, Value};
 use yrs::{Map, MapRef, Observable, SubscriptionId, TransactionMut};
 
 use crate::json_builder::JsonBuilder;
 use crate::shared_types::{
-    DeepSubscription, DefaultPyErr, PreliminaryObservationException, ShallowSubscription,
-    SharedType, SubId, TypeWithDoc,
+    deepcopy, integrated_copy_error, CompatiblePyType, DeepSubscription, DefaultPyErr,
+    IntegratedOperationException, ObserverCallback, PreliminaryObservationException,
+    ShallowSubscription, SharedType, SubId, TypeWithDoc, YPyType,
 };
-use crate::type_conversions::{events_into_py, PyObjectWrapper, ToPython, WithDocToPython};
-use crate::y_doc::{WithDoc, YDocInner};
-use crate::y_transaction::{YTransaction, YTransactionInner};
+use crate::type_conversions::{
+    decode_any, events_into_changes, events_into_py, guard_prelim, integrated_or_none,
+    materialize_value, py_into_prelim, py_type_tag, truncated_repr, value_into_json,
+    value_type_tag, PyObjectWrapper, ToPython, WithDocToPython, REPR_MAX_ITEMS, VALUE_KINDS,
+};
+use crate::y_doc::{with_committing_transaction, WithDoc, YDocInner};
+use crate::y_transaction::{origin_into_py, TransactionError, YTransaction, YTransactionInner};
 
 /// Collection used to store key-value entries in an unordered manner. Keys are always represented
 /// as UTF-8 strings. Values can be any value type supported by Yrs: JSON-like primitives as well as
@@ -41,18 +48,31 @@ impl WithDoc<YMap> for MapRef {
 #[pymethods]
 impl YMap {
     /// Creates a new preliminary instance of a `YMap` shared data type, with its state
-    /// initialized to provided parameter.
+    /// initialized to provided parameter: a dict, any other mapping or an iterable of
+    /// `(key, value)` pairs. Like all Yrs maps, `YMap` doesn't preserve the order of its entries.
     ///
     /// Preliminary instances can be nested into other shared data types such as `YArray` and `YMap`.
     /// Once a preliminary instance has been inserted this way, it becomes integrated into Ypy
     /// document store and cannot be nested again: attempt to do so will result in an exception.
     #[new]
-    pub fn new(dict: &PyDict) -> PyResult<Self> {
+    pub fn new(dict: Option<&PyAny>) -> PyResult<Self> {
         let mut map: HashMap<String, PyObject> = HashMap::new();
-        for (k, v) in dict.iter() {
-            let k = k.downcast::<pyo3::types::PyString>()?.to_string();
-            let v: PyObject = v.into();
-            map.insert(k, v);
+        if let Some(init) = dict {
+            // Mappings are read through their items, like `dict(...)` does
+            let pairs = if init.hasattr("keys")? {
+                init.call_method0("items")?
+            } else {
+                init
+            };
+            for pair in pairs.iter()? {
+                let pair = pair?;
+                let (k, v) = Self::extract_pair(pair).ok_or_else(|| {
+                    PyTypeError::new_err(format!(
+                        "YMap entries should be formatted as (str, value) tuples, found: {pair}"
+                    ))
+                })?;
+                map.insert(k, v);
+            }
         }
         Ok(YMap(SharedType::Prelim(map)))
     }
@@ -62,11 +82,90 @@ impl YMap {
     /// Preliminary instances can be nested into other shared data types such as `YArray` and `YMap`.
     /// Once a preliminary instance has been inserted this way, it becomes integrated into Ypy
     /// document store and cannot be nested again: attempt to do so will result in an exception.
+    /// The inserted object itself is integrated in place, so it can be used for further edits.
     #[getter]
     pub fn prelim(&self) -> bool {
         matches!(&self.0, SharedType::Prelim(_))
     }
 
+    /// Returns a shallow copy of a preliminary `YMap`. Integrated instances are bound to their
+    /// document, so they can't be copied.
+    pub fn __copy__(&self) -> PyResult<Self> {
+        match &self.0 {
+            SharedType::Prelim(entries) => Ok(YMap(SharedType::prelim(entries.clone()))),
+            SharedType::Integrated(_) => Err(integrated_copy_error("YMap")),
+        }
+    }
+
+    /// Returns a deep copy of a preliminary `YMap`. Integrated instances are bound to their
+    /// document, so they can't be copied.
+    pub fn __deepcopy__(&self, memo: &PyAny) -> PyResult<Self> {
+        match &self.0 {
+            SharedType::Prelim(entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), deepcopy(value, memo)?)))
+                    .collect::<PyResult<_>>()?;
+                Ok(YMap(SharedType::prelim(entries)))
+            }
+            SharedType::Integrated(_) => Err(integrated_copy_error("YMap")),
+        }
+    }
+
+    /// Returns a new preliminary `YMap` with each of string `keys` mapped to `value` (`None` by
+    /// default), like `dict.fromkeys`. Unlike `dict.fromkeys`, every key gets its own deep copy of
+    /// `value`, so that nested containers aren't shared between the entries.
+    #[staticmethod]
+    pub fn from_keys(keys: &PyAny, value: Option<PyObject>) -> PyResult<Self> {
+        let py = keys.py();
+        let value = value.unwrap_or_else(|| py.None());
+        let deepcopy = py.import("copy")?.getattr("deepcopy")?;
+        let mut map = HashMap::new();
+        for key in keys.iter()? {
+            let key: String = key?.extract()?;
+            map.insert(key, deepcopy.call1((&value,))?.into());
+        }
+        Ok(YMap(SharedType::Prelim(map)))
+    }
+
+    /// Returns `False` if this `YMap` has been removed from its document, either directly or
+    /// along with one of its ancestors. Removed types can no longer be modified.
+    pub fn is_alive(&self) -> bool {
+        self.0.is_alive()
+    }
+
+    /// Returns `True` if this `YMap` has been removed from its document. See `is_alive`.
+    pub fn is_deleted(&self) -> bool {
+        !self.is_alive()
+    }
+
+    /// Returns an identifier of this `YMap` which is the same on every peer: the name of a root
+    /// type or a `(client, clock)` pair identifying the update which has created a nested one.
+    /// Preliminary instances have no identifier and return `None`.
+    pub fn id(&self) -> PyObject {
+        self.0.id()
+    }
+
+    /// Returns a shared type directly containing this `YMap`, or `None` for root types,
+    /// preliminary instances and instances which have been removed from their document.
+    #[getter]
+    pub fn parent(&self) -> PyResult<PyObject> {
+        match &self.0 {
+            SharedType::Integrated(v) => v.parent_type(),
+            SharedType::Prelim(_) => Ok(Python::with_gil(|py| py.None())),
+        }
+    }
+
+    /// Returns a list of keys and indexes forming a path from the root type of a document down to
+    /// this `YMap`, in the same format as event paths. Returns `None` if this instance has been
+    /// removed from its document. Preliminary instances have no path.
+    pub fn path(&self) -> PyResult<PyObject> {
+        match &self.0 {
+            SharedType::Integrated(v) => Ok(v.path()),
+            SharedType::Prelim(_) => Err(IntegratedOperationException::default_message()),
+        }
+    }
+
     pub fn __len__(&self) -> usize {
         match &self.0 {
             SharedType::Integrated(v) => v.with_transaction(|txn| v.len(txn)) as usize,
@@ -74,26 +173,36 @@ impl YMap {
         }
     }
 
-    /// Returns a number of elements stored within this instance of `YArray` using a provided transaction.
-    fn _len(&self, txn: &YTransactionInner) -> usize {
+    /// Returns a number of entries stored within this `YMap`, optionally read through a given
+    /// `txn`. Unlike `len`, which starts a transaction of its own when none is pending, this lets
+    /// tight loops reuse an already open one.
+    pub fn length(&self, txn: Option<PyRef<YTransaction>>) -> PyResult<usize> {
+        match txn {
+            Some(txn) => Ok(self._len(&txn.get_active_inner()?.borrow())),
+            None => Ok(self.__len__()),
+        }
+    }
+
+    /// Returns `true` if an entry is stored under a given `key`, even if its value is `None`.
+    pub fn __contains__(&self, key: &str) -> bool {
         match &self.0 {
-            SharedType::Integrated(v) => v.len(txn) as usize,
-            SharedType::Prelim(v) => v.len(),
+            SharedType::Integrated(v) => v.with_transaction(|txn| v.contains_key(txn, key)),
+            SharedType::Prelim(v) => v.contains_key(key),
         }
     }
 
-    pub fn __str__(&self) -> String {
+    pub fn __str__(&self) -> PyResult<String> {
         Python::with_gil(|py| match &self.0 {
-            SharedType::Integrated(y_array) => {
-                y_array.with_transaction(|txn| y_array.to_json(txn).into_py(py).to_string())
-            }
-            SharedType::Prelim(py_contents) => py_contents.clone().into_py(py).to_string(),
+            SharedType::Integrated(y_array) => y_array
+                .with_transaction(|txn| decode_any(py, y_array.to_json(txn)))
+                .map(|value| value.to_string()),
+            SharedType::Prelim(py_contents) => Ok(py_contents.clone().into_py(py).to_string()),
         })
     }
 
     pub fn __dict__(&self) -> PyResult<PyObject> {
         Python::with_gil(|py| match &self.0 {
-            SharedType::Integrated(v) => v.with_transaction(|txn| Ok(v.to_json(txn).into_py(py))),
+            SharedType::Integrated(v) => v.with_transaction(|txn| decode_any(py, v.to_json(txn))),
             SharedType::Prelim(map) => {
                 let dict = PyDict::new(py);
                 for (k, v) in map.iter() {
@@ -104,17 +213,68 @@ impl YMap {
         })
     }
 
-    pub fn __repr__(&self) -> String {
-        format!("YMap({})", self.__str__())
+    /// Returns a `dict` holding all entries of this `YMap`, read within a single transaction. Unless
+    /// `deep` is set, nested shared types are returned as live references, which can be modified
+    /// further. Otherwise, they are fully converted into plain Python values, like with `__dict__`.
+    pub fn to_dict(&self, deep: Option<bool>) -> PyResult<PyObject> {
+        let deep = deep.unwrap_or(false);
+        Python::with_gil(|py| match &self.0 {
+            SharedType::Integrated(map) if !deep => map.with_transaction(|txn| {
+                let dict = PyDict::new(py);
+                for (key, value) in map.iter(txn) {
+                    dict.set_item(key, value.with_doc_into_py(map.doc.clone(), py)?)?;
+                }
+                Ok(dict.into())
+            }),
+            SharedType::Prelim(_) if deep => py
+                .import("json")?
+                .call_method1("loads", (self.to_json(None, None)?,))
+                .map(|dict| dict.into()),
+            _ => self.__dict__(),
+        })
     }
 
-    /// Converts contents of this `YMap` instance into a JSON representation.
-    pub fn to_json(&self) -> PyResult<String> {
-        let mut json_builder = JsonBuilder::new();
+    /// Returns a representation of this `YMap`, which - unlike `__str__` - only shows `10` of its
+    /// entries, followed by an ellipsis if there are more of them.
+    pub fn __repr__(&self) -> PyResult<String> {
+        Python::with_gil(|py| {
+            let entry = |key: &str, value: PyObject| -> PyResult<String> {
+                let key = key.to_object(py);
+                Ok(format!(
+                    "{}: {}",
+                    key.as_ref(py).repr()?,
+                    value.as_ref(py).repr()?
+                ))
+            };
+            let parts: Vec<String> = match &self.0 {
+                SharedType::Integrated(map) => map.with_transaction(|txn| {
+                    map.iter(txn)
+                        .take(REPR_MAX_ITEMS + 1)
+                        .map(|(key, value)| entry(key, decode_any(py, value.to_json(txn))?))
+                        .collect::<PyResult<Vec<String>>>()
+                })?,
+                SharedType::Prelim(entries) => entries
+                    .iter()
+                    .take(REPR_MAX_ITEMS + 1)
+                    .map(|(key, value)| entry(key, value.clone_ref(py)))
+                    .collect::<PyResult<Vec<String>>>()?,
+            };
+            Ok(format!("YMap({})", truncated_repr(parts, "{", "}")))
+        })
+    }
+
+    /// Converts contents of this `YMap` instance into a JSON representation. Nested XML nodes are
+    /// serialized as `{"tag", "attributes", "children"}` objects if `include_xml` is set, otherwise
+    /// they are either stringified (when integrated) or rejected. Keys are emitted in sorted order
+    /// (also in nested maps) if `sort_keys` is set, which makes the output deterministic.
+    pub fn to_json(&self, include_xml: Option<bool>, sort_keys: Option<bool>) -> PyResult<String> {
+        let include_xml = include_xml.unwrap_or(false);
+        let mut json_builder = JsonBuilder::with_options(include_xml, sort_keys.unwrap_or(false));
         match &self.0 {
-            SharedType::Integrated(dict) => {
-                dict.with_transaction(|txn| json_builder.append_json(&dict.to_json(txn)))?
-            }
+            SharedType::Integrated(dict) => dict.with_transaction(|txn| {
+                let value = Value::YMap(dict.inner.clone());
+                json_builder.append_json(&value_into_json(txn, &value, include_xml))
+            })?,
             SharedType::Prelim(dict) => json_builder.append_json(dict)?,
         }
         Ok(json_builder.into())
@@ -122,35 +282,122 @@ impl YMap {
 
     /// Sets a given `key`-`value` entry within this instance of `YMap`. If another entry was
     /// already stored under given `key`, it will be overridden with new `value`.
-    pub fn set(&mut self, txn: &mut YTransaction, key: &str, value: PyObject) -> PyResult<()> {
-        txn.transact(|txn| self._set(txn, key, value))
+    /// If `value` is a preliminary shared type, it's returned once integrated, so that it can be
+    /// edited further. Otherwise `None` is returned.
+    pub fn set(
+        &mut self,
+        txn: &mut YTransaction,
+        key: &str,
+        value: PyObject,
+    ) -> PyResult<PyObject> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._set(txn, key, value.clone()))
+        })??;
+        Ok(Python::with_gil(|py| integrated_or_none(py, &value)))
     }
 
-    fn _set(&mut self, txn: &mut YTransactionInner, key: &str, value: PyObject) {
+    fn _set(&mut self, txn: &mut YTransactionInner, key: &str, value: PyObject) -> PyResult<()> {
         match &mut self.0 {
-            SharedType::Integrated(v) => {
-                v.insert(
-                    txn,
-                    key.to_string(),
-                    PyObjectWrapper::new(value, v.doc.clone()),
-                );
-            }
+            SharedType::Integrated(v) => Python::with_gil(|py| {
+                // Convert primitive values upfront, so that invalid values are reported to the
+                // caller instead of being silently dropped during integration.
+                let py_type: CompatiblePyType = value.extract(py)?;
+                if let CompatiblePyType::YType(y_type) = py_type {
+                    guard_prelim(&y_type)?;
+                    let wrapped = PyObjectWrapper::new(value.clone(), v.doc.clone());
+                    v.insert(txn, key.to_string(), wrapped);
+                } else {
+                    v.insert(txn, key.to_string(), Any::try_from(py_type)?);
+                }
+                Ok(())
+            }),
             SharedType::Prelim(v) => {
                 v.insert(key.to_string(), value);
+                Ok(())
             }
         }
     }
-    /// Updates `YMap` with the key value pairs in the `items` object.
+    /// Sets all entries of `items` - a dict or an iterable of `(key, value)` pairs - within this
+    /// instance of `YMap`. Values are converted in a single pass before any of them is inserted,
+    /// so an invalid value leaves the map unchanged. It's a faster alternative to calling `set` for
+    /// each entry.
+    pub fn set_many(&mut self, txn: &mut YTransaction, items: PyObject) -> PyResult<()> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._set_many(txn, items))
+        })?
+    }
+
+    fn _set_many(&mut self, txn: &mut YTransactionInner, items: PyObject) -> PyResult<()> {
+        Python::with_gil(|py| {
+            let entries = Self::extract_entries(items.as_ref(py))?;
+            match &mut self.0 {
+                SharedType::Integrated(v) => {
+                    let mut values = Vec::with_capacity(entries.len());
+                    for (key, value) in entries {
+                        let value = match value.extract::<CompatiblePyType>(py)? {
+                            CompatiblePyType::YType(y_type) => {
+                                guard_prelim(&y_type)?;
+                                EntryValue::Shared(PyObjectWrapper::new(value, v.doc.clone()))
+                            }
+                            py_type => EntryValue::Any(Any::try_from(py_type)?),
+                        };
+                        values.push((key, value));
+                    }
+                    for (key, value) in values {
+                        match value {
+                            EntryValue::Any(any) => {
+                                v.insert(txn, key, any);
+                            }
+                            EntryValue::Shared(wrapped) => {
+                                v.insert(txn, key, wrapped);
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+                SharedType::Prelim(v) => {
+                    v.extend(entries);
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    /// Updates `YMap` with the key value pairs in the `items` object. `items` can be a dict, an
+    /// iterable of `(key, value)` pairs, another `YMap` or a `YArray` of `[key, value]` pairs. Entries
+    /// of a `YMap` or `YArray` are deep-copied: nested shared types are inserted as new preliminary
+    /// copies, so integrated ones are never moved out of their current parent.
     pub fn update(&mut self, txn: &mut YTransaction, items: PyObject) -> PyResult<()> {
-        txn.transact(|txn| self._update(txn, items))?
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._update(txn, items))
+        })?
     }
 
     fn _update(&mut self, txn: &mut YTransactionInner, items: PyObject) -> PyResult<()> {
         Python::with_gil(|py| {
+            // Copy contents of other shared types, detaching them from their document
+            let items = match YPyType::try_from(items.as_ref(py)) {
+                Ok(YPyType::Map(_) | YPyType::Array(_)) => py_into_prelim(txn, items.as_ref(py))?,
+                _ => items,
+            };
+            if let Ok(map) = items.extract::<PyRef<YMap>>(py) {
+                if let SharedType::Prelim(entries) = &map.0 {
+                    return entries
+                        .iter()
+                        .try_for_each(|(k, v)| self._set(txn, k, v.clone()));
+                }
+            }
             // Handle collection types
             if let Ok(dict) = items.extract::<HashMap<String, PyObject>>(py) {
-                dict.into_iter().for_each(|(k, v)| self._set(txn, &k, v));
-                return Ok(());
+                return dict
+                    .into_iter()
+                    .try_for_each(|(k, v)| self._set(txn, &k, v));
             }
             // Handle iterable of tuples
             match items.as_ref(py).iter() {
@@ -158,8 +405,8 @@ impl YMap {
                     for value in iterable {
                         match value {
                             Ok(kv_pair) => {
-                                if let Ok((key, value)) = kv_pair.extract::<(String, PyObject)>() {
-                                    self._set(txn, &key, value);
+                                if let Some((key, value)) = Self::extract_pair(kv_pair) {
+                                    self._set(txn, &key, value)?;
                                 } else {
                                     return Err(PyTypeError::new_err(format!("Update items should be formatted as (str, value) tuples, found: {}", kv_pair)));
                                 }
@@ -174,6 +421,58 @@ impl YMap {
         })
     }
 
+    /// Merges top-level entries of a preliminary `prelim_map` into this `YMap`, instead of nesting
+    /// it as a child. Entries under colliding keys are overwritten. Nested preliminary types are
+    /// copied before being integrated, so `prelim_map` stays preliminary and can be reused.
+    pub fn merge_from(&mut self, txn: &mut YTransaction, prelim_map: &YMap) -> PyResult<()> {
+        match &prelim_map.0 {
+            SharedType::Prelim(entries) => txn.transact(|txn| {
+                self.0
+                    .guard_alive(&*txn)
+                    .and_then(|_| self._merge_from(txn, entries))
+            })?,
+            SharedType::Integrated(_) => Err(PyTypeError::new_err(
+                "Only preliminary maps can be merged. Use `YMap.update` to copy entries of an integrated one.",
+            )),
+        }
+    }
+
+    /// Merges entries of a `dict`, another `YMap` or an iterable of `(key, value)` pairs into this
+    /// map, like `dict.__ior__`. Integrated maps are updated within the document's current
+    /// transaction or, if there's none, within a new one which is committed right away.
+    pub fn __ior__(&mut self, other: &PyAny) -> PyResult<()> {
+        match &mut self.0 {
+            SharedType::Integrated(map) => {
+                let txn = map.doc.borrow_mut().begin_transaction();
+                let mut txn = txn.borrow_mut();
+                self.0.guard_alive(&*txn)?;
+                self._update(&mut txn, other.into())
+            }
+            SharedType::Prelim(entries) => {
+                if let SharedType::Prelim(other) = YMap::new(Some(other))?.0 {
+                    entries.extend(other);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns a new preliminary `YMap` holding entries of this map merged with the ones of a `dict`
+    /// or another `YMap`, the latter taking precedence, like `dict.__or__`.
+    pub fn __or__(slf: &PyCell<Self>, other: &PyAny) -> PyResult<PyObject> {
+        let py = slf.py();
+        if !(other.is_instance_of::<PyDict>() || other.is_instance_of::<YMap>()) {
+            return Ok(py.NotImplemented());
+        }
+        let mut merged = YMap::new(Some(slf))?;
+        if let (SharedType::Prelim(entries), SharedType::Prelim(other)) =
+            (&mut merged.0, YMap::new(Some(other))?.0)
+        {
+            entries.extend(other);
+        }
+        Ok(merged.into_py(py))
+    }
+
     /// Removes an entry identified by a given `key` from this instance of `YMap`, if such exists.
     pub fn pop(
         &mut self,
@@ -181,7 +480,11 @@ impl YMap {
         key: &str,
         fallback: Option<PyObject>,
     ) -> PyResult<PyObject> {
-        txn.transact(|txn| self._pop(txn, key, fallback))?
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._pop(txn, key, fallback))
+        })?
     }
 
     fn _pop(
@@ -194,7 +497,8 @@ impl YMap {
             SharedType::Integrated(v) => v
                 .inner
                 .remove(txn, key)
-                .map(|value| Python::with_gil(|py| value.with_doc_into_py(v.doc.clone(), py))),
+                .map(|value| Python::with_gil(|py| value.with_doc_into_py(v.doc.clone(), py)))
+                .transpose()?,
             SharedType::Prelim(v) => v.remove(key),
         };
         if let Some(value) = popped {
@@ -206,30 +510,107 @@ impl YMap {
         }
     }
 
-    /// Retrieves an item from the map. If the item isn't found, the fallback value is returned.
-    pub fn get(&self, key: &str, fallback: Option<PyObject>) -> PyObject {
-        self.__getitem__(key)
-            .ok()
-            .unwrap_or_else(|| fallback.unwrap_or_else(|| Python::with_gil(|py| py.None())))
+    /// Removes an arbitrary entry from this instance of `YMap` and returns it as a `(key, value)`
+    /// tuple. Raises `KeyError` if the map is empty.
+    pub fn popitem(&mut self, txn: &mut YTransaction) -> PyResult<(String, PyObject)> {
+        txn.transact(|txn| self.0.guard_alive(&*txn).and_then(|_| self._popitem(txn)))?
     }
 
-    /// Returns value of an entry stored under given `key` within this instance of `YMap`,
-    /// or `undefined` if no such entry existed.
+    fn _popitem(&mut self, txn: &mut YTransactionInner) -> PyResult<(String, PyObject)> {
+        let key = match &self.0 {
+            SharedType::Integrated(v) => v.keys(txn).next().map(|key| key.to_string()),
+            SharedType::Prelim(v) => v.keys().next().cloned(),
+        };
+        match key {
+            Some(key) => {
+                let value = self._pop(txn, &key, None)?;
+                Ok((key, value))
+            }
+            None => Err(PyKeyError::new_err("popitem(): map is empty")),
+        }
+    }
+
+    /// Retrieves an item from the map. If the item isn't found, the `default` value (`None` unless
+    /// specified) is returned. Unlike `__getitem__`, this method never raises. Without a `default`,
+    /// a missing entry can't be told apart from one storing `None` - use `in` to check for that.
+    /// If `materialize` is set, a nested shared type is returned as a snapshot instead of a live
+    /// instance.
+    pub fn get(
+        &self,
+        key: &str,
+        default: Option<PyObject>,
+        materialize: Option<bool>,
+    ) -> PyResult<PyObject> {
+        Python::with_gil(|py| match self._get(key)? {
+            Some(value) => materialize_value(py, value, materialize.unwrap_or(false)),
+            None => Ok(default.unwrap_or_else(|| py.None())),
+        })
+    }
+
+    /// Returns value of an entry stored under given `key` within this instance of `YMap`, which is
+    /// `None` for entries explicitly set to `None`. Raises a `KeyError` if no such entry existed.
     pub fn __getitem__(&self, key: &str) -> PyResult<PyObject> {
-        let entry = match &self.0 {
+        self._get(key)?
+            .ok_or_else(|| PyKeyError::new_err(key.to_string()))
+    }
+
+    /// Returns value of an entry stored under given `key`, checking that its kind (as reported by
+    /// `type_of`) is `expected_kind` first. Raises a `KeyError` if no such entry existed and
+    /// a `TypeError` if the stored value is of a different kind.
+    pub fn get_typed(&self, key: &str, expected_kind: &str) -> PyResult<PyObject> {
+        if !VALUE_KINDS.contains(&expected_kind) {
+            return Err(PyValueError::new_err(format!(
+                "'{expected_kind}' is not a valid value kind (expected one of: {}).",
+                VALUE_KINDS.join(", ")
+            )));
+        }
+        let kind = self.type_of(key)?;
+        if kind != expected_kind {
+            return Err(PyTypeError::new_err(format!(
+                "Expected a value of kind '{expected_kind}' under key '{key}', found '{kind}'."
+            )));
+        }
+        self.__getitem__(key)
+    }
+
+    fn _get(&self, key: &str) -> PyResult<Option<PyObject>> {
+        match &self.0 {
             SharedType::Integrated(y_map) => y_map.with_transaction(|txn| {
-                y_map.inner.get(txn, key).map(|value| {
-                    Python::with_gil(|py| value.with_doc_into_py(y_map.doc.clone(), py))
-                })
+                y_map
+                    .inner
+                    .get(txn, key)
+                    .map(|value| {
+                        Python::with_gil(|py| value.with_doc_into_py(y_map.doc.clone(), py))
+                    })
+                    .transpose()
+            }),
+            SharedType::Prelim(hash_map) => Ok(hash_map.get(key).cloned()),
+        }
+    }
+
+    /// Returns a string tag describing the kind of value stored under a given `key`: `"map"`,
+    /// `"array"`, `"text"`, `"xml_element"`, `"xml_text"`, `"xml_fragment"`, `"doc"` or
+    /// `"primitive"` for any other value. The value itself is not converted into a Python object.
+    pub fn type_of(&self, key: &str) -> PyResult<&'static str> {
+        let tag = match &self.0 {
+            SharedType::Integrated(y_map) => y_map.with_transaction(|txn| {
+                y_map
+                    .inner
+                    .get(txn, key)
+                    .map(|value| value_type_tag(&value))
             }),
-            SharedType::Prelim(hash_map) => hash_map.get(key).cloned(),
+            SharedType::Prelim(hash_map) => hash_map
+                .get(key)
+                .map(|value| Python::with_gil(|py| py_type_tag(value.as_ref(py)))),
         };
 
-        entry.ok_or_else(|| PyKeyError::new_err(key.to_string()))
+        tag.ok_or_else(|| PyKeyError::new_err(key.to_string()))
     }
 
     /// Returns an item view that can be used to traverse over all entries stored within this
-    /// instance of `YMap`. Order of entry is not specified.
+    /// instance of `YMap`. Order of entries is not specified, unless `sorted` is set, in which case
+    /// they're ordered by their keys. If a `txn` is provided, the view reads the map through it,
+    /// which makes changes made within that transaction visible.
     ///
     /// Example:
     ///
@@ -242,74 +623,175 @@ impl YMap {
     /// with doc.begin_transaction() as txn:
     ///     map.set(txn, 'key1', 'value1')
     ///     map.set(txn, 'key2', true)
-    ///     for (key, value) in map.entries(txn)):
+    ///     for (key, value) in map.items(txn):
     ///         print(key, value)
     /// ```
-
-    pub fn items(&self) -> ItemView {
-        ItemView::new(self)
+    pub fn items(
+        &self,
+        txn: Option<PyRef<YTransaction>>,
+        sorted: Option<bool>,
+        materialize: Option<bool>,
+    ) -> PyResult<ItemView> {
+        let txn = Self::view_transaction(txn.as_deref())?;
+        let view = ItemView::new(self, txn, sorted.unwrap_or(false));
+        Ok(view.with_materialize(materialize.unwrap_or(false)))
     }
 
-    pub fn keys(&self) -> KeyView {
-        KeyView::new(self)
+    /// Returns a view over the keys of this `YMap`, optionally read through a given `txn`. Keys
+    /// are yielded in sorted order if `sorted` is set.
+    pub fn keys(
+        &self,
+        txn: Option<PyRef<YTransaction>>,
+        sorted: Option<bool>,
+    ) -> PyResult<KeyView> {
+        let txn = Self::view_transaction(txn.as_deref())?;
+        Ok(KeyView::new(self, txn, sorted.unwrap_or(false)))
     }
 
-    pub fn __iter__(&self) -> KeyIterator {
-        self.keys().__iter__()
+    pub fn __iter__(&self) -> PyResult<KeyIterator> {
+        KeyView::new(self, None, false).__iter__()
     }
 
-    pub fn values(&self) -> ValueView {
-        ValueView::new(self)
+    /// Returns a view over the values of this `YMap`, optionally read through a given `txn`.
+    /// Values are yielded in the order of their keys if `sorted` is set. If `materialize` is set,
+    /// nested shared types are yielded as snapshots instead of live instances.
+    pub fn values(
+        &self,
+        txn: Option<PyRef<YTransaction>>,
+        sorted: Option<bool>,
+        materialize: Option<bool>,
+    ) -> PyResult<ValueView> {
+        let txn = Self::view_transaction(txn.as_deref())?;
+        let view = ValueView::new(self, txn, sorted.unwrap_or(false));
+        Ok(view.with_materialize(materialize.unwrap_or(false)))
     }
 
-    pub fn observe(&mut self, f: PyObject) -> PyResult<ShallowSubscription> {
+    /// Subscribes to all operations happening over this instance of `YMap`. If `keys` are given,
+    /// the callback is only called for transactions which changed at least one of them, and
+    /// the `keys` of the events it receives only describe changes of these keys. If `shallow_keys`
+    /// is set, only entries being added or removed are reported, while values being replaced under
+    /// existing keys are ignored.
+    pub fn observe(
+        &mut self,
+        f: PyObject,
+        keys: Option<Vec<String>>,
+        shallow_keys: Option<bool>,
+        weak: Option<bool>,
+    ) -> PyResult<ShallowSubscription> {
         match &mut self.0 {
             SharedType::Integrated(v) => {
+                let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+                let callback = f.clone();
                 let doc = v.doc.clone();
+                let key_filter = KeyFilter::new(keys, shallow_keys.unwrap_or(false));
                 let sub_id: SubscriptionId = v
                     .inner
                     .observe(move |txn: &TransactionMut, e| {
+                        if let Some(key_filter) = &key_filter {
+                            let mut changes = e.keys(txn).iter();
+                            if !changes.any(|(key, change)| key_filter.matches(key, change)) {
+                                return;
+                            }
+                        }
                         Python::with_gil(|py| {
-                            let e = YMapEvent::new(e, txn, doc.clone());
-                            if let Err(err) = f.call1(py, (e,)) {
+                            let e = YMapEvent::new(e, txn, doc.clone())
+                                .with_key_filter(key_filter.clone());
+                            if let Err(err) =
+                                with_committing_transaction(&doc, txn, || f.call1(py, (e,)))
+                            {
                                 err.restore(py)
                             }
                         })
                     })
                     .into();
-                Ok(ShallowSubscription(sub_id))
+                let subscription = ShallowSubscription::new(sub_id, v.inner.clone());
+                callback.cancel_with({
+                    let subscription = subscription.clone();
+                    move || subscription.unobserve()
+                });
+                Ok(subscription)
             }
             SharedType::Prelim(_) => Err(PreliminaryObservationException::default_message()),
         }
     }
 
-    pub fn observe_deep(&mut self, f: PyObject) -> PyResult<DeepSubscription> {
+    pub fn observe_deep(&mut self, f: PyObject, weak: Option<bool>) -> PyResult<DeepSubscription> {
         match &mut self.0 {
             SharedType::Integrated(map) => {
+                let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+                let callback = f.clone();
                 let doc = map.doc.clone();
                 let sub: SubscriptionId = map
                     .inner
                     .observe_deep(move |txn, events| {
                         Python::with_gil(|py| {
                             let events = events_into_py(txn, events, doc.clone());
-                            if let Err(err) = f.call1(py, (events,)) {
+                            if let Err(err) =
+                                with_committing_transaction(&doc, txn, || f.call1(py, (events,)))
+                            {
+                                err.restore(py)
+                            }
+                        })
+                    })
+                    .into();
+                let subscription = DeepSubscription::new(sub, map.inner.clone());
+                callback.cancel_with({
+                    let subscription = subscription.clone();
+                    move || subscription.unobserve()
+                });
+                Ok(subscription)
+            }
+            SharedType::Prelim(_) => Err(PreliminaryObservationException::default_message()),
+        }
+    }
+    /// Observes changes made to this `YMap` and all of its nested shared types. Instead of a list
+    /// of events, the callback receives a single dictionary per transaction, keyed by the
+    /// `/`-separated path of each changed shared type relative to this one. Each value describes
+    /// the changes with a `delta` and/or `keys` entry, following the format of the corresponding
+    /// event type. Returns a `SubscriptionId` which can be used to cancel the callback with
+    /// `unobserve`.
+    pub fn observe_changes(
+        &mut self,
+        f: PyObject,
+        weak: Option<bool>,
+    ) -> PyResult<DeepSubscription> {
+        match &mut self.0 {
+            SharedType::Integrated(map) => {
+                let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+                let callback = f.clone();
+                let doc = map.doc.clone();
+                let sub: SubscriptionId = map
+                    .inner
+                    .observe_deep(move |txn, events| {
+                        Python::with_gil(|py| {
+                            let result = with_committing_transaction(&doc, txn, || {
+                                let changes = events_into_changes(txn, events, doc.clone())?;
+                                f.call1(py, (changes,))
+                            });
+                            if let Err(err) = result {
                                 err.restore(py)
                             }
                         })
                     })
                     .into();
-                Ok(DeepSubscription(sub))
+                let subscription = DeepSubscription::new(sub, map.inner.clone());
+                callback.cancel_with({
+                    let subscription = subscription.clone();
+                    move || subscription.unobserve()
+                });
+                Ok(subscription)
             }
             SharedType::Prelim(_) => Err(PreliminaryObservationException::default_message()),
         }
     }
+
     /// Cancels the observer callback associated with the `subscripton_id`.
     pub fn unobserve(&mut self, subscription_id: SubId) -> PyResult<()> {
         match &mut self.0 {
             SharedType::Integrated(map) => {
                 match subscription_id {
-                    SubId::Shallow(ShallowSubscription(id)) => map.unobserve(id),
-                    SubId::Deep(DeepSubscription(id)) => map.unobserve_deep(id),
+                    SubId::Shallow(ShallowSubscription(id, _)) => map.unobserve(id),
+                    SubId::Deep(DeepSubscription(id, _)) => map.unobserve_deep(id),
                 }
                 Ok(())
             }
@@ -318,165 +800,319 @@ impl YMap {
     }
 }
 
+impl YMap {
+    /// Returns a number of entries stored within this instance of `YMap` using a provided
+    /// transaction.
+    fn _len(&self, txn: &TransactionMut) -> usize {
+        match &self.0 {
+            SharedType::Integrated(v) => v.len(txn) as usize,
+            SharedType::Prelim(v) => v.len(),
+        }
+    }
+
+    /// Sets all `entries` of a preliminary map as top-level entries of this `YMap`.
+    fn _merge_from(
+        &mut self,
+        txn: &mut YTransactionInner,
+        entries: &HashMap<String, PyObject>,
+    ) -> PyResult<()> {
+        Python::with_gil(|py| {
+            entries.iter().try_for_each(|(key, value)| {
+                let value = py_into_prelim(txn, value.as_ref(py))?;
+                self._set(txn, key, value)
+            })
+        })
+    }
+
+    /// Collects entries of a dict or an iterable of `(key, value)` pairs.
+    fn extract_entries(items: &PyAny) -> PyResult<Vec<(String, PyObject)>> {
+        if let Ok(dict) = items.downcast::<PyDict>() {
+            return dict
+                .iter()
+                .map(|(key, value)| Ok((key.extract::<String>()?, value.into())))
+                .collect();
+        }
+        items
+            .iter()?
+            .map(|pair| {
+                let pair = pair?;
+                Self::extract_pair(pair).ok_or_else(|| {
+                    PyTypeError::new_err(format!(
+                        "Items should be formatted as (str, value) tuples, found: {pair}"
+                    ))
+                })
+            })
+            .collect()
+    }
+
+    /// Extracts a key-value entry from either a `(key, value)` tuple or a `[key, value]` list.
+    fn extract_pair(pair: &PyAny) -> Option<(String, PyObject)> {
+        pair.extract::<(String, PyObject)>().ok().or_else(|| {
+            let [key, value] = pair.extract::<[&PyAny; 2]>().ok()?;
+            Some((key.extract::<String>().ok()?, value.into()))
+        })
+    }
+
+    fn view_transaction(
+        txn: Option<&YTransaction>,
+    ) -> PyResult<Option<Rc<RefCell<YTransactionInner>>>> {
+        txn.map(|txn| txn.get_active_inner()).transpose()
+    }
+}
+
+/// Restricts the key changes reported to `YMap.observe` callbacks.
+struct KeyFilter {
+    /// Keys to report changes of, all keys if `None`.
+    keys: Option<HashSet<String>>,
+    /// Whether only entries being added or removed should be reported.
+    shallow: bool,
+}
+
+impl KeyFilter {
+    /// Returns a filter for given observer options, or `None` if no changes are filtered out.
+    fn new(keys: Option<Vec<String>>, shallow: bool) -> Option<Rc<Self>> {
+        if keys.is_none() && !shallow {
+            return None;
+        }
+        let keys = keys.map(|keys| keys.into_iter().collect());
+        Some(Rc::new(KeyFilter { keys, shallow }))
+    }
+
+    fn matches(&self, key: &str, change: &EntryChange) -> bool {
+        let key_matches = self.keys.as_ref().map_or(true, |keys| keys.contains(key));
+        key_matches && !(self.shallow && matches!(change, EntryChange::Updated(_, _)))
+    }
+}
+
+/// A value of a map entry converted ahead of its insertion.
+enum EntryValue {
+    Any(Any),
+    Shared(PyObjectWrapper),
+}
+
+/// Transaction used by map views: either provided explicitly by the caller or (when `None`)
+/// the current transaction of the map's document.
+type ViewTransaction = Option<Rc<RefCell<YTransactionInner>>>;
+
+/// Runs `f` with a given view transaction. Views and their iterators may outlive a transaction
+/// provided explicitly, so an error is returned once it has been committed.
+fn with_view_transaction<F, R>(
+    map: &TypeWithDoc<MapRef>,
+    txn: &ViewTransaction,
+    f: F,
+) -> PyResult<R>
+where
+    F: FnOnce(&TransactionMut<'static>) -> R,
+{
+    guard_view_transaction(txn)?;
+    match txn {
+        Some(txn) => Ok(f(&txn.borrow())),
+        None => Ok(map.with_transaction(f)),
+    }
+}
+
+/// Returns an error if a view transaction provided explicitly has already been committed.
+fn guard_view_transaction(txn: &ViewTransaction) -> PyResult<()> {
+    match txn {
+        Some(txn) if txn.borrow().committed => Err(TransactionError::new_err(
+            "Transaction used by this map view has already been committed.",
+        )),
+        _ => Ok(()),
+    }
+}
+
 #[pyclass(unsendable)]
-pub struct ItemView(*const YMap);
+pub struct ItemView(*const YMap, ViewTransaction, bool, bool);
 
 impl ItemView {
-    pub fn new(map: &YMap) -> Self {
+    pub fn new(map: &YMap, txn: ViewTransaction, sorted: bool) -> Self {
         let inner = map as *const YMap;
-        ItemView(inner)
+        ItemView(inner, txn, sorted, false)
+    }
+
+    /// Makes the view yield nested shared types as snapshots instead of live instances.
+    pub fn with_materialize(mut self, materialize: bool) -> Self {
+        self.3 = materialize;
+        self
     }
 }
 
 #[pymethods]
 impl ItemView {
-    fn __iter__(slf: PyRef<Self>) -> YMapIterator {
-        YMapIterator::from(slf.0)
+    fn __iter__(slf: PyRef<Self>) -> PyResult<YMapIterator> {
+        Ok(YMapIterator::new(slf.0, slf.1.clone(), slf.2)?.with_materialize(slf.3))
     }
 
-    fn __len__(&self) -> usize {
+    fn __len__(&self) -> PyResult<usize> {
         let ymap = unsafe { &*self.0 };
         match &ymap.0 {
-            SharedType::Integrated(map) => map.with_transaction(|txn| map.len(txn) as usize),
-            SharedType::Prelim(map) => map.len(),
+            SharedType::Integrated(map) => {
+                with_view_transaction(map, &self.1, |txn| map.len(txn) as usize)
+            }
+            SharedType::Prelim(map) => Ok(map.len()),
         }
     }
 
-    fn __str__(&self) -> String {
-        let vals: String = YMapIterator::from(self.0)
-            .map(|(key, val)| format!("({key}, {val})"))
-            .collect::<Vec<String>>()
+    fn __str__(&self) -> PyResult<String> {
+        let vals: String = YMapIterator::new(self.0, self.1.clone(), self.2)?
+            .map(|entry| entry.map(|(key, val)| format!("({key}, {val})")))
+            .collect::<PyResult<Vec<String>>>()?
             .join(", ");
-        format!("{{{vals}}}")
+        Ok(format!("{{{vals}}}"))
     }
 
-    fn __repr__(&self) -> String {
-        let data = self.__str__();
-        format!("ItemView({data})")
+    fn __repr__(&self) -> PyResult<String> {
+        let data = self.__str__()?;
+        Ok(format!("ItemView({data})"))
     }
 
-    fn __contains__(&self, el: PyObject) -> bool {
+    fn __contains__(&self, el: PyObject) -> PyResult<bool> {
         let ymap = unsafe { &*self.0 };
         let kv: Result<(String, PyObject), _> = Python::with_gil(|py| el.extract(py));
-        kv.ok()
-            .and_then(|(key, value)| match &ymap.0 {
-                SharedType::Integrated(map) => map.with_transaction(|txn| {
-                    if map.contains_key(txn, &key) {
-                        map.get(txn, &key).map(|v| {
-                            Python::with_gil(|py| {
-                                v.with_doc_into_py(map.doc.clone(), py).as_ref(py).eq(value)
-                            })
-                            .unwrap_or(false)
+        let Ok((key, value)) = kv else {
+            return Ok(false);
+        };
+        let contains = match &ymap.0 {
+            SharedType::Integrated(map) => with_view_transaction(map, &self.1, |txn| {
+                if map.contains_key(txn, &key) {
+                    map.get(txn, &key).map(|v| {
+                        Python::with_gil(|py| {
+                            let v = v.with_doc_into_py(map.doc.clone(), py)?;
+                            materialize_value(py, v, self.3)?.as_ref(py).eq(value)
                         })
-                    } else {
-                        None
-                    }
-                }),
-                SharedType::Prelim(map) if map.contains_key(&key) => map
-                    .get(&key)
-                    .map(|v| Python::with_gil(|py| v.as_ref(py).eq(value).unwrap_or(false))),
-                _ => None,
-            })
-            .unwrap_or(false)
+                        .unwrap_or(false)
+                    })
+                } else {
+                    None
+                }
+            })?,
+            SharedType::Prelim(map) if map.contains_key(&key) => map.get(&key).map(|v| {
+                Python::with_gil(|py| {
+                    materialize_value(py, v.clone(), self.3)?
+                        .as_ref(py)
+                        .eq(value)
+                })
+                .unwrap_or(false)
+            }),
+            _ => None,
+        };
+        Ok(contains.unwrap_or(false))
     }
 }
 
 #[pyclass(unsendable)]
-pub struct KeyView(*const YMap);
+pub struct KeyView(*const YMap, ViewTransaction, bool);
 
 impl KeyView {
-    pub fn new(map: &YMap) -> Self {
+    pub fn new(map: &YMap, txn: ViewTransaction, sorted: bool) -> Self {
         let inner = map as *const YMap;
-        KeyView(inner)
+        KeyView(inner, txn, sorted)
     }
 }
 
 #[pymethods]
 impl KeyView {
-    fn __iter__(&self) -> KeyIterator {
-        KeyIterator(YMapIterator::from(self.0))
+    fn __iter__(&self) -> PyResult<KeyIterator> {
+        Ok(KeyIterator(YMapIterator::new(
+            self.0,
+            self.1.clone(),
+            self.2,
+        )?))
     }
 
-    fn __len__(&self) -> usize {
+    fn __len__(&self) -> PyResult<usize> {
         let ymap = unsafe { &*self.0 };
         match &ymap.0 {
-            SharedType::Integrated(map) => map.with_transaction(|txn| map.len(txn) as usize),
-            SharedType::Prelim(map) => map.len(),
+            SharedType::Integrated(map) => {
+                with_view_transaction(map, &self.1, |txn| map.len(txn) as usize)
+            }
+            SharedType::Prelim(map) => Ok(map.len()),
         }
     }
 
-    fn __str__(&self) -> String {
-        let vals: String = YMapIterator::from(self.0)
-            .map(|(key, _)| key)
-            .collect::<Vec<String>>()
+    fn __str__(&self) -> PyResult<String> {
+        let vals: String = YMapIterator::new(self.0, self.1.clone(), self.2)?
+            .map(|entry| entry.map(|(key, _)| key))
+            .collect::<PyResult<Vec<String>>>()?
             .join(", ");
-        format!("{{{vals}}}")
+        Ok(format!("{{{vals}}}"))
     }
 
-    fn __repr__(&self) -> String {
-        let data = self.__str__();
-        format!("KeyView({data})")
+    fn __repr__(&self) -> PyResult<String> {
+        let data = self.__str__()?;
+        Ok(format!("KeyView({data})"))
     }
 
-    fn __contains__(&self, el: PyObject) -> bool {
+    fn __contains__(&self, el: PyObject) -> PyResult<bool> {
         let ymap = unsafe { &*self.0 };
         let key: Result<String, _> = Python::with_gil(|py| el.extract(py));
-        key.ok()
-            .map(|key| match &ymap.0 {
-                SharedType::Integrated(map) => {
-                    map.with_transaction(|txn| map.contains_key(txn, &key))
-                }
-                SharedType::Prelim(map) => map.contains_key(&key),
-            })
-            .unwrap_or(false)
+        match (key, &ymap.0) {
+            (Ok(key), SharedType::Integrated(map)) => {
+                with_view_transaction(map, &self.1, |txn| map.contains_key(txn, &key))
+            }
+            (Ok(key), SharedType::Prelim(map)) => Ok(map.contains_key(&key)),
+            (Err(_), _) => Ok(false),
+        }
     }
 }
 
 #[pyclass(unsendable)]
-pub struct ValueView(*const YMap);
+pub struct ValueView(*const YMap, ViewTransaction, bool, bool);
 
 impl ValueView {
-    pub fn new(map: &YMap) -> Self {
+    pub fn new(map: &YMap, txn: ViewTransaction, sorted: bool) -> Self {
         let inner = map as *const YMap;
-        ValueView(inner)
+        ValueView(inner, txn, sorted, false)
+    }
+
+    /// Makes the view yield nested shared types as snapshots instead of live instances.
+    pub fn with_materialize(mut self, materialize: bool) -> Self {
+        self.3 = materialize;
+        self
     }
 }
 
 #[pymethods]
 impl ValueView {
-    fn __iter__(slf: PyRef<Self>) -> ValueIterator {
-        ValueIterator(YMapIterator::from(slf.0))
+    fn __iter__(slf: PyRef<Self>) -> PyResult<ValueIterator> {
+        let iter = YMapIterator::new(slf.0, slf.1.clone(), slf.2)?;
+        Ok(ValueIterator(iter.with_materialize(slf.3)))
     }
 
-    fn __len__(&self) -> usize {
+    fn __len__(&self) -> PyResult<usize> {
         let ymap = unsafe { &*self.0 };
         match &ymap.0 {
-            SharedType::Integrated(map) => map.with_transaction(|txn| map.len(txn) as usize),
-            SharedType::Prelim(map) => map.len(),
+            SharedType::Integrated(map) => {
+                with_view_transaction(map, &self.1, |txn| map.len(txn) as usize)
+            }
+            SharedType::Prelim(map) => Ok(map.len()),
         }
     }
 
-    fn __str__(&self) -> String {
-        let vals: String = YMapIterator::from(self.0)
-            .map(|(_, v)| v.to_string())
-            .collect::<Vec<String>>()
+    fn __str__(&self) -> PyResult<String> {
+        let vals: String = YMapIterator::new(self.0, self.1.clone(), self.2)?
+            .map(|entry| entry.map(|(_, v)| v.to_string()))
+            .collect::<PyResult<Vec<String>>>()?
             .join(", ");
-        format!("{{{vals}}}")
+        Ok(format!("{{{vals}}}"))
     }
 
-    fn __repr__(&self) -> String {
-        let data = self.__str__();
-        format!("ValueView({data})")
+    fn __repr__(&self) -> PyResult<String> {
+        let data = self.__str__()?;
+        Ok(format!("ValueView({data})"))
     }
 }
 
 pub enum InnerYMapIterator {
-    Integrated(TypeWithDoc<MapIter<'static, &'static YTransactionInner, YTransactionInner>>),
+    Integrated(
+        TypeWithDoc<MapIter<'static, &'static TransactionMut<'static>, TransactionMut<'static>>>,
+    ),
     Prelim(std::collections::hash_map::Iter<'static, String, PyObject>),
+    Sorted(std::vec::IntoIter<(String, PyObject)>),
 }
 
 #[pyclass(unsendable)]
-pub struct YMapIterator(ManuallyDrop<InnerYMapIterator>);
+pub struct YMapIterator(ManuallyDrop<InnerYMapIterator>, ViewTransaction, bool);
 
 impl Drop for YMapIterator {
     fn drop(&mut self) {
@@ -484,37 +1120,77 @@ impl Drop for YMapIterator {
     }
 }
 
-impl From<*const YMap> for YMapIterator {
-    fn from(inner_map_ptr: *const YMap) -> Self {
+impl YMapIterator {
+    /// Creates an iterator over entries of a given map. If `sorted` is set, all entries are
+    /// collected upfront, so that they can be yielded in the order of their keys.
+    fn new(inner_map_ptr: *const YMap, txn: ViewTransaction, sorted: bool) -> PyResult<Self> {
+        let iter = Self::unsorted(inner_map_ptr, txn)?;
+        if !sorted {
+            return Ok(iter);
+        }
+        let txn = iter.1.clone();
+        let mut entries = iter.collect::<PyResult<Vec<_>>>()?;
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let sorted_iter = InnerYMapIterator::Sorted(entries.into_iter());
+        Ok(YMapIterator(ManuallyDrop::new(sorted_iter), txn, false))
+    }
+
+    fn unsorted(inner_map_ptr: *const YMap, txn: ViewTransaction) -> PyResult<Self> {
         let map = unsafe { &*inner_map_ptr };
         match &map.0 {
             SharedType::Integrated(val) => {
-                let iter = val.with_transaction(|txn| {
-                    let txn = txn as *const YTransactionInner;
+                let iter = with_view_transaction(val, &txn, |txn| {
+                    let txn = txn as *const TransactionMut;
                     unsafe { val.iter(&*txn) }
-                });
+                })?;
                 let shared_iter =
                     InnerYMapIterator::Integrated(TypeWithDoc::new(iter, val.doc.clone()));
-                YMapIterator(ManuallyDrop::new(shared_iter))
+                // Keep an explicitly provided transaction around for as long as the iterator is,
+                // so that `next` can tell once it has been committed.
+                Ok(YMapIterator(ManuallyDrop::new(shared_iter), txn, false))
             }
             SharedType::Prelim(val) => {
                 let shared_iter = InnerYMapIterator::Prelim(val.iter());
-                YMapIterator(ManuallyDrop::new(shared_iter))
+                Ok(YMapIterator(ManuallyDrop::new(shared_iter), txn, false))
             }
         }
     }
+
+    /// Makes the iterator yield nested shared types as snapshots instead of live instances. Values
+    /// are only materialized when yielded to Python, so `Iterator::next` keeps returning them live.
+    fn with_materialize(mut self, materialize: bool) -> Self {
+        self.2 = materialize;
+        self
+    }
+
+    fn next_materialized(&mut self) -> PyResult<Option<(String, PyObject)>> {
+        let materialize = self.2;
+        self.next()
+            .map(|entry| {
+                let (key, value) = entry?;
+                let value = Python::with_gil(|py| materialize_value(py, value, materialize))?;
+                Ok((key, value))
+            })
+            .transpose()
+    }
 }
 
 impl Iterator for YMapIterator {
-    type Item = (String, PyObject);
+    type Item = PyResult<(String, PyObject)>;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.0.deref_mut() {
             InnerYMapIterator::Integrated(iter) => Python::with_gil(|py| {
-                iter.next()
-                    .map(|(k, v)| (k.to_string(), v.with_doc_into_py(iter.doc.clone(), py)))
+                // the underlying iterator reads through the transaction, which is released on commit
+                if let Err(err) = guard_view_transaction(&self.1) {
+                    return Some(Err(err));
+                }
+                let (k, v) = iter.next()?;
+                let value = v.with_doc_into_py(iter.doc.clone(), py);
+                Some(value.map(|v| (k.to_string(), v)))
             }),
-            InnerYMapIterator::Prelim(iter) => iter.next().map(|(k, v)| (k.clone(), v.clone())),
+            InnerYMapIterator::Prelim(iter) => iter.next().map(|(k, v)| Ok((k.clone(), v.clone()))),
+            InnerYMapIterator::Sorted(iter) => iter.next().map(Ok),
         }
     }
 }
@@ -524,8 +1200,8 @@ impl YMapIterator {
     fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
         slf
     }
-    pub fn __next__(mut slf: PyRefMut<Self>) -> Option<(String, PyObject)> {
-        slf.next()
+    pub fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<(String, PyObject)>> {
+        slf.next_materialized()
     }
 }
 
@@ -537,8 +1213,8 @@ impl KeyIterator {
     fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
         slf
     }
-    fn __next__(mut slf: PyRefMut<Self>) -> Option<String> {
-        slf.0.next().map(|(k, _)| k)
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<String>> {
+        Ok(slf.0.next().transpose()?.map(|(k, _)| k))
     }
 }
 
@@ -550,8 +1226,8 @@ impl ValueIterator {
     fn __iter__(slf: PyRef<Self>) -> PyRef<Self> {
         slf
     }
-    fn __next__(mut slf: PyRefMut<Self>) -> Option<PyObject> {
-        slf.0.next().map(|(_, v)| v)
+    fn __next__(mut slf: PyRefMut<Self>) -> PyResult<Option<PyObject>> {
+        Ok(slf.0.next_materialized()?.map(|(_, v)| v))
     }
 }
 
@@ -563,6 +1239,7 @@ pub struct YMapEvent {
     txn: *const TransactionMut<'static>,
     target: Option<PyObject>,
     keys: Option<PyObject>,
+    key_filter: Option<Rc<KeyFilter>>,
 }
 
 impl YMapEvent {
@@ -577,9 +1254,16 @@ impl YMapEvent {
             txn,
             target: None,
             keys: None,
+            key_filter: None,
         }
     }
 
+    /// Restricts the changes reported by `keys` to the ones made under given keys.
+    fn with_key_filter(mut self, key_filter: Option<Rc<KeyFilter>>) -> Self {
+        self.key_filter = key_filter;
+        self
+    }
+
     fn inner(&self) -> &MapEvent {
         unsafe { self.inner.as_ref().unwrap() }
     }
@@ -606,11 +1290,13 @@ impl YMapEvent {
         }
     }
 
-    pub fn __repr__(&mut self) -> String {
+    pub fn __repr__(&mut self) -> PyResult<String> {
         let target = self.target();
-        let keys = self.keys();
+        let keys = self.keys()?;
         let path = self.path();
-        format!("YMapEvent(target={target}, keys={keys}, path={path})")
+        Ok(format!(
+            "YMapEvent(target={target}, keys={keys}, path={path})"
+        ))
     }
 
     /// Returns an array of keys and indexes creating a path from root type down to current instance
@@ -619,29 +1305,45 @@ impl YMapEvent {
         Python::with_gil(|py| self.inner().path().into_py(py))
     }
 
+    /// Returns the origin of the transaction which has made the changes, or `None` if it has no
+    /// origin, e.g. `apply_update(doc, update, origin="remote")` reports `"remote"`.
+    #[getter]
+    pub fn origin(&self) -> PyObject {
+        Python::with_gil(|py| origin_into_py(self.txn().origin(), py))
+    }
+
+    /// Returns `"map"` - the kind of the `target` shared type, as reported by `type_of`.
+    #[getter]
+    pub fn kind(&self) -> &'static str {
+        "map"
+    }
+
     // Returns a list of key-value changes made over corresponding `YMap` collection within
     // bounds of current transaction. These changes follow a format:
     //
     // / - { action: 'add'|'update'|'delete', oldValue: any|undefined, newValue: any|undefined }
     #[getter]
-    pub fn keys(&mut self) -> PyObject {
+    pub fn keys(&mut self) -> PyResult<PyObject> {
         if let Some(keys) = &self.keys {
-            keys.clone()
+            Ok(keys.clone())
         } else {
             let keys: PyObject = Python::with_gil(|py| {
                 let keys = self.inner().keys(self.txn());
                 let result = PyDict::new(py);
                 for (key, value) in keys.iter() {
                     let key = &**key;
-                    result
-                        .set_item(key, value.with_doc_into_py(self.doc.clone(), py))
-                        .unwrap();
+                    if let Some(key_filter) = &self.key_filter {
+                        if !key_filter.matches(key, value) {
+                            continue;
+                        }
+                    }
+                    result.set_item(key, value.with_doc_into_py(self.doc.clone(), py)?)?;
                 }
-                result.into()
-            });
+                PyResult::Ok(result.into())
+            })?;
 
             self.keys = Some(keys.clone());
-            keys
+            Ok(keys)
         }
     }
 }
@@ -0,0 +1,37 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use pyo3::prelude::*;
+use yrs::StickyIndex;
+
+use crate::shared_types::TypeWithDoc;
+use crate::y_doc::YDocInner;
+
+/// A position within a sequential shared type (e.g. `YText`) which sticks to the content around
+/// it. Unlike a plain index, it keeps pointing at the same place when local or concurrent remote
+/// edits insert or remove content in front of it.
+#[pyclass(unsendable)]
+pub struct YStickyIndex(TypeWithDoc<StickyIndex>);
+
+impl YStickyIndex {
+    pub fn new(index: StickyIndex, doc: Rc<RefCell<YDocInner>>) -> Self {
+        YStickyIndex(TypeWithDoc::new(index, doc))
+    }
+}
+
+#[pymethods]
+impl YStickyIndex {
+    /// Returns the index this position currently corresponds to, measured in the same unit as the
+    /// indexes of the shared type it was created for. Returns `None` if that type no longer exists.
+    pub fn index(&self) -> Option<u32> {
+        self.0
+            .with_transaction(|txn| self.0.get_offset(txn).map(|offset| offset.index))
+    }
+
+    pub fn __repr__(&self) -> String {
+        match self.index() {
+            Some(index) => format!("YStickyIndex({index})"),
+            None => "YStickyIndex(None)".to_string(),
+        }
+    }
+}
@@ -1,22 +1,31 @@
 use crate::shared_types::{
-    CompatiblePyType, DeepSubscription, DefaultPyErr, IntegratedOperationException,
-    PreliminaryObservationException, ShallowSubscription, SharedType, SubId, TypeWithDoc,
+    insert_position, integrated_copy_error, CompatiblePyType, DeepSubscription, DefaultPyErr,
+    InitialState, IntegratedOperationException, ObserverCallback, PreliminaryObservationException,
+    ShallowSubscription, SharedType, SubId, TypeWithDoc,
+};
+use crate::type_conversions::{events_into_py, ToPython, WithDocToPython, REPR_MAX_CHARS};
+use crate::y_doc::{with_committing_transaction, WithDoc, YDocInner};
+use crate::y_sticky_index::YStickyIndex;
+use crate::y_transaction::{
+    origin_into_py, EncodingException, TransactionError, YTransaction, YTransactionInner,
 };
-use crate::type_conversions::{events_into_py, ToPython, WithDocToPython};
-use crate::y_doc::{WithDoc, YDocInner};
-use crate::y_transaction::{YTransaction, YTransactionInner};
 use lib0::any::Any;
+use pyo3::exceptions::{PyIndexError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::PyList;
+use pyo3::types::{PyDict, PyList};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::TryInto;
 use std::rc::Rc;
 use std::sync::Arc;
 use yrs::types::text::TextEvent;
-use yrs::types::Attrs;
 use yrs::types::DeepObservable;
-use yrs::{GetString, Observable, Text, TextRef, TransactionMut};
+use yrs::types::{Attrs, Delta, Value};
+use yrs::updates::decoder::Decode;
+use yrs::{
+    Assoc, GetString, IndexedSequence, Observable, OffsetKind, Snapshot, Text, TextRef,
+    TransactionMut,
+};
 
 /// A shared data type used for collaborative text editing. It enables multiple users to add and
 /// remove chunks of text in efficient manner. This type is internally represented as a mutable
@@ -59,11 +68,65 @@ impl YText {
     /// Preliminary instances can be nested into other shared data types such as `YArray` and `YMap`.
     /// Once a preliminary instance has been inserted this way, it becomes integrated into Ypy
     /// document store and cannot be nested again: attempt to do so will result in an exception.
+    /// The inserted object itself is integrated in place, so it can be used for further edits.
     #[getter]
     pub fn prelim(&self) -> bool {
         matches!(self.0, SharedType::Prelim(_))
     }
 
+    /// Returns a shallow copy of a preliminary `YText`. Integrated instances are bound to their
+    /// document, so they can't be copied.
+    pub fn __copy__(&self) -> PyResult<Self> {
+        match &self.0 {
+            SharedType::Prelim(text) => Ok(YText(SharedType::prelim(text.clone()))),
+            SharedType::Integrated(_) => Err(integrated_copy_error("YText")),
+        }
+    }
+
+    /// Returns a copy of a preliminary `YText`. Since strings are immutable, it's the same as a
+    /// shallow copy.
+    pub fn __deepcopy__(&self, _memo: &PyAny) -> PyResult<Self> {
+        self.__copy__()
+    }
+
+    /// Returns `False` if this `YText` has been removed from its document, either directly or
+    /// along with one of its ancestors. Removed types can no longer be modified.
+    pub fn is_alive(&self) -> bool {
+        self.0.is_alive()
+    }
+
+    /// Returns `True` if this `YText` has been removed from its document. See `is_alive`.
+    pub fn is_deleted(&self) -> bool {
+        !self.is_alive()
+    }
+
+    /// Returns an identifier of this `YText` which is the same on every peer: the name of a root
+    /// type or a `(client, clock)` pair identifying the update which has created a nested one.
+    /// Preliminary instances have no identifier and return `None`.
+    pub fn id(&self) -> PyObject {
+        self.0.id()
+    }
+
+    /// Returns a shared type directly containing this `YText`, or `None` for root types,
+    /// preliminary instances and instances which have been removed from their document.
+    #[getter]
+    pub fn parent(&self) -> PyResult<PyObject> {
+        match &self.0 {
+            SharedType::Integrated(v) => v.parent_type(),
+            SharedType::Prelim(_) => Ok(Python::with_gil(|py| py.None())),
+        }
+    }
+
+    /// Returns a list of keys and indexes forming a path from the root type of a document down to
+    /// this `YText`, in the same format as event paths. Returns `None` if this instance has been
+    /// removed from its document. Preliminary instances have no path.
+    pub fn path(&self) -> PyResult<PyObject> {
+        match &self.0 {
+            SharedType::Integrated(v) => Ok(v.path()),
+            SharedType::Prelim(_) => Err(IntegratedOperationException::default_message()),
+        }
+    }
+
     /// Returns an underlying shared string stored in this data type.
     pub fn __str__(&self) -> String {
         match &self.0 {
@@ -72,35 +135,347 @@ impl YText {
         }
     }
 
-    pub fn __repr__(&self) -> String {
-        format!("YText({})", self.__str__())
+    /// Returns a representation of this `YText`, which - unlike `__str__` - only shows its first
+    /// `100` characters, followed by an ellipsis if the text is longer.
+    pub fn __repr__(&self) -> PyResult<String> {
+        let text = self.substring(0, Some(REPR_MAX_CHARS + 1), Some(true))?;
+        if text.chars().count() > REPR_MAX_CHARS {
+            let text: String = text.chars().take(REPR_MAX_CHARS).collect();
+            Ok(format!("YText({text}...)"))
+        } else {
+            Ok(format!("YText({text})"))
+        }
     }
 
-    /// Returns length of an underlying string stored in this `YText` instance,
-    /// understood as a number of UTF-8 encoded bytes.
+    /// Returns length of an underlying string stored in this `YText` instance, measured in the
+    /// offsets used by its document: a number of UTF-8 encoded bytes unless the document was created
+    /// with another `offset_kind`. The length is read straight from the document, without starting
+    /// a transaction.
     pub fn __len__(&self) -> usize {
         match &self.0 {
-            SharedType::Integrated(v) => v.with_transaction(|txn| v.len(txn)) as usize,
+            SharedType::Integrated(v) => v.branch().content_len() as usize,
             SharedType::Prelim(v) => v.len(),
         }
     }
 
+    /// Returns the length of this `YText` in the offsets used by its document, optionally read
+    /// through a given `txn`.
+    /// Raises a `TransactionError` if `txn` has already been committed.
+    pub fn length(&self, txn: Option<PyRef<YTransaction>>) -> PyResult<usize> {
+        match txn {
+            Some(txn) => Ok(self._len(&txn.get_active_inner()?.borrow())),
+            None => Ok(self.__len__()),
+        }
+    }
+
+    /// Returns the string stored in this `YText` instance as it was at the time the `snapshot`
+    /// (created with `YDoc.snapshot`) was captured. Requires a document created with
+    /// `skip_gc=True`.
+    ///
+    /// Reading a past state splits blocks at the boundaries of the snapshot, which can't be done
+    /// while a transaction is being committed, so this raises a `TransactionError` when called
+    /// from an observer callback.
+    pub fn to_string_at(&self, snapshot: Vec<u8>) -> PyResult<String> {
+        match &self.0 {
+            SharedType::Integrated(text) => {
+                text.doc.borrow().guard_snapshots()?;
+                if text.doc.borrow().committing_transaction().is_some() {
+                    return Err(TransactionError::new_err(
+                        "Past states of a text can't be read from within observer callbacks.",
+                    ));
+                }
+                let snapshot = Snapshot::decode_v1(snapshot.as_slice())
+                    .map_err(|e| EncodingException::new_err(e.to_string()))?;
+                let diff = text.with_transaction_mut(|txn| {
+                    text.diff_range(txn, Some(&snapshot), None, |_| ())
+                });
+                let mut result = String::new();
+                for chunk in diff {
+                    if let Value::Any(Any::String(s)) = chunk.insert {
+                        result.push_str(&s);
+                    }
+                }
+                Ok(result)
+            }
+            SharedType::Prelim(_) => Err(IntegratedOperationException::default_message()),
+        }
+    }
+
+    /// Returns length of an underlying string stored in this `YText` instance,
+    /// understood as a number of Unicode characters (code points). Unlike `__len__`,
+    /// this length matches the length of the equivalent Python `str`.
+    #[getter]
+    pub fn char_len(&self) -> usize {
+        match &self.0 {
+            SharedType::Integrated(v) if matches!(self.offset_kind(), OffsetKind::Utf32) => {
+                v.branch().content_len() as usize
+            }
+            _ => self.__str__().chars().count(),
+        }
+    }
+
+    /// Converts a UTF-8 byte offset (as used by `__len__`, `insert` and `delete`) into an offset
+    /// counted in Unicode characters, which can be used to index the equivalent Python `str`.
+    /// Raises a `ValueError` if the document of this text doesn't use UTF-8 offsets.
+    pub fn byte_to_char_index(&self, index: usize) -> PyResult<usize> {
+        self.guard_utf8_offsets()?;
+        let string = self.__str__();
+        if index > string.len() {
+            Err(PyIndexError::new_err(format!(
+                "Byte index {index} is out of range 0..={}.",
+                string.len()
+            )))
+        } else if !string.is_char_boundary(index) {
+            Err(PyValueError::new_err(format!(
+                "Byte index {index} does not lie on a character boundary."
+            )))
+        } else {
+            Ok(string[..index].chars().count())
+        }
+    }
+
+    /// Converts an offset counted in Unicode characters (as used to index Python `str`) into
+    /// a UTF-8 byte offset, which can be passed to `insert` and `delete` methods. Raises
+    /// a `ValueError` if the document of this text doesn't use UTF-8 offsets.
+    pub fn char_to_byte_index(&self, index: usize) -> PyResult<usize> {
+        self.guard_utf8_offsets()?;
+        let string = self.__str__();
+        string
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(string.len()))
+            .nth(index)
+            .ok_or_else(|| {
+                PyIndexError::new_err(format!(
+                    "Character index {index} is out of range 0..={}.",
+                    string.chars().count()
+                ))
+            })
+    }
+
+    /// Returns length of an underlying string stored in this `YText` instance, understood as
+    /// a number of UTF-16 code units. This is the length JavaScript peers see.
+    #[getter]
+    pub fn utf16_len(&self) -> usize {
+        match &self.0 {
+            SharedType::Integrated(v) if matches!(self.offset_kind(), OffsetKind::Utf16) => {
+                v.branch().content_len() as usize
+            }
+            _ => self.__str__().encode_utf16().count(),
+        }
+    }
+
+    /// Converts an offset counted in Unicode characters (as used to index Python `str`) into
+    /// an offset counted in UTF-16 code units, as used by JavaScript strings. In documents created
+    /// with UTF-16 offsets, the result can be passed to `insert` and `delete` methods.
+    pub fn char_to_utf16_index(&self, index: usize) -> PyResult<usize> {
+        let string = self.__str__();
+        let len = string.chars().count();
+        if index > len {
+            Err(PyIndexError::new_err(format!(
+                "Character index {index} is out of range 0..={len}."
+            )))
+        } else {
+            Ok(string.chars().take(index).map(char::len_utf16).sum())
+        }
+    }
+
+    /// Converts an offset counted in UTF-16 code units (as used by JavaScript strings, and by
+    /// `insert` and `delete` in documents created with UTF-16 offsets) into an offset counted in
+    /// Unicode characters, which can be used to index the equivalent Python `str`.
+    pub fn utf16_to_char_index(&self, index: usize) -> PyResult<usize> {
+        let string = self.__str__();
+        let len = string.encode_utf16().count();
+        if index > len {
+            return Err(PyIndexError::new_err(format!(
+                "UTF-16 index {index} is out of range 0..={len}."
+            )));
+        }
+        let mut offset = 0;
+        for (i, c) in string.chars().enumerate() {
+            if offset >= index {
+                break;
+            }
+            offset += c.len_utf16();
+            if offset == index {
+                return Ok(i + 1);
+            }
+        }
+        if index == 0 {
+            Ok(0)
+        } else {
+            Err(PyValueError::new_err(format!(
+                "UTF-16 index {index} points into the middle of a surrogate pair."
+            )))
+        }
+    }
+
+    /// Returns the offset of the first occurrence of `substring` in this `YText` instance at or
+    /// after `start`, or `None` if there is none. Offsets are counted in UTF-8 bytes (as used by
+    /// `insert` and `delete`) unless `chars` is set, in which case both `start` and the result are
+    /// counted in Unicode characters, like indexes of the equivalent Python `str`.
+    pub fn find(
+        &self,
+        substring: &str,
+        start: Option<usize>,
+        chars: Option<bool>,
+    ) -> PyResult<Option<usize>> {
+        let string = self.__str__();
+        let chars = chars.unwrap_or(false);
+        let start = start.unwrap_or(0);
+        let byte_start = if chars {
+            match string.char_indices().map(|(i, _)| i).nth(start) {
+                Some(i) => i,
+                None if start == string.chars().count() => string.len(),
+                None => return Ok(None),
+            }
+        } else if start > string.len() {
+            return Ok(None);
+        } else if !string.is_char_boundary(start) {
+            return Err(PyValueError::new_err(format!(
+                "Byte index {start} does not lie on a character boundary."
+            )));
+        } else {
+            start
+        };
+        Ok(string[byte_start..].find(substring).map(|i| {
+            let i = byte_start + i;
+            if chars {
+                string[..i].chars().count()
+            } else {
+                i
+            }
+        }))
+    }
+
+    /// Returns offsets of all non-overlapping occurrences of `substring` in this `YText` instance,
+    /// in ascending order. Offsets are counted in UTF-8 bytes unless `chars` is set, in which case
+    /// they are counted in Unicode characters (see `find`).
+    pub fn find_all(&self, substring: &str, chars: Option<bool>) -> Vec<usize> {
+        let string = self.__str__();
+        let matches = string.match_indices(substring).map(|(i, _)| i);
+        if chars.unwrap_or(false) {
+            let mut offset = 0;
+            let mut char_offset = 0;
+            matches
+                .map(|i| {
+                    char_offset += string[offset..i].chars().count();
+                    offset = i;
+                    char_offset
+                })
+                .collect()
+        } else {
+            matches.collect()
+        }
+    }
+
+    /// Returns the part of the string stored in this `YText` instance between `start` (inclusive)
+    /// and `end` (exclusive, the end of the text unless specified). Like `find`, offsets are
+    /// counted in UTF-8 bytes unless `chars` is set, in which case they are counted in Unicode
+    /// characters. Offsets past the end of the text are clamped, the same way Python slices are.
+    ///
+    /// Only the requested range is copied out of the text chunks, which makes reading a window of
+    /// a large text cheaper than slicing the result of `str`. Embeds aren't part of the string.
+    pub fn substring(
+        &self,
+        start: usize,
+        end: Option<usize>,
+        chars: Option<bool>,
+    ) -> PyResult<String> {
+        let chars = chars.unwrap_or(false);
+        match &self.0 {
+            SharedType::Integrated(text) => {
+                let diff = text.with_transaction(|txn| text.diff(txn, |_| ()));
+                let chunks = diff.iter().filter_map(|chunk| match &chunk.insert {
+                    Value::Any(Any::String(s)) => Some(&**s),
+                    _ => None,
+                });
+                slice_chunks(chunks, start, end, chars)
+            }
+            SharedType::Prelim(v) => slice_chunks(std::iter::once(v.as_str()), start, end, chars),
+        }
+    }
+
+    /// Returns a list of `(index, value)` pairs for every object embedded into this `YText`
+    /// instance with `insert_embed`, ordered by their position. Indexes are measured in the same
+    /// unit as the ones accepted by `insert`. Preliminary instances never contain embeds.
+    pub fn embeds(&self) -> PyResult<Vec<(u32, PyObject)>> {
+        match &self.0 {
+            SharedType::Integrated(text) => {
+                let offset_kind = text.doc.borrow().offset_kind();
+                let diff = text.with_transaction(|txn| text.diff(txn, |_| ()));
+                let mut index = 0;
+                let mut embeds = Vec::new();
+                for chunk in diff {
+                    let len = chunk_len(&chunk.insert, offset_kind);
+                    if !matches!(chunk.insert, Value::Any(Any::String(_))) {
+                        let value = Python::with_gil(|py| {
+                            chunk.insert.with_doc_into_py(text.doc.clone(), py)
+                        })?;
+                        embeds.push((index, value));
+                    }
+                    index += len;
+                }
+                Ok(embeds)
+            }
+            SharedType::Prelim(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// Returns the contents of this `YText` instance as a list of contiguous runs, each one being
+    /// a chunk of text or an embed, in the same format as the `delta` of a `YTextEvent` inserting
+    /// it. Neighbouring characters sharing the same formatting are merged into a single run, so
+    /// this shows formatting boundaries rather than the exact blocks the text is stored in.
+    pub fn chunks(&self) -> PyResult<PyObject> {
+        Python::with_gil(|py| match &self.0 {
+            SharedType::Integrated(text) => {
+                let diff = text.with_transaction(|txn| text.diff(txn, |_| ()));
+                let chunks = diff
+                    .into_iter()
+                    .map(|chunk| {
+                        Delta::Inserted(chunk.insert, chunk.attributes)
+                            .with_doc_into_py(text.doc.clone(), py)
+                    })
+                    .collect::<PyResult<Vec<_>>>()?;
+                Ok(PyList::new(py, chunks).into())
+            }
+            SharedType::Prelim(v) if v.is_empty() => Ok(PyList::empty(py).into()),
+            SharedType::Prelim(v) => {
+                let chunk = PyDict::new(py);
+                chunk.set_item("insert", v).unwrap();
+                Ok(PyList::new(py, [chunk]).into())
+            }
+        })
+    }
+
     /// Returns an underlying shared string stored in this data type.
     pub fn to_json(&self) -> String {
         format!("\"{}\"", self.__str__())
     }
 
+    /// Inserts a given `chunk` of text into this `YText` instance, starting at a given `index`.
+    /// The `index` is counted in UTF-8 bytes (see `char_to_byte_index`). Negative indexes are
+    /// counted from the end of the text, like with `list.insert`.
+    /// Returns the length of the text after insertion or, if `sticky_index` is set, a
+    /// `YStickyIndex` pointing right after the inserted chunk.
     pub fn insert(
         &mut self,
         txn: &mut YTransaction,
-        index: u32,
+        index: isize,
         chunk: &str,
         attributes: Option<HashMap<String, PyObject>>,
-    ) -> PyResult<()> {
-        txn.transact(|txn| self._insert(txn, index, chunk, attributes))?
+        sticky_index: Option<bool>,
+    ) -> PyResult<PyObject> {
+        txn.transact(|txn| {
+            self.0.guard_alive(&*txn)?;
+            let start = self.normalize_insert_index(txn, index)?;
+            self._insert(txn, start, chunk, attributes)?;
+            self.inserted(txn, sticky_index, |text, txn| {
+                text.sticky_index_after(txn, start, chunk)
+            })
+        })?
     }
 
-    /// Inserts a given `chunk` of text into this `YText` instance, starting at a given `index`.
     fn _insert(
         &mut self,
         txn: &mut YTransactionInner,
@@ -114,12 +489,13 @@ impl YText {
             match &mut self.0 {
                 SharedType::Integrated(text) => {
                     text.insert_with_attributes(txn, index, chunk, attributes);
-                    Ok(())
                 }
-                SharedType::Prelim(_) => Err(IntegratedOperationException::default_message()),
+                SharedType::Prelim(_) => {
+                    return Err(IntegratedOperationException::default_message())
+                }
             }
         } else if let Some(Err(error)) = attributes {
-            Err(error)
+            return Err(error);
         } else {
             match &mut self.0 {
                 SharedType::Integrated(text) => text.insert(txn, index, chunk),
@@ -127,8 +503,66 @@ impl YText {
                     prelim_string.insert_str(index as usize, chunk)
                 }
             }
-            Ok(())
         }
+        Ok(())
+    }
+
+    /// Replaces `length` bytes of text starting at a given `index` with a given `chunk`. Both
+    /// operations happen within the same transaction, so observers only see the final result.
+    /// If no `attributes` are provided, the new text picks up the formatting of the surrounding
+    /// text. Returns the length of the text after replacement.
+    pub fn replace(
+        &mut self,
+        txn: &mut YTransaction,
+        index: u32,
+        length: u32,
+        chunk: &str,
+        attributes: Option<HashMap<String, PyObject>>,
+    ) -> PyResult<usize> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._replace(txn, index, length, chunk, attributes))
+        })?
+    }
+
+    fn _replace(
+        &mut self,
+        txn: &mut YTransactionInner,
+        index: u32,
+        length: u32,
+        chunk: &str,
+        attributes: Option<HashMap<String, PyObject>>,
+    ) -> PyResult<usize> {
+        let len = self._len(txn);
+        let (start, end) = (index as usize, index as usize + length as usize);
+        if end > len {
+            return Err(PyIndexError::new_err(format!(
+                "Cannot replace range {start}..{end} of a text of length {len}."
+            )));
+        }
+        match &mut self.0 {
+            SharedType::Integrated(text) => {
+                let attributes = attributes.map(Self::parse_attrs).transpose()?;
+                text.remove_range(txn, index, length);
+                match attributes {
+                    Some(attributes) => text.insert_with_attributes(txn, index, chunk, attributes),
+                    None => text.insert(txn, index, chunk),
+                }
+            }
+            SharedType::Prelim(_) if attributes.is_some() => {
+                return Err(IntegratedOperationException::default_message())
+            }
+            SharedType::Prelim(v) => {
+                if !v.is_char_boundary(start) || !v.is_char_boundary(end) {
+                    return Err(PyValueError::new_err(format!(
+                        "Range {start}..{end} does not lie on character boundaries."
+                    )));
+                }
+                v.replace_range(start..end, chunk);
+            }
+        }
+        Ok(self._len(txn))
     }
 
     /// Inserts a given `embed` object into this `YText` instance, starting at a given `index`.
@@ -136,14 +570,25 @@ impl YText {
     /// Optional object with defined `attributes` will be used to wrap provided `embed`
     /// with a formatting blocks.`attributes` are only supported for a `YText` instance which
     /// already has been integrated into document store.
+    ///
+    /// Returns the length of the text after insertion or, if `sticky_index` is set,
+    /// a `YStickyIndex` attached to the inserted embed, which keeps pointing at it while other
+    /// edits shift its position, e.g. to `format` the embed later on.
     pub fn insert_embed(
         &mut self,
         txn: &mut YTransaction,
         index: u32,
         embed: PyObject,
         attributes: Option<HashMap<String, PyObject>>,
-    ) -> PyResult<()> {
-        txn.transact(|txn| self._insert_embed(txn, index, embed, attributes))?
+        sticky_index: Option<bool>,
+    ) -> PyResult<PyObject> {
+        txn.transact(|txn| {
+            self.0.guard_alive(&*txn)?;
+            self._insert_embed(txn, index, embed, attributes)?;
+            self.inserted(txn, sticky_index, |text, txn| {
+                text.sticky_index_at(txn, index)
+            })
+        })?
     }
 
     fn _insert_embed(
@@ -173,14 +618,29 @@ impl YText {
     /// Wraps an existing piece of text within a range described by `index`-`length` parameters with
     /// formatting blocks containing provided `attributes` metadata. This method only works for
     /// `YText` instances that already have been integrated into document store.
+    ///
+    /// If `previous` is set, returns the formatting the range had before, as a list of
+    /// `(index, length, attributes)` spans covering it, e.g. to undo the change or to tell whether
+    /// the whole range is already formatted.
     pub fn format(
         &mut self,
         txn: &mut YTransaction,
         index: u32,
         length: u32,
         attributes: HashMap<String, PyObject>,
-    ) -> PyResult<()> {
-        txn.transact(|txn| self._format(txn, index, length, attributes))?
+        previous: Option<bool>,
+    ) -> PyResult<Option<Vec<(u32, u32, PyObject)>>> {
+        txn.transact(|txn| {
+            self.0.guard_alive(&*txn)?;
+            self.check_format_range(txn, index, length)?;
+            let spans = if previous.unwrap_or(false) {
+                Some(self.attribute_spans(txn, index, length)?)
+            } else {
+                None
+            };
+            self._format(txn, index, length, attributes)?;
+            Ok(spans)
+        })?
     }
 
     fn _format(
@@ -202,9 +662,37 @@ impl YText {
         }
     }
 
-    /// Appends a given `chunk` of text at the end of current `YText` instance.
-    pub fn extend(&mut self, txn: &mut YTransaction, chunk: &str) -> PyResult<()> {
-        txn.transact(|txn| self._extend(txn, chunk))
+    /// Ensures that the range between `index` and `index + length` lies within this text. The end
+    /// of the range is computed in `usize`, so it cannot overflow.
+    fn check_format_range(&self, txn: &YTransactionInner, index: u32, length: u32) -> PyResult<()> {
+        let len = self._len(txn);
+        let (start, end) = (index as usize, index as usize + length as usize);
+        if end > len {
+            Err(PyIndexError::new_err(format!(
+                "Cannot format range {start}..{end} of a text of length {len}."
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Appends a given `chunk` of text at the end of current `YText` instance. Returns the length
+    /// of the text after insertion or, if `sticky_index` is set, a `YStickyIndex` pointing right
+    /// after the appended chunk.
+    pub fn extend(
+        &mut self,
+        txn: &mut YTransaction,
+        chunk: &str,
+        sticky_index: Option<bool>,
+    ) -> PyResult<PyObject> {
+        txn.transact(|txn| {
+            self.0.guard_alive(&*txn)?;
+            let start = self._len(txn) as u32;
+            self._extend(txn, chunk);
+            self.inserted(txn, sticky_index, |text, txn| {
+                text.sticky_index_after(txn, start, chunk)
+            })
+        })?
     }
     fn _extend(&mut self, txn: &mut YTransactionInner, chunk: &str) {
         match &mut self.0 {
@@ -212,7 +700,7 @@ impl YText {
             SharedType::Prelim(v) => v.push_str(chunk),
         }
     }
-    /// Deletes character at the specified index.
+    /// Deletes character at the specified index, counted in UTF-8 bytes.
     pub fn delete(&mut self, txn: &mut YTransaction, index: u32) -> PyResult<()> {
         self.delete_range(txn, index, 1)
     }
@@ -225,7 +713,11 @@ impl YText {
         index: u32,
         length: u32,
     ) -> PyResult<()> {
-        txn.transact(|txn| self._delete_range(txn, index, length))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._delete_range(txn, index, length))
+        })?
     }
 
     fn _delete_range(&mut self, txn: &mut YTransactionInner, index: u32, length: u32) {
@@ -237,45 +729,73 @@ impl YText {
         }
     }
 
-    /// Observes updates from the `YText` instance.
-    pub fn observe(&mut self, f: PyObject) -> PyResult<ShallowSubscription> {
+    /// Observes updates from the `YText` instance. If `emit_initial` is set, `f` is called right
+    /// away with an event inserting the current contents of the text, before any change happens.
+    pub fn observe(
+        &mut self,
+        f: PyObject,
+        emit_initial: Option<bool>,
+        weak: Option<bool>,
+    ) -> PyResult<ShallowSubscription> {
         match &mut self.0 {
             SharedType::Integrated(text) => {
+                if emit_initial.unwrap_or(false) {
+                    let event = YTextEvent::initial(text);
+                    Python::with_gil(|py| f.call1(py, (event,)))?;
+                }
+                let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+                let callback = f.clone();
                 let doc = text.doc.clone();
                 let sub_id = text
                     .inner
                     .observe(move |txn, e| {
                         let e = YTextEvent::new(e, txn, doc.clone());
                         Python::with_gil(|py| {
-                            if let Err(err) = f.call1(py, (e,)) {
+                            if let Err(err) =
+                                with_committing_transaction(&doc, txn, || f.call1(py, (e,)))
+                            {
                                 err.restore(py)
                             }
                         });
                     })
                     .into();
-                Ok(ShallowSubscription(sub_id))
+                let subscription = ShallowSubscription::new(sub_id, text.inner.clone());
+                callback.cancel_with({
+                    let subscription = subscription.clone();
+                    move || subscription.unobserve()
+                });
+                Ok(subscription)
             }
             SharedType::Prelim(_) => Err(PreliminaryObservationException::default_message()),
         }
     }
 
     /// Observes updates from the `YText` instance and all of its nested children.
-    pub fn observe_deep(&mut self, f: PyObject) -> PyResult<DeepSubscription> {
+    pub fn observe_deep(&mut self, f: PyObject, weak: Option<bool>) -> PyResult<DeepSubscription> {
         match &mut self.0 {
             SharedType::Integrated(text) => {
+                let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+                let callback = f.clone();
                 let doc = text.doc.clone();
                 let sub = text
                     .inner
                     .observe_deep(move |txn, events| {
                         Python::with_gil(|py| {
                             let events = events_into_py(txn, events, doc.clone());
-                            if let Err(err) = f.call1(py, (events,)) {
+                            if let Err(err) =
+                                with_committing_transaction(&doc, txn, || f.call1(py, (events,)))
+                            {
                                 err.restore(py)
                             }
                         })
                     })
                     .into();
-                Ok(DeepSubscription(sub))
+                let subscription = DeepSubscription::new(sub, text.inner.clone());
+                callback.cancel_with({
+                    let subscription = subscription.clone();
+                    move || subscription.unobserve()
+                });
+                Ok(subscription)
             }
             SharedType::Prelim(_) => Err(PreliminaryObservationException::default_message()),
         }
@@ -285,8 +805,8 @@ impl YText {
         match &mut self.0 {
             SharedType::Integrated(text) => {
                 match subscription_id {
-                    SubId::Shallow(ShallowSubscription(id)) => text.unobserve(id),
-                    SubId::Deep(DeepSubscription(id)) => text.unobserve_deep(id),
+                    SubId::Shallow(ShallowSubscription(id, _)) => text.unobserve(id),
+                    SubId::Deep(DeepSubscription(id, _)) => text.unobserve_deep(id),
                 }
                 Ok(())
             }
@@ -296,6 +816,146 @@ impl YText {
 }
 
 impl YText {
+    /// Returns length of an underlying string stored in this `YText` using a provided transaction.
+    fn _len(&self, txn: &TransactionMut) -> usize {
+        match &self.0 {
+            SharedType::Integrated(v) => v.len(txn) as usize,
+            SharedType::Prelim(v) => v.len(),
+        }
+    }
+
+    /// Returns what `insert`, `insert_embed` and `extend` give back once their content is inserted:
+    /// the length of the text or, if `sticky_index` is set, a position created by `sticky`.
+    fn inserted<F>(
+        &self,
+        txn: &mut YTransactionInner,
+        sticky_index: Option<bool>,
+        sticky: F,
+    ) -> PyResult<PyObject>
+    where
+        F: FnOnce(&Self, &mut YTransactionInner) -> PyResult<YStickyIndex>,
+    {
+        if sticky_index.unwrap_or(false) {
+            let index = sticky(self, txn)?;
+            Ok(Python::with_gil(|py| index.into_py(py)))
+        } else {
+            let len = self._len(txn);
+            Ok(Python::with_gil(|py| len.into_py(py)))
+        }
+    }
+
+    /// Returns the kind of offsets used by the document of this text. Preliminary texts are measured
+    /// in UTF-8 bytes.
+    fn offset_kind(&self) -> OffsetKind {
+        match &self.0 {
+            SharedType::Integrated(text) => text.doc.borrow().offset_kind(),
+            SharedType::Prelim(_) => OffsetKind::Bytes,
+        }
+    }
+
+    /// Byte offsets converted by `byte_to_char_index` and `char_to_byte_index` are only meaningful
+    /// to `insert` and `delete` of documents using UTF-8 offsets.
+    fn guard_utf8_offsets(&self) -> PyResult<()> {
+        if matches!(self.offset_kind(), OffsetKind::Bytes) {
+            Ok(())
+        } else {
+            Err(PyValueError::new_err(
+                "Byte offsets can only be converted for texts of documents using UTF-8 offsets.",
+            ))
+        }
+    }
+
+    /// Returns a position right after a `chunk` inserted at a given `start` index. The position is
+    /// associated with the end of the chunk, so that text inserted later at the same place (also
+    /// concurrently) ends up after it.
+    fn sticky_index_after(
+        &self,
+        txn: &mut YTransactionInner,
+        start: u32,
+        chunk: &str,
+    ) -> PyResult<YStickyIndex> {
+        match &self.0 {
+            SharedType::Integrated(text) => {
+                let end = start + str_len(chunk, text.doc.borrow().offset_kind());
+                text.sticky_index(txn, end, Assoc::Before)
+                    .map(|index| YStickyIndex::new(index, text.doc.clone()))
+                    .ok_or_else(|| PyIndexError::new_err(format!("Index {end} is out of bounds.")))
+            }
+            SharedType::Prelim(_) => Err(IntegratedOperationException::default_message()),
+        }
+    }
+
+    /// Returns a position associated with the content found at a given `index`, which moves along
+    /// with that content.
+    fn sticky_index_at(&self, txn: &mut YTransactionInner, index: u32) -> PyResult<YStickyIndex> {
+        match &self.0 {
+            SharedType::Integrated(text) => text
+                .sticky_index(txn, index, Assoc::After)
+                .map(|sticky| YStickyIndex::new(sticky, text.doc.clone()))
+                .ok_or_else(|| PyIndexError::new_err(format!("Index {index} is out of bounds."))),
+            SharedType::Prelim(_) => Err(IntegratedOperationException::default_message()),
+        }
+    }
+
+    /// Returns `(index, length, attributes)` spans of the formatting applied to the text between
+    /// `index` and `index + length`. Adjacent chunks sharing the same attributes are reported as
+    /// a single span. The range must have been validated with `check_format_range` beforehand.
+    fn attribute_spans(
+        &self,
+        txn: &YTransactionInner,
+        index: u32,
+        length: u32,
+    ) -> PyResult<Vec<(u32, u32, PyObject)>> {
+        match &self.0 {
+            SharedType::Integrated(text) => {
+                let offset_kind = text.doc.borrow().offset_kind();
+                let end = index + length;
+                let mut offset = 0;
+                let mut spans: Vec<(u32, u32, Attrs)> = Vec::new();
+                for chunk in text.diff(txn, |_| ()) {
+                    let len = chunk_len(&chunk.insert, offset_kind);
+                    let (from, to) = (offset.max(index), (offset + len).min(end));
+                    if from < to {
+                        let attrs = chunk.attributes.map(|attrs| *attrs).unwrap_or_default();
+                        match spans.last_mut() {
+                            Some((start, span_len, last))
+                                if *start + *span_len == from && *last == attrs =>
+                            {
+                                *span_len += to - from
+                            }
+                            _ => spans.push((from, to - from, attrs)),
+                        }
+                    }
+                    offset += len;
+                }
+                Python::with_gil(|py| {
+                    spans
+                        .into_iter()
+                        .map(|(start, len, attrs)| {
+                            Ok((start, len, (&attrs).with_doc_into_py(text.doc.clone(), py)?))
+                        })
+                        .collect()
+                })
+            }
+            SharedType::Prelim(_) => Err(IntegratedOperationException::default_message()),
+        }
+    }
+
+    /// Converts a (possibly negative) insertion `index` into a byte offset within this text, the
+    /// same way `YArray.insert` does. Offsets splitting a character of a preliminary text are
+    /// rejected.
+    fn normalize_insert_index(&self, txn: &TransactionMut, index: isize) -> PyResult<u32> {
+        let position = insert_position(index, self._len(txn), "a text")?;
+        match &self.0 {
+            SharedType::Prelim(v) if !v.is_char_boundary(position as usize) => {
+                Err(PyIndexError::new_err(format!(
+                    "Cannot insert at index {index}, as it's not a character boundary."
+                )))
+            }
+            _ => Ok(position),
+        }
+    }
+
     fn parse_attrs(attrs: HashMap<String, PyObject>) -> PyResult<Attrs> {
         Python::with_gil(|py| {
             attrs
@@ -310,6 +970,77 @@ impl YText {
     }
 }
 
+/// Concatenates the parts of consecutive string `chunks` which fall between `start` and `end`
+/// offsets, counted in bytes or - if `chars` is set - in Unicode characters.
+fn slice_chunks<'a>(
+    chunks: impl Iterator<Item = &'a str>,
+    start: usize,
+    end: Option<usize>,
+    chars: bool,
+) -> PyResult<String> {
+    let end = end.unwrap_or(usize::MAX);
+    let mut result = String::new();
+    let mut offset = 0;
+    for chunk in chunks {
+        if offset >= end {
+            break;
+        }
+        let len = if chars {
+            chunk.chars().count()
+        } else {
+            chunk.len()
+        };
+        if offset + len > start && start < end {
+            let from = start.saturating_sub(offset);
+            let to = len.min(end - offset);
+            let (from, to) = if chars {
+                let mut boundaries = chunk
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .chain(std::iter::once(chunk.len()));
+                let from_byte = boundaries.nth(from).unwrap_or(chunk.len());
+                let to_byte = if to > from {
+                    boundaries.nth(to - from - 1).unwrap_or(chunk.len())
+                } else {
+                    from_byte
+                };
+                (from_byte, to_byte)
+            } else {
+                for index in [from, to] {
+                    if !chunk.is_char_boundary(index) {
+                        return Err(PyValueError::new_err(format!(
+                            "Byte index {} does not lie on a character boundary.",
+                            offset + index
+                        )));
+                    }
+                }
+                (from, to)
+            };
+            result.push_str(&chunk[from..to]);
+        }
+        offset += len;
+    }
+    Ok(result)
+}
+
+/// Returns the length of an inserted text chunk measured in a given `offset_kind`. Embeds always
+/// have a length of 1.
+fn chunk_len(value: &Value, offset_kind: OffsetKind) -> u32 {
+    match value {
+        Value::Any(Any::String(s)) => str_len(s, offset_kind),
+        _ => 1,
+    }
+}
+
+/// Returns the length of a string measured in a given `offset_kind`.
+fn str_len(s: &str, offset_kind: OffsetKind) -> u32 {
+    match offset_kind {
+        OffsetKind::Bytes => s.len() as u32,
+        OffsetKind::Utf16 => s.encode_utf16().count() as u32,
+        OffsetKind::Utf32 => s.chars().count() as u32,
+    }
+}
+
 /// Event generated by `YYText.observe` method. Emitted during transaction commit phase.
 #[pyclass(unsendable)]
 pub struct YTextEvent {
@@ -318,6 +1049,7 @@ pub struct YTextEvent {
     txn: *const TransactionMut<'static>,
     target: Option<PyObject>,
     delta: Option<PyObject>,
+    initial: Option<InitialState<Delta>>,
 }
 
 impl YTextEvent {
@@ -333,6 +1065,34 @@ impl YTextEvent {
             txn,
             target: None,
             delta: None,
+            initial: None,
+        }
+    }
+
+    /// Creates an event inserting the current contents of a given `text`.
+    fn initial(text: &TypeWithDoc<TextRef>) -> Self {
+        let delta = text.with_transaction(|txn| {
+            text.diff(txn, |_| ())
+                .into_iter()
+                .map(|diff| Delta::Inserted(diff.insert, diff.attributes))
+                .collect()
+        });
+        let target =
+            Python::with_gil(|py| text.inner.clone().with_doc(text.doc.clone()).into_py(py));
+        YTextEvent {
+            inner: std::ptr::null(),
+            doc: text.doc.clone(),
+            txn: std::ptr::null(),
+            target: Some(target),
+            delta: None,
+            initial: Some(InitialState { delta }),
+        }
+    }
+
+    fn deltas(&self) -> &[Delta] {
+        match &self.initial {
+            Some(initial) => &initial.delta,
+            None => self.inner().delta(self.txn()),
         }
     }
 
@@ -365,7 +1125,28 @@ impl YTextEvent {
     /// Returns an array of keys and indexes creating a path from root type down to current instance
     /// of shared type (accessible via `target` getter).
     pub fn path(&self) -> PyObject {
-        Python::with_gil(|py| self.inner().path().into_py(py))
+        match &self.initial {
+            // events of shallow observers are relative to their target, so their path is empty
+            Some(_) => Python::with_gil(|py| PyList::empty(py).into()),
+            None => Python::with_gil(|py| self.inner().path().into_py(py)),
+        }
+    }
+
+    /// Returns the origin of the transaction which has made the changes, or `None` if it has no
+    /// origin, e.g. `apply_update(doc, update, origin="remote")` reports `"remote"`. Initial events
+    /// emitted by `observe(..., emit_initial=True)` have no origin.
+    #[getter]
+    pub fn origin(&self) -> PyObject {
+        Python::with_gil(|py| match &self.initial {
+            Some(_) => py.None(),
+            None => origin_into_py(self.txn().origin(), py),
+        })
+    }
+
+    /// Returns `"text"` - the kind of the `target` shared type, as reported by `type_of`.
+    #[getter]
+    pub fn kind(&self) -> &'static str {
+        "text"
     }
 
     /// Returns a list of text changes made over corresponding `YText` collection within
@@ -375,28 +1156,80 @@ impl YTextEvent {
     /// - { delete: number }
     /// - { retain: number, attributes: any|undefined }
     #[getter]
-    pub fn delta(&mut self) -> PyObject {
+    pub fn delta(&mut self) -> PyResult<PyObject> {
         if let Some(delta) = &self.delta {
-            delta.clone()
+            Ok(delta.clone())
         } else {
             let delta: PyObject = Python::with_gil(|py| {
-                let delta = {
-                    self.inner()
-                        .delta(self.txn())
-                        .iter()
-                        .map(|d| d.clone().with_doc_into_py(self.doc.clone(), py))
-                };
-                PyList::new(py, delta).into()
-            });
+                let delta = self
+                    .deltas()
+                    .iter()
+                    .map(|d| d.clone().with_doc_into_py(self.doc.clone(), py))
+                    .collect::<PyResult<Vec<_>>>()?;
+                PyResult::Ok(PyList::new(py, delta).into())
+            })?;
             self.delta = Some(delta.clone());
-            delta
+            Ok(delta)
         }
     }
 
-    fn __repr__(&mut self) -> String {
+    /// Returns the changes from `delta` with the absolute indexes at which they apply, computed
+    /// by accumulating the lengths of preceding retains and inserts. Changes follow a format:
+    ///
+    /// - { type: "insert", index: number, insert: string, attributes: any|undefined }
+    /// - { type: "delete", index: number, length: number }
+    /// - { type: "format", index: number, length: number, attributes: any }
+    ///
+    /// Retains which don't change formatting attributes only move the index, so they are skipped.
+    pub fn changes(&self) -> PyResult<PyObject> {
+        let offset_kind = self.doc.borrow().offset_kind();
+        Python::with_gil(|py| {
+            let mut index = 0;
+            let mut changes = Vec::new();
+            for delta in self.deltas() {
+                let change = PyDict::new(py);
+                change.set_item("index", index).unwrap();
+                match delta {
+                    Delta::Inserted(value, attrs) => {
+                        index += chunk_len(value, offset_kind);
+                        change.set_item("type", "insert").unwrap();
+                        let value = value.clone().with_doc_into_py(self.doc.clone(), py)?;
+                        change.set_item("insert", value).unwrap();
+                        if let Some(attrs) = attrs {
+                            let attrs = attrs.as_ref().with_doc_into_py(self.doc.clone(), py)?;
+                            change.set_item("attributes", attrs).unwrap();
+                        }
+                    }
+                    Delta::Deleted(len) => {
+                        change.set_item("type", "delete").unwrap();
+                        change.set_item("length", len).unwrap();
+                    }
+                    Delta::Retain(len, attrs) => {
+                        index += len;
+                        match attrs {
+                            Some(attrs) => {
+                                change.set_item("type", "format").unwrap();
+                                change.set_item("length", len).unwrap();
+                                let attrs =
+                                    attrs.as_ref().with_doc_into_py(self.doc.clone(), py)?;
+                                change.set_item("attributes", attrs).unwrap();
+                            }
+                            None => continue,
+                        }
+                    }
+                }
+                changes.push(change);
+            }
+            Ok(PyList::new(py, changes).into())
+        })
+    }
+
+    fn __repr__(&mut self) -> PyResult<String> {
         let target = self.target();
-        let delta = self.delta();
+        let delta = self.delta()?;
         let path = self.path();
-        format!("YTextEvent(target={target}, delta={delta}, path={path})")
+        Ok(format!(
+            "YTextEvent(target={target}, delta={delta}, path={path})"
+        ))
     }
 }
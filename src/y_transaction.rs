@@ -1,4 +1,6 @@
-use pyo3::exceptions::{PyAssertionError, PyException};
+use crate::update_buffer::update_into_py;
+use crate::y_doc::{WithTransaction, YDoc};
+use pyo3::exceptions::PyException;
 use pyo3::types::PyBytes;
 use pyo3::{create_exception, prelude::*};
 use std::cell::RefCell;
@@ -8,11 +10,17 @@ use std::ops::{Deref, DerefMut};
 use std::rc::Rc;
 use yrs::updates::decoder::Decode;
 use yrs::updates::encoder::{Encode, Encoder};
-use yrs::{
-    updates::{decoder::DecoderV1, encoder::EncoderV1},
-    StateVector, Update,
-};
-use yrs::{ReadTxn, TransactionMut};
+use yrs::{diff_updates_v1, merge_updates_v1, Origin, ReadTxn, TransactionMut};
+use yrs::{updates::encoder::EncoderV1, StateVector, Update};
+
+/// Converts an origin of a transaction into a Python `str`, or `None` if it has no origin.
+pub(crate) fn origin_into_py(origin: Option<&Origin>, py: Python) -> PyObject {
+    origin.map_or(py.None(), |origin| {
+        String::from_utf8_lossy(origin.as_ref())
+            .into_owned()
+            .into_py(py)
+    })
+}
 
 create_exception!(
     y_py,
@@ -20,6 +28,16 @@ create_exception!(
     PyException,
     "Occurs due to issues in the encoding/decoding process of y_py updates."
 );
+create_exception!(
+    y_py,
+    TransactionError,
+    PyException,
+    "Occurs when a transaction is misused, e.g. when a new transaction is started while another one is still pending, or when a committed transaction is used again."
+);
+
+/// Part of the updates applied to a document which couldn't be integrated yet because of missing
+/// dependencies, encoded using lib0 v1 encoding. Shared between a document and its transactions.
+pub type PendingUpdate = Rc<RefCell<Option<Vec<u8>>>>;
 
 /// A transaction that serves as a proxy to document block store. Ypy shared data types execute
 /// their operations in a context of a given transaction. Each document can have only one active
@@ -42,6 +60,12 @@ pub struct YTransactionInner {
     pub inner: ManuallyDrop<TransactionMut<'static>>,
     pub cached_before_state: Option<PyObject>,
     pub committed: bool,
+    pending: PendingUpdate,
+    /// Origin of the transaction, kept around so that it can be read after commit.
+    origin: Option<Origin>,
+    /// Number of `YDoc.batch` contexts holding this transaction open. Until the outermost of them
+    /// exits, requests to commit the transaction are deferred.
+    pub batches: usize,
 }
 
 impl ReadTxn for YTransactionInner {
@@ -73,11 +97,15 @@ impl Drop for YTransactionInner {
 }
 
 impl YTransactionInner {
-    pub fn new(txn: TransactionMut<'static>) -> Self {
+    pub fn new(txn: TransactionMut<'static>, pending: PendingUpdate) -> Self {
+        let origin = txn.origin().cloned();
         YTransactionInner {
             inner: ManuallyDrop::new(txn),
             cached_before_state: None,
             committed: false,
+            pending,
+            origin,
+            batches: 0,
         }
     }
 }
@@ -96,6 +124,41 @@ impl YTransactionInner {
         return self.cached_before_state.as_ref().unwrap().clone();
     }
 
+    /// Applies an update encoded using lib0 v1 encoding. Parts of the update which can't be
+    /// integrated yet because of missing dependencies are buffered by yrs. Since yrs doesn't expose
+    /// that buffer, they are also tracked as the pending update of the document.
+    ///
+    /// Returns `true` if all blocks of the update have been integrated.
+    pub fn apply_v1(&mut self, diff: &[u8]) -> PyResult<bool> {
+        let update =
+            Update::decode_v1(diff).map_err(|e| EncodingException::new_err(e.to_string()))?;
+        let update_state = update.state_vector();
+        self.deref_mut().apply_update(update);
+
+        let mut pending = self.pending.borrow_mut();
+        let state = self.state_vector();
+        let integrated = update_state
+            .iter()
+            .all(|(client, &clock)| clock <= state.get(client));
+        if integrated && pending.is_none() {
+            return Ok(true);
+        }
+        let merged = match pending.take() {
+            Some(previous) => merge_updates_v1(&[&previous, diff]),
+            None => Ok(diff.to_vec()),
+        };
+        let remaining = merged
+            .and_then(|merged| diff_updates_v1(&merged, &state.encode_v1()))
+            .map_err(|e| EncodingException::new_err(e.to_string()))?;
+        let remaining_state = Update::decode_v1(&remaining)
+            .map_err(|e| EncodingException::new_err(e.to_string()))?
+            .state_vector();
+        if !remaining_state.is_empty() {
+            *pending = Some(remaining);
+        }
+        Ok(integrated)
+    }
+
     /// Triggers a post-update series of operations without `free`ing the transaction. This includes
     /// compaction and optimization of internal representation of updates, triggering events etc.
     /// Ypy transactions are auto-committed when they are `free`d.
@@ -127,8 +190,17 @@ impl YTransaction {
         self.inner.clone()
     }
 
+    /// Returns the inner transaction, provided that it has not been committed yet.
+    pub fn get_active_inner(&self) -> PyResult<Rc<RefCell<YTransactionInner>>> {
+        if self.inner.borrow().committed {
+            Err(self.raise_alread_committed())
+        } else {
+            Ok(self.get_inner())
+        }
+    }
+
     fn raise_alread_committed(&self) -> PyErr {
-        PyAssertionError::new_err("Transaction already committed!")
+        TransactionError::new_err("Transaction already committed!")
     }
 
     pub fn transact<F, R>(&self, f: F) -> PyResult<R>
@@ -152,9 +224,21 @@ impl YTransaction {
         self.get_inner().borrow_mut().before_state()
     }
 
+    /// Returns the origin this transaction has been started with, or `None` if it has no origin.
+    /// See `YDoc.transact`.
+    #[getter]
+    pub fn origin(&self) -> PyObject {
+        let origin = self.inner.borrow().origin.clone();
+        Python::with_gil(|py| origin_into_py(origin.as_ref(), py))
+    }
+
     pub fn commit(&mut self) -> PyResult<()> {
-        if !self.committed {
-            self.get_inner().borrow_mut().commit();
+        // the transaction may have been released with `YDoc.abort_transaction`
+        if !self.committed && !self.inner.borrow().committed {
+            // within `YDoc.batch`, the transaction is committed once the batch exits
+            if self.inner.borrow().batches == 0 {
+                self.get_inner().borrow_mut().commit();
+            }
             self.committed = true;
             Ok(())
         } else {
@@ -165,7 +249,8 @@ impl YTransaction {
     /// Encodes a state vector of a given transaction document into its binary representation using
     /// lib0 v1 encoding. State vector is a compact representation of updates performed on a given
     /// document and can be used by `encode_state_as_update` on remote peer to generate a delta
-    /// update payload to synchronize changes between peers.
+    /// update payload to synchronize changes between peers. The result is an immutable `bytes`
+    /// object.
     ///
     /// Example:
     ///
@@ -190,7 +275,7 @@ impl YTransaction {
     ///
     /// ```
 
-    pub fn state_vector_v1(&self) -> PyObject {
+    pub fn state_vector_v1(&self) -> Py<PyBytes> {
         let sv = self.get_inner().borrow().state_vector();
         let payload = sv.encode_v1();
         Python::with_gil(|py| PyBytes::new(py, &payload).into())
@@ -199,7 +284,8 @@ impl YTransaction {
     /// Encodes all updates that have happened since a given version `vector` into a compact delta
     /// representation using lib0 v1 encoding. If `vector` parameter has not been provided, generated
     /// delta payload will contain all changes of a current Ypy document, working effectively as
-    /// its state snapshot.
+    /// its state snapshot. The result is an immutable `bytes` object or, if `copy` is `False`, an
+    /// `UpdateBuffer` sharing memory with the encoder, which avoids copying large payloads.
     ///
     /// Example:
     ///
@@ -222,22 +308,47 @@ impl YTransaction {
     ///     del local_txn
     ///     del remote_txn
     /// ```
-    pub fn diff_v1(&self, vector: Option<Vec<u8>>) -> PyResult<PyObject> {
+    pub fn diff_v1(&self, vector: Option<Vec<u8>>, copy: Option<bool>) -> PyResult<PyObject> {
+        let mut encoder = EncoderV1::new();
+        let sv = decode_state_vector(vector)?;
+        self.get_inner().borrow_mut().encode_diff(&sv, &mut encoder);
+        let copy = copy.unwrap_or(true);
+        Ok(Python::with_gil(|py| {
+            update_into_py(py, encoder.to_vec(), copy)
+        }))
+    }
+
+    /// Encodes all updates of a current transaction's document which `other` document hasn't seen
+    /// yet, using lib0 v1 encoding. This is a shorthand for diffing against the state vector of
+    /// `other`, so that it doesn't have to be encoded and passed around manually.
+    ///
+    /// Example:
+    ///
+    /// ```python
+    /// from y_py import YDoc
+    ///
+    /// local_doc = YDoc()
+    /// remote_doc = YDoc()
+    ///
+    /// with remote_doc.begin_transaction() as remote_txn:
+    ///     remote_delta = remote_txn.diff_since(local_doc)
+    /// with local_doc.begin_transaction() as local_txn:
+    ///     local_txn.apply_v1(remote_delta)
+    /// ```
+    pub fn diff_since(&self, other: &YDoc) -> Py<PyBytes> {
+        let sv = other.with_transaction(|txn| txn.state_vector());
         let mut encoder = EncoderV1::new();
-        let sv = if let Some(vector) = vector {
-            StateVector::decode_v1(vector.to_vec().as_slice())
-                .map_err(|e| EncodingException::new_err(e.to_string()))?
-        } else {
-            StateVector::default()
-        };
         self.get_inner().borrow_mut().encode_diff(&sv, &mut encoder);
-        let bytes: PyObject = Python::with_gil(|py| PyBytes::new(py, &encoder.to_vec()).into());
-        Ok(bytes)
+        Python::with_gil(|py| PyBytes::new(py, &encoder.to_vec()).into())
     }
 
     /// Applies delta update generated by the remote document replica to a current transaction's
     /// document. This method assumes that a payload maintains lib0 v1 encoding format.
     ///
+    /// Returns `True` if the update has been fully integrated. Otherwise some of the updates it
+    /// depends on haven't been applied yet, so its remaining content is kept pending until they
+    /// arrive (see `YDoc.pending_update`).
+    ///
     /// Example:
     ///
     /// ```python
@@ -259,13 +370,8 @@ impl YTransaction {
     ///     del local_txn
     ///     del remote_txn
     /// ```
-    pub fn apply_v1(&mut self, diff: Vec<u8>) -> PyResult<()> {
-        let diff: Vec<u8> = diff.to_vec();
-        let mut decoder = DecoderV1::from(diff.as_slice());
-        let update =
-            Update::decode(&mut decoder).map_err(|e| EncodingException::new_err(e.to_string()))?;
-        self.get_inner().borrow_mut().apply_update(update);
-        Ok(())
+    pub fn apply_v1(&mut self, diff: Vec<u8>) -> PyResult<bool> {
+        self.get_inner().borrow_mut().apply_v1(&diff)
     }
 
     /// Allows YTransaction to be used with a Python context block.
@@ -308,3 +414,13 @@ impl YTransaction {
         Ok(exception_type.is_none())
     }
 }
+
+/// Decodes a state vector encoded using lib0 v1 encoding. A missing `vector` stands for an empty
+/// state vector, which makes diffs against it contain the whole document state.
+pub(crate) fn decode_state_vector(vector: Option<Vec<u8>>) -> PyResult<StateVector> {
+    match vector {
+        Some(vector) => StateVector::decode_v1(vector.as_slice())
+            .map_err(|e| EncodingException::new_err(e.to_string())),
+        None => Ok(StateVector::default()),
+    }
+}
@@ -1,21 +1,25 @@
-use crate::shared_types::{SubId, TypeWithDoc};
-use crate::y_doc::{WithDoc, YDocInner};
+use crate::shared_types::{insert_position, DefaultPyErr, SubId, TypeWithDoc};
+use crate::y_doc::{with_committing_transaction, WithDoc, YDocInner};
+use lib0::any::Any;
+use pyo3::exceptions::PyIndexError;
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::mem::ManuallyDrop;
 use std::ops::Deref;
 use std::rc::Rc;
+use yrs::types::text::YChange;
 use yrs::types::xml::{TreeWalker, Xml, XmlEvent, XmlTextEvent};
-use yrs::types::{DeepObservable, EntryChange, Path, PathSegment};
-use yrs::XmlFragmentRef;
+use yrs::types::{DeepObservable, EntryChange, Path, PathSegment, Value};
 use yrs::XmlTextRef;
 use yrs::{GetString, XmlElementPrelim, XmlElementRef, XmlTextPrelim};
-use yrs::{Observable, SubscriptionId, Text, TransactionMut, XmlFragment, XmlNode};
+use yrs::{Observable, ReadTxn, SubscriptionId, Text, TransactionMut, XmlFragment, XmlNode};
+use yrs::{XmlFragmentPrelim, XmlFragmentRef};
 
-use crate::shared_types::{DeepSubscription, ShallowSubscription};
+use crate::shared_types::{DeepSubscription, ObserverCallback, ShallowSubscription};
 use crate::type_conversions::{events_into_py, ToPython, WithDocToPython};
-use crate::y_transaction::{YTransaction, YTransactionInner};
+use crate::y_transaction::{origin_into_py, YTransaction, YTransactionInner};
 
 /// XML element data type. It represents an XML node, which can contain key-value attributes
 /// (interpreted as strings) as well as other nested XML elements or rich text (represented by
@@ -43,6 +47,10 @@ impl YXmlElement {
     fn new(v: XmlElementRef, doc: Rc<RefCell<YDocInner>>) -> Self {
         YXmlElement(TypeWithDoc::new(v, doc))
     }
+
+    fn _len(&self, txn: &TransactionMut) -> usize {
+        self.0.len(txn) as usize
+    }
 }
 
 #[pymethods]
@@ -57,10 +65,6 @@ impl YXmlElement {
         self.0.with_transaction(|txn| self._len(txn))
     }
 
-    fn _len(&self, txn: &YTransactionInner) -> usize {
-        self.0.len(txn) as usize
-    }
-
     /// Inserts a new instance of `YXmlElement` as a child of this XML node and returns it.
     pub fn insert_xml_element(
         &self,
@@ -68,7 +72,11 @@ impl YXmlElement {
         index: u32,
         name: &str,
     ) -> PyResult<YXmlElement> {
-        txn.transact(|txn| self._insert_xml_element(txn, index, name))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._insert_xml_element(txn, index, name))
+        })?
     }
 
     fn _insert_xml_element(
@@ -83,7 +91,11 @@ impl YXmlElement {
 
     // /// Inserts a new instance of `YXmlText` as a child of this XML node and returns it.
     pub fn insert_xml_text(&self, txn: &mut YTransaction, index: u32) -> PyResult<YXmlText> {
-        txn.transact(|txn| self._insert_xml_text(txn, index))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._insert_xml_text(txn, index))
+        })?
     }
 
     fn _insert_xml_text(&self, txn: &mut YTransactionInner, index: u32) -> YXmlText {
@@ -91,10 +103,41 @@ impl YXmlElement {
         YXmlText::new(inner_node, self.0.doc.clone())
     }
 
+    /// Inserts a new, empty instance of `YXmlFragment` as a child of this XML node and returns it.
+    /// Fragments group other XML nodes without introducing a tag of their own.
+    pub fn insert_xml_fragment(
+        &self,
+        txn: &mut YTransaction,
+        index: u32,
+    ) -> PyResult<YXmlFragment> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._insert_xml_fragment(txn, index))
+        })?
+    }
+
+    fn _insert_xml_fragment(
+        &self,
+        txn: &mut YTransactionInner,
+        index: u32,
+    ) -> PyResult<YXmlFragment> {
+        if index as usize > self._len(txn) {
+            return Err(PyIndexError::default_message());
+        }
+        let prelim = XmlFragmentPrelim::<_, XmlTextPrelim<&str>>::new(None);
+        let inner_node = self.0.insert(txn, index, prelim);
+        Ok(YXmlFragment::new(inner_node, self.0.doc.clone()))
+    }
+
     /// Removes a range of children XML nodes from this `YXmlElement` instance,
     /// starting at given `index`.
     pub fn delete(&self, txn: &mut YTransaction, index: u32, length: u32) -> PyResult<()> {
-        txn.transact(|txn| self._delete(txn, index, length))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._delete(txn, index, length))
+        })?
     }
 
     fn _delete(&self, txn: &mut YTransactionInner, index: u32, length: u32) {
@@ -103,7 +146,11 @@ impl YXmlElement {
 
     /// Appends a new instance of `YXmlElement` as the last child of this XML node and returns it.
     pub fn push_xml_element(&self, txn: &mut YTransaction, name: &str) -> PyResult<YXmlElement> {
-        txn.transact(|txn| self._push_xml_element(txn, name))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._push_xml_element(txn, name))
+        })?
     }
     fn _push_xml_element(&self, txn: &mut YTransactionInner, name: &str) -> YXmlElement {
         let index = self._len(txn) as u32;
@@ -112,20 +159,30 @@ impl YXmlElement {
 
     /// Appends a new instance of `YXmlText` as the last child of this XML node and returns it.
     pub fn push_xml_text(&self, txn: &mut YTransaction) -> PyResult<YXmlText> {
-        txn.transact(|txn| self._push_xml_text(txn))
+        txn.transact(|txn| self.0.guard_alive(&*txn).map(|_| self._push_xml_text(txn)))?
     }
     fn _push_xml_text(&self, txn: &mut YTransactionInner) -> YXmlText {
         let index = self._len(txn) as u32;
         self._insert_xml_text(txn, index)
     }
 
+    /// Appends a new, empty instance of `YXmlFragment` as the last child of this XML node and
+    /// returns it.
+    pub fn push_xml_fragment(&self, txn: &mut YTransaction) -> PyResult<YXmlFragment> {
+        txn.transact(|txn| {
+            self.0.guard_alive(&*txn)?;
+            let index = self._len(txn) as u32;
+            self._insert_xml_fragment(txn, index)
+        })?
+    }
+
     /// Returns a first child of this XML node.
     /// It can be either `YXmlElement`, `YXmlText` or `undefined` if current node has not children.
     #[getter]
     pub fn first_child(&self) -> PyObject {
         Python::with_gil(|py| {
             self.0.inner.first_child().map_or(py.None(), |xml| {
-                xml.with_doc_into_py(self.0.doc.clone(), py)
+                xml_node_into_py(xml, self.0.doc.clone(), py)
             })
         })
     }
@@ -138,7 +195,7 @@ impl YXmlElement {
         Python::with_gil(|py| {
             self.0.with_transaction(|txn| {
                 self.0.siblings(txn).next().map_or(py.None(), |xml| {
-                    xml.with_doc_into_py(self.0.doc.clone(), py)
+                    xml_node_into_py(xml, self.0.doc.clone(), py)
                 })
             })
         })
@@ -156,18 +213,35 @@ impl YXmlElement {
                     .siblings(txn)
                     .next_back()
                     .map_or(py.None(), |xml| {
-                        xml.with_doc_into_py(self.0.doc.clone(), py)
+                        xml_node_into_py(xml, self.0.doc.clone(), py)
                     })
             })
         })
     }
 
+    /// Returns `False` if this `YXmlElement` has been removed from its document, either directly or
+    /// along with one of its ancestors. Removed types can no longer be modified.
+    pub fn is_alive(&self) -> bool {
+        self.0.is_alive()
+    }
+
+    /// Returns `True` if this `YXmlElement` has been removed from its document. See `is_alive`.
+    pub fn is_deleted(&self) -> bool {
+        !self.is_alive()
+    }
+
+    /// Returns an identifier of this `YXmlElement` which is the same on every peer: the name of a root
+    /// type or a `(client, clock)` pair identifying the update which has created a nested one.
+    pub fn id(&self) -> PyObject {
+        self.0.id()
+    }
+
     /// Returns a parent `YXmlElement` node or `undefined` if current node has no parent assigned.
     #[getter]
     pub fn parent(&self) -> PyObject {
         Python::with_gil(|py| {
             self.0.parent().map_or(py.None(), |xml| {
-                xml.with_doc_into_py(self.0.doc.clone(), py)
+                xml_node_into_py(xml, self.0.doc.clone(), py)
             })
         })
     }
@@ -181,34 +255,59 @@ impl YXmlElement {
         format!("YXmlElement({})", self.__str__())
     }
 
+    /// Returns a stable, pretty-printed XML representation of this node. Each child node is placed
+    /// on its own line, indented by `indent` spaces (2 by default) per nesting level - use `0` for
+    /// a compact output. Attributes are sorted by their names unless `sort_attributes` is `False`.
+    /// Special characters in text and attribute values are escaped as XML entities.
+    pub fn to_xml(&self, indent: Option<usize>, sort_attributes: Option<bool>) -> String {
+        let node = XmlNode::Element(self.0.inner.clone());
+        let indent = indent.unwrap_or(2);
+        let sort_attributes = sort_attributes.unwrap_or(true);
+        let mut buf = String::new();
+        self.0
+            .with_transaction(|txn| write_xml(txn, &node, indent, sort_attributes, 0, &mut buf));
+        buf
+    }
+
     /// Sets a `name` and `value` as new attribute for this XML node. If an attribute with the same
     /// `name` already existed on that node, its value with be overridden with a provided one.
     pub fn set_attribute(&self, txn: &mut YTransaction, name: &str, value: &str) -> PyResult<()> {
-        txn.transact(|txn| self.0.insert_attribute(txn, name, value))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self.0.insert_attribute(txn, name, value))
+        })?
     }
 
     /// Returns a value of an attribute given its `name`. If no attribute with such name existed,
     /// `null` will be returned.
     pub fn get_attribute(&self, name: &str) -> Option<String> {
         self.0
-            .with_transaction(|txn: &YTransactionInner| self.0.get_attribute(txn, name))
+            .with_transaction(|txn| self.0.get_attribute(txn, name))
     }
 
     pub fn remove_attribute(&self, txn: &mut YTransaction, name: &str) -> PyResult<()> {
-        txn.transact(|txn| self.0.remove_attribute(txn, &name))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self.0.remove_attribute(txn, &name))
+        })?
     }
 
-    /// Returns the attributes of this XML node as a Python list of tuples
-    pub fn attributes(&self) -> PyObject {
+    /// Returns the attributes of this XML node as a Python list of `(name, value)` tuples, or as
+    /// a dict if `as_dict` is `True`. Attributes are stored in unspecified order, unless `sort` is
+    /// `True` in which case they're ordered by their names. Attribute values are always strings.
+    pub fn attributes(&self, as_dict: Option<bool>, sort: Option<bool>) -> PyResult<PyObject> {
         Python::with_gil(|py| {
-            self.0
-                .with_transaction(|txn| {
-                    let attributes = self.0.attributes(txn);
-                    attributes
-                        .map(|(k, v)| (k.to_string(), v))
-                        .collect::<Vec<_>>()
-                })
-                .into_py(py)
+            self.0.with_transaction(|txn| {
+                attributes_into_py(
+                    py,
+                    txn,
+                    &*self.0,
+                    as_dict.unwrap_or(false),
+                    sort.unwrap_or(false),
+                )
+            })
         })
     }
 
@@ -218,30 +317,51 @@ impl YXmlElement {
         YXmlTreeWalker::from(self)
     }
 
+    /// Returns the number of all XML nodes nested within this XML element, as visited by
+    /// `tree_walker`. If `depth` is given, only nodes up to that many levels below this element
+    /// are counted, e.g. `depth=1` counts direct children only.
+    pub fn descendant_count(&self, depth: Option<u32>) -> usize {
+        self.0.with_transaction(|txn| match depth {
+            Some(depth) => count_descendants(txn, self.0.deref(), depth),
+            None => self.0.successors(txn).count(),
+        })
+    }
+
     /// Subscribes to all operations happening over this instance of `YXmlElement`. All changes are
     /// batched and eventually triggered during transaction commit phase.
     /// Returns an `SubscriptionId` which, can be used to unsubscribe the observer.
-    pub fn observe(&mut self, f: PyObject) -> ShallowSubscription {
+    pub fn observe(&mut self, f: PyObject, weak: Option<bool>) -> PyResult<ShallowSubscription> {
+        let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+        let callback = f.clone();
         let doc = self.0.doc.clone();
         let sub_id = self
             .0
             .observe(move |txn, e| {
                 Python::with_gil(|py| {
                     let event = YXmlEvent::new(e, txn, doc.clone());
-                    if let Err(err) = f.call1(py, (event,)) {
+                    if let Err(err) =
+                        with_committing_transaction(&doc, txn, || f.call1(py, (event,)))
+                    {
                         err.restore(py)
                     }
                 })
             })
             .into();
 
-        ShallowSubscription(sub_id)
+        let subscription = ShallowSubscription::new(sub_id, self.0.inner.clone());
+        callback.cancel_with({
+            let subscription = subscription.clone();
+            move || subscription.unobserve()
+        });
+        Ok(subscription)
     }
 
     /// Subscribes to all operations happening over this instance of `YXmlElement` and all of its children.
     /// All changes are batched and eventually triggered during transaction commit phase.
     /// Returns an `SubscriptionId` which, can be used to unsubscribe the observer.
-    pub fn observe_deep(&mut self, f: PyObject) -> DeepSubscription {
+    pub fn observe_deep(&mut self, f: PyObject, weak: Option<bool>) -> PyResult<DeepSubscription> {
+        let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+        let callback = f.clone();
         let doc = self.0.doc.clone();
         let sub_id = self
             .0
@@ -249,20 +369,27 @@ impl YXmlElement {
             .observe_deep(move |txn, events| {
                 Python::with_gil(|py| {
                     let events = events_into_py(txn, events, doc.clone());
-                    if let Err(err) = f.call1(py, (events,)) {
+                    if let Err(err) =
+                        with_committing_transaction(&doc, txn, || f.call1(py, (events,)))
+                    {
                         err.restore(py)
                     }
                 })
             })
             .into();
-        DeepSubscription(sub_id)
+        let subscription = DeepSubscription::new(sub_id, self.0.inner.clone());
+        callback.cancel_with({
+            let subscription = subscription.clone();
+            move || subscription.unobserve()
+        });
+        Ok(subscription)
     }
 
     /// Cancels the observer callback associated with the `subscripton_id`.
     pub fn unobserve(&mut self, subscription_id: SubId) {
         match subscription_id {
-            SubId::Shallow(ShallowSubscription(id)) => self.0.unobserve(id),
-            SubId::Deep(DeepSubscription(id)) => self.0.unobserve_deep(id),
+            SubId::Shallow(ShallowSubscription(id, _)) => self.0.unobserve(id),
+            SubId::Deep(DeepSubscription(id, _)) => self.0.unobserve_deep(id),
         }
     }
 }
@@ -296,6 +423,14 @@ impl YXmlText {
     fn new(v: XmlTextRef, doc: Rc<RefCell<YDocInner>>) -> Self {
         YXmlText(TypeWithDoc::new(v, doc))
     }
+
+    fn _len(&self, txn: &TransactionMut) -> usize {
+        self.0.len(txn) as usize
+    }
+
+    fn normalize_insert_index(&self, txn: &TransactionMut, index: isize) -> PyResult<u32> {
+        insert_position(index, self._len(txn), "a text")
+    }
 }
 
 #[pymethods]
@@ -306,21 +441,25 @@ impl YXmlText {
         self.0.with_transaction(|txn| self._len(txn))
     }
 
-    fn _len(&self, txn: &YTransactionInner) -> usize {
-        self.0.len(txn) as usize
-    }
-
     /// Inserts a given `chunk` of text into this `YXmlText` instance, starting at a given `index`.
-    pub fn insert(&self, txn: &mut YTransaction, index: i32, chunk: &str) -> PyResult<()> {
-        txn.transact(|txn| self._insert(txn, index, chunk))
+    /// The `index` is counted in UTF-8 bytes. Negative indexes are counted from the end of the
+    /// text, like with `list.insert`.
+    pub fn insert(&self, txn: &mut YTransaction, index: isize, chunk: &str) -> PyResult<()> {
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .and_then(|_| self._insert(txn, index, chunk))
+        })?
     }
-    fn _insert(&self, txn: &mut YTransactionInner, index: i32, chunk: &str) {
-        self.0.insert(txn, index as u32, chunk)
+    fn _insert(&self, txn: &mut YTransactionInner, index: isize, chunk: &str) -> PyResult<()> {
+        let index = self.normalize_insert_index(txn, index)?;
+        self.0.insert(txn, index, chunk);
+        Ok(())
     }
 
     /// Appends a given `chunk` of text at the end of `YXmlText` instance.
     pub fn push(&self, txn: &mut YTransaction, chunk: &str) -> PyResult<()> {
-        txn.transact(|txn| self._push(txn, chunk))
+        txn.transact(|txn| self.0.guard_alive(&*txn).map(|_| self._push(txn, chunk)))?
     }
 
     fn _push(&self, txn: &mut YTransactionInner, chunk: &str) {
@@ -330,7 +469,11 @@ impl YXmlText {
     /// Deletes a specified range of of characters, starting at a given `index`.
     /// Both `index` and `length` are counted in terms of a number of UTF-8 character bytes.
     pub fn delete(&self, txn: &mut YTransaction, index: u32, length: u32) -> PyResult<()> {
-        txn.transact(|txn| self._delete(txn, index, length))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._delete(txn, index, length))
+        })?
     }
     fn _delete(&self, txn: &mut YTransactionInner, index: u32, length: u32) {
         self.0.remove_range(txn, index, length)
@@ -344,7 +487,7 @@ impl YXmlText {
         Python::with_gil(|py| {
             self.0.with_transaction(|txn| {
                 self.0.siblings(txn).next().map_or(py.None(), |xml| {
-                    xml.with_doc_into_py(self.0.doc.clone(), py)
+                    xml_node_into_py(xml, self.0.doc.clone(), py)
                 })
             })
         })
@@ -362,18 +505,35 @@ impl YXmlText {
                     .siblings(txn)
                     .next_back()
                     .map_or(py.None(), |xml| {
-                        xml.with_doc_into_py(self.0.doc.clone(), py)
+                        xml_node_into_py(xml, self.0.doc.clone(), py)
                     })
             })
         })
     }
 
+    /// Returns `False` if this `YXmlText` has been removed from its document, either directly or
+    /// along with one of its ancestors. Removed types can no longer be modified.
+    pub fn is_alive(&self) -> bool {
+        self.0.is_alive()
+    }
+
+    /// Returns `True` if this `YXmlText` has been removed from its document. See `is_alive`.
+    pub fn is_deleted(&self) -> bool {
+        !self.is_alive()
+    }
+
+    /// Returns an identifier of this `YXmlText` which is the same on every peer: the name of a root
+    /// type or a `(client, clock)` pair identifying the update which has created a nested one.
+    pub fn id(&self) -> PyObject {
+        self.0.id()
+    }
+
     /// Returns a parent `YXmlElement` node or `undefined` if current node has no parent assigned.
     #[getter]
     pub fn parent(&self) -> PyObject {
         Python::with_gil(|py| {
             self.0.parent().map_or(py.None(), |xml| {
-                xml.with_doc_into_py(self.0.doc.clone(), py)
+                xml_node_into_py(xml, self.0.doc.clone(), py)
             })
         })
     }
@@ -390,7 +550,11 @@ impl YXmlText {
     /// Sets a `name` and `value` as new attribute for this XML node. If an attribute with the same
     /// `name` already existed on that node, its value with be overridden with a provided one.
     pub fn set_attribute(&self, txn: &mut YTransaction, name: &str, value: &str) -> PyResult<()> {
-        txn.transact(|txn| self.0.insert_attribute(txn, name, value))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self.0.insert_attribute(txn, name, value))
+        })?
     }
 
     /// Returns a value of an attribute given its `name`. If no attribute with such name existed,
@@ -402,67 +566,87 @@ impl YXmlText {
 
     /// Removes an attribute from this XML node, given its `name`.
     pub fn remove_attribute(&self, txn: &mut YTransaction, name: &str) -> PyResult<()> {
-        txn.transact(|txn| self.0.remove_attribute(txn, &name))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self.0.remove_attribute(txn, &name))
+        })?
     }
 
-    /// Returns an iterator that enables to traverse over all attributes of this XML node in
-    /// unspecified order.
-    pub fn attributes(&self) -> PyObject {
+    /// Returns the attributes of this XML node as a Python list of `(name, value)` tuples, or as
+    /// a dict if `as_dict` is `True`. Attributes are stored in unspecified order, unless `sort` is
+    /// `True` in which case they're ordered by their names. Attribute values are always strings.
+    pub fn attributes(&self, as_dict: Option<bool>, sort: Option<bool>) -> PyResult<PyObject> {
         Python::with_gil(|py| {
-            self.0
-                .with_transaction(|txn| {
-                    let attributes = self.0.attributes(txn);
-                    attributes
-                        .map(|(k, v)| (k.to_string(), v))
-                        .collect::<Vec<_>>()
-                })
-                .into_py(py)
+            self.0.with_transaction(|txn| {
+                attributes_into_py(
+                    py,
+                    txn,
+                    &*self.0,
+                    as_dict.unwrap_or(false),
+                    sort.unwrap_or(false),
+                )
+            })
         })
     }
 
     /// Subscribes to all operations happening over this instance of `YXmlText`. All changes are
     /// batched and eventually triggered during transaction commit phase.
     /// Returns an `SubscriptionId` which, which can be used to unsubscribe the callback function.
-    pub fn observe(&mut self, f: PyObject) -> ShallowSubscription {
+    pub fn observe(&mut self, f: PyObject, weak: Option<bool>) -> PyResult<ShallowSubscription> {
+        let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+        let callback = f.clone();
         let doc = self.0.doc.clone();
         let sub_id: SubscriptionId = self
             .0
             .observe(move |txn, e| {
                 Python::with_gil(|py| {
                     let e = YXmlTextEvent::new(e, txn, doc.clone());
-                    if let Err(err) = f.call1(py, (e,)) {
+                    if let Err(err) = with_committing_transaction(&doc, txn, || f.call1(py, (e,))) {
                         err.restore(py)
                     }
                 })
             })
             .into();
-        ShallowSubscription(sub_id)
+        let subscription = ShallowSubscription::new(sub_id, self.0.inner.clone());
+        callback.cancel_with({
+            let subscription = subscription.clone();
+            move || subscription.unobserve()
+        });
+        Ok(subscription)
     }
 
     /// Subscribes to all operations happening over this instance of `YXmlText` and its child elements. All changes are
     /// batched and eventually triggered during transaction commit phase.
     /// Returns an `SubscriptionId` which, which can be used to unsubscribe the callback function.
-    pub fn observe_deep(&mut self, f: PyObject) -> DeepSubscription {
+    pub fn observe_deep(&mut self, f: PyObject, weak: Option<bool>) -> PyResult<DeepSubscription> {
+        let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+        let callback = f.clone();
         let doc = self.0.doc.clone();
         let sub_id: SubscriptionId = self
             .0
             .observe_deep(move |txn, events| {
                 Python::with_gil(|py| {
                     let e = events_into_py(txn, events, doc.clone());
-                    if let Err(err) = f.call1(py, (e,)) {
+                    if let Err(err) = with_committing_transaction(&doc, txn, || f.call1(py, (e,))) {
                         err.restore(py)
                     }
                 })
             })
             .into();
-        DeepSubscription(sub_id)
+        let subscription = DeepSubscription::new(sub_id, self.0.inner.clone());
+        callback.cancel_with({
+            let subscription = subscription.clone();
+            move || subscription.unobserve()
+        });
+        Ok(subscription)
     }
 
     /// Cancels the observer callback associated with the `subscripton_id`.
     pub fn unobserve(&mut self, subscription_id: SubId) {
         match subscription_id {
-            SubId::Shallow(ShallowSubscription(id)) => self.0.unobserve(id),
-            SubId::Deep(DeepSubscription(id)) => self.0.unobserve_deep(id),
+            SubId::Shallow(ShallowSubscription(id, _)) => self.0.unobserve(id),
+            SubId::Deep(DeepSubscription(id, _)) => self.0.unobserve_deep(id),
         }
     }
 }
@@ -480,6 +664,10 @@ impl YXmlFragment {
     fn new(v: XmlFragmentRef, doc: Rc<RefCell<YDocInner>>) -> Self {
         YXmlFragment(TypeWithDoc::new(v, doc))
     }
+
+    fn _len(&self, txn: &TransactionMut) -> usize {
+        self.0.len(txn) as usize
+    }
 }
 
 #[pymethods]
@@ -489,10 +677,6 @@ impl YXmlFragment {
         self.0.with_transaction(|txn| self._len(txn))
     }
 
-    fn _len(&self, txn: &YTransactionInner) -> usize {
-        self.0.len(txn) as usize
-    }
-
     /// Inserts a new instance of `YXmlElement` as a child of this XML fragment and returns it.
     pub fn insert_xml_element(
         &self,
@@ -500,7 +684,11 @@ impl YXmlFragment {
         index: u32,
         name: &str,
     ) -> PyResult<YXmlElement> {
-        txn.transact(|txn| self._insert_xml_element(txn, index, name))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._insert_xml_element(txn, index, name))
+        })?
     }
 
     fn _insert_xml_element(
@@ -515,7 +703,11 @@ impl YXmlFragment {
 
     // /// Inserts a new instance of `YXmlText` as a child of this XML node and returns it.
     pub fn insert_xml_text(&self, txn: &mut YTransaction, index: u32) -> PyResult<YXmlText> {
-        txn.transact(|txn| self._insert_xml_text(txn, index))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._insert_xml_text(txn, index))
+        })?
     }
 
     fn _insert_xml_text(&self, txn: &mut YTransactionInner, index: u32) -> YXmlText {
@@ -526,7 +718,11 @@ impl YXmlFragment {
     /// Removes a range of children XML nodes from this `YXmlElement` instance,
     /// starting at given `index`.
     pub fn delete(&self, txn: &mut YTransaction, index: u32, length: u32) -> PyResult<()> {
-        txn.transact(|txn| self._delete(txn, index, length))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._delete(txn, index, length))
+        })?
     }
 
     fn _delete(&self, txn: &mut YTransactionInner, index: u32, length: u32) {
@@ -535,7 +731,11 @@ impl YXmlFragment {
 
     /// Appends a new instance of `YXmlElement` as the last child of this XML node and returns it.
     pub fn push_xml_element(&self, txn: &mut YTransaction, name: &str) -> PyResult<YXmlElement> {
-        txn.transact(|txn| self._push_xml_element(txn, name))
+        txn.transact(|txn| {
+            self.0
+                .guard_alive(&*txn)
+                .map(|_| self._push_xml_element(txn, name))
+        })?
     }
     fn _push_xml_element(&self, txn: &mut YTransactionInner, name: &str) -> YXmlElement {
         let index = self._len(txn) as u32;
@@ -544,7 +744,7 @@ impl YXmlFragment {
 
     /// Appends a new instance of `YXmlText` as the last child of this XML node and returns it.
     pub fn push_xml_text(&self, txn: &mut YTransaction) -> PyResult<YXmlText> {
-        txn.transact(|txn| self._push_xml_text(txn))
+        txn.transact(|txn| self.0.guard_alive(&*txn).map(|_| self._push_xml_text(txn)))?
     }
     fn _push_xml_text(&self, txn: &mut YTransactionInner) -> YXmlText {
         let index = self._len(txn) as u32;
@@ -557,17 +757,34 @@ impl YXmlFragment {
     pub fn first_child(&self) -> PyObject {
         Python::with_gil(|py| {
             self.0.inner.first_child().map_or(py.None(), |xml| {
-                xml.with_doc_into_py(self.0.doc.clone(), py)
+                xml_node_into_py(xml, self.0.doc.clone(), py)
             })
         })
     }
 
+    /// Returns `False` if this `YXmlFragment` has been removed from its document, either directly or
+    /// along with one of its ancestors. Removed types can no longer be modified.
+    pub fn is_alive(&self) -> bool {
+        self.0.is_alive()
+    }
+
+    /// Returns `True` if this `YXmlFragment` has been removed from its document. See `is_alive`.
+    pub fn is_deleted(&self) -> bool {
+        !self.is_alive()
+    }
+
+    /// Returns an identifier of this `YXmlFragment` which is the same on every peer: the name of a root
+    /// type or a `(client, clock)` pair identifying the update which has created a nested one.
+    pub fn id(&self) -> PyObject {
+        self.0.id()
+    }
+
     /// Returns a parent `YXmlElement` node or `undefined` if current node has no parent assigned.
     #[getter]
     pub fn parent(&self) -> PyObject {
         Python::with_gil(|py| {
             self.0.parent().map_or(py.None(), |xml| {
-                xml.with_doc_into_py(self.0.doc.clone(), py)
+                xml_node_into_py(xml, self.0.doc.clone(), py)
             })
         })
     }
@@ -586,27 +803,38 @@ impl YXmlFragment {
     /// Subscribes to all operations happening over this instance of `YXmlElement`. All changes are
     /// batched and eventually triggered during transaction commit phase.
     /// Returns an `SubscriptionId` which, can be used to unsubscribe the observer.
-    pub fn observe(&mut self, f: PyObject) -> ShallowSubscription {
+    pub fn observe(&mut self, f: PyObject, weak: Option<bool>) -> PyResult<ShallowSubscription> {
+        let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+        let callback = f.clone();
         let doc = self.0.doc.clone();
         let sub_id = self
             .0
             .observe(move |txn, e| {
                 Python::with_gil(|py| {
                     let event = YXmlEvent::new(e, txn, doc.clone());
-                    if let Err(err) = f.call1(py, (event,)) {
+                    if let Err(err) =
+                        with_committing_transaction(&doc, txn, || f.call1(py, (event,)))
+                    {
                         err.restore(py)
                     }
                 })
             })
             .into();
 
-        ShallowSubscription(sub_id)
+        let subscription = ShallowSubscription::new(sub_id, self.0.inner.clone());
+        callback.cancel_with({
+            let subscription = subscription.clone();
+            move || subscription.unobserve()
+        });
+        Ok(subscription)
     }
 
     /// Subscribes to all operations happening over this instance of `YXmlElement` and all of its children.
     /// All changes are batched and eventually triggered during transaction commit phase.
     /// Returns an `SubscriptionId` which, can be used to unsubscribe the observer.
-    pub fn observe_deep(&mut self, f: PyObject) -> DeepSubscription {
+    pub fn observe_deep(&mut self, f: PyObject, weak: Option<bool>) -> PyResult<DeepSubscription> {
+        let f = Python::with_gil(|py| ObserverCallback::new(py, f, weak))?;
+        let callback = f.clone();
         let doc = self.0.doc.clone();
         let sub_id = self
             .0
@@ -614,20 +842,27 @@ impl YXmlFragment {
             .observe_deep(move |txn, events| {
                 Python::with_gil(|py| {
                     let events = events_into_py(txn, events, doc.clone());
-                    if let Err(err) = f.call1(py, (events,)) {
+                    if let Err(err) =
+                        with_committing_transaction(&doc, txn, || f.call1(py, (events,)))
+                    {
                         err.restore(py)
                     }
                 })
             })
             .into();
-        DeepSubscription(sub_id)
+        let subscription = DeepSubscription::new(sub_id, self.0.inner.clone());
+        callback.cancel_with({
+            let subscription = subscription.clone();
+            move || subscription.unobserve()
+        });
+        Ok(subscription)
     }
 
     /// Cancels the observer callback associated with the `subscripton_id`.
     pub fn unobserve(&mut self, subscription_id: SubId) {
         match subscription_id {
-            SubId::Shallow(ShallowSubscription(id)) => self.0.unobserve(id),
-            SubId::Deep(DeepSubscription(id)) => self.0.unobserve_deep(id),
+            SubId::Shallow(ShallowSubscription(id, _)) => self.0.unobserve(id),
+            SubId::Deep(DeepSubscription(id, _)) => self.0.unobserve_deep(id),
         }
     }
 
@@ -638,15 +873,211 @@ impl YXmlFragment {
             self.0.with_transaction(|txn| {
                 self.0
                     .get(txn, index)
-                    .map(|xml| xml.with_doc_into_py(self.0.doc.clone(), py))
+                    .map(|xml| xml_node_into_py(xml, self.0.doc.clone(), py))
             })
         })
     }
 }
 
+/// Converts attributes of a given XML node into a Python list of `(name, value)` tuples or,
+/// if `as_dict` is set, into a dict. Attributes are ordered by their names if `sort` is set.
+fn attributes_into_py<T: ReadTxn, X: Xml>(
+    py: Python,
+    txn: &T,
+    xml: &X,
+    as_dict: bool,
+    sort: bool,
+) -> PyResult<PyObject> {
+    let mut attributes: Vec<_> = xml
+        .attributes(txn)
+        .map(|(name, value)| (name.to_string(), value))
+        .collect();
+    if sort {
+        attributes.sort_by(|(a, _), (b, _)| a.cmp(b));
+    }
+    if as_dict {
+        let dict = PyDict::new(py);
+        for (name, value) in attributes {
+            dict.set_item(name, value)?;
+        }
+        Ok(dict.into())
+    } else {
+        Ok(attributes.into_py(py))
+    }
+}
+
+/// Writes an XML representation of a given `node` nested at `depth` level into `buf`.
+fn write_xml<T: ReadTxn>(
+    txn: &T,
+    node: &XmlNode,
+    indent: usize,
+    sort_attributes: bool,
+    depth: usize,
+    buf: &mut String,
+) {
+    let padding = " ".repeat(indent * depth);
+    match node {
+        XmlNode::Text(text) => {
+            buf.push_str(&padding);
+            write_xml_text(txn, text, buf);
+        }
+        XmlNode::Fragment(fragment) => {
+            let nodes = (0..fragment.len(txn))
+                .filter_map(|i| fragment.get(txn, i))
+                .collect::<Vec<_>>();
+            for (i, child) in nodes.iter().enumerate() {
+                if i > 0 && indent > 0 {
+                    buf.push('\n');
+                }
+                write_xml(txn, child, indent, sort_attributes, depth, buf);
+            }
+        }
+        XmlNode::Element(element) => {
+            let tag = element.tag();
+            let mut attributes: Vec<_> = element.attributes(txn).collect();
+            if sort_attributes {
+                attributes.sort_by(|(a, _), (b, _)| a.cmp(b));
+            }
+            buf.push_str(&padding);
+            buf.push('<');
+            buf.push_str(tag);
+            for (name, value) in attributes {
+                write_xml_attribute(name, &value, buf);
+            }
+            buf.push('>');
+            let nodes = (0..element.len(txn))
+                .filter_map(|i| element.get(txn, i))
+                .collect::<Vec<_>>();
+            for child in nodes.iter() {
+                if indent > 0 {
+                    buf.push('\n');
+                }
+                write_xml(txn, child, indent, sort_attributes, depth + 1, buf);
+            }
+            if !nodes.is_empty() && indent > 0 {
+                buf.push('\n');
+                buf.push_str(&padding);
+            }
+            buf.push_str("</");
+            buf.push_str(tag);
+            buf.push('>');
+        }
+    }
+}
+
+/// Writes the contents of an XML `text` node into `buf`. Formatting attributes are written as
+/// enclosing tags, the same way `XmlTextRef::get_string` does, but text and attribute values are
+/// escaped.
+fn write_xml_text<T: ReadTxn>(txn: &T, text: &XmlTextRef, buf: &mut String) {
+    for chunk in text.diff(txn, YChange::identity) {
+        let mut attrs: Vec<_> = chunk
+            .attributes
+            .iter()
+            .flat_map(|attributes| attributes.iter())
+            .collect();
+        attrs.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (tag, value) in attrs.iter() {
+            buf.push('<');
+            buf.push_str(tag);
+            if let Any::Map(value) = value {
+                for (name, value) in value.iter() {
+                    write_xml_attribute(name, &value.to_string(), buf);
+                }
+            }
+            buf.push('>');
+        }
+        if let Value::Any(any) = &chunk.insert {
+            escape_xml(&any.to_string(), buf);
+        }
+        for (tag, _) in attrs.iter().rev() {
+            buf.push_str("</");
+            buf.push_str(tag);
+            buf.push('>');
+        }
+    }
+}
+
+/// Writes a ` name="value"` attribute pair into `buf`, escaping its value.
+fn write_xml_attribute(name: &str, value: &str, buf: &mut String) {
+    buf.push(' ');
+    buf.push_str(name);
+    buf.push_str("=\"");
+    escape_xml(value, buf);
+    buf.push('"');
+}
+
+/// Writes `value` into `buf`, replacing characters with a special meaning in XML with entities.
+fn escape_xml(value: &str, buf: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => buf.push_str("&amp;"),
+            '<' => buf.push_str("&lt;"),
+            '>' => buf.push_str("&gt;"),
+            '"' => buf.push_str("&quot;"),
+            c => buf.push(c),
+        }
+    }
+}
+
+/// Converts an XML `node` into a JSON-like representation. Elements become
+/// `{"tag", "attributes", "children"}` objects, fragments become `{"children"}` objects and text
+/// nodes become strings.
+pub(crate) fn xml_into_json<T: ReadTxn>(txn: &T, node: &XmlNode) -> Any {
+    match node {
+        XmlNode::Text(text) => Any::String(text.get_string(txn).into_boxed_str()),
+        XmlNode::Fragment(fragment) => Any::Map(Box::new(HashMap::from([(
+            "children".to_string(),
+            xml_children_into_json(txn, fragment),
+        )]))),
+        XmlNode::Element(element) => {
+            let attributes: HashMap<String, Any> = element
+                .attributes(txn)
+                .map(|(name, value)| (name.to_string(), Any::String(value.into_boxed_str())))
+                .collect();
+            Any::Map(Box::new(HashMap::from([
+                (
+                    "tag".to_string(),
+                    Any::String(element.tag().as_ref().into()),
+                ),
+                ("attributes".to_string(), Any::Map(Box::new(attributes))),
+                ("children".to_string(), xml_children_into_json(txn, element)),
+            ])))
+        }
+    }
+}
+
+fn xml_children_into_json<T: ReadTxn, X: XmlFragment>(txn: &T, xml: &X) -> Any {
+    let children: Vec<Any> = (0..xml.len(txn))
+        .filter_map(|i| xml.get(txn, i))
+        .map(|child| xml_into_json(txn, &child))
+        .collect();
+    Any::Array(children.into_boxed_slice())
+}
+
+/// Counts XML nodes nested within `xml`, up to `depth` levels below it.
+fn count_descendants<T: ReadTxn, X: XmlFragment>(txn: &T, xml: &X, depth: u32) -> usize {
+    if depth == 0 {
+        return 0;
+    }
+    (0..xml.len(txn))
+        .filter_map(|i| xml.get(txn, i))
+        .map(|child| {
+            1 + match child {
+                XmlNode::Element(element) => count_descendants(txn, &element, depth - 1),
+                XmlNode::Fragment(fragment) => count_descendants(txn, &fragment, depth - 1),
+                XmlNode::Text(_) => 0,
+            }
+        })
+        .sum()
+}
+
 #[pyclass(unsendable)]
 pub struct YXmlTreeWalker(
-    TypeWithDoc<ManuallyDrop<TreeWalker<'static, &'static YTransactionInner, YTransactionInner>>>,
+    TypeWithDoc<
+        ManuallyDrop<
+            TreeWalker<'static, &'static TransactionMut<'static>, TransactionMut<'static>>,
+        >,
+    >,
 );
 
 impl From<&YXmlElement> for YXmlTreeWalker {
@@ -657,7 +1088,7 @@ impl From<&YXmlElement> for YXmlTreeWalker {
 
         let walker = xml_element.0.with_transaction(|txn| {
             // HACK: get rid of lifetime
-            let txn = txn as *const YTransactionInner;
+            let txn = txn as *const TransactionMut;
             unsafe { xml_element.0.successors(&*txn) }
         });
         YXmlTreeWalker(TypeWithDoc::new(
@@ -675,7 +1106,7 @@ impl From<&YXmlFragment> for YXmlTreeWalker {
 
         let walker = xml_fragment.0.with_transaction(|txn| {
             // HACK: get rid of lifetime
-            let txn = txn as *const YTransactionInner;
+            let txn = txn as *const TransactionMut;
             unsafe { xml_fragment.0.successors(&*txn) }
         });
         YXmlTreeWalker(TypeWithDoc::new(
@@ -700,7 +1131,7 @@ impl YXmlTreeWalker {
         Python::with_gil(|py| {
             slf.0
                 .next()
-                .map(|v| v.with_doc_into_py(slf.0.doc.clone(), py))
+                .map(|v| xml_node_into_py(v, slf.0.doc.clone(), py))
         })
     }
 }
@@ -761,13 +1192,15 @@ impl YXmlEvent {
         }
     }
 
-    fn __repr__(&mut self) -> String {
+    fn __repr__(&mut self) -> PyResult<String> {
         let target = self.target();
-        let delta = self.delta();
-        let keys = self.keys();
+        let delta = self.delta()?;
+        let keys = self.keys()?;
         let path = self.path();
 
-        format!("YXmlEvent(target={target}, delta={delta}, keys={keys}, path={path})")
+        Ok(format!(
+            "YXmlEvent(target={target}, delta={delta}, keys={keys}, path={path})"
+        ))
     }
 
     /// Returns an array of keys and indexes creating a path from root type down to current instance
@@ -776,26 +1209,42 @@ impl YXmlEvent {
         Python::with_gil(|py| self.inner().path().into_py(py))
     }
 
+    /// Returns the origin of the transaction which has made the changes, or `None` if it has no
+    /// origin, e.g. `apply_update(doc, update, origin="remote")` reports `"remote"`.
+    #[getter]
+    pub fn origin(&self) -> PyObject {
+        Python::with_gil(|py| origin_into_py(self.txn().origin(), py))
+    }
+
+    /// Returns the kind of the `target` shared type, as reported by `type_of`: `"xml_element"` or
+    /// `"xml_fragment"`.
+    #[getter]
+    pub fn kind(&self) -> &'static str {
+        match self.inner().target() {
+            XmlNode::Element(_) => "xml_element",
+            XmlNode::Fragment(_) => "xml_fragment",
+            XmlNode::Text(_) => "xml_text",
+        }
+    }
+
     /// Returns all changes done upon map component of a current shared data type (which can be
     /// accessed via `target`) within a bounds of corresponding transaction `txn`. These
     /// changes are done in result of operations made on `YMap` data type or attribute changes of
     /// `YXmlElement` and `YXmlText` types.
     #[getter]
-    pub fn keys(&mut self) -> PyObject {
+    pub fn keys(&mut self) -> PyResult<PyObject> {
         if let Some(keys) = &self.keys {
-            keys.clone()
+            Ok(keys.clone())
         } else {
             Python::with_gil(|py| {
                 let keys = self.inner().keys(self.txn());
                 let result = PyDict::new(py);
                 for (key, value) in keys.iter() {
-                    result
-                        .set_item(key.deref(), value.with_doc_into_py(self.doc.clone(), py))
-                        .unwrap();
+                    result.set_item(key.deref(), value.with_doc_into_py(self.doc.clone(), py)?)?;
                 }
                 let keys = PyObject::from(result);
                 self.keys = Some(keys.clone());
-                keys
+                Ok(keys)
             })
         }
     }
@@ -805,20 +1254,21 @@ impl YXmlEvent {
     /// of operations done on `YArray` and `YText`/`XmlText` types, but also whenever `XmlElement`
     /// children nodes list is modified.
     #[getter]
-    pub fn delta(&mut self) -> PyObject {
+    pub fn delta(&mut self) -> PyResult<PyObject> {
         if let Some(delta) = &self.delta {
-            delta.clone()
+            Ok(delta.clone())
         } else {
             Python::with_gil(|py| {
                 let delta = self
                     .inner()
                     .delta(self.txn())
                     .iter()
-                    .map(|d| Python::with_gil(|py| d.with_doc_into_py(self.doc.clone(), py)));
+                    .map(|d| d.with_doc_into_py(self.doc.clone(), py))
+                    .collect::<PyResult<Vec<_>>>()?;
                 let result = pyo3::types::PyList::new(py, delta);
                 let delta: PyObject = result.into();
                 self.delta = Some(delta.clone());
-                delta
+                Ok(delta)
             })
         }
     }
@@ -877,13 +1327,15 @@ impl YXmlTextEvent {
         }
     }
 
-    fn __repr__(&mut self) -> String {
+    fn __repr__(&mut self) -> PyResult<String> {
         let target = self.target();
-        let delta = self.delta();
-        let keys = self.keys();
+        let delta = self.delta()?;
+        let keys = self.keys()?;
         let path = self.path();
 
-        format!("YXmlEvent(target={target}, delta={delta}, keys={keys}, path={path})")
+        Ok(format!(
+            "YXmlEvent(target={target}, delta={delta}, keys={keys}, path={path})"
+        ))
     }
 
     /// Returns a current shared type instance, that current event changes refer to.
@@ -891,26 +1343,37 @@ impl YXmlTextEvent {
         Python::with_gil(|py| self.inner().path().into_py(py))
     }
 
+    /// Returns the origin of the transaction which has made the changes, or `None` if it has no
+    /// origin, e.g. `apply_update(doc, update, origin="remote")` reports `"remote"`.
+    #[getter]
+    pub fn origin(&self) -> PyObject {
+        Python::with_gil(|py| origin_into_py(self.txn().origin(), py))
+    }
+
+    /// Returns `"xml_text"` - the kind of the `target` shared type, as reported by `type_of`.
+    #[getter]
+    pub fn kind(&self) -> &'static str {
+        "xml_text"
+    }
+
     /// Returns all changes done upon map component of a current shared data type (which can be
     /// accessed via `target`) within a bounds of corresponding transaction `txn`. These
     /// changes are done in result of operations made on `YMap` data type or attribute changes of
     /// `YXmlElement` and `YXmlText` types.
     #[getter]
-    pub fn keys(&mut self) -> PyObject {
+    pub fn keys(&mut self) -> PyResult<PyObject> {
         if let Some(keys) = &self.keys {
-            keys.clone()
+            Ok(keys.clone())
         } else {
             Python::with_gil(|py| {
                 let keys = self.inner().keys(self.txn());
                 let result = PyDict::new(py);
                 for (key, value) in keys.iter() {
-                    result
-                        .set_item(key.deref(), value.with_doc_into_py(self.doc.clone(), py))
-                        .unwrap();
+                    result.set_item(key.deref(), value.with_doc_into_py(self.doc.clone(), py)?)?;
                 }
                 let keys = PyObject::from(result);
                 self.keys = Some(keys.clone());
-                keys
+                Ok(keys)
             })
         }
     }
@@ -922,58 +1385,61 @@ impl YXmlTextEvent {
     /// - { delete: number }
     /// - { retain: number, attributes: any|undefined }
     #[getter]
-    pub fn delta(&mut self) -> PyObject {
+    pub fn delta(&mut self) -> PyResult<PyObject> {
         if let Some(delta) = &self.delta {
-            delta.clone()
+            Ok(delta.clone())
         } else {
             Python::with_gil(|py| {
-                let delta = self.inner().delta(self.txn()).iter().map(|d| {
-                    Python::with_gil(|py| d.clone().with_doc_into_py(self.doc.clone(), py))
-                });
+                let delta = self
+                    .inner()
+                    .delta(self.txn())
+                    .iter()
+                    .map(|d| d.clone().with_doc_into_py(self.doc.clone(), py))
+                    .collect::<PyResult<Vec<_>>>()?;
                 let result = pyo3::types::PyList::new(py, delta);
                 let delta: PyObject = result.into();
                 self.delta = Some(delta.clone());
-                delta
+                Ok(delta)
             })
         }
     }
 }
 
 // XML Type Conversions
-impl WithDocToPython for XmlNode {
-    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyObject {
-        match self {
-            XmlNode::Element(v) => v.with_doc(doc).into_py(py),
-            XmlNode::Text(v) => v.with_doc(doc).into_py(py),
-            XmlNode::Fragment(v) => v.with_doc(doc).into_py(py),
-        }
+/// Wraps a given XML `node` into a Python object of a matching type. Unlike other values read from
+/// a document, XML nodes are never passed through decoders, so this conversion can't fail.
+fn xml_node_into_py(node: XmlNode, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyObject {
+    match node {
+        XmlNode::Element(v) => v.with_doc(doc).into_py(py),
+        XmlNode::Text(v) => v.with_doc(doc).into_py(py),
+        XmlNode::Fragment(v) => v.with_doc(doc).into_py(py),
     }
 }
 
 impl WithDocToPython for &EntryChange {
-    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyObject {
+    fn with_doc_into_py(self, doc: Rc<RefCell<YDocInner>>, py: Python) -> PyResult<PyObject> {
         let result = PyDict::new(py);
         let action = "action";
         match self {
             EntryChange::Inserted(new) => {
-                let new_value = new.clone().with_doc_into_py(doc.clone(), py);
+                let new_value = new.clone().with_doc_into_py(doc.clone(), py)?;
                 result.set_item(action, "add").unwrap();
                 result.set_item("newValue", new_value).unwrap();
             }
             EntryChange::Updated(old, new) => {
-                let old_value = old.clone().with_doc_into_py(doc.clone(), py);
-                let new_value = new.clone().with_doc_into_py(doc.clone(), py);
+                let old_value = old.clone().with_doc_into_py(doc.clone(), py)?;
+                let new_value = new.clone().with_doc_into_py(doc.clone(), py)?;
                 result.set_item(action, "update").unwrap();
                 result.set_item("oldValue", old_value).unwrap();
                 result.set_item("newValue", new_value).unwrap();
             }
             EntryChange::Removed(old) => {
-                let old_value = old.clone().with_doc_into_py(doc.clone(), py);
+                let old_value = old.clone().with_doc_into_py(doc.clone(), py)?;
                 result.set_item(action, "delete").unwrap();
                 result.set_item("oldValue", old_value).unwrap();
             }
         }
-        result.into()
+        Ok(result.into())
     }
 }
 
@@ -1,5 +1,9 @@
 from y_py import YMap, YDoc
 from math import isclose
+from decimal import Decimal
+from threading import Thread
+import y_py as Y
+import pytest
 
 
 def test_int_conversion():
@@ -28,3 +32,159 @@ def test_int_conversion():
     assert type(map["edge_number"]) == float and isclose(
         float(JS_MAX_NUMBER), map["edge_number"]
     )
+
+
+def test_large_int_conversion():
+    """
+    Ensures that integers within the 64-bit signed range round-trip exactly, while integers
+    outside of it raise an `OverflowError` instead of being truncated.
+    """
+    JS_MAX_NUMBER = 2 ** 53 - 1
+    doc = YDoc()
+    map = doc.get_map("map")
+    in_range = {
+        "max": 2 ** 63 - 1,
+        "min": -(2 ** 63),
+        "negative_big_int": -JS_MAX_NUMBER - 1,
+    }
+    with doc.begin_transaction() as txn:
+        map.update(txn, in_range)
+    for key, value in in_range.items():
+        assert type(map[key]) == int and map[key] == value
+
+    for value in [2 ** 63, 2 ** 64, -(2 ** 63) - 1, -(2 ** 64)]:
+        with pytest.raises(OverflowError):
+            with doc.begin_transaction() as txn:
+                map.set(txn, "overflow", value)
+    assert "overflow" not in map
+
+
+def test_bool_conversion():
+    """
+    `bool` is a subclass of `int`, but booleans must round-trip as `bool` rather than `1`/`0`,
+    both as top-level values and nested within collections.
+    """
+    doc = YDoc()
+    map = doc.get_map("map")
+    array = doc.get_array("array")
+    with doc.begin_transaction() as txn:
+        map.update(txn, {"yes": True, "no": False, "one": 1, "nested": {"flags": [True, 0]}})
+        array.extend(txn, [True, False, 1, 0])
+
+    assert map["yes"] is True and map["no"] is False
+    assert type(map["one"]) != bool and map["one"] == 1
+    assert type(map["nested"]["flags"][0]) == bool
+    assert type(map["nested"]["flags"][1]) != bool
+    assert [type(v) for v in array] == [bool, bool, float, float]
+    assert '"yes":true' in map.to_json() and '"no":false' in map.to_json()
+
+    # booleans survive synchronization with another document
+    remote = YDoc()
+    Y.apply_update(remote, Y.encode_state_as_update(doc))
+    assert remote.get_map("map")["yes"] is True
+    assert list(remote.get_array("array"))[:2] == [True, False]
+
+
+def test_numpy_scalars():
+    """
+    Foreign numeric scalars are either converted unambiguously or rejected with an error naming
+    their type, but never silently reinterpreted.
+    """
+    np = pytest.importorskip("numpy")
+    doc = YDoc()
+    map = doc.get_map("map")
+    with doc.begin_transaction() as txn:
+        # numpy floats subclass `float`, so they're stored as regular numbers
+        map.set(txn, "float", np.float64(1.5))
+    assert type(map["float"]) == float and map["float"] == 1.5
+
+    for value in [np.int64(5), np.bool_(True)]:
+        with pytest.raises(TypeError, match="numpy"):
+            with doc.begin_transaction() as txn:
+                map.set(txn, "scalar", value)
+    assert "scalar" not in map
+
+
+def test_custom_encoders():
+    """
+    Registered encoders and decoders are thread-local, so this test registers them from a
+    separate thread to keep other tests unaffected.
+    """
+    results = {}
+
+    def decode(value):
+        if isinstance(value, dict) and "$decimal" in value:
+            return Decimal(value["$decimal"])
+        return value
+
+    def run():
+        doc = YDoc()
+        map = doc.get_map("map")
+        Y.register_encoder(Decimal, lambda d: {"$decimal": str(d)})
+        with doc.begin_transaction() as txn:
+            map.set(txn, "price", Decimal("9.99"))
+            map.set(txn, "prices", [Decimal("1.5"), 2])
+        results["encoded"] = map["price"]
+        Y.register_decoder(decode)
+        results["decoded"] = (map["price"], map["prices"])
+
+        # encoders must return values that can be stored
+        Y.register_encoder(Decimal, lambda d: d)
+        try:
+            with doc.begin_transaction() as txn:
+                map.set(txn, "invalid", Decimal("1"))
+        except TypeError:
+            results["invalid"] = "invalid" not in map
+        results["update"] = Y.encode_state_as_update(doc)
+
+    thread = Thread(target=run)
+    thread.start()
+    thread.join()
+
+    # values are stored in their encoded form
+    assert results["encoded"] == {"$decimal": "9.99"}
+    assert results["decoded"] == (Decimal("9.99"), [Decimal("1.5"), 2.0])
+    assert results["invalid"]
+
+    # registrations don't leak into other threads
+    doc = YDoc()
+    map = doc.get_map("map")
+    Y.apply_update(doc, results["update"])
+    assert map["price"] == {"$decimal": "9.99"}
+    with pytest.raises(TypeError):
+        with doc.begin_transaction() as txn:
+            map.set(txn, "other", Decimal("1"))
+
+
+def test_decoder_errors():
+    """
+    Exceptions raised by decoders are propagated to the code reading the values.
+    """
+    results = {}
+
+    def decode(value):
+        if value == "invalid":
+            raise ValueError("cannot decode")
+        return value
+
+    def run():
+        doc = YDoc()
+        map = doc.get_map("map")
+        with doc.begin_transaction() as txn:
+            map.set(txn, "valid", "ok")
+            map.set(txn, "invalid", "invalid")
+            map.set(txn, "nested", ["invalid"])
+        Y.register_decoder(decode)
+        results["valid"] = map["valid"]
+        for read in [lambda: map["invalid"], lambda: map["nested"], lambda: dict(map)]:
+            try:
+                read()
+            except ValueError as e:
+                results.setdefault("errors", []).append(str(e))
+
+    thread = Thread(target=run)
+    thread.start()
+    thread.join()
+
+    assert results["valid"] == "ok"
+    assert results["errors"] == ["cannot decode"] * 3
@@ -1,9 +1,10 @@
+import gc
 import json
 from copy import deepcopy
 
 import pytest
 from test_helper import exchange_updates
-from y_py import YArray, YArrayEvent, YDoc
+from y_py import YArray, YArrayEvent, YDoc, YMap, YText
 
 
 def test_inserts():
@@ -39,6 +40,38 @@ def test_inserts():
     assert values == [["insert"], ["extend"]]
 
 
+def test_prelim_integrated_in_place():
+    d1 = YDoc()
+    array = d1.get_array("test")
+    inserted, appended, extended = YArray([1]), YArray([2]), YText("3")
+    nested = YMap({"a": 1})
+    parent = YMap({"nested": nested})
+    with d1.begin_transaction() as txn:
+        array.insert(txn, 0, inserted)
+        array.append(txn, appended)
+        array.extend(txn, [extended, parent])
+    for value in [inserted, appended, extended, parent, nested]:
+        assert not value.prelim
+
+    # references held by the caller are the integrated shared types
+    with d1.begin_transaction() as txn:
+        inserted.append(txn, 10)
+        extended.extend(txn, "0")
+        nested.set(txn, "b", 2)
+    assert list(array[0]) == [1, 10]
+    assert str(array[2]) == "30"
+    assert dict(array[3]["nested"].items()) == {"a": 1, "b": 2}
+
+    # integrated instances can't be inserted once again
+    with d1.begin_transaction() as txn:
+        with pytest.raises(Exception, match="already integrated"):
+            array.append(txn, inserted)
+        with pytest.raises(Exception, match="already integrated"):
+            array.extend(txn, [appended])
+        with pytest.raises(Exception, match="already integrated"):
+            parent.set(txn, "copy", nested)
+    assert len(array) == 4
+
 
 def test_to_string():
     arr = YArray([7, "awesome", True, ["nested"], {"testing": "dicts"}])
@@ -46,6 +79,18 @@ def test_to_string():
     assert str(arr) == expected_str
     assert arr.__repr__() == f"YArray({expected_str})"
 
+
+def test_repr_truncation():
+    doc = YDoc()
+    arr = doc.get_array("test")
+    with doc.begin_transaction() as txn:
+        arr.extend(txn, list(range(20)))
+    for a in [arr, YArray(list(range(20)))]:
+        assert repr(a) == "YArray([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, ...])"
+        assert str(a) == str(list(range(20)))
+    assert repr(YArray(list(range(10)))) == f"YArray({list(range(10))})"
+
+
 def test_to_json():
     contents = [7, "awesome", True, ["nested"], {"testing": "dicts"}]
     doc = YDoc()
@@ -147,6 +192,10 @@ def test_get():
         assert arr[0:] == expected
         assert arr[4:1:-1] == expected[4:1:-1]
         assert arr[::-1] == expected[::-1]
+        assert arr[::-2] == expected[::-2]
+        assert arr[3::-2] == expected[3::-2]
+        assert arr[:-1:2] == expected[:-1:2]
+        assert arr[3:1] == []
 
 
 def test_iterator():
@@ -164,6 +213,12 @@ def test_iterator():
     # Test contains
     assert 2.0 in x
 
+    # Elements appended during the iteration are not visited
+    for v in x:
+        with d1.begin_transaction() as txn:
+            x.append(txn, v)
+    assert list(x) == [1.0, 2.0, 3.0, 1.0, 2.0, 3.0]
+
 
 def test_borrow_mut_edge_case():
     """
@@ -235,6 +290,37 @@ def test_observer():
     assert delta == None
 
 
+def test_observe_emit_initial():
+    d1 = YDoc()
+    x = d1.get_array("test")
+    with d1.begin_transaction() as txn:
+        x.extend(txn, [1, 2])
+
+    events = []
+    x.observe(lambda e: events.append((e.delta, e.path(), e.origin)), emit_initial=True)
+    assert events == [([{"insert": [1, 2]}], [], None)]
+
+    with d1.begin_transaction() as txn:
+        x.append(txn, 3)
+    assert events[1][0] == [{"retain": 2}, {"insert": [3]}]
+
+    empty = d1.get_array("empty")
+    empty_events = []
+    empty.observe(lambda e: empty_events.append(e.delta), emit_initial=True)
+    assert empty_events == [[]]
+
+    # like the events which follow, the initial event of a nested array has an empty path
+    container = d1.get_map("container")
+    with d1.begin_transaction() as txn:
+        container.set(txn, "nested", YArray([1]))
+    nested = container["nested"]
+    paths = []
+    nested.observe(lambda e: paths.append(e.path()), emit_initial=True)
+    with d1.begin_transaction() as txn:
+        nested.append(txn, 2)
+    assert paths == [[], []]
+
+
 def test_deep_observe():
     """
     Ensure that changes to elements inside the array trigger a callback.
@@ -307,6 +393,89 @@ def test_move_to():
     with pytest.raises(Exception):
         doc.transact(lambda t: arr.move_to(t, 0, -5))
 
+
+def test_move_to_prelim_matches_integrated():
+    """
+    Ensure that preliminary and integrated arrays move elements the same way.
+    """
+    size = 6
+    for source in range(size):
+        for target in range(size + 1):
+            doc = YDoc()
+            integrated = doc.get_array("test")
+            prelim = YArray(list(range(size)))
+            with doc.begin_transaction() as txn:
+                integrated.extend(txn, list(range(size)))
+            with doc.begin_transaction() as txn:
+                integrated.move_to(txn, source, target)
+                prelim.move_to(txn, source, target)
+            assert list(prelim) == list(integrated), (source, target)
+
+    doc = YDoc()
+    prelim = YArray([1, 2, 3])
+    with doc.begin_transaction() as txn:
+        with pytest.raises(IndexError):
+            prelim.move_to(txn, 3, 0)
+        with pytest.raises(IndexError):
+            prelim.move_to(txn, 0, 4)
+
+
+def test_reverse_and_sort():
+    words = ["pear", "Fig", "apple", "kiwi", "banana", "fig"]
+    doc = YDoc()
+    integrated = doc.get_array("test")
+    with doc.begin_transaction() as txn:
+        integrated.extend(txn, words)
+    prelim = YArray(words)
+    for array in [integrated, prelim]:
+        with doc.begin_transaction() as txn:
+            array.reverse(txn)
+        assert list(array) == words[::-1]
+        with doc.begin_transaction() as txn:
+            array.sort(txn)
+        assert list(array) == sorted(words)
+        with doc.begin_transaction() as txn:
+            array.sort(txn, key=str.lower, reverse=True)
+        assert list(array) == sorted(words, key=str.lower, reverse=True)
+        with doc.begin_transaction() as txn:
+            array.sort(txn, key=len)
+        assert list(array) == sorted(sorted(words, key=str.lower, reverse=True), key=len)
+
+    remote = YDoc()
+    exchange_updates([doc, remote])
+    assert list(remote.get_array("test")) == list(integrated)
+
+    with doc.begin_transaction() as txn:
+        integrated.append(txn, 1)
+        with pytest.raises(TypeError):
+            integrated.sort(txn)
+
+
+def test_sort_with_nan_and_failing_key():
+    values = [3.0, float("nan"), 1.0, float("nan"), 2.0]
+    doc = YDoc()
+    integrated = doc.get_array("test")
+    with doc.begin_transaction() as txn:
+        integrated.extend(txn, values)
+    prelim = YArray(values)
+    for array in [integrated, prelim]:
+        # NaN doesn't compare to anything, so the order follows whatever `sorted` does with it
+        with doc.begin_transaction() as txn:
+            array.sort(txn)
+        assert repr(list(array)) == repr(sorted(values))
+
+        def failing_key(value):
+            if value == 2.0:
+                raise ValueError("unsupported value")
+            return value
+
+        before = repr(list(array))
+        with doc.begin_transaction() as txn:
+            with pytest.raises(ValueError, match="unsupported value"):
+                array.sort(txn, key=failing_key)
+        assert repr(list(array)) == before
+
+
 @pytest.mark.skip("move_range_to has failing debug assert in yrs 0.16 with this test")
 def test_move_range_to():
     """
@@ -399,3 +568,293 @@ def test_move_range_to():
         arr.extend(t, [0,1,2,3,4,5,6,7,8,9])
     with pytest.raises(Exception):
         doc.transact(lambda t: arr.move_range_to(t, 0, -1, 3))
+
+
+def test_insert_negative_index():
+    doc = YDoc()
+    arr = doc.get_array("test")
+    prelim = YArray([1, 2, 3])
+    with doc.begin_transaction() as txn:
+        arr.extend(txn, [1, 2, 3])
+        for a in [arr, prelim]:
+            a.insert(txn, -1, "a")
+            a.insert_range(txn, -4, ["b", "c"])
+            a.insert(txn, -len(a), "d")
+    for a in [arr, prelim]:
+        assert list(a) == ["d", "b", "c", 1, 2, "a", 3]
+
+    for a in [arr, prelim]:
+        for index in [len(a) + 1, -len(a) - 1, 2**40]:
+            with pytest.raises(IndexError, match=f"index {index}"):
+                with doc.begin_transaction() as txn:
+                    a.insert(txn, index, "x")
+            with pytest.raises(IndexError, match=f"index {index}"):
+                with doc.begin_transaction() as txn:
+                    a.insert_range(txn, index, ["x"])
+
+
+def test_pop():
+    doc = YDoc()
+    arr = doc.get_array("test")
+    prelim = YArray([1, 2, 3, 4])
+    with doc.begin_transaction() as txn:
+        arr.extend(txn, [1, 2, 3, 4])
+        for a in [arr, prelim]:
+            assert a.pop(txn) == 4
+            assert a.pop(txn, 0) == 1
+            assert a.pop(txn, -2) == 2
+            assert list(a) == [3]
+            with pytest.raises(IndexError):
+                a.pop(txn, 1)
+            assert a.pop(txn) == 3
+            with pytest.raises(IndexError):
+                a.pop(txn)
+
+
+
+def test_remove():
+    doc = YDoc()
+    arr = doc.get_array("test")
+    prelim = YArray([1, "a", 2, "a", {"b": 1}])
+    with doc.begin_transaction() as txn:
+        arr.extend(txn, [1, "a", 2, "a", {"b": 1}])
+        for a in [arr, prelim]:
+            a.remove(txn, "a")
+            a.remove(txn, {"b": 1})
+            assert list(a) == [1, 2, "a"]
+            with pytest.raises(ValueError):
+                a.remove(txn, 3)
+            assert list(a) == [1, 2, "a"]
+
+def test_type_of():
+    doc = YDoc()
+    arr = doc.get_array("test")
+    with doc.begin_transaction() as txn:
+        arr.extend(txn, [1, "a", YMap({}), YArray([]), YText("")])
+    expected = ["primitive", "primitive", "map", "array", "text"]
+    assert [arr.type_of(i) for i in range(len(arr))] == expected
+    assert arr.type_of(-1) == "text"
+    with pytest.raises(IndexError):
+        arr.type_of(len(arr))
+
+
+def test_reads_in_observer():
+    doc = YDoc()
+    arr = doc.get_array("test")
+    seen = []
+
+    def callback(e):
+        seen.append((len(e.target), e.target[0], list(e.target)))
+
+    arr.observe(callback)
+    with doc.begin_transaction() as txn:
+        arr.extend(txn, [1, 2])
+    assert seen == [(2, 1, [1, 2])]
+
+
+def test_splice():
+    doc = YDoc()
+    arr = doc.get_array("test")
+    prelim = YArray([1, 2, 3, 4, 5])
+    with doc.begin_transaction() as txn:
+        arr.extend(txn, [1, 2, 3, 4, 5])
+        for a in [arr, prelim]:
+            assert a.splice(txn, 1, 2, ["a", "b", "c"]) == [2, 3]
+            assert list(a) == [1, "a", "b", "c", 4, 5]
+            assert a.splice(txn, -2, 10) == [4, 5]
+            assert a.splice(txn, 0, 0, [0]) == []
+            assert list(a) == [0, 1, "a", "b", "c"]
+            with pytest.raises(IndexError):
+                a.splice(txn, 6, 1)
+    assert arr.to_json() == '[0,1,"a","b","c"]'
+
+
+def test_removed_shared_types_are_snapshots():
+    doc = YDoc()
+    arr = doc.get_array("test")
+    with doc.begin_transaction() as txn:
+        arr.extend(
+            txn,
+            [YMap({"list": YArray([1, YText("a")])}), YText("text"), YArray([2]), 3],
+        )
+    with doc.begin_transaction() as txn:
+        assert arr.pop(txn, 0) == {"list": [1, "a"]}
+        assert arr.splice(txn, 0, 2) == ["text", [2]]
+    assert list(arr) == [3]
+
+
+def test_iterator_yields_live_types():
+    doc = YDoc()
+    arr = doc.get_array("test")
+    with doc.begin_transaction() as txn:
+        arr.extend(txn, [YMap({"id": 0}), YMap({"id": 1})])
+    for m in arr:
+        assert isinstance(m, YMap)
+        with doc.begin_transaction() as txn:
+            m.set(txn, "seen", True)
+    assert [dict(m) for m in arr] == [
+        {"id": 0, "seen": True},
+        {"id": 1, "seen": True},
+    ]
+
+
+def test_slice_yields_live_types():
+    doc = YDoc()
+    arr = doc.get_array("test")
+    with doc.begin_transaction() as txn:
+        arr.extend(txn, [1, YMap({}), YArray([]), YText("")])
+    _, ymap, yarray, ytext = arr[0:4]
+    reversed_text = arr[::-1][0]
+    with doc.begin_transaction() as txn:
+        ymap.set(txn, "key", "value")
+        yarray.append(txn, 1)
+        ytext.extend(txn, "abc")
+    assert arr[1]["key"] == "value"
+    assert list(arr[2]) == [1]
+    assert str(arr[3]) == "abc"
+    assert str(reversed_text) == "abc"
+
+
+def test_insert_relative():
+    d1 = YDoc()
+    x = d1.get_array("test")
+    with d1.begin_transaction() as txn:
+        x.extend(txn, ["a", "c", "e"])
+        x.insert_before(txn, "c", "b")
+        x.insert_after(txn, "c", "d")
+        x.insert_after(txn, "e", "f")
+        x.insert_before(txn, "a", 0)
+    assert list(x) == [0, "a", "b", "c", "d", "e", "f"]
+
+    with pytest.raises(ValueError):
+        with d1.begin_transaction() as txn:
+            x.insert_before(txn, "missing", "g")
+    assert len(x) == 7
+
+    prelim = YArray([1, 2])
+    with d1.begin_transaction() as txn:
+        prelim.insert_after(txn, 1, 1.5)
+    assert list(prelim) == [1, 1.5, 2]
+
+
+def test_first_last():
+    d1 = YDoc()
+    x = d1.get_array("test")
+    assert x.first() is None
+    assert x.last("empty") == "empty"
+    with d1.begin_transaction() as txn:
+        x.extend(txn, [1, 2, 3])
+    assert (x.first(), x.last()) == (1, 3)
+    assert (YArray(["a", "b"]).first(), YArray(["a", "b"]).last()) == ("a", "b")
+    assert YArray().first(default=0) == 0
+
+    with d1.begin_transaction() as txn:
+        x.append(txn, YMap({"a": 1}))
+    assert isinstance(x.last(), YMap)
+    assert x.last(materialize=True) == {"a": 1}
+    assert x.first(materialize=True) == 1
+
+
+def test_extend_from_integrated():
+    d1 = YDoc()
+    source = d1.get_array("source")
+    text = d1.get_text("text")
+    with d1.begin_transaction() as txn:
+        source.extend(txn, [1, YMap({"a": 1}), YArray([2, 3])])
+        text.extend(txn, "hi")
+    target = d1.get_array("target")
+    with d1.begin_transaction() as txn:
+        target.extend(txn, source)
+        target.extend(txn, text)
+    assert target.to_json() == [1, {"a": 1}, [2, 3], "h", "i"]
+
+    # nested shared types are detached copies
+    with d1.begin_transaction() as txn:
+        target[1].set(txn, "a", 2)
+    assert source[1]["a"] == 1
+
+    # sources from other documents and the array itself
+    d2 = YDoc()
+    other = d2.get_array("other")
+    with d2.begin_transaction() as txn:
+        other.insert_range(txn, 0, source)
+    assert other.to_json() == [1, {"a": 1}, [2, 3]]
+    with d2.begin_transaction() as txn:
+        other.extend(txn, other)
+    assert other.to_json() == [1, {"a": 1}, [2, 3], 1, {"a": 1}, [2, 3]]
+
+
+def test_length():
+    doc = YDoc()
+    array = doc.get_array("array")
+    text = doc.get_text("text")
+    with doc.begin_transaction() as txn:
+        array.extend(txn, [1, 2, 3])
+        text.extend(txn, "héllo")
+        assert array.length(txn) == len(array) == 3
+        assert text.length(txn) == len(text) == 6
+    assert (array.length(), text.length()) == (3, 6)
+    assert (YArray([1]).length(), YText("ab").length()) == (1, 2)
+
+
+def test_observe_range():
+    doc = YDoc()
+    array = doc.get_array("array")
+    with doc.begin_transaction() as txn:
+        array.extend(txn, list(range(10)))
+
+    deltas = []
+    sub = array.observe_range(4, 6, lambda e: deltas.append(e.delta))
+
+    # changes outside of the range are skipped, but shift it
+    with doc.begin_transaction() as txn:
+        array.insert(txn, 0, "a")
+        array.delete(txn, 10)
+    assert deltas == []
+
+    # the range now spans [5, 7): elements 4 and 5
+    with doc.begin_transaction() as txn:
+        array.insert(txn, 6, "b")
+    assert deltas == [[{"retain": 6}, {"insert": ["b"]}]]
+
+    # the range has been extended to [5, 8), of which only the first element is deleted
+    deltas.clear()
+    with doc.begin_transaction() as txn:
+        array.delete_range(txn, 3, 3)
+    assert deltas == [[{"retain": 3}, {"delete": 1}]]
+
+    # the range has shrunk to [3, 5), so inserting at its end isn't reported
+    deltas.clear()
+    with doc.begin_transaction() as txn:
+        array.insert(txn, 5, "after")
+    assert deltas == []
+
+    array.unobserve(sub)
+    with pytest.raises(ValueError):
+        array.observe_range(2, 1, lambda e: None)
+
+
+def test_observe_range_weak():
+    doc = YDoc()
+    array = doc.get_array("array")
+    with doc.begin_transaction() as txn:
+        array.extend(txn, list(range(4)))
+
+    deltas = []
+
+    class Listener:
+        def on_change(self, e):
+            deltas.append(e.delta)
+
+    listener = Listener()
+    array.observe_range(1, 3, listener.on_change, weak=True)
+    with doc.begin_transaction() as txn:
+        array.insert(txn, 1, "a")
+    assert deltas == [[{"retain": 1}, {"insert": ["a"]}]]
+
+    deltas.clear()
+    del listener
+    gc.collect()
+    with doc.begin_transaction() as txn:
+        array.insert(txn, 1, "b")
+    assert deltas == []
@@ -2,6 +2,8 @@ from y_py import YDoc, AfterTransactionEvent
 
 import y_py as Y
 import pytest
+import base64
+import json
 
 
 def test_constructor_options():
@@ -14,6 +16,12 @@ def test_constructor_options():
     YDoc(client_id=3)
     YDoc(4, skip_gc=True)
 
+    # Subdocument loading flags
+    default = YDoc()
+    assert not default.auto_load and default.should_load
+    flagged = YDoc(auto_load=True, should_load=False)
+    assert flagged.auto_load and not flagged.should_load
+
     # Handle encoding string variation
     YDoc(offset_kind="utf8")
     YDoc(offset_kind="utf-8")
@@ -126,3 +134,729 @@ def test_get_update():
         m.set(txn, "hi", "there")
 
     assert type(update) == bytes
+
+
+def test_snapshots():
+    """
+    Ensures that past document states can be restored from snapshots.
+    """
+    doc = YDoc(skip_gc=True)
+    text = doc.get_text("test")
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "hello")
+    snapshot = doc.snapshot()
+    assert type(snapshot) == bytes
+    with doc.begin_transaction() as txn:
+        text.extend(txn, " world")
+        text.delete_range(txn, 0, 1)
+
+    assert str(text) == "ello world"
+    assert text.to_string_at(snapshot) == "hello"
+
+    past = YDoc()
+    Y.apply_update(past, doc.encode_state_from_snapshot(snapshot))
+    assert str(past.get_text("test")) == "hello"
+
+    # snapshots can be taken and encoded from within observer callbacks
+    captured = []
+
+    def callback(event):
+        captured.append(doc.snapshot())
+        captured.append(doc.encode_state_from_snapshot(snapshot))
+        with pytest.raises(Y.TransactionError):
+            text.to_string_at(snapshot)
+
+    text.observe(callback)
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "!")
+    latest, past_state = captured
+    assert text.to_string_at(latest) == "ello world!"
+    assert past_state == doc.encode_state_from_snapshot(snapshot)
+
+    # Snapshots are not supported for garbage collected documents
+    gc_doc = YDoc()
+    with pytest.raises(ValueError):
+        gc_doc.snapshot()
+
+
+def test_encoded_payloads_are_bytes():
+    """
+    Ensures that all encoding functions return immutable (hashable) `bytes`.
+    """
+    doc = YDoc()
+    text = doc.get_text("test")
+    updates = []
+    doc.observe_after_transaction(lambda e: updates.append(e))
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "hello")
+        payloads = [txn.state_vector_v1(), txn.diff_v1()]
+
+    event = updates[0]
+    payloads += [
+        Y.encode_state_vector(doc),
+        Y.encode_state_as_update(doc),
+        event.before_state,
+        event.after_state,
+        event.delete_set,
+        event.get_update(),
+    ]
+    for payload in payloads:
+        assert type(payload) == bytes
+        assert {payload: True}[payload]
+
+
+def test_diff_since():
+    local_doc = YDoc()
+    remote_doc = YDoc()
+    local_text = local_doc.get_text("test")
+    remote_text = remote_doc.get_text("test")
+    with local_doc.begin_transaction() as txn:
+        local_text.extend(txn, "hello")
+    with remote_doc.begin_transaction() as txn:
+        remote_text.extend(txn, "world")
+
+    with remote_doc.begin_transaction() as txn:
+        delta = txn.diff_since(local_doc)
+        assert delta == txn.diff_v1(Y.encode_state_vector(local_doc))
+    with local_doc.begin_transaction() as txn:
+        txn.apply_v1(delta)
+    with local_doc.begin_transaction() as txn:
+        delta = txn.diff_since(remote_doc)
+    Y.apply_update(remote_doc, delta)
+    assert str(local_text) == str(remote_text)
+
+    # Nothing is left to sync once both documents converge.
+    with local_doc.begin_transaction() as txn:
+        assert txn.diff_since(remote_doc) == txn.diff_since(local_doc)
+
+
+def test_diff_docs():
+    old_doc = YDoc()
+    with old_doc.begin_transaction() as txn:
+        old_doc.get_text("test").extend(txn, "hello")
+    new_doc = YDoc()
+    Y.apply_update(new_doc, Y.encode_state_as_update(old_doc))
+    new_text = new_doc.get_text("test")
+    with new_doc.begin_transaction() as txn:
+        new_text.extend(txn, " world")
+
+    diff = Y.diff_docs(old_doc, new_doc)
+    assert diff == Y.encode_state_as_update(new_doc, Y.encode_state_vector(old_doc))
+    with pytest.raises(ValueError):
+        Y.diff_docs(new_doc, old_doc)
+    Y.apply_update(old_doc, diff)
+    assert str(old_doc.get_text("test")) == "hello world"
+
+
+def test_base64_updates():
+    source = YDoc()
+    with source.begin_transaction() as txn:
+        source.get_text("test").extend(txn, "hello")
+    update = Y.encode_state_as_update_b64(source)
+    assert isinstance(update, str)
+    assert base64.b64decode(update) == Y.encode_state_as_update(source)
+
+    target = YDoc()
+    assert Y.apply_update_b64(target, update)
+    assert str(target.get_text("test")) == "hello"
+    with pytest.raises(ValueError, match="base64"):
+        Y.apply_update_b64(target, "not base64!")
+
+
+def test_apply_update_with_origin():
+    source = YDoc()
+    with source.begin_transaction() as txn:
+        source.get_text("test").extend(txn, "hello")
+    update = Y.encode_state_as_update(source)
+
+    target = YDoc()
+    text = target.get_text("test")
+    origins = []
+    text.observe(lambda e: origins.append(e.origin))
+    assert Y.apply_update(target, update, origin="peer-1")
+    with target.begin_transaction() as txn:
+        text.extend(txn, "!")
+    assert origins == ["peer-1", None]
+
+    with target.begin_transaction() as txn:
+        with pytest.raises(ValueError):
+            Y.apply_update(target, update, txn, origin="peer-1")
+        with pytest.raises(Y.TransactionError):
+            Y.apply_update(target, update, origin="peer-1")
+
+
+def test_walk():
+    doc = YDoc()
+    with doc.begin_transaction() as txn:
+        doc.get_text("text").extend(txn, "hello")
+        doc.get_map("map").update(
+            txn, {"b": Y.YArray([1, Y.YText("nested")]), "a": {"y": [True], "x": None}}
+        )
+    assert doc.walk() == [
+        (["map", "a", "x"], None),
+        (["map", "a", "y", 0], True),
+        (["map", "b", 0], 1),
+        (["map", "b", 1], "nested"),
+        (["text"], "hello"),
+    ]
+    assert YDoc().walk() == []
+
+    remote = YDoc()
+    with remote.begin_transaction() as txn:
+        remote.get_array("untyped").append(txn, 1)
+    Y.apply_update(doc, Y.encode_state_as_update(remote))
+    assert [path for path, _ in doc.walk()][-1] == ["text"]
+
+
+def test_has_pending_transaction():
+    doc = YDoc()
+    assert not doc.has_pending_transaction
+    with doc.begin_transaction() as txn:
+        assert doc.has_pending_transaction
+    assert not doc.has_pending_transaction
+
+
+def test_module_functions_with_transaction():
+    """
+    Ensures that module-level encoding functions can operate within an explicit transaction.
+    """
+    local = YDoc()
+    remote = YDoc()
+    text = remote.get_text("test")
+    local_text = local.get_text("test")
+    with remote.begin_transaction() as remote_txn:
+        text.extend(remote_txn, "hello")
+        with local.begin_transaction() as local_txn:
+            local_sv = Y.encode_state_vector(local, local_txn)
+            update = Y.encode_state_as_update(remote, local_sv, remote_txn)
+            Y.apply_update(local, update, local_txn)
+            assert str(local_text) == "hello"
+
+    # Committed transactions can't be reused
+    with pytest.raises(Y.TransactionError):
+        Y.encode_state_vector(local, local_txn)
+
+
+def test_observe_destroy():
+    doc = Y.YDoc(client_id=7)
+    destroyed = []
+    doc.observe_destroy(lambda d: destroyed.append(d.client_id))
+
+    with doc.begin_transaction() as txn:
+        with pytest.raises(Y.TransactionError):
+            doc.destroy()
+    assert destroyed == []
+
+    doc.destroy()
+    assert destroyed == [7]
+
+
+def test_gc():
+    docs = [YDoc(client_id=1), YDoc(client_id=1, skip_gc=True)]
+    for doc in docs:
+        text = doc.get_text("test")
+        with doc.begin_transaction() as txn:
+            text.extend(txn, "hello world")
+            text.delete_range(txn, 0, 6)
+        assert str(text) == "world"
+    gc_doc, kept_doc = docs
+    snapshot = kept_doc.snapshot()
+
+    # Deleted content is only retained by the document which skips garbage collection
+    gc_update = Y.encode_state_as_update(gc_doc)
+    assert len(gc_update) < len(Y.encode_state_as_update(kept_doc))
+    collected = kept_doc.encode_state_gc()
+    assert len(collected) < len(Y.encode_state_as_update(kept_doc))
+
+    # the collected state keeps the history, so later changes can still be merged into it
+    restored = YDoc()
+    Y.apply_update(restored, collected)
+    with kept_doc.begin_transaction() as txn:
+        kept_doc.get_text("test").extend(txn, "!")
+    Y.apply_update(
+        restored,
+        Y.encode_state_as_update(kept_doc, Y.encode_state_vector(restored)),
+    )
+    assert str(restored.get_text("test")) == "world!"
+
+    # the document itself still retains its deleted content
+    assert kept_doc.get_text("test").to_string_at(snapshot) == "world"
+
+
+def test_reads_in_after_transaction():
+    doc = YDoc()
+    text = doc.get_text("test")
+    seen = []
+    doc.observe_after_transaction(lambda _: seen.append(str(text)))
+
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "abc")
+    doc.transact(lambda txn: text.extend(txn, "def"))
+    assert seen == ["abc", "abcdef"]
+
+
+def test_to_json():
+    doc = YDoc()
+    assert json.loads(doc.to_json()) == {}
+
+    text = doc.get_text("text")
+    array = doc.get_array("array")
+    map = doc.get_map("map")
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "hello")
+        array.extend(txn, [1, "two", Y.YMap({"nested": True})])
+        map.set(txn, "key", Y.YArray([1, 2]))
+    assert json.loads(doc.to_json()) == {
+        "text": "hello",
+        "array": [1, "two", {"nested": True}],
+        "map": {"key": [1, 2]},
+    }
+
+    xml = doc.get_xml_element("xml")
+    with doc.begin_transaction() as txn:
+        xml.set_attribute(txn, "id", "root")
+        xml.push_xml_text(txn).push(txn, "content")
+        assert json.loads(doc.to_json(include_xml=True))["xml"] == {
+            "tag": "xml",
+            "attributes": {"id": "root"},
+            "children": ["content"],
+        }
+
+
+def test_to_json_undefined_roots():
+    remote = YDoc()
+    with remote.begin_transaction() as txn:
+        remote.get_array("untyped").append(txn, 1)
+    doc = YDoc()
+    Y.apply_update(doc, Y.encode_state_as_update(remote))
+    # roots which haven't been fetched yet have no kind to serialize them with
+    assert json.loads(doc.to_json()) == {}
+    with doc.begin_transaction() as txn:
+        doc.get_text("typed").extend(txn, "hello")
+    assert json.loads(doc.to_json()) == {"typed": "hello"}
+    doc.get_array("untyped")
+    assert json.loads(doc.to_json()) == {"typed": "hello", "untyped": [1]}
+
+
+def test_pending_update():
+    remote = YDoc(client_id=1)
+    text = remote.get_text("text")
+    updates = []
+    for chunk in ["hello", " world"]:
+        before = Y.encode_state_vector(remote)
+        with remote.begin_transaction() as txn:
+            text.extend(txn, chunk)
+        updates.append(Y.encode_state_as_update(remote, before))
+
+    doc = YDoc()
+    local_text = doc.get_text("text")
+    assert doc.pending_update() is None
+    assert doc.missing_update_ranges() == {}
+
+    # the second update depends on the first one, so it can't be integrated yet
+    assert not Y.apply_update(doc, updates[1])
+    assert str(local_text) == ""
+    assert doc.has_pending()
+    assert doc.pending_update() is not None
+    assert doc.missing_update_ranges() == {1: (0, 11)}
+
+    assert Y.apply_update(doc, updates[0])
+    assert str(local_text) == "hello world"
+    assert not doc.has_pending()
+    assert doc.pending_update() is None
+    assert doc.missing_update_ranges() == {}
+
+
+def test_apply_update_collecting_events():
+    remote = YDoc()
+    remote_text = remote.get_text("text")
+    remote_map = remote.get_map("map")
+    with remote.begin_transaction() as txn:
+        remote_map.set(txn, "nested", Y.YMap({}))
+
+    doc = YDoc()
+    text = doc.get_text("text")
+    map = doc.get_map("map")
+    [event] = doc.apply_update_collecting_events(Y.encode_state_as_update(remote))
+    assert event["root"] == "map" and event["path"] == []
+    assert event["keys"]["nested"]["action"] == "add"
+    assert event["keys"]["nested"]["newValue"].to_json() == "{}"
+
+    before = Y.encode_state_vector(remote)
+    with remote.begin_transaction() as txn:
+        remote_text.extend(txn, "hello")
+        remote_map["nested"].set(txn, "key", "value")
+    events = doc.apply_update_collecting_events(Y.encode_state_as_update(remote, before))
+    events.sort(key=lambda event: event["root"])
+    assert events == [
+        {"root": "map", "path": ["nested"], "keys": {"key": {"action": "add", "newValue": "value"}}},
+        {"root": "text", "path": [], "delta": [{"insert": "hello"}]},
+    ]
+    assert str(text) == "hello"
+
+
+def test_compact():
+    doc = YDoc()
+    text = doc.get_text("text")
+    map = doc.get_map("map")
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "hello world")
+        text.format(txn, 0, 5, {"bold": True})
+        map.set(txn, "nested", Y.YMap({"list": Y.YArray([1, "two"])}))
+        map.set(txn, "removed", "x" * 1000)
+    with doc.begin_transaction() as txn:
+        text.delete_range(txn, 5, 6)
+        map.pop(txn, "removed")
+
+    update = doc.compact()
+    assert len(update) < len(Y.encode_state_as_update(doc))
+
+    copy = YDoc()
+    copy_text = copy.get_text("text")
+    copy.get_map("map")
+    deltas = []
+    copy_text.observe(lambda event: deltas.append(event.delta))
+    Y.apply_update(copy, update)
+    assert str(copy_text) == "hello"
+    assert deltas == [[{"insert": "hello", "attributes": {"bold": True}}]]
+    assert copy.to_json(sort_keys=True) == doc.to_json(sort_keys=True)
+    assert Y.encode_state_vector(copy) != Y.encode_state_vector(doc)
+
+    remote = YDoc()
+    Y.apply_update(remote, Y.encode_state_as_update(doc))
+    with pytest.raises(ValueError):
+        remote.compact()
+    remote.get_text("text")
+    remote.get_map("map")
+    assert remote.compact()
+
+
+def test_compact_xml_element_root():
+    doc = YDoc()
+    xml = doc.get_xml_element("xml")
+    with doc.begin_transaction() as txn:
+        xml.set_attribute(txn, "id", "root")
+        child = xml.insert_xml_element(txn, 0, "p")
+        child.set_attribute(txn, "class", "intro")
+        child.push_xml_text(txn).push(txn, "hello")
+
+    copy = YDoc()
+    copy_xml = copy.get_xml_element("xml")
+    Y.apply_update(copy, doc.compact())
+    assert copy_xml.get_attribute("id") == "root"
+    assert str(copy_xml) == str(xml)
+
+
+def test_docs_equal():
+    a = YDoc()
+    b = YDoc()
+    with a.begin_transaction() as txn:
+        a.get_map("map").set(txn, "key", "value")
+    with b.begin_transaction() as txn:
+        b.get_text("text").extend(txn, "hello")
+    assert not Y.docs_equal(a, b)
+
+    a.get_text("text")
+    b.get_map("map")
+    Y.apply_update(a, Y.encode_state_as_update(b))
+    Y.apply_update(b, Y.encode_state_as_update(a))
+    assert Y.docs_equal(a, b)
+
+    # same visible contents written by a different client
+    c = YDoc()
+    c.get_text("text")
+    with c.begin_transaction() as txn:
+        c.get_map("map").set(txn, "key", "value")
+        c.get_text("text").extend(txn, "hello")
+    assert c.to_json(sort_keys=True) == a.to_json(sort_keys=True)
+    assert not Y.docs_equal(a, c)
+
+
+def test_update_buffer():
+    doc = YDoc()
+    text = doc.get_text("text")
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "hello" * 1000)
+
+    expected = Y.encode_state_as_update(doc)
+    buffer = Y.encode_state_as_update(doc, copy=False)
+    assert isinstance(buffer, Y.UpdateBuffer)
+    assert len(buffer) == len(expected)
+    assert bytes(buffer) == expected
+    assert buffer[0] == expected[0] and buffer[-1] == expected[-1]
+    view = memoryview(buffer)
+    assert view.readonly and view.tobytes() == expected
+    with pytest.raises(IndexError):
+        buffer[len(buffer)]
+    with doc.begin_transaction() as txn:
+        assert bytes(txn.diff_v1(copy=False)) == expected
+
+    remote = YDoc()
+    remote_text = remote.get_text("text")
+    Y.apply_update(remote, buffer)
+    assert str(remote_text) == str(text)
+
+
+def test_clients():
+    doc = Y.YDoc(client_id=1)
+    assert doc.clients() == set()
+    text = doc.get_text("text")
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "hello")
+    assert doc.clients() == {1}
+
+    remote = Y.YDoc(client_id=2)
+    remote_text = remote.get_text("text")
+    with remote.begin_transaction() as txn:
+        remote_text.extend(txn, " world")
+        remote_text.delete_range(txn, 0, 6)
+    Y.apply_update(doc, Y.encode_state_as_update(remote))
+    assert doc.clients() == {1, 2}
+
+
+def test_get_and_set_path():
+    doc = Y.YDoc()
+    settings = doc.get_map("settings")
+    with doc.begin_transaction() as txn:
+        settings.set(txn, "tags", Y.YArray(["a", {"b": 1}]))
+        doc.set_path(txn, ["settings", "editor", "theme"], "dark")
+        doc.set_path(txn, ["settings", "tags", 0], "c")
+        doc.set_path(txn, ["settings", "tags", 2], "d")
+        doc.set_path(txn, ["settings", "tags", -1], "e")
+    assert settings.to_json(sort_keys=True) == (
+        '{"editor":{"theme":"dark"},"tags":["c",{"b":1},"e"]}'
+    )
+
+    assert doc.get_path(["settings", "editor", "theme"]) == "dark"
+    assert doc.get_path(["settings", "tags", 1, "b"]) == 1
+    assert doc.get_path(["settings", "tags", -1]) == "e"
+    assert isinstance(doc.get_path(["settings", "editor"]), Y.YMap)
+    assert isinstance(doc.get_path(["settings"]), Y.YMap)
+    assert doc.get_path(["settings", "font", "size"]) is None
+    assert doc.get_path(["settings", "tags", 5], "missing") == "missing"
+    assert doc.get_path(["missing", "key"], 0) == 0
+    with pytest.raises(TypeError):
+        doc.get_path(["settings", "tags", "first"])
+    with pytest.raises(TypeError):
+        doc.get_path(["settings", "editor", "theme", "color"])
+
+    with doc.begin_transaction() as txn:
+        with pytest.raises(KeyError):
+            doc.set_path(txn, ["missing", "key"], 1)
+        with pytest.raises(IndexError):
+            doc.set_path(txn, ["settings", "tags", 10], 1)
+        with pytest.raises(KeyError):
+            doc.set_path(txn, ["settings", "list", 0], 1)
+        with pytest.raises(ValueError):
+            doc.set_path(txn, ["settings"], 1)
+        nested = doc.set_path(txn, ["settings", "editor", "font"], Y.YMap({"size": 12}))
+        nested.set(txn, "family", "mono")
+    assert doc.get_path(["settings", "editor", "font", "family"]) == "mono"
+
+    remote = Y.YDoc()
+    with remote.begin_transaction() as txn:
+        remote.get_map("untyped").set(txn, "key", "value")
+    Y.apply_update(doc, Y.encode_state_as_update(remote))
+    assert doc.get_path(["untyped"], "undefined") == "undefined"
+    assert doc.get_path(["untyped", "key"]) == "value"
+
+
+def test_integrate():
+    doc = Y.YDoc()
+    template = Y.YMap({"title": "draft", "tags": Y.YArray(["a"])})
+    integrated = doc.integrate("config", template)
+    assert integrated is template
+    assert not template.prelim
+    assert not template["tags"].prelim
+    assert doc.get_map("config").to_json(sort_keys=True) == '{"tags":["a"],"title":"draft"}'
+
+    text = doc.integrate("text", Y.YText("hello"))
+    with doc.begin_transaction() as txn:
+        text.extend(txn, " world")
+    assert str(doc.get_text("text")) == "hello world"
+
+    remote = Y.YDoc()
+    Y.apply_update(remote, Y.encode_state_as_update(doc))
+    assert remote.get_map("config")["title"] == "draft"
+
+    with pytest.raises(ValueError):
+        doc.integrate("text", Y.YText("again"))
+    # remote "text" root hasn't been fetched yet, but it exists nonetheless
+    with pytest.raises(ValueError):
+        remote.integrate("text", Y.YText())
+    with pytest.raises(TypeError):
+        doc.integrate("other", [1, 2])
+    with pytest.raises(Exception, match="already integrated"):
+        doc.integrate("other", text)
+    with doc.begin_transaction():
+        with pytest.raises(Y.TransactionError):
+            doc.integrate("other", Y.YArray())
+
+
+def test_update_info():
+    assert Y.update_info(Y.encode_state_as_update(Y.YDoc())) == {
+        "byte_size": 2,
+        "struct_count": 0,
+        "client_count": 0,
+    }
+
+    doc = Y.YDoc(client_id=1)
+    remote = Y.YDoc(client_id=2)
+    text = doc.get_text("text")
+    nested = doc.get_map("map")
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "hello")
+        nested.set(txn, "nested", Y.YArray([1, 2]))
+    Y.apply_update(remote, Y.encode_state_as_update(doc))
+    with remote.begin_transaction() as txn:
+        remote.get_text("text").extend(txn, " world")
+    update = Y.encode_state_as_update(remote)
+    info = Y.update_info(update)
+    # "hello", the nested array with its contents and " world"
+    assert info == {"byte_size": len(update), "struct_count": 4, "client_count": 2}
+
+    # malformed updates raise an ordinary exception rather than a Rust panic
+    for malformed in [b"\x01\x05", update[:-1], update[: len(update) // 2]]:
+        with pytest.raises(Exception):
+            Y.update_info(malformed)
+
+
+def test_root_type():
+    doc = Y.YDoc()
+    assert doc.root_type("text") is None
+    doc.get_text("text")
+    doc.get_xml_fragment("xml")
+    assert doc.root_type("text") == "text"
+    assert doc.root_type("xml") == "xml_fragment"
+
+    doc.get_text("text", strict=True)
+    with pytest.raises(TypeError):
+        doc.get_map("text", strict=True)
+    doc.get_map("text")
+    assert doc.root_type("text") == "text"
+    assert doc.root_type("missing") is None
+    with pytest.raises(TypeError):
+        doc.get_array("xml", strict=True)
+
+    # kinds of root types aren't carried by updates
+    with doc.begin_transaction() as txn:
+        doc.get_text("text").extend(txn, "hello")
+    remote = Y.YDoc()
+    Y.apply_update(remote, Y.encode_state_as_update(doc))
+    assert remote.root_type("text") == "undefined"
+    remote.get_text("text", strict=True)
+    assert remote.root_type("text") == "text"
+
+
+def test_validate_update():
+    doc = Y.YDoc()
+    with doc.begin_transaction() as txn:
+        doc.get_text("text").extend(txn, "hello")
+        doc.get_map("map").set(txn, "key", [1, 2])
+    update = Y.encode_state_as_update(doc)
+    assert Y.validate_update(update)
+    assert Y.validate_update(Y.encode_state_as_update(Y.YDoc()))
+
+    assert not Y.validate_update(b"")
+    assert not Y.validate_update(update[:-1])
+    assert not Y.validate_update(update[: len(update) // 2])
+    assert not Y.validate_update(b"\xff\xff\xff")
+    # counts claimed by a malformed update don't make it allocate memory up front
+    assert not Y.validate_update(b"\xff\xff\xff\xff\x0f")
+
+
+def test_observe_after_transaction_origin_and_skip_empty():
+    doc = Y.YDoc()
+    text = doc.get_text("text")
+    all_events = []
+    changes = []
+    doc.observe_after_transaction(all_events.append)
+    doc.observe_after_transaction(changes.append, skip_empty=True)
+
+    # reading starts a transaction which doesn't change anything
+    str(text)
+    with doc.begin_transaction():
+        pass
+    doc.transact(lambda txn: text.extend(txn, "hello"), origin="local")
+    with doc.begin_transaction() as txn:
+        text.delete_range(txn, 0, 1)
+
+    assert len(all_events) == 4
+    assert [e.origin for e in changes] == ["local", None]
+    assert changes[0].get_update() == all_events[2].get_update()
+
+
+def test_encode_roots_as_update():
+    doc = YDoc()
+    settings = doc.get_map("settings")
+    body = doc.get_text("body")
+    with doc.begin_transaction() as txn:
+        settings.set(txn, "theme", "dark")
+        settings.set(txn, "nested", Y.YMap({"list": Y.YArray([1, 2])}))
+        body.extend(txn, "a long text")
+        settings.get("nested").get("list").append(txn, 3)
+
+    remote = YDoc()
+    Y.apply_update(remote, doc.encode_roots_as_update(["settings"]))
+    assert remote.get_map("settings").to_json() == {
+        "theme": "dark",
+        "nested": {"list": [1, 2, 3]},
+    }
+    assert str(remote.get_text("body")) == ""
+    assert remote.pending_update() is None
+
+    # incremental updates only carry changes made since the given state
+    vector = Y.encode_state_vector(remote)
+    with doc.begin_transaction() as txn:
+        body.extend(txn, "!")
+        settings.pop(txn, "theme")
+    update = doc.encode_roots_as_update(["settings"], vector)
+    Y.apply_update(remote, update)
+    assert remote.get_map("settings").to_json() == {"nested": {"list": [1, 2, 3]}}
+    assert str(remote.get_text("body")) == ""
+
+
+def test_encode_roots_as_update_after_gc():
+    doc = YDoc(skip_gc=False)
+    settings = doc.get_map("settings")
+    items = doc.get_array("items")
+    body = doc.get_text("body")
+    with doc.begin_transaction() as txn:
+        settings.set(txn, "nested", Y.YMap({"list": Y.YArray([1, 2])}))
+        items.extend(txn, [1, Y.YMap({"key": "value"}), 2, 3])
+        body.extend(txn, "hello world")
+    # deleted content, along with everything nested in it, gets garbage collected
+    with doc.begin_transaction() as txn:
+        settings.pop(txn, "nested")
+        items.delete_range(txn, 1, 2)
+        body.delete_range(txn, 0, 6)
+    with doc.begin_transaction() as txn:
+        settings.set(txn, "theme", "dark")
+        items.insert(txn, 1, 4)
+        body.insert(txn, 0, "new ")
+
+    remote = YDoc()
+    Y.apply_update(remote, doc.encode_roots_as_update(["settings", "items"]))
+    assert remote.get_map("settings").to_json() == {"theme": "dark"}
+    assert remote.get_array("items").to_json() == [1, 4, 3]
+    assert str(remote.get_text("body")) == ""
+    assert remote.pending_update() is None
+
+
+def test_from_update():
+    doc = YDoc()
+    with doc.begin_transaction() as txn:
+        doc.get_text("text").extend(txn, "hello")
+        doc.get_map("map").set(txn, "key", "value")
+    update = Y.encode_state_as_update(doc)
+
+    loaded = YDoc.from_update(update, client_id=7)
+    assert loaded.client_id == 7
+    assert str(loaded.get_text("text")) == "hello"
+    assert dict(loaded.get_map("map")) == {"key": "value"}
+    assert not loaded.has_pending_transaction
+    with loaded.begin_transaction() as txn:
+        loaded.get_text("text").extend(txn, "!")
+
+    with pytest.raises(Exception):
+        YDoc.from_update(b"\xff\xff")
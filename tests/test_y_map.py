@@ -1,8 +1,9 @@
-from copy import deepcopy
+from copy import copy, deepcopy
+import gc
 import json
 import pytest
 import y_py as Y
-from y_py import YMap, YMapEvent
+from y_py import YArray, YMap, YMapEvent, YText
 
 
 def test_get():
@@ -27,6 +28,25 @@ def test_get():
         m["doesn't exist"]
 
 
+def test_none_values():
+    d = Y.YDoc()
+    m = d.get_map("map")
+    with d.begin_transaction() as txn:
+        m.set(txn, "unset", None)
+
+    remote = Y.YDoc()
+    remote_map = remote.get_map("map")
+    Y.apply_update(remote, Y.encode_state_as_update(d))
+    for test in [m, remote_map, YMap({"unset": None})]:
+        assert "unset" in test
+        assert "missing" not in test
+        assert test["unset"] is None
+        assert test.get("unset", "default") is None
+        assert test.get("missing", "default") == "default"
+        with pytest.raises(KeyError):
+            test["missing"]
+
+
 def test_set():
     d1 = Y.YDoc()
     x = d1.get_map("test")
@@ -85,6 +105,40 @@ def test_update():
             ymap.update(txn, [1])
 
 
+
+def test_update_from_shared_types():
+    doc = Y.YDoc()
+    source = doc.get_map("source")
+    pairs = doc.get_array("pairs")
+    target = doc.get_map("target")
+    with doc.begin_transaction() as txn:
+        source.update(txn, {"a": 1, "nested": Y.YMap({"b": Y.YText("text")})})
+        pairs.extend(txn, [["c", 2]])
+    with doc.begin_transaction() as txn:
+        target.update(txn, source)
+        target.update(txn, pairs)
+        target.update(txn, Y.YMap({"d": Y.YArray([3])}))
+
+    # integrated nested types are copied, not moved out of the source map
+    nested_copy = target["nested"]
+    assert isinstance(nested_copy, Y.YMap)
+    assert not nested_copy.prelim
+    assert str(nested_copy["b"]) == "text"
+    assert str(source["nested"]["b"]) == "text"
+    with doc.begin_transaction() as txn:
+        source["nested"]["b"].extend(txn, "!")
+    assert str(nested_copy["b"]) == "text"
+    assert target["a"] == 1
+    assert target["c"] == 2
+    assert list(target["d"]) == [3]
+
+    # copying between documents
+    other = Y.YDoc()
+    other_map = other.get_map("copy")
+    with other.begin_transaction() as txn:
+        other_map.update(txn, source)
+    assert str(other_map["nested"]["b"]) == "text!"
+
 def test_set_nested():
     d1 = Y.YDoc()
     x = d1.get_map("test")
@@ -237,6 +291,71 @@ def test_observer():
     assert entries == None
 
 
+def test_observe_keys():
+    d1 = Y.YDoc()
+    x = d1.get_map("test")
+    events = []
+    x.observe(lambda e: events.append(e.keys), keys=["watched", "other"])
+
+    with d1.begin_transaction() as txn:
+        x.set(txn, "ignored", 1)
+    assert events == []
+
+    with d1.begin_transaction() as txn:
+        x.set(txn, "ignored", 2)
+        x.set(txn, "watched", 1)
+    assert events == [{"watched": {"action": "add", "newValue": 1}}]
+
+
+def test_observe_shallow_keys():
+    d1 = Y.YDoc()
+    x = d1.get_map("test")
+    events = []
+    x.observe(lambda e: events.append(e.keys), shallow_keys=True)
+
+    with d1.begin_transaction() as txn:
+        x.set(txn, "a", 1)
+    assert events == [{"a": {"action": "add", "newValue": 1}}]
+
+    events.clear()
+    with d1.begin_transaction() as txn:
+        x.set(txn, "a", 2)
+    assert events == []
+
+    with d1.begin_transaction() as txn:
+        x.set(txn, "a", 3)
+        x.set(txn, "b", 1)
+    assert events == [{"b": {"action": "add", "newValue": 1}}]
+
+    events.clear()
+    with d1.begin_transaction() as txn:
+        x.pop(txn, "b")
+    assert events == [{"b": {"action": "delete", "oldValue": 1}}]
+
+
+def test_observe_weak():
+    d1 = Y.YDoc()
+    x = d1.get_map("test")
+    events = []
+
+    class Listener:
+        def on_change(self, e):
+            events.append(e.keys)
+
+    listener = Listener()
+    x.observe(listener.on_change, weak=True)
+    with d1.begin_transaction() as txn:
+        x.set(txn, "a", 1)
+    assert events == [{"a": {"action": "add", "newValue": 1}}]
+
+    events.clear()
+    del listener
+    gc.collect()
+    with d1.begin_transaction() as txn:
+        x.set(txn, "b", 1)
+    assert events == []
+
+
 def test_deep_observe():
     """
     Ensure that changes to elements inside the array trigger a callback.
@@ -282,3 +401,602 @@ def test_integrate_nested_map():
 
     d1.transact(lambda txn: array.append(txn, nested))
     assert array[0]["a"][0] == "A"
+
+
+@pytest.mark.parametrize("value", [float("nan"), float("inf"), float("-inf")])
+def test_non_finite_floats(value):
+    """
+    Ensures that non-finite floats are rejected instead of producing invalid JSON.
+    """
+    doc = Y.YDoc()
+    ymap = doc.get_map("test")
+    with pytest.raises(ValueError):
+        with doc.begin_transaction() as txn:
+            ymap.set(txn, "value", value)
+    with pytest.raises(ValueError):
+        with doc.begin_transaction() as txn:
+            ymap.set(txn, "nested", {"value": [value]})
+    assert len(ymap) == 0
+
+    prelim = YMap({"value": value})
+    with pytest.raises(ValueError):
+        prelim.to_json()
+
+
+def test_views_within_transaction():
+    """
+    Ensures that map views read through an active transaction observe its pending changes.
+    """
+    doc = Y.YDoc()
+    ymap = doc.get_map("test")
+    with doc.begin_transaction() as txn:
+        ymap.update(txn, {"a": 1, "b": 2})
+        assert sorted(ymap.keys(txn)) == ["a", "b"]
+        ymap.pop(txn, "a")
+        ymap.set(txn, "c", 3)
+        assert sorted(ymap.items(txn)) == [("b", 2.0), ("c", 3.0)]
+        assert sorted(ymap.values(txn)) == [2.0, 3.0]
+        assert len(ymap.keys(txn)) == 2
+        assert "c" in ymap.keys(txn)
+        assert "a" not in ymap.keys(txn)
+
+    # A committed transaction can't be reused
+    with pytest.raises(Y.TransactionError):
+        ymap.items(txn)
+
+
+def test_views_after_commit():
+    """
+    Ensures that map views and their iterators can't read through a transaction once it has been
+    committed.
+    """
+    doc = Y.YDoc()
+    ymap = doc.get_map("test")
+    with doc.begin_transaction() as txn:
+        ymap.update(txn, {"a": 1, "b": 2})
+        keys = ymap.keys(txn)
+        items = iter(ymap.items(txn))
+        values = iter(ymap.values(txn))
+        assert next(items) in [("a", 1.0), ("b", 2.0)]
+
+    with pytest.raises(Y.TransactionError):
+        next(items)
+    with pytest.raises(Y.TransactionError):
+        next(values)
+    with pytest.raises(Y.TransactionError):
+        len(keys)
+    with pytest.raises(Y.TransactionError):
+        "a" in keys
+    with pytest.raises(Y.TransactionError):
+        list(keys)
+
+
+def test_popitem():
+    doc = Y.YDoc()
+    ymap = doc.get_map("test")
+    prelim = YMap({"a": 1, "b": 2})
+    with doc.begin_transaction() as txn:
+        ymap.update(txn, {"a": 1, "b": 2})
+        for m in [ymap, prelim]:
+            popped = dict([m.popitem(txn), m.popitem(txn)])
+            assert popped == {"a": 1, "b": 2}
+            assert len(m) == 0
+            with pytest.raises(KeyError):
+                m.popitem(txn)
+
+
+def test_type_of():
+    doc = Y.YDoc()
+    ymap = doc.get_map("test")
+
+    def contents():
+        return {
+            "number": 1,
+            "list": [1, 2],
+            "array": YArray([1, 2]),
+            "map": YMap({"a": 1}),
+            "text": YText("hello"),
+        }
+
+    expected = {
+        "number": "primitive",
+        "list": "primitive",
+        "array": "array",
+        "map": "map",
+        "text": "text",
+    }
+    prelim = YMap(contents())
+    with doc.begin_transaction() as txn:
+        ymap.update(txn, contents())
+    for m in [ymap, prelim]:
+        for key, kind in expected.items():
+            assert m.type_of(key) == kind
+        with pytest.raises(KeyError):
+            m.type_of("missing")
+
+
+def test_get_typed():
+    doc = Y.YDoc()
+    ymap = doc.get_map("test")
+    with doc.begin_transaction() as txn:
+        ymap.update(txn, {"number": 1, "text": YText("hello"), "none": None})
+    assert ymap.get_typed("number", "primitive") == 1
+    assert str(ymap.get_typed("text", "text")) == "hello"
+    with pytest.raises(TypeError):
+        ymap.get_typed("text", "map")
+    with pytest.raises(KeyError):
+        ymap.get_typed("missing", "primitive")
+    with pytest.raises(ValueError):
+        ymap.get_typed("number", "unknown")
+    # A stored `None` is returned as is rather than replaced by the default.
+    assert ymap.get("none", "default") is None
+    assert ymap.get("missing", default="default") == "default"
+
+
+def test_reads_in_observer():
+    doc = Y.YDoc()
+    ymap = doc.get_map("test")
+    seen = []
+
+    def callback(e):
+        target = e.target
+        seen.append((len(target), target["a"], target.get("b"), dict(target.items())))
+
+    ymap.observe(callback)
+    with doc.begin_transaction() as txn:
+        ymap.set(txn, "a", "x")
+    assert seen == [(1, "x", None, {"a": "x"})]
+
+
+def test_observe_changes():
+    doc = Y.YDoc()
+    container = doc.get_map("container")
+    with doc.begin_transaction() as txn:
+        container.set(txn, "inner", YMap({"key": "initial"}))
+        container.set(txn, "list", YArray([1]))
+
+    changes = None
+
+    def callback(c):
+        nonlocal changes
+        changes = c
+
+    sub = container.observe_changes(callback)
+    with doc.begin_transaction() as txn:
+        container["inner"].set(txn, "key", "updated")
+        container["list"].append(txn, 2)
+        container.set(txn, "flag", True)
+
+    assert changes == {
+        "": {"keys": {"flag": {"action": "add", "newValue": True}}},
+        "inner": {
+            "keys": {"key": {"action": "update", "oldValue": "initial", "newValue": "updated"}}
+        },
+        "list": {"delta": [{"retain": 1}, {"insert": [2]}]},
+    }
+
+    changes = None
+    container.unobserve(sub)
+    with doc.begin_transaction() as txn:
+        container.set(txn, "flag", False)
+    assert changes is None
+
+
+def test_observe_changes_weak():
+    doc = Y.YDoc()
+    container = doc.get_map("container")
+    changes = []
+
+    class Listener:
+        def on_change(self, c):
+            changes.append(c)
+
+    listener = Listener()
+    container.observe_changes(listener.on_change, weak=True)
+    with doc.begin_transaction() as txn:
+        container.set(txn, "a", 1)
+    assert changes == [{"": {"keys": {"a": {"action": "add", "newValue": 1}}}}]
+
+    changes.clear()
+    del listener
+    gc.collect()
+    with doc.begin_transaction() as txn:
+        container.set(txn, "b", 1)
+    assert changes == []
+
+
+def test_path():
+    doc = Y.YDoc()
+    root = doc.get_map("root")
+    with doc.begin_transaction() as txn:
+        root.set(txn, "list", Y.YArray([1, Y.YMap({"text": Y.YText("abc")})]))
+    nested_map = root["list"][1]
+    text = nested_map["text"]
+    assert root.path() == []
+    assert root["list"].path() == ["list"]
+    assert nested_map.path() == ["list", 1]
+    assert text.path() == ["list", 1, "text"]
+
+    # paths match the ones reported by events
+    paths = []
+    sub = root.observe_deep(lambda events: paths.extend(e.path() for e in events))
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "d")
+    root.unobserve(sub)
+    assert paths == [text.path()]
+
+    # indexes skip removed siblings
+    with doc.begin_transaction() as txn:
+        root["list"].delete(txn, 0)
+    assert text.path() == ["list", 0, "text"]
+
+    with doc.begin_transaction() as txn:
+        root.pop(txn, "list")
+    assert text.path() is None
+    with pytest.raises(Exception):
+        Y.YMap({}).path()
+
+
+def test_parent():
+    doc = Y.YDoc()
+    root = doc.get_map("root")
+    with doc.begin_transaction() as txn:
+        root.set(txn, "list", Y.YArray([Y.YMap({"text": Y.YText("abc")})]))
+    array = root["list"]
+    nested_map = array[0]
+    text = nested_map["text"]
+    assert root.parent is None
+    assert isinstance(array.parent, Y.YMap)
+    assert array.parent.path() == []
+    assert isinstance(nested_map.parent, Y.YArray)
+    assert isinstance(text.parent, Y.YMap)
+    assert text.parent.path() == ["list", 0]
+    assert Y.YText("prelim").parent is None
+
+    with doc.begin_transaction() as txn:
+        root.pop(txn, "list")
+    assert nested_map.parent is None and text.parent is None
+
+
+def test_constructor_inputs():
+    expected = {"a": 1, "b": "two"}
+    for init in [
+        expected,
+        [("a", 1), ("b", "two")],
+        (["a", 1], ["b", "two"]),
+        ((k, v) for k, v in expected.items()),
+        YMap(expected),
+    ]:
+        assert dict(YMap(init)) == expected
+    assert dict(YMap()) == {}
+    with pytest.raises(TypeError):
+        YMap([("a", 1, 2)])
+    with pytest.raises(TypeError):
+        YMap([(1, "a")])
+
+
+def test_copy_prelim():
+    nested = Y.YArray([1, {"a": [2]}])
+    template = Y.YMap({"items": [1, 2], "nested": nested, "title": Y.YText("hi")})
+
+    shallow = copy(template)
+    assert shallow.prelim and shallow["items"] is template["items"]
+
+    deep = deepcopy(template)
+    assert deep.prelim and deep["items"] == [1, 2]
+    assert deep["items"] is not template["items"]
+    assert deep["nested"] is not nested and deep["nested"].prelim
+    assert deep["nested"][1] == {"a": [2]}
+    assert str(deep["title"]) == "hi"
+
+    # copies can be integrated independently
+    doc = Y.YDoc()
+    root = doc.get_map("root")
+    with doc.begin_transaction() as txn:
+        root.set(txn, "first", deepcopy(template))
+        root.set(txn, "second", deepcopy(template))
+    assert root["first"].to_json() == root["second"].to_json()
+
+    for integrated in [root, root["first"], root["first"]["nested"], root["first"]["title"]]:
+        with pytest.raises(TypeError):
+            copy(integrated)
+        with pytest.raises(TypeError):
+            deepcopy(integrated)
+
+
+def test_is_alive():
+    doc = Y.YDoc()
+    root = doc.get_map("test")
+    with doc.begin_transaction() as txn:
+        root.set(txn, "nested", YMap({"inner": YArray([1])}))
+    nested = root["nested"]
+    inner = nested["inner"]
+    assert root.is_alive() and nested.is_alive() and inner.is_alive()
+    assert YMap().is_alive()
+
+    with doc.begin_transaction() as txn:
+        root.pop(txn, "nested")
+    assert root.is_alive()
+    assert nested.is_deleted() and inner.is_deleted()
+    with pytest.raises(Exception, match="removed from its YDoc"):
+        with doc.begin_transaction() as txn:
+            nested.set(txn, "key", "value")
+    with pytest.raises(Exception, match="removed from its YDoc"):
+        with doc.begin_transaction() as txn:
+            inner.append(txn, 2)
+
+
+def test_is_alive_with_undefined_roots():
+    remote = Y.YDoc()
+    with remote.begin_transaction() as txn:
+        remote.get_array("untyped").append(txn, 1)
+    doc = Y.YDoc()
+    Y.apply_update(doc, Y.encode_state_as_update(remote))
+    # "untyped" root hasn't been fetched, so its kind is still undefined
+    root = doc.get_map("test")
+    with doc.begin_transaction() as txn:
+        root.set(txn, "nested", YMap({}))
+    nested = root["nested"]
+    assert nested.is_alive()
+    with doc.begin_transaction() as txn:
+        nested.set(txn, "key", "value")
+    assert dict(nested) == {"key": "value"}
+
+
+def test_sorted_views():
+    d = Y.YDoc()
+    m = d.get_map("map")
+    entries = {"b": 2, "c": {"z": 1, "a": [{"y": 1, "x": 2}]}, "a": 1}
+    with d.begin_transaction() as txn:
+        m.update(txn, entries)
+    expected_keys = sorted(entries.keys())
+    assert list(m.keys(sorted=True)) == expected_keys
+    assert list(m.values(sorted=True)) == [entries[k] for k in expected_keys]
+    assert list(m.items(sorted=True)) == [(k, entries[k]) for k in expected_keys]
+
+    expected_json = json.dumps(entries, sort_keys=True, separators=(",", ":"))
+    assert m.to_json(sort_keys=True) == expected_json
+    assert d.to_json(sort_keys=True) == f'{{"map":{expected_json}}}'
+
+    prelim = YMap(entries)
+    assert list(prelim.keys(sorted=True)) == expected_keys
+    assert prelim.to_json(sort_keys=True) == expected_json
+
+
+def test_merge_from():
+    d = Y.YDoc()
+    m = d.get_map("map")
+    with d.begin_transaction() as txn:
+        m.update(txn, {"a": 1, "b": 2})
+    template = YMap({"b": 3, "child": YMap({"nested": YArray([1, 2])})})
+    with d.begin_transaction() as txn:
+        m.merge_from(txn, template)
+    assert m.to_json(sort_keys=True) == '{"a":1,"b":3,"child":{"nested":[1,2]}}'
+    child = m["child"]
+    assert isinstance(child, YMap)
+    assert child.prelim is False
+    assert isinstance(child["nested"], YArray)
+
+    # The template is left untouched and can be merged again.
+    assert template.prelim is True
+    assert template["child"].prelim is True
+    other = d.get_map("other")
+    with d.begin_transaction() as txn:
+        other.merge_from(txn, template)
+    assert other.to_json(sort_keys=True) == '{"b":3,"child":{"nested":[1,2]}}'
+
+    with d.begin_transaction() as txn:
+        with pytest.raises(TypeError):
+            other.merge_from(txn, m)
+
+
+def test_merge_operators():
+    d1 = Y.YDoc()
+    m = d1.get_map("map")
+    events = []
+    m.observe(lambda e: events.append(e.keys))
+
+    # without an open transaction, a new one is committed right away
+    m |= {"a": 1, "b": 2}
+    assert len(events) == 1
+    assert dict(m.items()) == {"a": 1.0, "b": 2.0}
+
+    with d1.begin_transaction():
+        m |= YMap({"b": 3})
+        m |= [("c", "x")]
+        assert len(events) == 1
+    assert len(events) == 2
+    assert dict(m.items()) == {"a": 1.0, "b": 3.0, "c": "x"}
+
+    merged = m | {"c": "y", "d": None}
+    assert merged.prelim
+    assert dict(merged.items()) == {"a": 1.0, "b": 3.0, "c": "y", "d": None}
+    assert dict(m.items()) == {"a": 1.0, "b": 3.0, "c": "x"}
+
+    prelim = YMap({"a": 1})
+    prelim |= m
+    assert dict(prelim.items()) == {"a": 1.0, "b": 3.0, "c": "x"}
+    assert dict((YMap({"z": 0}) | prelim).items()) == {"z": 0, "a": 1.0, "b": 3.0, "c": "x"}
+    with pytest.raises(TypeError):
+        m | [("a", 1)]
+
+
+def test_id():
+    d1 = Y.YDoc(client_id=1)
+    root = d1.get_map("test")
+    with d1.begin_transaction() as txn:
+        root.set(txn, "nested", YMap({"inner": YArray([1])}))
+    nested = root["nested"]
+    assert root.id() == "test"
+    assert nested.id() == (1, 0)
+    assert YMap().id() is None
+
+    # identifiers are the same on a remote peer
+    d2 = Y.YDoc(client_id=2)
+    Y.apply_update(d2, Y.encode_state_as_update(d1))
+    remote = d2.get_map("test")
+    assert remote["nested"].id() == nested.id()
+    assert remote["nested"]["inner"].id() == nested["inner"].id()
+
+    # identifiers can be read from within observer callbacks
+    ids = []
+    root.observe(lambda event: ids.append((event.target.id(), nested.id())))
+    with d1.begin_transaction() as txn:
+        root.set(txn, "key", "value")
+    assert ids == [("test", (1, 0))]
+
+
+def test_set_many():
+    d = Y.YDoc()
+    m = d.get_map("map")
+    with d.begin_transaction() as txn:
+        m.set(txn, "a", "old")
+        m.set_many(txn, {"a": 1, "b": True, "nested": YMap({"c": "d"})})
+        m.set_many(txn, [("e", "f"), ["g", None]])
+    assert m.to_json(sort_keys=True) == (
+        '{"a":1,"b":true,"e":"f","g":null,"nested":{"c":"d"}}'
+    )
+    assert m["nested"].prelim is False
+
+    # an invalid value leaves the map unchanged
+    with d.begin_transaction() as txn:
+        with pytest.raises(TypeError):
+            m.set_many(txn, {"x": 1, "y": object()})
+        with pytest.raises(TypeError):
+            m.set_many(txn, ["not a pair"])
+    assert "x" not in m
+
+    prelim = YMap()
+    with d.begin_transaction() as txn:
+        prelim.set_many(txn, {"a": 1})
+    assert dict(prelim.items()) == {"a": 1}
+
+
+def test_set_returns_integrated_type():
+    d = Y.YDoc()
+    m = d.get_map("map")
+    with d.begin_transaction() as txn:
+        child = m.set(txn, "child", YArray([1, 2]))
+        assert m.set(txn, "number", 1) is None
+        child.append(txn, 3)
+    assert child.prelim is False
+    assert m["child"].to_json() == "[1,2,3]"
+
+    prelim = YMap()
+    with d.begin_transaction() as txn:
+        assert prelim.set(txn, "text", YText("a")) is None
+
+    arr = d.get_array("array")
+    with d.begin_transaction() as txn:
+        text = arr.append(txn, YText("ab"))
+        nested = arr.insert(txn, 0, YMap({"k": "v"}))
+        assert arr.append(txn, "plain") is None
+        text.extend(txn, "c")
+        nested.set(txn, "k", "w")
+    assert arr.to_json() == '[{"k":"w"},"abc","plain"]'
+
+
+def test_deep_observe_event_kinds():
+    doc = Y.YDoc()
+    container = doc.get_map("container")
+    with doc.begin_transaction() as txn:
+        text = container.set(txn, "text", YText())
+        array = container.set(txn, "array", YArray())
+
+    kinds = []
+    sub = container.observe_deep(lambda events: kinds.extend(e.kind for e in events))
+    with doc.begin_transaction() as txn:
+        container.set(txn, "key", 1)
+        text.extend(txn, "hello")
+        array.append(txn, 1)
+    container.unobserve(sub)
+    assert sorted(kinds) == ["array", "map", "text"]
+
+
+def test_repr_truncation():
+    d = Y.YDoc()
+    m = d.get_map("map")
+    contents = {f"key{i}": i for i in range(15)}
+    with d.begin_transaction() as txn:
+        m.update(txn, contents)
+    for test in [m, YMap(contents)]:
+        r = repr(test)
+        assert r.startswith("YMap({") and r.endswith(", ...})")
+        assert r.count(": ") == 10
+        assert json.loads(test.to_json()) == contents
+    assert repr(YMap({"a": [1]})) == "YMap({'a': [1]})"
+
+
+def test_from_keys():
+    m = YMap.from_keys(["a", "b"])
+    assert m.prelim
+    assert dict(m.items()) == {"a": None, "b": None}
+
+    default = {"tags": []}
+    m = YMap.from_keys(iter(["a", "b"]), default)
+    m["a"]["tags"].append("x")
+    assert m["b"] == {"tags": []}
+    assert default == {"tags": []}
+
+    m = YMap.from_keys(["todo", "done"], YArray([1]))
+    assert m["todo"] is not m["done"]
+    d = Y.YDoc()
+    with d.begin_transaction() as txn:
+        d.get_map("map").set(txn, "lists", m)
+    assert d.get_map("map").to_json(sort_keys=True) == '{"lists":{"done":[1],"todo":[1]}}'
+
+    with pytest.raises(TypeError):
+        YMap.from_keys([1, 2])
+
+
+def test_to_dict():
+    doc = Y.YDoc()
+    ymap = doc.get_map("map")
+    with doc.begin_transaction() as txn:
+        ymap.update(txn, {"a": 1, "nested": YMap({"list": YArray([1, 2])})})
+
+    shallow = ymap.to_dict()
+    assert shallow["a"] == 1
+    assert isinstance(shallow["nested"], YMap)
+    with doc.begin_transaction() as txn:
+        shallow["nested"].set(txn, "b", True)
+    assert ymap.to_dict(deep=True) == {"a": 1, "nested": {"list": [1, 2], "b": True}}
+
+    prelim = YMap({"nested": YMap({"a": 1})})
+    assert isinstance(prelim.to_dict()["nested"], YMap)
+    assert prelim.to_dict(deep=True) == {"nested": {"a": 1}}
+
+
+def test_materialize():
+    doc = Y.YDoc()
+    ymap = doc.get_map("map")
+    with doc.begin_transaction() as txn:
+        ymap.update(txn, {"a": 1, "list": YArray([1, 2]), "text": YText("hi")})
+
+    assert isinstance(ymap.get("list"), YArray)
+    snapshot = ymap.get("list", materialize=True)
+    assert snapshot == [1, 2]
+    assert ymap.get("text", materialize=True) == "hi"
+    assert ymap.get("missing", "default", materialize=True) == "default"
+
+    with doc.begin_transaction() as txn:
+        ymap["list"].append(txn, 3)
+    assert snapshot == [1, 2]
+    assert dict(ymap.items(materialize=True)) == {"a": 1, "list": [1, 2, 3], "text": "hi"}
+    assert ("list", [1, 2, 3]) in ymap.items(materialize=True)
+    assert list(ymap.values(sorted=True, materialize=True)) == [1, [1, 2, 3], "hi"]
+    assert YMap({"m": YMap({"b": 2})}).get("m", materialize=True) == {"b": 2}
+
+
+def test_length():
+    doc = Y.YDoc()
+    ymap = doc.get_map("map")
+    with doc.begin_transaction() as txn:
+        ymap.set(txn, "a", 1)
+        assert ymap.length(txn) == 1
+        ymap.set(txn, "b", 2)
+        assert ymap.length(txn) == len(ymap) == 2
+    assert ymap.length() == 2
+    assert YMap({"a": 1}).length() == 1
+    with pytest.raises(Y.TransactionError):
+        ymap.length(txn)
@@ -1,4 +1,5 @@
 from test_helper import exchange_updates
+import pytest
 import y_py as Y
 from y_py import YText, YTextEvent
 
@@ -17,6 +18,18 @@ def test_to_string():
         assert test.__repr__() == f"YText({expected})"
 
 
+def test_repr_truncation():
+    d = Y.YDoc()
+    content = "ż" * 150
+    integrated = d.get_text("test")
+    with d.begin_transaction() as txn:
+        integrated.extend(txn, content)
+    for test in [YText(content), integrated]:
+        assert repr(test) == f"YText({content[:100]}...)"
+        assert str(test) == content
+    assert repr(YText("ż" * 100)) == f"YText({'ż' * 100})"
+
+
 def test_inserts():
     d1 = Y.YDoc()
     x = d1.get_text("test")
@@ -192,13 +205,69 @@ def test_formatting():
     ]
 
     with d1.begin_transaction() as txn:
-        text.format(txn, 4, 7, {"bold": True})
+        text.format(txn, 4, 3, {"bold": True})
 
     assert delta == [{"retain": 4}, {"retain": 3, "attributes": {"bold": True}}]
 
     text.unobserve(sub)
 
 
+def test_format_previous():
+    d1 = Y.YDoc()
+    text = d1.get_text("test")
+    with d1.begin_transaction() as txn:
+        text.insert(txn, 0, "stylish")
+        assert text.format(txn, 0, 4, {"bold": True}) is None
+
+    with d1.begin_transaction() as txn:
+        previous = text.format(txn, 2, 4, {"italic": True}, previous=True)
+    assert previous == [(2, 2, {"bold": True}), (4, 2, {})]
+
+    # restoring the previous formatting undoes the change
+    with d1.begin_transaction() as txn:
+        for index, length, _ in previous:
+            text.format(txn, index, length, {"italic": None})
+    with d1.begin_transaction() as txn:
+        assert text.format(txn, 0, 7, {}, previous=True) == [
+            (0, 4, {"bold": True}),
+            (4, 3, {}),
+        ]
+
+    # ranges overflowing the end of the text are rejected before collecting spans
+    with d1.begin_transaction() as txn:
+        with pytest.raises(IndexError):
+            text.format(txn, 1, 2**32 - 1, {}, previous=True)
+
+
+def test_format_bounds():
+    d = Y.YDoc()
+    text = d.get_text("test")
+    with d.begin_transaction() as txn:
+        text.extend(txn, "hello")
+
+    delta = None
+
+    def callback(e):
+        nonlocal delta
+        delta = e.delta
+
+    sub = text.observe(callback)
+    with d.begin_transaction() as txn:
+        # formatting the exact tail of the text
+        text.format(txn, 3, 2, {"bold": True})
+    assert delta == [{"retain": 3}, {"retain": 2, "attributes": {"bold": True}}]
+
+    with d.begin_transaction() as txn:
+        # zero-length ranges are valid, even at the end of the text
+        text.format(txn, 5, 0, {"italic": True})
+        with pytest.raises(IndexError, match="0..6 of a text of length 5"):
+            text.format(txn, 0, 6, {"bold": True})
+        with pytest.raises(IndexError):
+            text.format(txn, 6, 0, {"bold": True})
+    assert str(text) == "hello"
+    text.unobserve(sub)
+
+
 def test_deep_observe():
     d = Y.YDoc()
     text = d.get_text("text")
@@ -226,3 +295,354 @@ def test_deep_observe():
         text.extend(txn, " should not trigger")
 
     assert events is None
+
+
+def test_char_len():
+    d = Y.YDoc()
+    text = d.get_text("test")
+    expected = "zażółć"
+    with d.begin_transaction() as txn:
+        text.extend(txn, expected)
+    for test in [text, YText(expected)]:
+        assert len(test) == len(expected.encode("utf-8"))
+        assert test.char_len == len(expected)
+        assert test.byte_to_char_index(0) == 0
+        assert test.byte_to_char_index(4) == 3
+        assert test.byte_to_char_index(len(test)) == len(expected)
+        assert test.char_to_byte_index(3) == 4
+        assert test.char_to_byte_index(len(expected)) == len(test)
+        with pytest.raises(ValueError):
+            test.byte_to_char_index(3)
+        with pytest.raises(IndexError):
+            test.byte_to_char_index(len(test) + 1)
+        with pytest.raises(IndexError):
+            test.char_to_byte_index(len(expected) + 1)
+
+    # Convert Python string positions before editing
+    with d.begin_transaction() as txn:
+        text.insert(txn, text.char_to_byte_index(expected.index("ł")), "-")
+    assert str(text) == "zażó-łć"
+
+    # byte offsets don't apply to documents counting offsets differently
+    d = Y.YDoc(offset_kind="utf32")
+    text = d.get_text("test")
+    with d.begin_transaction() as txn:
+        text.extend(txn, expected)
+    assert text.char_len == len(text) == len(expected)
+    with pytest.raises(ValueError):
+        text.byte_to_char_index(0)
+    with pytest.raises(ValueError):
+        text.char_to_byte_index(0)
+
+
+def test_utf16_index():
+    d = Y.YDoc()
+    text = d.get_text("test")
+    expected = "a😀b"
+    with d.begin_transaction() as txn:
+        text.extend(txn, expected)
+    for test in [text, YText(expected)]:
+        assert test.utf16_len == len(expected.encode("utf-16-le")) // 2 == 4
+        assert [test.char_to_utf16_index(i) for i in range(4)] == [0, 1, 3, 4]
+        assert [test.utf16_to_char_index(i) for i in [0, 1, 3, 4]] == [0, 1, 2, 3]
+        with pytest.raises(ValueError):
+            test.utf16_to_char_index(2)
+        with pytest.raises(IndexError):
+            test.utf16_to_char_index(5)
+        with pytest.raises(IndexError):
+            test.char_to_utf16_index(4)
+
+    # in documents counting offsets in UTF-16 code units, converted offsets are used for editing
+    d = Y.YDoc(offset_kind="utf16")
+    text = d.get_text("test")
+    with d.begin_transaction() as txn:
+        text.extend(txn, expected)
+        assert text.utf16_len == len(text) == 4
+        text.insert(txn, text.char_to_utf16_index(2), "-")
+    assert str(text) == "a😀-b"
+
+
+def test_insert_index_bounds():
+    d = Y.YDoc()
+    text = d.get_text("test")
+    with d.begin_transaction() as txn:
+        assert text.insert(txn, 0, "hello") == 5
+        # Inserting exactly at the end of the text is allowed
+        assert text.insert(txn, len(text), "!") == 6
+        # Negative indexes count from the end, like with `list.insert`
+        assert text.insert(txn, -1, " world") == 12
+        assert text.insert(txn, -len(text), ">") == 13
+        with pytest.raises(IndexError, match="valid range: -13..=13"):
+            text.insert(txn, 14, "x")
+        with pytest.raises(IndexError):
+            text.insert(txn, -14, "x")
+    assert str(text) == ">hello world!"
+
+
+def test_read_target_in_observer():
+    """
+    Reading the event target from within an observer callback reuses the committing transaction.
+    """
+    local = Y.YDoc()
+    remote = Y.YDoc()
+    text = remote.get_text("test")
+    seen = []
+    text.observe(lambda e: seen.append(str(e.target)))
+
+    local_text = local.get_text("test")
+    with local.begin_transaction() as txn:
+        local_text.extend(txn, "hello")
+    Y.apply_update(remote, Y.encode_state_as_update(local))
+    assert seen == ["hello"]
+
+    with remote.begin_transaction() as txn:
+        text.extend(txn, " world")
+    assert seen == ["hello", "hello world"]
+
+
+def test_replace():
+    d = Y.YDoc()
+    text = d.get_text("test")
+    prelim = YText("hello world")
+    with d.begin_transaction() as txn:
+        text.extend(txn, "hello world")
+        text.format(txn, 0, 5, {"bold": True})
+        assert prelim.replace(txn, 6, 5, "there") == 11
+        with pytest.raises(IndexError):
+            text.replace(txn, 8, 10, "x")
+    assert str(prelim) == "hello there"
+
+    delta = None
+
+    def callback(e):
+        nonlocal delta
+        delta = e.delta
+
+    sub = text.observe(callback)
+    with d.begin_transaction() as txn:
+        assert text.replace(txn, 1, 4, "ELLO") == 11
+    assert str(text) == "hELLO world"
+    # deletion and insertion are delivered as a single change and the new
+    # text picks up the formatting of the text it replaced
+    assert {"insert": "ELLO", "attributes": {"bold": True}} in delta
+    assert {"delete": 4} in delta
+    text.unobserve(sub)
+
+
+def test_observe_context_manager():
+    d = Y.YDoc()
+    text = d.get_text("test")
+    events = []
+    deep_events = []
+
+    with text.observe(events.append), text.observe_deep(deep_events.append):
+        with d.begin_transaction() as txn:
+            text.extend(txn, "observed")
+    with d.begin_transaction() as txn:
+        text.extend(txn, " ignored")
+
+    assert len(events) == 1
+    assert len(deep_events) == 1
+    assert str(text) == "observed ignored"
+
+
+def test_event_changes():
+    d1 = Y.YDoc()
+    text = d1.get_text("test")
+    with d1.begin_transaction() as txn:
+        text.extend(txn, "hello world")
+
+    changes = None
+
+    def callback(e: YTextEvent):
+        nonlocal changes
+        changes = e.changes()
+
+    text.observe(callback)
+    with d1.begin_transaction() as txn:
+        text.insert(txn, 5, ",")
+        text.format(txn, 7, 5, {"bold": True})
+        text.delete_range(txn, 0, 1)
+        text.insert(txn, 0, "H")
+    assert str(text) == "Hello, world"
+    assert changes == [
+        {"type": "insert", "index": 0, "insert": "H"},
+        {"type": "delete", "index": 1, "length": 1},
+        {"type": "insert", "index": 5, "insert": ","},
+        {"type": "format", "index": 7, "length": 5, "attributes": {"bold": True}},
+    ]
+
+
+def test_subscription_identity():
+    d1 = Y.YDoc()
+    text = d1.get_text("test")
+    first = text.observe(lambda e: None)
+    second = text.observe(lambda e: None)
+    deep = text.observe_deep(lambda e: None)
+
+    subscriptions = {first, second, deep}
+    assert len(subscriptions) == 3
+    assert first in subscriptions and first != second
+    assert first != deep
+    assert repr(first).startswith("ShallowSubscription(")
+    assert repr(deep).startswith("DeepSubscription(")
+
+    for subscription in subscriptions:
+        text.unobserve(subscription)
+
+
+def test_observe_emit_initial():
+    d1 = Y.YDoc()
+    x = d1.get_text("test")
+    with d1.begin_transaction() as txn:
+        x.extend(txn, "hello world")
+        x.format(txn, 0, 5, {"bold": True})
+
+    events = []
+
+    def callback(e: YTextEvent):
+        events.append((str(e.target), e.delta, e.changes(), e.origin))
+
+    x.observe(callback, emit_initial=True)
+    assert events == [
+        (
+            "hello world",
+            [{"insert": "hello", "attributes": {"bold": True}}, {"insert": " world"}],
+            [
+                {"type": "insert", "index": 0, "insert": "hello", "attributes": {"bold": True}},
+                {"type": "insert", "index": 5, "insert": " world"},
+            ],
+            None,
+        )
+    ]
+
+    with d1.begin_transaction() as txn:
+        x.extend(txn, "!")
+    assert events[1][1] == [{"retain": 11}, {"insert": "!"}]
+
+
+def test_find():
+    d = Y.YDoc()
+    text = d.get_text("test")
+    content = "żółw i żółć"
+    with d.begin_transaction() as txn:
+        text.extend(txn, content)
+    for test in [text, YText(content)]:
+        assert test.find("żół") == 0
+        assert test.find("żół", 1, chars=True) == content.find("żół", 1)
+        assert test.find("żół", 1, chars=True) == test.byte_to_char_index(test.find("żół", 2))
+        assert test.find("x") is None
+        assert test.find("ż", 100) is None
+        assert test.find_all("żół", chars=True) == [0, 7]
+        assert test.find_all("żół") == [0, test.char_to_byte_index(7)]
+        with pytest.raises(ValueError):
+            test.find("ż", 1)
+
+
+def test_substring():
+    d = Y.YDoc()
+    text = d.get_text("test")
+    content = "żółw i żółć"
+    with d.begin_transaction() as txn:
+        text.extend(txn, "żółw ")
+        text.extend(txn, "i żółć")
+        text.format(txn, 0, 2, {"bold": True})
+        text.insert_embed(txn, text.char_to_byte_index(5), {"image": "a.png"})
+    for test in [text, YText(content)]:
+        assert test.substring(0) == content
+        assert test.substring(2, 9, chars=True) == content[2:9]
+        assert test.substring(4, 100, chars=True) == content[4:]
+        assert test.substring(5, 3, chars=True) == ""
+        assert test.substring(100) == ""
+        assert test.substring(0, 6) == "żół"
+        assert test.substring(test.char_to_byte_index(7)) == "żółć"
+        with pytest.raises(ValueError):
+            test.substring(1)
+        with pytest.raises(ValueError):
+            test.substring(0, 3)
+
+
+def test_embeds():
+    doc = Y.YDoc()
+    text = doc.get_text("test")
+    assert text.embeds() == []
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "hello world")
+        text.insert_embed(txn, 5, {"image": "a.png"})
+        text.insert_embed(txn, 0, "mention", {"bold": True})
+    assert text.embeds() == [(0, "mention"), (6, {"image": "a.png"})]
+    assert YText("prelim").embeds() == []
+
+
+def test_chunks():
+    doc = Y.YDoc()
+    text = doc.get_text("test")
+    assert text.chunks() == []
+    with doc.begin_transaction() as txn:
+        text.extend(txn, "hello")
+        text.extend(txn, " world")
+        text.format(txn, 0, 5, {"bold": True})
+        text.insert_embed(txn, 11, {"image": "a.png"})
+    assert text.chunks() == [
+        {"insert": "hello", "attributes": {"bold": True}},
+        {"insert": " world"},
+        {"insert": {"image": "a.png"}},
+    ]
+    assert YText("prelim").chunks() == [{"insert": "prelim"}]
+    assert YText().chunks() == []
+
+
+def test_insert_embed_sticky_index():
+    d1 = Y.YDoc(client_id=1)
+    x = d1.get_text("test")
+    with d1.begin_transaction() as txn:
+        assert x.extend(txn, "hello world") == 11
+        assert x.insert_embed(txn, 0, "mention") == 12
+        image = x.insert_embed(txn, 6, {"image": "a.png"}, sticky_index=True)
+    assert image.index() == 6
+
+    # the index follows the embed as local and remote edits shift it
+    d2 = Y.YDoc(client_id=2)
+    exchange_updates([d1, d2])
+    with d2.begin_transaction() as txn:
+        d2.get_text("test").insert(txn, 0, ">> ")
+    with d1.begin_transaction() as txn:
+        x.insert(txn, 6, "!")
+    exchange_updates([d1, d2])
+    assert image.index() == 10
+    assert x.embeds() == [(3, "mention"), (10, {"image": "a.png"})]
+
+    deltas = []
+    x.observe(lambda e: deltas.append(e.delta))
+    with d1.begin_transaction() as txn:
+        x.format(txn, image.index(), 1, {"width": 200})
+    assert deltas == [[{"retain": 10}, {"retain": 1, "attributes": {"width": 200}}]]
+
+
+def test_sticky_index():
+    d1 = Y.YDoc(client_id=1)
+    x = d1.get_text("test")
+    with d1.begin_transaction() as txn:
+        x.extend(txn, "world")
+    d2 = Y.YDoc(client_id=2)
+    exchange_updates([d1, d2])
+
+    with d1.begin_transaction() as txn:
+        assert x.extend(txn, "!") == 6
+        cursor = x.insert(txn, 0, "hello", sticky_index=True)
+    assert cursor.index() == 5
+
+    # remote edits before the tracked position shift it, while inserts at it don't
+    exchange_updates([d1, d2])
+    y = d2.get_text("test")
+    with d2.begin_transaction() as txn:
+        y.insert(txn, 0, ">> ")
+    exchange_updates([d1, d2])
+    with d1.begin_transaction() as txn:
+        x.insert(txn, 8, " ")
+    assert str(x) == ">> hello world!"
+    assert cursor.index() == 8
+
+    with d1.begin_transaction() as txn:
+        end = x.extend(txn, "?", sticky_index=True)
+    assert end.index() == len(str(x))
@@ -19,7 +19,7 @@ def test_transaction_already_committed():
     with doc.begin_transaction() as txn:
         text.extend(txn, "Hello")
 
-    with pytest.raises(AssertionError) as excinfo:
+    with pytest.raises(Y.TransactionError) as excinfo:
         text.extend(txn, "Bug")
         
     assert str(excinfo.value) == "Transaction already committed!"
@@ -31,14 +31,14 @@ def test_transaction_already_committed():
     assert str(text) == "HelloBug"
     txn.commit()
 
-    with pytest.raises(AssertionError) as excinfo:
+    with pytest.raises(Y.TransactionError) as excinfo:
         txn.commit()
     assert str(excinfo.value) == "Transaction already committed!"
 
     # Try smuggling transaction out of callback and reusing it
     smuggle = {}
     doc.transact(lambda txn: smuggle.update({"txn": txn}))
-    with pytest.raises(AssertionError) as excinfo:
+    with pytest.raises(Y.TransactionError) as excinfo:
         text.extend(smuggle["txn"], "Bug")
     assert str(excinfo.value) == "Transaction already committed!"
     assert str(text) == "HelloBug"
@@ -48,8 +48,89 @@ def test_document_modification_during_transaction():
     doc = Y.YDoc()
     text = doc.get_text("test")
     with doc.begin_transaction() as txn:
-        with pytest.raises(AssertionError) as excinfo:
+        with pytest.raises(Y.TransactionError) as excinfo:
             text_2 = doc.get_text("test2")
-        assert str(excinfo.value) == "Transaction already started!"
+        assert str(excinfo.value).startswith("Transaction already started!")
+        assert "abort_transaction" in str(excinfo.value)
 
     doc.get_text("test2")
+
+
+def test_abort_transaction():
+    doc = Y.YDoc()
+    text = doc.get_text("test")
+    assert not doc.abort_transaction()
+
+    leaked = doc.begin_transaction()
+    text.extend(leaked, "hello")
+    with pytest.raises(Y.TransactionError):
+        doc.get_text("other")
+    assert doc.abort_transaction()
+    assert not doc.has_pending_transaction
+    assert str(text) == "hello"
+    doc.get_text("other")
+
+    with pytest.raises(Y.TransactionError, match="already committed"):
+        leaked.commit()
+    assert not issubclass(Y.TransactionError, AssertionError)
+    with doc.begin_transaction() as txn:
+        text.extend(txn, " world")
+    assert str(text) == "hello world"
+
+
+def test_transact_origin():
+    doc = Y.YDoc()
+    text = doc.get_text("test")
+    origins = []
+    text.observe(lambda e: origins.append(e.origin))
+
+    def callback(txn):
+        text.extend(txn, "hello")
+        text.extend(txn, " world")
+        return txn.origin
+
+    assert doc.transact(callback, origin="batch") == "batch"
+    assert origins == ["batch"]
+    assert doc.transact(lambda txn: txn.origin) is None
+
+    with doc.begin_transaction() as txn:
+        assert txn.origin is None
+        with pytest.raises(Y.TransactionError):
+            doc.transact(callback, origin="batch")
+
+
+def test_batch():
+    doc = Y.YDoc()
+    array = doc.get_array("test")
+    events = []
+    array.observe(lambda e: events.append(e.delta))
+
+    with doc.batch() as txn:
+        array.append(txn, 1)
+        with doc.begin_transaction() as inner:
+            array.append(inner, 2)
+        doc.transact(lambda inner: array.append(inner, 3))
+        with doc.batch() as nested:
+            array.append(nested, 4)
+        assert events == []
+        assert doc.has_pending_transaction
+    assert events == [[{"insert": [1, 2, 3, 4]}]]
+    assert not doc.has_pending_transaction
+
+    # the batch is committed even if its block raises
+    with pytest.raises(ValueError):
+        with doc.batch() as txn:
+            array.append(txn, 5)
+            raise ValueError()
+    assert list(array) == [1, 2, 3, 4, 5]
+    assert not doc.has_pending_transaction
+
+    # a batch entered within a transaction leaves committing it to the transaction's owner
+    events.clear()
+    with doc.begin_transaction() as txn:
+        with doc.batch() as batched:
+            array.append(batched, 6)
+        assert events == []
+        array.append(txn, 7)
+    assert events == [[{"retain": 5}, {"insert": [6, 7]}]]
+    assert list(array) == [1, 2, 3, 4, 5, 6, 7]
@@ -1,5 +1,7 @@
+import json
 import unittest
 
+import pytest
 import y_py as Y
 from test_helper import exchange_updates
 
@@ -19,6 +21,25 @@ def test_insert():
     assert s == "<test><p>hello</p>world</test>"
 
 
+def test_xml_text_insert_index():
+    d1 = Y.YDoc()
+    text = d1.get_xml_text("test")
+    with d1.begin_transaction() as txn:
+        text.insert(txn, 0, "żółw")
+        text.insert(txn, len(text), "!")
+        text.insert(txn, -1, "?")
+        text.insert(txn, -len(text), ">")
+    assert str(text) == ">żółw?!"
+
+    length = len(text)
+    with d1.begin_transaction() as txn:
+        with pytest.raises(IndexError, match=f"valid range: -{length}..={length}"):
+            text.insert(txn, length + 1, "x")
+        with pytest.raises(IndexError):
+            text.insert(txn, -length - 1, "x")
+    assert str(text) == ">żółw?!"
+
+
 def test_attributes():
     d1 = Y.YDoc()
     root = d1.get_xml_element("test")
@@ -41,6 +62,20 @@ def test_attributes():
     assert actual == {"key1": None, "key2": "value2"}
 
 
+def test_attributes_options():
+    d1 = Y.YDoc()
+    root = d1.get_xml_element("test")
+    with d1.begin_transaction() as txn:
+        text = root.push_xml_text(txn)
+        for node in [root, text]:
+            for key in ["c", "a", "b"]:
+                node.set_attribute(txn, key, key.upper())
+    for node in [root, text]:
+        assert node.attributes(sort=True) == [("a", "A"), ("b", "B"), ("c", "C")]
+        assert node.attributes(as_dict=True) == {"a": "A", "b": "B", "c": "C"}
+        assert list(node.attributes(as_dict=True, sort=True)) == ["a", "b", "c"]
+
+
 def test_siblings():
     d1 = Y.YDoc()
     root = d1.get_xml_element("test")
@@ -79,6 +114,23 @@ def test_tree_walker():
     assert actual == expected
 
 
+def test_descendant_count():
+    d1 = Y.YDoc()
+    root = d1.get_xml_element("test")
+    assert root.descendant_count() == 0
+    with d1.begin_transaction() as txn:
+        p = root.push_xml_element(txn, "p")
+        p.push_xml_element(txn, "b").push_xml_text(txn).push(txn, "bold")
+        p.push_xml_text(txn).push(txn, "hello")
+        root.push_xml_text(txn).push(txn, "world")
+
+    assert root.descendant_count() == len(list(root.tree_walker())) == 5
+    assert root.descendant_count(depth=0) == 0
+    assert root.descendant_count(depth=1) == 2
+    assert root.descendant_count(depth=2) == 4
+    assert root.descendant_count(depth=3) == 5
+
+
 def test_xml_text_observer():
     d1 = Y.YDoc()
 
@@ -274,6 +326,11 @@ def test_deep_observe():
         container.first_child.push(txn, "nested")
 
     assert events != None
+    assert [e.kind for e in events] == ["xml_text"]
+
+    with ydoc.begin_transaction() as txn:
+        container.set_attribute(txn, "key", "value")
+    assert [e.kind for e in events] == ["xml_element"]
 
 
 def test_xml_fragment():
@@ -302,3 +359,104 @@ def test_xml_fragment():
     actual = [str(child) for child in fragment.tree_walker()]
     expected = ['<a key="value"></a>', "<c><d></d></c>", "<d></d>"]
     assert actual == expected
+
+
+def test_reads_in_observer():
+    doc = Y.YDoc()
+    element = doc.get_xml_element("element")
+    text = doc.get_xml_text("text")
+    seen = []
+    element.observe(lambda e: seen.append((len(e.target), e.target.get_attribute("a"))))
+    text.observe(lambda e: seen.append((len(e.target), str(e.target))))
+
+    with doc.begin_transaction() as txn:
+        element.set_attribute(txn, "a", "1")
+        element.push_xml_text(txn)
+        text.push(txn, "hi")
+    assert sorted(seen) == [(1, "1"), (2, "hi")]
+
+
+def test_to_xml():
+    d1 = Y.YDoc()
+    root = d1.get_xml_element("test")
+    with d1.begin_transaction() as txn:
+        root.set_attribute(txn, "b", "2")
+        root.set_attribute(txn, "a", "1")
+        p = root.push_xml_element(txn, "p")
+        p.push_xml_text(txn).push(txn, "hello")
+        root.push_xml_element(txn, "br")
+        root.push_xml_text(txn).push(txn, "world")
+
+    assert root.to_xml() == "\n".join(
+        [
+            '<test a="1" b="2">',
+            "  <p>",
+            "    hello",
+            "  </p>",
+            "  <br></br>",
+            "  world",
+            "</test>",
+        ]
+    )
+    assert root.to_xml(indent=0) == '<test a="1" b="2"><p>hello</p><br></br>world</test>'
+
+
+def test_to_xml_escaping():
+    d1 = Y.YDoc()
+    root = d1.get_xml_element("test")
+    with d1.begin_transaction() as txn:
+        root.set_attribute(txn, "title", 'say "a & b"')
+        text = root.push_xml_text(txn)
+        text.push(txn, 'a < b & "c" > d')
+
+    assert root.to_xml(indent=0) == (
+        '<test title="say &quot;a &amp; b&quot;">'
+        "a &lt; b &amp; &quot;c&quot; &gt; d"
+        "</test>"
+    )
+
+
+def test_nested_fragment():
+    d1 = Y.YDoc()
+    root = d1.get_xml_element("test")
+    with d1.begin_transaction() as txn:
+        root.push_xml_text(txn).push(txn, "a")
+        fragment = root.insert_xml_fragment(txn, 0)
+        fragment.push_xml_element(txn, "p")
+        fragment.push_xml_text(txn).push(txn, "b")
+        root.push_xml_fragment(txn)
+        with pytest.raises(IndexError):
+            root.insert_xml_fragment(txn, 10)
+
+    assert isinstance(root.first_child, Y.YXmlFragment)
+    assert len(root) == 3
+    assert str(root) == "<test><p></p>ba</test>"
+
+    d2 = Y.YDoc()
+    exchange_updates([d1, d2])
+    assert str(d2.get_xml_element("test")) == str(root)
+
+
+def test_to_json_include_xml():
+    d1 = Y.YDoc()
+    root = d1.get_xml_element("test")
+    fragment = d1.get_xml_fragment("fragment")
+    with d1.begin_transaction() as txn:
+        root.set_attribute(txn, "a", "1")
+        root.push_xml_element(txn, "p").push_xml_text(txn).push(txn, "hello")
+        fragment.push_xml_text(txn).push(txn, "text")
+
+    mixed = Y.YArray([1, Y.YMap({"xml": root}), fragment])
+    with pytest.raises(TypeError):
+        mixed.to_json()
+    assert json.loads(mixed.to_json(include_xml=True)) == [
+        1,
+        {
+            "xml": {
+                "tag": "test",
+                "attributes": {"a": "1"},
+                "children": [{"tag": "p", "attributes": {}, "children": ["hello"]}],
+            }
+        },
+        {"children": ["text"]},
+    ]
@@ -1,13 +1,42 @@
 from typing import (Any, Callable, Dict, Iterable, Iterator, List, Literal,
-                    Optional, Tuple, TypedDict, Union)
+                    Mapping, Optional, Set, Tuple, TypedDict, Union)
 
 class SubscriptionId:
     """
     Tracks an observer callback. Pass this to the `unobserve` method to cancel
     its associated callback.
+
+    Subscriptions returned by shared types can also be used as context managers,
+    which cancel the callback when the `with` block exits::
+
+        with text.observe(callback):
+            with doc.begin_transaction() as txn:
+                text.extend(txn, "observed")
+
+    Subscriptions are compared and hashed by their ids, which are unique among subscriptions of
+    the same shared type, so they can be stored in sets or used as dictionary keys.
+    """
+
+    def __enter__(self) -> SubscriptionId: ...
+    def __exit__(self, exc_type, exc_value, traceback) -> bool: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+
+class TransactionError(Exception):
+    """
+    Raised when a transaction is misused, e.g. when a new transaction is started while another one
+    is still pending, or when a committed transaction is used again.
     """
 
+# Events passed to `observe_deep` callbacks can be told apart by their `kind` attribute.
 Event = Union[YTextEvent, YArrayEvent, YMapEvent, YXmlTextEvent, YXmlElementEvent]
+# Changes reported by `observe_changes`, keyed by path of a changed shared type.
+Changes = Dict[str, Dict[Literal["delta", "keys"], Any]]
+
+# Kind of a value stored within a shared collection, as reported by `type_of`.
+ValueKind = Literal[
+    "primitive", "text", "array", "map", "xml_element", "xml_text", "xml_fragment", "doc"
+]
 
 class YDoc:
     """
@@ -32,16 +61,52 @@ class YDoc:
     """
 
     client_id: int
+    has_pending_transaction: bool
+    """True if this document has a transaction that hasn't been committed yet."""
+    auto_load: bool
+    """True if this document is loaded automatically by peers it's shared with as a subdocument."""
+    should_load: bool
+    """True if this document should be synchronized by providers now."""
     def __init__(
         self,
         client_id: Optional[int] = None,
         offset_kind: str = "utf8",
         skip_gc: bool = False,
+        auto_load: bool = False,
+        should_load: bool = True,
     ):
         """
         Creates a new Ypy document. If `client_id` parameter was passed it will be used as this
         document globally unique identifier (it's up to caller to ensure that requirement).
         Otherwise it will be assigned a randomly generated number.
+
+        `auto_load` and `should_load` are the subdocument loading flags used by providers: when
+        `auto_load` is set, peers load this document as soon as it's shared with them, while
+        `should_load` tells whether it should be synchronized now.
+        """
+    @staticmethod
+    def from_update(
+        update: YDocUpdate,
+        client_id: Optional[int] = None,
+        offset_kind: str = "utf8",
+        skip_gc: bool = False,
+        auto_load: bool = False,
+        should_load: bool = True,
+    ) -> YDoc:
+        """
+        Creates a new Ypy document configured with given options (see `YDoc()`) and applies an `update`
+        to it, e.g. to load a persisted document. Parts of the update depending on changes it doesn't
+        contain are kept pending (see `pending_update`).
+
+        Raises:
+            EncodingException: If the update is malformed.
+        """
+    def clients(self) -> Set[int]:
+        """
+        Returns:
+            A set of ids of all clients, whose changes (including deleted ones) are present in this
+            document's store, as recorded by its state vector. Ids of peers, whose updates are still
+            pending integration, are not included.
         """
     def begin_transaction(self) -> YTransaction:
         """
@@ -63,8 +128,57 @@ class YDoc:
                 text.insert(txn, 0, 'hello world')
 
         """
-    def transact(self, callback: Callable[[YTransaction]]): ...
-    def get_map(self, name: str) -> YMap:
+    def batch(self) -> YBatch:
+        """
+        Holds a single transaction open for the whole `with` block and commits it - firing observer
+        callbacks once for all the changes made within the block - when the block exits.
+
+        Batches are reentrant: a batch entered within another one (or while a transaction is already
+        open) shares its transaction and leaves committing it to the outermost batch (or whoever has
+        opened the transaction). Transactions
+        returned by `begin_transaction` and used by `transact` within a batch are the batched
+        transaction too, so their commits are deferred until the batch exits.
+
+        Example::
+
+            from y_py import YDoc
+
+            doc = YDoc()
+            array = doc.get_array("array")
+            with doc.batch() as txn:
+                for i in range(100):
+                    with doc.begin_transaction() as inner:
+                        array.append(inner, i)
+            # observers of `array` are called once here
+
+        Returns:
+            A context manager yielding the batched transaction.
+        """
+    def abort_transaction(self) -> bool:
+        """
+        Forcibly releases a transaction of this document which hasn't been committed, e.g. because
+        it has been leaked by a code path which never committed it. Rollbacks are not supported, so
+        changes made within that transaction are committed. Any further use of the released
+        transaction object raises an error.
+
+        Returns:
+            `True` if there was a transaction to release.
+        """
+    def transact(
+        self, callback: Callable[[YTransaction], Any], origin: Optional[str] = None
+    ) -> Any:
+        """
+        Runs a `callback` within a new transaction, which is committed once the callback returns.
+
+        Args:
+            callback: A function called with the transaction. Its result is returned by `transact`.
+            origin: If given, the transaction is tagged with it, so that the callback (see `YTransaction.origin`)
+                and observers of the changes it makes can read it.
+
+        Raises:
+            TransactionError: If an `origin` is provided while the document has a pending transaction.
+        """
+    def get_map(self, name: str, strict: bool = False) -> YMap:
         """
         Returns:
             A `YMap` shared data type, that's accessible for subsequent accesses using given `name`.
@@ -72,9 +186,13 @@ class YDoc:
         If there was no instance with this name before, it will be created and then returned.
 
         If there was an instance with this name, but it was of different type, it will be projected
-        onto `YMap` instance.
+        onto `YMap` instance, unless `strict` is `True`.
+
+        Raises:
+            TypeError: If `strict` is `True` and a root type called `name` is of a different kind (see
+                `root_type`).
         """
-    def get_xml_element(self, name: str) -> YXmlElement:
+    def get_xml_element(self, name: str, strict: bool = False) -> YXmlElement:
         """
         Returns:
             A `YXmlElement` shared data type, that's accessible for subsequent accesses using given `name`.
@@ -82,9 +200,13 @@ class YDoc:
         If there was no instance with this name before, it will be created and then returned.
 
         If there was an instance with this name, but it was of different type, it will be projected
-        onto `YXmlElement` instance.
+        onto `YXmlElement` instance, unless `strict` is `True`.
+
+        Raises:
+            TypeError: If `strict` is `True` and a root type called `name` is of a different kind (see
+                `root_type`).
         """
-    def get_xml_text(self, name: str) -> YXmlText:
+    def get_xml_text(self, name: str, strict: bool = False) -> YXmlText:
         """
         Returns:
             A `YXmlText` shared data type, that's accessible for subsequent accesses using given `name`.
@@ -92,9 +214,13 @@ class YDoc:
         If there was no instance with this name before, it will be created and then returned.
 
         If there was an instance with this name, but it was of different type, it will be projected
-        onto `YXmlText` instance.
+        onto `YXmlText` instance, unless `strict` is `True`.
+
+        Raises:
+            TypeError: If `strict` is `True` and a root type called `name` is of a different kind (see
+                `root_type`).
         """
-    def get_xml_fragment(self, name: str) -> YXmlFragment:
+    def get_xml_fragment(self, name: str, strict: bool = False) -> YXmlFragment:
         """
         Returns:
             A `YXmlFragment` shared data type, that's accessible for subsequent accesses using given `name`.
@@ -102,9 +228,13 @@ class YDoc:
         If there was no instance with this name before, it will be created and then returned.
 
         If there was an instance with this name, but it was of different type, it will be projected
-        onto `YXmlFragment` instance.
+        onto `YXmlFragment` instance, unless `strict` is `True`.
+
+        Raises:
+            TypeError: If `strict` is `True` and a root type called `name` is of a different kind (see
+                `root_type`).
         """
-    def get_array(self, name: str) -> YArray:
+    def get_array(self, name: str, strict: bool = False) -> YArray:
         """
         Returns:
             A `YArray` shared data type, that's accessible for subsequent accesses using given `name`.
@@ -112,9 +242,13 @@ class YDoc:
         If there was no instance with this name before, it will be created and then returned.
 
         If there was an instance with this name, but it was of different type, it will be projected
-        onto `YArray` instance.
+        onto `YArray` instance, unless `strict` is `True`.
+
+        Raises:
+            TypeError: If `strict` is `True` and a root type called `name` is of a different kind (see
+                `root_type`).
         """
-    def get_text(self, name: str) -> YText:
+    def get_text(self, name: str, strict: bool = False) -> YText:
         """
 
         Args:
@@ -124,10 +258,252 @@ class YDoc:
 
         If there was no instance with this name before, it will be created and then returned.
         If there was an instance with this name, but it was of different type, it will be projected
-        onto `YText` instance.
+        onto `YText` instance, unless `strict` is `True`.
+
+        Raises:
+            TypeError: If `strict` is `True` and a root type called `name` is of a different kind (see
+                `root_type`).
+        """
+    def root_type(self, name: str) -> Optional[str]:
+        """
+        Checks the kind of a root type without creating it, e.g. to detect that another peer has created
+        a root of the same name but a different kind, which the `get_*` methods would silently project.
+
+        Returns:
+            The kind of the root type called `name`, as reported by `YArray.type_of`, or `None` if it doesn't
+            exist. Roots introduced by updates from remote peers are reported as `"undefined"` until they're
+            fetched with one of the `get_*` methods, since updates don't carry the kinds of root types.
+        """
+    def integrate(self, name: str, prelim: Union[YText, YArray, YMap]) -> Union[YText, YArray, YMap]:
+        """
+        Attaches a preliminary `YText`, `YArray` or `YMap` to this document as a new root type called
+        `name`, copying its contents in a transaction of its own. Like inserting it into another shared
+        type, this integrates the `prelim` object in place.
+
+        Raises a `ValueError` if a root type called `name` already exists, a `TypeError` if `prelim`
+        isn't a shared type and a `MultipleIntegrationError` if it's already integrated.
+
+        Example::
+
+            from y_py import YDoc, YArray
+
+            template = YArray([1, 2, 3])
+            doc = YDoc()
+            array = doc.integrate("array", template)
+            assert array is template and not array.prelim
+            assert doc.get_array("array").to_json() == "[1,2,3]"
+
+        Returns:
+            The integrated `prelim` object.
+        """
+    def encode_state_gc(self) -> bytes:
+        """
+        Encodes the whole state of the document into a single update using lib0 v1 encoding, with
+        the content of deleted items replaced by lightweight tombstones. Unlike `compact`, the
+        result keeps the history of the document, so it can be merged with updates of other peers.
+
+        Documents created with `skip_gc=False` (the default) collect deleted content whenever a
+        transaction commits, so for them the result matches `encode_state_as_update`. Documents
+        created with `skip_gc=True` keep their deleted content, so that past states can be
+        restored from snapshots. This method lets them be persisted without it, while the document
+        itself is left untouched - a document restored from the result can't be used with
+        snapshots taken before.
+
+        Deleted content can't be garbage collected in place, as shared types and observers of the
+        document keep pointing into its store. To compact a document before persisting it, persist
+        the result of this method instead and restore the document from it later on.
+
+        Returns:
+            The garbage collected state of the document.
+        """
+    def snapshot(self) -> EncodedSnapshot:
+        """
+        Captures the current state of this document. Only available for documents created with
+        `skip_gc=True`.
+
+        Returns:
+            A binary snapshot that can be used to read the document as it was at the time of capture.
+
+        Example::
+
+            from y_py import YDoc
+
+            doc = YDoc(skip_gc=True)
+            text = doc.get_text('name')
+            with doc.begin_transaction() as txn:
+                text.extend(txn, 'hello')
+            snapshot = doc.snapshot()
+            with doc.begin_transaction() as txn:
+                text.extend(txn, ' world')
+            assert text.to_string_at(snapshot) == 'hello'
+        """
+    def encode_state_from_snapshot(self, snapshot: EncodedSnapshot) -> YDocUpdate:
+        """
+        Encodes the state of this document at the time given `snapshot` was captured. Only
+        available for documents created with `skip_gc=True`.
+
+        Returns:
+            An update payload (lib0 v1 encoding) which recreates the historical document state when
+            applied to an empty document.
+        """
+    def apply_update_collecting_events(self, update: YDocUpdate) -> List[Dict[str, Any]]:
+        """
+        Applies an `update` (lib0 v1 encoding) in a transaction of its own and collects all events it
+        triggered on root types which existed before the update was applied, as well as on their nested
+        shared types. Observers are only registered for the time of applying the update, which makes
+        this method convenient for testing.
+
+        Returns:
+            A list of events, each serialized into a dictionary with the `root` type name, the `path`
+            to the changed shared type within it and `delta` and/or `keys` describing the changes,
+            following the format of the corresponding event type.
+
+        Raises:
+            TransactionError: If the document has a pending transaction.
+        """
+    def pending_update(self) -> Optional[YDocUpdate]:
+        """
+        Returns:
+            The part of updates applied to this document which couldn't be integrated yet, because
+            the updates it depends on haven't been applied (lib0 v1 encoding). Pending content is
+            integrated automatically once the missing updates are applied. `None` if all applied
+            updates have been integrated.
+        """
+    def has_pending(self) -> bool:
+        """
+        Returns:
+            `True` if some of the updates applied to this document couldn't be integrated yet, because
+            the updates they depend on haven't been applied.
+        """
+    def missing_update_ranges(self) -> Dict[int, Tuple[int, int]]:
+        """
+        Describes gaps which prevent the pending update of this document from being integrated.
+
+        Returns:
+            A dictionary mapping client ids to `(start, end)` clock ranges, where `start` is the clock
+            of the next update expected from that client and `end` is the clock right after the last
+            pending update of that client. Updates within that range have to be applied (some of them
+            may already be pending) before the pending update can be integrated.
+        """
+    def to_json(
+        self, include_xml: bool = False, sort_keys: bool = False
+    ) -> str:
+        """
+        Converts the contents of all root types of this document into a single JSON object keyed by
+        root type names. Texts are serialized as strings, arrays as lists and maps as objects. Root types
+        received from remote updates are left out until they're fetched with one of the `get_*` methods.
+
+        Args:
+            include_xml: If `True`, XML nodes are serialized as `{"tag", "attributes", "children"}` objects
+                (fragments only have `children`, text nodes become strings). Otherwise they are stringified.
+            sort_keys: If `True`, keys of all maps are emitted in sorted order, making the output deterministic.
+        """
+    def walk(self) -> List[Tuple[List[Union[str, int]], Any]]:
+        """
+        Traverses all root types of this document and collects their leaf values. Nested maps and arrays,
+        both shared types and plain dicts and lists, are descended into. Texts and XML nodes are reported
+        as their string representation. Root types and map keys are visited in sorted order. Root types
+        received from remote updates are skipped until they're fetched with one of the `get_*` methods.
+
+        Example::
+
+            from y_py import YDoc
+
+            doc = YDoc()
+            with doc.begin_transaction() as txn:
+                doc.get_map("map").set(txn, "tags", ["a", "b"])
+            assert doc.walk() == [(["map", "tags", 0], "a"), (["map", "tags", 1], "b")]
+
+        Returns:
+            A list of `(path, value)` pairs, where `path` starts with a root type name followed by the keys
+            and indexes leading to a leaf `value`.
+        """
+    def get_path(self, path: List[Union[str, int]], default: Any = None) -> Any:
+        """
+        Retrieves a value stored under a given `path`: a root type name followed by map keys (strings)
+        and array indexes (integers, negative ones are counted from the end), the same kind of paths as
+        returned by `walk`. Raises a `TypeError` if a segment doesn't match the kind of value it's
+        applied to. A root type received from a remote update, which hasn't been fetched with one of the
+        `get_*` methods yet, can only be looked into, as its own kind is still undefined.
+
+        Example::
+
+            from y_py import YDoc, YMap
+
+            doc = YDoc()
+            settings = doc.get_map("settings")
+            with doc.begin_transaction() as txn:
+                settings.set(txn, "editor", YMap({"theme": "dark"}))
+            assert doc.get_path(["settings", "editor", "theme"]) == "dark"
+            assert doc.get_path(["settings", "font", "size"], 12) == 12
+
+        Returns:
+            The value stored under `path` (nested shared types are returned as `YMap`, `YArray` etc.) or
+            `default` if there's nothing stored there.
+        """
+    def set_path(
+        self, txn: YTransaction, path: List[Union[str, int]], value: Any
+    ) -> Optional[Union[YText, YArray, YMap]]:
+        """
+        Stores a `value` under a given `path` (see `get_path`). The root type has to exist already, while
+        missing intermediate maps are created along the way. The last segment is either a map key to set
+        or an array index to replace - an index equal to the length of the array appends the `value`
+        instead. Raises a `KeyError` or an `IndexError` if an intermediate array entry is missing, since
+        arrays are never created implicitly.
+
+        Example::
+
+            from y_py import YDoc
+
+            doc = YDoc()
+            doc.get_map("settings")
+            with doc.begin_transaction() as txn:
+                doc.set_path(txn, ["settings", "editor", "theme"], "dark")
+            assert doc.get_path(["settings", "editor", "theme"]) == "dark"
+
+        Returns:
+            The integrated shared type if `value` was a preliminary one, `None` otherwise.
+        """
+    def compact(self) -> bytes:
+        """
+        Re-encodes the currently visible contents of this document into a single, minimal update.
+        Deleted content and editing history are dropped, and the contents are written by a freshly
+        generated client id.
+
+        The result shares no history with this document: applying it to a peer which already holds
+        this document's updates duplicates the contents instead of merging them. Use it to bootstrap
+        new documents, not to synchronize existing peers incrementally.
+
+        Raises a `ValueError` if the document holds a root type received from a remote update, which
+        hasn't been fetched with one of the `get_*` methods yet, since its kind is unknown. The same
+        goes for documents holding subdocuments or texts with embedded shared types, which can't be
+        re-created.
+
+        Returns:
+            An update encoded using lib0 v1 encoding, which recreates current contents of this document
+            when applied to an empty `YDoc`.
+        """
+    def encode_roots_as_update(
+        self, names: List[str], vector: Optional[EncodedStateVector] = None
+    ) -> bytes:
+        """
+        Works like `encode_state_as_update`, but only includes changes made to the root types with
+        given `names`, including the shared types nested in them.
+
+        Changes of other roots are replaced with garbage collected placeholders, so that the
+        sequence of changes made by each client stays unbroken. A peer that applies the result can
+        never receive these changes afterwards: it has to keep synchronizing the same set of roots.
+        Deletions of all roots are included.
+
+        Args:
+            names: The names of root types to include.
+            vector: The state vector of the remote peer. If omitted, all changes are included.
+
+        Returns:
+            A delta update encoded using lib0 v1 encoding.
         """
     def observe_after_transaction(
-        self, callback: Callable[[AfterTransactionEvent]]
+        self, callback: Callable[[AfterTransactionEvent]], skip_empty: bool = False
     ) -> SubscriptionId:
         """
         Subscribe callback function to updates on the YDoc. The callback will receive encoded state updates and
@@ -135,13 +511,41 @@ class YDoc:
 
         Args:
             callback: A function that receives YDoc state information affected by the transaction.
+            skip_empty: If `True`, the callback isn't called for transactions which haven't changed the
+                document, e.g. ones started only to read its contents.
 
         Returns:
             A subscription identifier that can be used to cancel the callback.
         """
+    def observe_destroy(self, callback: Callable[[YDoc]]) -> SubscriptionId:
+        """
+        Subscribe callback function to be called when the YDoc is destroyed.
+
+        Args:
+            callback: A function that receives the destroyed document.
+
+        Returns:
+            A subscription identifier of the callback.
+        """
+    def destroy(self):
+        """
+        Destroys the YDoc together with all of its subdocuments and triggers callbacks registered
+        with `observe_destroy`. All other event subscriptions of the document are dropped.
+        A destroyed document should no longer be used.
+
+        Destroying is meant for subdocuments, which get replaced with a fresh, unloaded document
+        within their parent. A root document has nothing to be removed from, so destroying it only
+        triggers the callbacks of itself and its subdocuments.
+
+        Raises:
+            TransactionError: If the document or its parent document has a pending transaction.
+        """
 
+# Encoded payloads are always returned as immutable `bytes` (never `bytearray`), so they can be
+# hashed and used as dictionary keys.
 EncodedStateVector = bytes
 EncodedDeleteSet = bytes
+EncodedSnapshot = bytes
 YDocUpdate = bytes
 
 class AfterTransactionEvent:
@@ -161,6 +565,10 @@ class AfterTransactionEvent:
     """
     Elements deleted by the associated transaction.
     """
+    origin: Optional[str]
+    """
+    The origin of the transaction, `None` if it has no origin.
+    """
 
     def get_update(self) -> YDocUpdate:
         """
@@ -168,12 +576,15 @@ class AfterTransactionEvent:
             Encoded payload of all updates produced by the transaction.
         """
 
-def encode_state_vector(doc: YDoc) -> EncodedStateVector:
+def encode_state_vector(
+    doc: YDoc, txn: Optional[YTransaction] = None
+) -> EncodedStateVector:
     """
     Encodes a state vector of a given Ypy document into its binary representation using lib0 v1
     encoding. State vector is a compact representation of updates performed on a given document and
     can be used by `encode_state_as_update` on remote peer to generate a delta update payload to
-    synchronize changes between peers.
+    synchronize changes between peers. If `txn` is provided, the state is read through it instead
+    of the document's current transaction.
 
     Example::
 
@@ -192,13 +603,18 @@ def encode_state_vector(doc: YDoc) -> EncodedStateVector:
     """
 
 def encode_state_as_update(
-    doc: YDoc, vector: Optional[Union[EncodedStateVector, List[int]]] = None
-) -> YDocUpdate:
+    doc: YDoc,
+    vector: Optional[Union[EncodedStateVector, List[int]]] = None,
+    txn: Optional[YTransaction] = None,
+    copy: bool = True,
+) -> Union[YDocUpdate, UpdateBuffer]:
     """
     Encodes all updates that have happened since a given version `vector` into a compact delta
     representation using lib0 v1 encoding. If `vector` parameter has not been provided, generated
     delta payload will contain all changes of a current Ypy document, working effectively as its
-    state snapshot.
+    state snapshot. If `txn` is provided, the state is read through it instead of the document's
+    current transaction. If `copy` is `False`, an `UpdateBuffer` is returned instead of `bytes`
+    (see `YTransaction.diff_v1`).
 
     Example::
 
@@ -215,10 +631,24 @@ def encode_state_as_update(
         apply_update(local_doc, remote_delta)
     """
 
-def apply_update(doc: YDoc, diff: Union[YDocUpdate, List[int]]):
+def apply_update(
+    doc: YDoc,
+    diff: Union[YDocUpdate, List[int]],
+    txn: Optional[YTransaction] = None,
+    origin: Optional[str] = None,
+) -> bool:
     """
     Applies delta update generated by the remote document replica to a current document. This
-    method assumes that a payload maintains lib0 v1 encoding format.
+    method assumes that a payload maintains lib0 v1 encoding format. If `txn` is provided, the
+    update is applied within it instead of the document's current transaction.
+
+    If an `origin` is provided, the update is applied in a new transaction tagged with it, so that
+    observers can recognize the changes (see the `origin` of events), e.g. to avoid sending them back
+    to the peer they came from. An origin can't be combined with `txn`.
+
+    Returns `True` if the update has been fully integrated. Otherwise some of the updates it depends
+    on haven't been applied yet, so its remaining content is kept pending until they arrive (see
+    `YDoc.pending_update`).
 
     Example::
 
@@ -233,7 +663,161 @@ def apply_update(doc: YDoc, diff: Union[YDocUpdate, List[int]]):
         remote_delta = encode_state_as_update(remote_doc, local_sv)
 
         apply_update(local_doc, remote_delta)
+
+    Raises:
+        ValueError: If both `txn` and `origin` are provided.
+        TransactionError: If an `origin` is provided while the document has a pending transaction.
+    """
+
+def encode_state_as_update_b64(
+    doc: YDoc,
+    vector: Optional[Union[EncodedStateVector, List[int]]] = None,
+    txn: Optional[YTransaction] = None,
+) -> str:
+    """
+    Works like `encode_state_as_update`, but returns the update as a base64-encoded `str`, which can
+    be sent over transports unable to carry raw bytes (e.g. JSON).
+    """
+
+def apply_update_b64(
+    doc: YDoc,
+    diff: str,
+    txn: Optional[YTransaction] = None,
+    origin: Optional[str] = None,
+) -> bool:
+    """
+    Works like `apply_update`, but accepts the update as a base64-encoded `str`, as produced by
+    `encode_state_as_update_b64`.
+
+    Raises:
+        ValueError: If `diff` isn't valid base64.
+    """
+
+def diff_docs(old_doc: YDoc, new_doc: YDoc) -> YDocUpdate:
+    """
+    Computes a delta update which, once applied to `old_doc`, brings it to the state of `new_doc`.
+    Both documents are expected to be versions of the same logical document, where `new_doc` has
+    seen every change of `old_doc`.
+
+    Example::
+
+        from y_py import YDoc, apply_update, diff_docs, encode_state_as_update
+
+        old_doc = YDoc()
+        new_doc = YDoc()
+        apply_update(new_doc, encode_state_as_update(old_doc))
+        # ... modify new_doc
+
+        apply_update(old_doc, diff_docs(old_doc, new_doc))
+
+    Raises:
+        ValueError: If `old_doc` contains changes which `new_doc` hasn't seen.
+    """
+
+def docs_equal(a: YDoc, b: YDoc) -> bool:
+    """
+    Checks if two documents are in sync. Unlike comparing encoded updates, this doesn't depend on client
+    ids or the order in which changes were applied.
+
+    Example::
+
+        from y_py import YDoc, apply_update, docs_equal, encode_state_as_update
+
+        a = YDoc()
+        b = YDoc()
+        # ... modify a
+        apply_update(b, encode_state_as_update(a))
+        assert docs_equal(a, b)
+
+    Returns:
+        `True` only if both documents have seen exactly the same changes (their state vectors are equal) and
+        the visible contents of all their root types, including XML nodes, are the same. Formatting attributes
+        of texts aren't compared.
+    """
+
+class UpdateInfo(TypedDict):
+    byte_size: int
+    struct_count: int
+    client_count: int
+
+def update_info(update: YDocUpdate) -> UpdateInfo:
+    """
+    Describes an update without applying it to a document, e.g. so that a relay can enforce size limits
+    or collect telemetry before forwarding updates.
+
+    Example::
+
+        from y_py import YDoc, encode_state_as_update, update_info
+
+        doc = YDoc()
+        with doc.begin_transaction() as txn:
+            doc.get_text("text").extend(txn, "hello")
+        info = update_info(encode_state_as_update(doc))
+        assert info["client_count"] == 1 and info["struct_count"] == 1
+
+    Returns:
+        A dictionary with the `byte_size` of the update, the number of structs (blocks of inserted, deleted
+        or garbage collected content) it carries as `struct_count` and the number of clients, whose changes
+        it contains, as `client_count`.
+
+    Raises:
+        EncodingException: If the update is malformed.
+    """
+
+def validate_update(update: YDocUpdate) -> bool:
+    """
+    Checks whether an update is well-formed by decoding it without applying it to a document, e.g. so that
+    payloads of untrusted clients can be rejected before they are persisted. Only the structure of the update
+    is checked: a well-formed update may still depend on changes that the document it's applied to doesn't
+    have yet.
+
+    Returns:
+        `True` if the update could be decoded, `False` otherwise. Malformed updates never raise.
+    """
+
+def register_encoder(type: type, encoder: Callable[[Any], Any]):
+    """
+    Registers an `encoder` for values of a given `type` (including its subclasses), which otherwise
+    couldn't be stored in a shared type. The encoder must return a value that can be stored: a `bool`,
+    `int`, `float`, `str`, `None`, `list`, `dict` or a shared type. Registering another encoder for
+    the same type replaces the previous one.
+
+    Values are stored as their encoded primitive form, so reading them back returns that form unless
+    a decoder was registered with `register_decoder`. Registered encoders are local to the thread
+    that registered them.
+
+    Example::
+
+        from decimal import Decimal
+        from y_py import YDoc, register_encoder
+
+        register_encoder(Decimal, str)
+        doc = YDoc()
+        map = doc.get_map("prices")
+        with doc.begin_transaction() as txn:
+            map.set(txn, "total", Decimal("9.99"))
+        assert map["total"] == "9.99"
+
+    Raises:
+        TypeError: When storing a value, if its encoder returns a value that cannot be stored.
+    """
+
+def register_decoder(decoder: Callable[[Any], Any]):
+    """
+    Registers a `decoder` applied to values read back from shared types. Decoders are called with
+    every primitive value read (nested values before the collections containing them) and should
+    return values they don't recognize unchanged. When multiple decoders are registered, each one
+    receives the result of the previous one. An exception raised by a decoder is propagated to the
+    code reading the value. Registered decoders are local to the thread that registered them.
+    """
+
+class YBatch:
     """
+    Context manager returned by `YDoc.batch`.
+    """
+
+    def __enter__(self) -> YTransaction: ...
+    def __exit__(self, exception_type, exception_value, exception_traceback) -> bool: ...
 
 class YTransaction:
     """
@@ -254,6 +838,8 @@ class YTransaction:
     """
 
     before_state: Dict[int, int]
+    origin: Optional[str]
+    """The origin this transaction has been started with, `None` if it has no origin."""
 
     def get_text(self, name: str) -> YText:
         """
@@ -319,13 +905,18 @@ class YTransaction:
                 del remote_txn
 
         """
-    def diff_v1(self, vector: Optional[EncodedStateVector] = None) -> YDocUpdate:
+    def diff_v1(
+        self, vector: Optional[EncodedStateVector] = None, copy: bool = True
+    ) -> Union[YDocUpdate, UpdateBuffer]:
         """
         Encodes all updates that have happened since a given version `vector` into a compact delta
         representation using lib0 v1 encoding. If `vector` parameter has not been provided, generated
         delta payload will contain all changes of a current Ypy document, working effectively as
         its state snapshot.
 
+        If `copy` is `False`, an `UpdateBuffer` sharing memory with the encoder is returned instead of
+        `bytes`. This avoids copying the payload, which halves peak memory use when encoding large documents.
+
         Example::
 
             from y_py import YDoc
@@ -346,11 +937,33 @@ class YTransaction:
                 del local_txn
                 del remote_txn
         """
-    def apply_v1(self, diff: YDocUpdate):
+    def diff_since(self, other: YDoc) -> YDocUpdate:
+        """
+        Encodes all updates of the transaction's document which `other` document hasn't seen yet,
+        using lib0 v1 encoding. Equivalent to diffing against the state vector of `other`.
+
+        Example::
+
+            from y_py import YDoc
+
+            local_doc = YDoc()
+            remote_doc = YDoc()
+
+            with remote_doc.begin_transaction() as remote_txn:
+                remote_delta = remote_txn.diff_since(local_doc)
+            with local_doc.begin_transaction() as local_txn:
+                local_txn.apply_v1(remote_delta)
+        """
+    def apply_v1(self, diff: YDocUpdate) -> bool:
         """
         Applies delta update generated by the remote document replica to a current transaction's
         document. This method assumes that a payload maintains lib0 v1 encoding format.
 
+        Returns:
+            `True` if the update has been fully integrated. Otherwise some of the updates it depends on
+            haven't been applied yet, so its remaining content is kept pending until they arrive (see
+            `YDoc.pending_update`).
+
         Example::
 
             from y_py import YDoc
@@ -392,7 +1005,15 @@ class YText:
     """
 
     prelim: bool
-    """True if this element has not been integrated into a YDoc."""
+    """
+    True if this element has not been integrated into a YDoc. Inserting a preliminary instance into a YDoc
+    integrates that very object in place, so it can be used to keep editing the inserted data.
+    """
+    parent: Optional[Union[YText, YArray, YMap, YXmlElement, YXmlText, YXmlFragment]]
+    """
+    The shared type directly containing this `YText`, or `None` for root types, preliminary instances and
+    instances which have been removed from their document.
+    """
 
     def __init__(self, init: str = ""):
         """
@@ -403,6 +1024,49 @@ class YText:
         Once a preliminary instance has been inserted this way, it becomes integrated into Ypy
         document store and cannot be nested again: attempt to do so will result in an exception.
         """
+    def is_alive(self) -> bool:
+        """
+        Returns:
+            `False` if this `YText` has been removed from its document, either directly or along with
+            one of its ancestors. Removed types can no longer be modified.
+        """
+    def is_deleted(self) -> bool:
+        """
+        Returns:
+            `True` if this `YText` has been removed from its document. See `is_alive`.
+        """
+    def id(self) -> Optional[Union[str, Tuple[int, int]]]:
+        """
+        Returns:
+            An identifier of this `YText` which is the same on every peer: the name of a root type, or a
+            `(client, clock)` pair identifying the update which has created a nested one.
+            Preliminary instances return `None`.
+        """
+    def __copy__(self) -> YText:
+        """
+        Returns a shallow copy of this preliminary `YText`.
+
+        Raises:
+            TypeError: If this instance is integrated, as it's bound to its `YDoc`.
+        """
+    def __deepcopy__(self, memo: Dict[int, Any]) -> YText:
+        """
+        Returns a copy of this preliminary `YText`.
+
+        Raises:
+            TypeError: If this instance is integrated, as it's bound to its `YDoc`.
+        """
+    def path(self) -> Optional[List[Union[int, str]]]:
+        """
+        Returns:
+            Array of keys and indexes creating a path from the root type of a document down to this `YText`, in the
+            same format as event paths, or `None` if it has been removed from its document. The path is built by
+            following parent links, so its cost depends on how deeply this `YText` is nested rather than on the size
+            of the document.
+
+        Raises:
+            IntegratedOperationException: If this `YText` is preliminary.
+        """
     def __str__(self) -> str:
         """
         Returns:
@@ -411,72 +1075,253 @@ class YText:
     def __repr__(self) -> str:
         """
         Returns:
-            The string representation wrapped in 'YText()'
+            The string representation wrapped in 'YText()'. Only the first 100 characters are shown,
+            followed by an ellipsis if the text is longer. Use `str` to get the whole text.
         """
     def __len__(self) -> int:
         """
         Returns:
-            The length of an underlying string stored in this `YText` instance, understood as a number of UTF-8 encoded bytes.
+            The length of an underlying string stored in this `YText` instance, measured in the offsets used by its
+            document: a number of UTF-8 encoded bytes unless the document was created with another `offset_kind`.
+            It's read without starting a transaction.
+        """
+    def length(self, txn: Optional[YTransaction] = None) -> int:
+        """
+        Returns:
+            The length of this `YText` in the offsets used by its document, optionally read through a given `txn`.
+
+        Raises:
+            TransactionError: If `txn` has already been committed.
+        """
+    char_len: int
+    """
+    The length of an underlying string stored in this `YText` instance, understood as a number of
+    Unicode characters. Unlike `__len__`, it matches the length of the equivalent Python `str`.
+    """
+    def byte_to_char_index(self, index: int) -> int:
+        """
+        Converts a UTF-8 byte offset (as used by `__len__`, `insert` and `delete`) into an offset
+        counted in Unicode characters, usable to index the equivalent Python `str`.
+
+        Raises:
+            IndexError: If `index` is greater than the byte length of the text.
+            ValueError: If `index` points into the middle of a multi-byte character, or if the document of this
+                text doesn't use UTF-8 offsets.
+        """
+    def char_to_byte_index(self, index: int) -> int:
+        """
+        Converts an offset counted in Unicode characters into a UTF-8 byte offset, which can be
+        passed to `insert` and `delete` methods.
+
+        Raises:
+            IndexError: If `index` is greater than the number of characters in the text.
+            ValueError: If the document of this text doesn't use UTF-8 offsets.
+        """
+    utf16_len: int
+    """
+    The length of an underlying string stored in this `YText` instance, understood as a number of
+    UTF-16 code units. This is the length JavaScript peers see, and the same as `len` for documents
+    created with `offset_kind="utf16"`.
+    """
+    def char_to_utf16_index(self, index: int) -> int:
+        """
+        Converts an offset counted in Unicode characters (as used to index Python `str`) into an offset
+        counted in UTF-16 code units, as used by JavaScript strings. In documents created with
+        `offset_kind="utf16"`, the result can be passed to `insert` and `delete` methods.
+
+        Raises:
+            IndexError: If `index` is greater than the number of characters in the text.
+        """
+    def utf16_to_char_index(self, index: int) -> int:
+        """
+        Converts an offset counted in UTF-16 code units (as used by JavaScript strings, and by `insert` and
+        `delete` in documents created with `offset_kind="utf16"`) into an offset counted in Unicode characters,
+        which can be used to index the equivalent Python `str`.
+
+        Raises:
+            IndexError: If `index` is greater than `utf16_len`.
+            ValueError: If `index` points into the middle of a surrogate pair.
+        """
+    def find(
+        self, substring: str, start: int = 0, chars: bool = False
+    ) -> Optional[int]:
+        """
+        Searches this text for `substring` without materializing it as a Python `str`.
+
+        Args:
+            substring: The text to look for.
+            start: The offset to start searching from.
+            chars: If `True`, `start` and the returned offset are counted in Unicode characters, like indexes
+                of the equivalent Python `str`. Otherwise they are counted in UTF-8 bytes, as used by `insert`
+                and `delete`.
+
+        Returns:
+            The offset of the first occurrence of `substring` at or after `start`, or `None` if there is none.
+
+        Raises:
+            ValueError: If `start` is a byte offset pointing into the middle of a multi-byte character.
+        """
+    def find_all(self, substring: str, chars: bool = False) -> List[int]:
+        """
+        Returns:
+            Offsets of all non-overlapping occurrences of `substring` in ascending order, counted in Unicode
+            characters if `chars` is `True` or in UTF-8 bytes otherwise.
+        """
+    def substring(self, start: int, end: Optional[int] = None, chars: bool = False) -> str:
+        """
+        Reads a part of the text without materializing the whole string first, which is useful for
+        rendering a visible window of a large document. Offsets are counted in Unicode characters if
+        `chars` is `True` or in UTF-8 bytes otherwise, and are clamped to the length of the text like
+        Python slices. Raises a `ValueError` if a byte offset doesn't lie on a character boundary.
+
+        Returns:
+            The part of the text between `start` (inclusive) and `end` (exclusive, the end of the text by
+            default). Embeds aren't part of the returned string.
+        """
+    def embeds(self) -> List[Tuple[int, Any]]:
+        """
+        Returns:
+            A list of `(index, value)` pairs for every object embedded with `insert_embed`, ordered by
+            their position. Indexes are measured in the same unit as the ones accepted by `insert`.
+            Preliminary instances never contain embeds.
+        """
+    def chunks(self) -> List[YTextChangeInsert]:
+        """
+        Shows how the text is split by formatting, e.g. to test where formatting boundaries fall.
+        Neighbouring characters sharing the same attributes are merged into a single run, so the result
+        doesn't necessarily match the blocks the text is stored in internally.
+
+        Returns:
+            The contents of this `YText` as a list of contiguous runs of text or embeds, in the same format
+            as the `delta` of a `YTextEvent` inserting them. Runs without formatting have no `attributes`.
         """
     def to_json(self) -> str:
         """
         Returns:
             The underlying shared string stored in this data type.
         """
+    def to_string_at(self, snapshot: EncodedSnapshot) -> str:
+        """
+        Returns:
+            The string stored in this `YText` instance at the time the `snapshot` was captured with
+            `YDoc.snapshot`. Requires a document created with `skip_gc=True`.
+
+        Raises:
+            TransactionError: If called from an observer callback. Reading a past state splits blocks
+                at the boundaries of the snapshot, which can't be done while a transaction is being
+                committed.
+        """
     def insert(
         self,
         txn: YTransaction,
         index: int,
         chunk: str,
         attributes: Dict[str, Any] = {},
-    ):
+        sticky_index: bool = False,
+    ) -> Union[int, YStickyIndex]:
         """
         Inserts a string of text into the `YText` instance starting at a given `index`.
+        The `index` is counted in UTF-8 bytes (see `char_to_byte_index`). Negative indexes are
+        counted from the end of the text, like with `list.insert`.
+        Returns the length of the text after insertion or, if `sticky_index` is `True`, a `YStickyIndex`
+        pointing right after the inserted chunk, which keeps track of that position under later edits.
         Attributes are optional style modifiers (`{"bold": True}`) that can be attached to the inserted string.
         Attributes are only supported for a `YText` instance which already has been integrated into document store.
         """
+    def replace(
+        self,
+        txn: YTransaction,
+        index: int,
+        length: int,
+        chunk: str,
+        attributes: Dict[str, Any] = {},
+    ) -> int:
+        """
+        Replaces `length` bytes of text starting at a given `index` with a new `chunk` of text.
+        Deletion and insertion happen within the same transaction, so observers only see the final result.
+        If no `attributes` are provided, the new text picks up the formatting of the surrounding text.
+        Attributes are only supported for a `YText` instance which already has been integrated into document store.
+
+        Returns:
+            The length of the text after replacement.
+
+        Raises:
+            IndexError: If the replaced range exceeds the length of the text.
+        """
     def insert_embed(
         self,
         txn: YTransaction,
         index: int,
         embed: Any,
         attributes: Dict[str, Any] = {},
-    ):
+        sticky_index: bool = False,
+    ) -> Union[int, YStickyIndex]:
         """
         Inserts embedded content into the YText at the provided index. Attributes are user-defined metadata associated with the embedded content.
         Attributes are only supported for a `YText` instance which already has been integrated into document store.
+
+        Returns:
+            The length of the text after insertion or, if `sticky_index` is `True`, a `YStickyIndex` attached to
+            the inserted embed. It keeps pointing at the embed while other edits shift its position, so that
+            the embed can be found (e.g. to `format` it) later on.
         """
     def format(
-        self, txn: YTransaction, index: int, length: int, attributes: Dict[str, Any]
-    ):
+        self,
+        txn: YTransaction,
+        index: int,
+        length: int,
+        attributes: Dict[str, Any],
+        previous: bool = False,
+    ) -> Optional[List[Tuple[int, int, Dict[str, Any]]]]:
         """
         Wraps an existing piece of text within a range described by `index`-`length` parameters with
         formatting blocks containing provided `attributes` metadata. This method only works for
         `YText` instances that already have been integrated into document store
+
+        Returns:
+            If `previous` is `True`, the formatting the range had before, as a list of
+            `(index, length, attributes)` spans covering it. Otherwise `None`.
+
+        Raises:
+            IndexError: If `index + length` exceeds the length of the text.
         """
-    def extend(self, txn: YTransaction, chunk: str):
+    def extend(
+        self, txn: YTransaction, chunk: str, sticky_index: bool = False
+    ) -> Union[int, YStickyIndex]:
         """
-        Appends a given `chunk` of text at the end of current `YText` instance.
+        Appends a given `chunk` of text at the end of current `YText` instance. Returns the length of the text
+        after insertion or, if `sticky_index` is `True`, a `YStickyIndex` pointing right after the appended chunk.
         """
     def delete(self, txn: YTransaction, index: int):
         """
-        Deletes the character at the specified `index`.
+        Deletes the character at the specified `index`, counted in UTF-8 bytes.
         """
     def delete_range(self, txn: YTransaction, index: int, length: int):
         """
         Deletes a specified range of of characters, starting at a given `index`.
         Both `index` and `length` are counted in terms of a number of UTF-8 character bytes.
         """
-    def observe(self, f: Callable[[YTextEvent]]) -> SubscriptionId:
+    def observe(
+        self,
+        f: Callable[[YTextEvent]],
+        emit_initial: bool = False,
+        weak: bool = False,
+    ) -> SubscriptionId:
         """
         Assigns a callback function to listen to YText updates.
 
         Args:
             f: Callback function that runs when the text object receives an update.
+            emit_initial: If `True`, `f` is called right away with an event whose `delta` inserts the current
+                contents of the text. Such an event has no `origin`.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             A reference to the callback subscription.
         """
-    def observe_deep(self, f: Callable[[List[Event]]]) -> SubscriptionId:
+    def observe_deep(
+        self, f: Callable[[List[Event]]], weak: bool = False
+    ) -> SubscriptionId:
         """
         Assigns a callback function to listen to the updates of the YText instance and those of its nested attributes.
         Currently, this listens to the same events as YText.observe, but in the future this will also listen to
@@ -484,6 +1329,8 @@ class YText:
 
         Args:
             f: Callback function that runs when the text object or its nested attributes receive an update.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             A reference to the callback subscription.
         """
@@ -495,6 +1342,43 @@ class YText:
             subscription_id: reference to a subscription provided by the `observe` method.
         """
 
+class UpdateBuffer:
+    """
+    A read-only buffer holding an encoded update, returned by `diff_v1` and `encode_state_as_update`
+    called with `copy=False`. It supports the buffer protocol, so it can be passed wherever a bytes-like
+    object is accepted (e.g. `socket.send`, `memoryview` or `apply_update`) without copying the payload.
+    Use `bytes(buffer)` to get an independent copy.
+    """
+
+    def __len__(self) -> int: ...
+    def __getitem__(self, index: int) -> int: ...
+
+class YStickyIndex:
+    """
+    A position within a sequential shared type (e.g. `YText`) which sticks to the content around it.
+    Unlike a plain index, it keeps pointing at the same place when local or concurrent remote edits
+    insert or remove content in front of it.
+
+    Example::
+
+        from y_py import YDoc
+
+        doc = YDoc()
+        text = doc.get_text("text")
+        with doc.begin_transaction() as txn:
+            cursor = text.extend(txn, "world", sticky_index=True)
+        with doc.begin_transaction() as txn:
+            text.insert(txn, 0, "hello ")
+        assert cursor.index() == 11
+    """
+
+    def index(self) -> Optional[int]:
+        """
+        Returns:
+            The index this position currently corresponds to, measured in the same unit as the indexes of
+            the shared type it was created for. `None` if that type no longer exists.
+        """
+
 class YTextEvent:
     """
     Communicates updates that occurred during a transaction for an instance of `YText`.
@@ -504,14 +1388,44 @@ class YTextEvent:
 
     target: YText
     delta: List[YTextDelta]
+    origin: Optional[str]
+    """The origin of the transaction which has made the changes, `None` if it has no origin."""
+    kind: Literal["text"]
+    """The kind of the `target` shared type, as reported by `type_of`."""
     def path(self) -> List[Union[int, str]]:
         """
         Returns:
             Array of keys and indexes creating a path from root type down to current instance of shared type (accessible via `target` getter).
         """
+    def changes(self) -> List[YTextChange]:
+        """
+        Returns:
+            The changes from `delta` with the absolute indexes at which they apply, computed by accumulating
+            the lengths of preceding retains and inserts. Retains which don't change formatting attributes
+            only move the index, so they are skipped.
+        """
 
 YTextDelta = Union[YTextChangeInsert, YTextChangeDelete, YTextChangeRetain]
 
+class YTextIndexedInsert(TypedDict):
+    type: Literal["insert"]
+    index: int
+    insert: str
+    attributes: Optional[Any]
+
+class YTextIndexedDelete(TypedDict):
+    type: Literal["delete"]
+    index: int
+    length: int
+
+class YTextIndexedFormat(TypedDict):
+    type: Literal["format"]
+    index: int
+    length: int
+    attributes: Any
+
+YTextChange = Union[YTextIndexedInsert, YTextIndexedDelete, YTextIndexedFormat]
+
 class YTextChangeInsert(TypedDict):
     insert: str
     attributes: Optional[Any]
@@ -524,8 +1438,26 @@ class YTextChangeRetain(TypedDict):
     attributes: Optional[Any]
 
 class YArray:
+    """
+    An ordered sequence of values, which can be JSON-like primitives or other shared types.
+
+    Reading elements of an integrated `YArray` (by index, slice, iteration, `first` or `last`) returns
+    nested shared types as live instances: they keep reflecting the document and can be edited in place.
+    Serializing methods such as `to_json` and `str` always produce snapshots. Methods taking a
+    `materialize` flag return a snapshot of a nested value instead when it's set: arrays and maps become
+    lists and dictionaries, while texts and XML nodes become strings.
+    """
+
     prelim: bool
-    """True if this element has not been integrated into a YDoc."""
+    """
+    True if this element has not been integrated into a YDoc. Inserting a preliminary instance into a YDoc
+    integrates that very object in place, so it can be used to keep editing the inserted data.
+    """
+    parent: Optional[Union[YText, YArray, YMap, YXmlElement, YXmlText, YXmlFragment]]
+    """
+    The shared type directly containing this `YArray`, or `None` for root types, preliminary instances and
+    instances which have been removed from their document.
+    """
 
     def __init__(init: Optional[Iterable[Any]] = None):
         """
@@ -536,10 +1468,61 @@ class YArray:
         Once a preliminary instance has been inserted this way, it becomes integrated into Ypy
         document store and cannot be nested again: attempt to do so will result in an exception.
         """
+    def is_alive(self) -> bool:
+        """
+        Returns:
+            `False` if this `YArray` has been removed from its document, either directly or along with
+            one of its ancestors. Removed types can no longer be modified.
+        """
+    def is_deleted(self) -> bool:
+        """
+        Returns:
+            `True` if this `YArray` has been removed from its document. See `is_alive`.
+        """
+    def id(self) -> Optional[Union[str, Tuple[int, int]]]:
+        """
+        Returns:
+            An identifier of this `YArray` which is the same on every peer: the name of a root type, or a
+            `(client, clock)` pair identifying the update which has created a nested one.
+            Preliminary instances return `None`.
+        """
+    def __copy__(self) -> YArray:
+        """
+        Returns a shallow copy of this preliminary `YArray`.
+
+        Raises:
+            TypeError: If this instance is integrated, as it's bound to its `YDoc`.
+        """
+    def __deepcopy__(self, memo: Dict[int, Any]) -> YArray:
+        """
+        Returns a deep copy of this preliminary `YArray`, copying nested values with `copy.deepcopy`.
+
+        Raises:
+            TypeError: If this instance is integrated, as it's bound to its `YDoc`.
+        """
+    def path(self) -> Optional[List[Union[int, str]]]:
+        """
+        Returns:
+            Array of keys and indexes creating a path from the root type of a document down to this `YArray`, in the
+            same format as event paths, or `None` if it has been removed from its document. The path is built by
+            following parent links, so its cost depends on how deeply this `YArray` is nested rather than on the size
+            of the document.
+
+        Raises:
+            IntegratedOperationException: If this `YArray` is preliminary.
+        """
     def __len__(self) -> int:
         """
         Returns:
-            Number of elements in the `YArray`
+            Number of elements in the `YArray`. It's read without starting a transaction.
+        """
+    def length(self, txn: Optional[YTransaction] = None) -> int:
+        """
+        Returns:
+            Number of elements in the `YArray`, optionally read through a given `txn`.
+
+        Raises:
+            TransactionError: If `txn` has already been committed.
         """
     def __str__(self) -> str:
         """
@@ -549,27 +1532,70 @@ class YArray:
     def __repr__(self) -> str:
         """
         Returns:
-            The string representation of YArray wrapped in `YArray()`
+            The string representation of YArray wrapped in `YArray()`. Only the first 10 elements
+            are shown, followed by an ellipsis if there are more of them. Use `str` or `to_json` to
+            get all of them.
         """
-    def to_json(self) -> str:
+    def to_json(
+        self, include_xml: bool = False, sort_keys: bool = False
+    ) -> str:
         """
         Converts an underlying contents of this `YArray` instance into their JSON representation.
+
+        Args:
+            include_xml: If `True`, nested XML nodes are serialized as `{"tag", "attributes", "children"}` objects
+                (fragments only have `children`, text nodes become strings). Otherwise integrated XML nodes are
+                stringified and preliminary collections containing them raise a `TypeError`.
+            sort_keys: If `True`, keys of all maps are emitted in sorted order, making the output deterministic.
+        """
+    def insert(self, txn: YTransaction, index: int, item: Any) -> Optional[Union[YText, YArray, YMap]]:
+        """
+        Inserts an item at the provided index in the `YArray`. Negative indexes are counted from
+        the end of the array.
+
+        Returns:
+            The `item` itself if it's a preliminary shared type integrated by the insertion, so that it can
+            be edited further. `None` otherwise.
+
+        Raises:
+            IndexError: If `index` is outside of the `-len..=len` range.
+        """
+    def insert_before(self, txn: YTransaction, anchor: Any, item: Any):
+        """
+        Inserts an item right before the first element equal to `anchor`.
+
+        Raises:
+            ValueError: If there's no element equal to `anchor`.
         """
-    def insert(self, txn: YTransaction, index: int, item: Any):
+    def insert_after(self, txn: YTransaction, anchor: Any, item: Any):
         """
-        Inserts an item at the provided index in the `YArray`.
+        Inserts an item right after the first element equal to `anchor`.
+
+        Raises:
+            ValueError: If there's no element equal to `anchor`.
         """
     def insert_range(self, txn: YTransaction, index: int, items: Iterable):
         """
         Inserts a given range of `items` into this `YArray` instance, starting at given `index`.
+        Negative indexes are counted from the end of the array. When `items` is an integrated
+        `YArray` or `YText`, its contents are copied directly, with nested shared types inserted as
+        detached copies.
+
+        Raises:
+            IndexError: If `index` is outside of the `-len..=len` range.
         """
-    def append(self, txn: YTransaction, item: Any):
+    def append(self, txn: YTransaction, item: Any) -> Optional[Union[YText, YArray, YMap]]:
         """
         Adds a single item to the end of the `YArray`
+
+        Returns:
+            The `item` itself if it's a preliminary shared type integrated by the insertion, so that it can
+            be edited further. `None` otherwise.
         """
     def extend(self, txn: YTransaction, items: Iterable):
         """
-        Appends a sequence of `items` at the end of this `YArray` instance.
+        Appends a sequence of `items` at the end of this `YArray` instance. Like with `insert_range`,
+        nested shared types of an integrated `YArray` source are copied rather than moved.
         """
     def delete(self, txn: YTransaction, index: int):
         """
@@ -579,19 +1605,63 @@ class YArray:
             txn: The transaction where the array is being modified.
             index: The index of the element to be deleted.
         """
+    def remove(self, txn: YTransaction, value: Any):
+        """
+        Removes the first element equal to `value` (as compared by `==`), like `list.remove`.
+
+        Raises:
+            ValueError: If there's no such element.
+        """
+    def pop(self, txn: YTransaction, index: int = -1) -> Any:
+        """
+        Removes the element at the given `index` (the last one by default) and returns it.
+        Negative indexes are counted from the end of the array. Removed shared types can't be read anymore,
+        so they're returned as snapshots of their contents, like with `materialize=True` in `first` and `last`:
+        arrays and maps as lists and dictionaries, texts and XML nodes as strings.
+
+        Raises:
+            IndexError: If the array is empty or `index` is out of range.
+        """
     def delete_range(self, txn: YTransaction, index: int, length: int):
         """
         Deletes a range of items of given `length` from current `YArray` instance,
         starting from given `index`.
         """
+    def splice(
+        self,
+        txn: YTransaction,
+        index: int,
+        delete_count: int,
+        items: Optional[Iterable] = None,
+    ) -> List[Any]:
+        """
+        Removes `delete_count` items starting at a given `index` and inserts `items` in their place,
+        within the same transaction. Mirrors JavaScript's `Array.splice`.
+
+        Args:
+            txn: The transaction where the array is being modified.
+            index: The position where items are removed and inserted. Negative indexes are counted from the end of the array.
+            delete_count: The number of items to remove. Clamped to the number of items following `index`.
+            items: The items to insert at `index`.
+        Returns:
+            The removed items, with shared types among them turned into snapshots the same way `pop` does.
+        Raises:
+            IndexError: If `index` is out of bounds.
+        """
     def move_to(self, txn: YTransaction, source: int, target: int):
         """
-        Moves a single item found at `source` index into `target` index position.
+        Moves a single item found at `source` index in front of the item found at `target` index.
+        Both indexes refer to the state of the array before the move, so moving an item to either
+        `source` or `source + 1` leaves the array unchanged, while moving it to `len(array)` places
+        it at the end. Preliminary and integrated arrays follow the same semantics.
 
         Args:
             txn: The transaction where the array is being modified.
             source: The index of the element to be moved.
             target: The new position of the element.
+
+        Raises:
+            IndexError: If `source` is not a valid index or `target` is greater than the length of the array.
         """
     def move_range_to(self, txn: YTransaction, start: int, end: int, target: int):
         """
@@ -620,15 +1690,70 @@ class YArray:
             array.move_range_to(t, 1, 2, 4);
         ```
         """
+    def reverse(self, txn: YTransaction):
+        """
+        Reverses the order of elements of this `YArray` in place. Elements of integrated arrays are moved
+        rather than deleted and inserted again, so they keep their identity.
+        """
+    def sort(
+        self,
+        txn: YTransaction,
+        key: Optional[Callable[[Any], Any]] = None,
+        reverse: bool = False,
+    ):
+        """
+        Sorts elements of this `YArray` in place, following the semantics of Python's `sorted`. Elements of
+        integrated arrays are moved rather than deleted and inserted again, so they keep their identity.
+
+        Args:
+            txn: A transaction to perform the sort within.
+            key: A function applied to each element to get the value it's compared by.
+            reverse: If `True`, elements are sorted in descending order. The sort stays stable.
+        """
     def __getitem__(self, index: Union[int, slice]) -> Any:
         """
         Returns:
             The element stored under given `index` or a new list of elements from the slice range.
         """
+    def first(self, default: Any = None, materialize: bool = False) -> Any:
+        """
+        Args:
+            default: The value returned when this `YArray` is empty.
+            materialize: If `True`, a nested shared type is returned as a snapshot (see `YArray`).
+
+        Returns:
+            The first element of this `YArray`, or `default` if it's empty.
+        """
+    def last(self, default: Any = None, materialize: bool = False) -> Any:
+        """
+        Args:
+            default: The value returned when this `YArray` is empty.
+            materialize: If `True`, a nested shared type is returned as a snapshot (see `YArray`).
+
+        Returns:
+            The last element of this `YArray`, or `default` if it's empty.
+        """
+    def type_of(self, index: int) -> ValueKind:
+        """
+        Inspects the kind of an element without converting it into a Python object.
+
+        Args:
+            index: The position of the element. Negative indexes are counted from the end of the array.
+
+        Returns:
+            A tag describing the element: `"map"`, `"array"`, `"text"`, one of the XML types,
+            `"doc"`, or `"primitive"` for any other value.
+
+        Raises:
+            IndexError: If the index is out of bounds.
+        """
     def __iter__(self) -> Iterator:
         """
         Returns:
             An iterator that can be used to traverse over the values stored withing this instance of `YArray`.
+            Values are read once the iteration starts and converted into Python objects one at a
+            time, and nested shared types are returned as live instances. Changes made to the array
+            itself during the iteration are not reflected by the iterator.
 
         Example::
 
@@ -641,21 +1766,77 @@ class YArray:
             for item in array:
                 print(item)
         """
-    def observe(self, f: Callable[[YArrayEvent]]) -> SubscriptionId:
+    def observe(
+        self,
+        f: Callable[[YArrayEvent]],
+        emit_initial: bool = False,
+        weak: bool = False,
+    ) -> SubscriptionId:
         """
         Assigns a callback function to listen to YArray updates.
 
         Args:
             f: Callback function that runs when the array object receives an update.
+            emit_initial: If `True`, `f` is called right away with an event whose `delta` inserts the current
+                contents of the array. Such an event has no `origin`.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
+        Returns:
+            An identifier associated with the callback subscription.
+        """
+    def observe_range(
+        self, start: int, end: int, f: Callable[[YArrayEvent]], weak: bool = False
+    ) -> SubscriptionId:
+        """
+        Assigns a callback function to listen to changes overlapping a range of the YArray. The callback
+        is only called for transactions which inserted or removed elements within the range, and the
+        `delta` of the events it receives only describes these changes, preceded by a retain up to where
+        they begin.
+
+        The range moves along with the elements it spans: changes made in front of it shift it, elements
+        inserted within it extend it and removed ones shrink it. Elements inserted right at the `start` of
+        the range are considered to be within it, while those inserted at its `end` are not.
+
+        Args:
+            start: The index of the first element of the range.
+            end: The index right after the last element of the range.
+            f: Callback function that runs when elements within the range change.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             An identifier associated with the callback subscription.
+
+        Raises:
+            ValueError: If `start` is greater than `end`.
+            PreliminaryObservationException: If this `YArray` is preliminary.
         """
-    def observe_deep(self, f: Callable[[List[Event]]]) -> SubscriptionId:
+    def observe_deep(
+        self, f: Callable[[List[Event]]], weak: bool = False
+    ) -> SubscriptionId:
         """
         Assigns a callback function to listen to the aggregated updates of the YArray and its child elements.
 
         Args:
             f: Callback function that runs when the array object or components receive an update.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
+        Returns:
+            An identifier associated with the callback subscription.
+        """
+    def observe_changes(
+        self, f: Callable[[Changes]], weak: bool = False
+    ) -> SubscriptionId:
+        """
+        Assigns a callback function to listen to the aggregated updates of the YArray and its child elements.
+        Unlike `observe_deep`, the callback receives a single dictionary per transaction, keyed by the
+        `/`-separated path of each changed shared type relative to this YArray (`""` for the YArray itself).
+        Each value describes the changes with a `delta` and/or `keys` entry, in the same format as
+        the corresponding event type.
+
+        Args:
+            f: Callback function that runs when the array object or its children receive an update.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             An identifier associated with the callback subscription.
         """
@@ -678,6 +1859,10 @@ class YArrayEvent:
 
     target: YArray
     delta: List[ArrayDelta]
+    origin: Optional[str]
+    """The origin of the transaction which has made the changes, `None` if it has no origin."""
+    kind: Literal["array"]
+    """The kind of the `target` shared type, as reported by `type_of`."""
     def path(self) -> List[Union[int, str]]:
         """
         Returns:
@@ -703,22 +1888,116 @@ class ArrayChangeRetain:
     retain: int
 
 class YMap:
+    """
+    A collection of key-value entries, whose values can be JSON-like primitives or other shared types.
+
+    Like with `YArray`, values read from an integrated `YMap` (with `[]`, `get`, `items`, `values` or
+    `to_dict`) are nested shared types returned as live instances. `to_json`, `to_dict(deep=True)` and
+    `__dict__` produce snapshots. `get`, `items` and `values` return snapshots of nested values when
+    called with `materialize=True`.
+    """
+
     prelim: bool
-    """True if this element has not been integrated into a YDoc."""
-    def __init__(dict: dict):
+    """
+    True if this element has not been integrated into a YDoc. Inserting a preliminary instance into a YDoc
+    integrates that very object in place, so it can be used to keep editing the inserted data.
+    """
+    parent: Optional[Union[YText, YArray, YMap, YXmlElement, YXmlText, YXmlFragment]]
+    """
+    The shared type directly containing this `YMap`, or `None` for root types, preliminary instances and
+    instances which have been removed from their document.
+    """
+    def __init__(
+        self,
+        dict: Optional[Union[Mapping[str, Any], Iterable[Tuple[str, Any]]]] = None,
+    ):
         """
         Creates a new preliminary instance of a `YMap` shared data type, with its state
-        initialized to provided parameter.
+        initialized to provided parameter: a dict, any other mapping or an iterable of `(key, value)` pairs.
+        Like all Yrs maps, `YMap` doesn't preserve the order in which its entries were inserted.
 
         Preliminary instances can be nested into other shared data types such as `YArray` and `YMap`.
         Once a preliminary instance has been inserted this way, it becomes integrated into Ypy
         document store and cannot be nested again: attempt to do so will result in an exception.
         """
+    def is_alive(self) -> bool:
+        """
+        Returns:
+            `False` if this `YMap` has been removed from its document, either directly or along with
+            one of its ancestors. Removed types can no longer be modified.
+        """
+    def is_deleted(self) -> bool:
+        """
+        Returns:
+            `True` if this `YMap` has been removed from its document. See `is_alive`.
+        """
+    def id(self) -> Optional[Union[str, Tuple[int, int]]]:
+        """
+        Returns:
+            An identifier of this `YMap` which is the same on every peer: the name of a root type, or a
+            `(client, clock)` pair identifying the update which has created a nested one.
+            Preliminary instances return `None`.
+        """
+    def __copy__(self) -> YMap:
+        """
+        Returns a shallow copy of this preliminary `YMap`.
+
+        Raises:
+            TypeError: If this instance is integrated, as it's bound to its `YDoc`.
+        """
+    def __deepcopy__(self, memo: Dict[int, Any]) -> YMap:
+        """
+        Returns a deep copy of this preliminary `YMap`, copying nested values with `copy.deepcopy`.
+
+        Raises:
+            TypeError: If this instance is integrated, as it's bound to its `YDoc`.
+        """
+    @staticmethod
+    def from_keys(keys: Iterable[str], value: Any = None) -> YMap:
+        """
+        Creates a preliminary `YMap` with each of `keys` mapped to `value`, like `dict.fromkeys`. Unlike
+        `dict.fromkeys`, every key gets its own deep copy of `value`, so that nested containers aren't
+        shared between the entries.
+
+        Example::
+
+            from y_py import YArray, YMap
+
+            template = YMap.from_keys(["todo", "done"], YArray())
+            assert template["todo"] is not template["done"]
+        """
+    def path(self) -> Optional[List[Union[int, str]]]:
+        """
+        Returns:
+            Array of keys and indexes creating a path from the root type of a document down to this `YMap`, in the
+            same format as event paths, or `None` if it has been removed from its document. The path is built by
+            following parent links, so its cost depends on how deeply this `YMap` is nested rather than on the size
+            of the document.
+
+        Raises:
+            IntegratedOperationException: If this `YMap` is preliminary.
+        """
     def __len__(self) -> int:
         """
         Returns:
             The number of entries stored within this instance of `YMap`.
         """
+    def length(self, txn: Optional[YTransaction] = None) -> int:
+        """
+        Unlike `len`, which starts a transaction of its own when none is pending, this lets tight
+        loops reuse an already open `txn`.
+
+        Returns:
+            The number of entries stored within this instance of `YMap`.
+
+        Raises:
+            TransactionError: If `txn` has already been committed.
+        """
+    def __contains__(self, key: str) -> bool:
+        """
+        Returns:
+            `True` if an entry is stored under given `key`, even if its value is `None`.
+        """
     def __str__(self) -> str:
         """
         Returns:
@@ -730,31 +2009,118 @@ class YMap:
         Returns:
             Contents of the `YMap` inside a Python dictionary.
         """
+    def to_dict(self, deep: bool = False) -> Dict[str, Any]:
+        """
+        Reads all entries of the `YMap` within a single transaction.
+
+        Args:
+            deep: If `True`, nested shared types are fully converted into plain Python values.
+                Otherwise they are returned as live references, which can be modified further.
+
+        Returns:
+            Contents of the `YMap` inside a Python dictionary.
+        """
 
     def __repr__(self) -> str:
         """
         Returns:
-            The string representation of the `YMap` wrapped in 'YMap()'
+            The string representation of the `YMap` wrapped in 'YMap()'. Only 10 entries are shown,
+            followed by an ellipsis if there are more of them. Use `str` or `to_json` to get all of them.
         """
-    def to_json(self) -> str:
+    def to_json(
+        self, include_xml: bool = False, sort_keys: bool = False
+    ) -> str:
         """
         Converts contents of this `YMap` instance into a JSON representation.
+
+        Args:
+            include_xml: If `True`, nested XML nodes are serialized as `{"tag", "attributes", "children"}` objects
+                (fragments only have `children`, text nodes become strings). Otherwise integrated XML nodes are
+                stringified and preliminary collections containing them raise a `TypeError`.
+            sort_keys: If `True`, keys of all maps are emitted in sorted order, making the output deterministic.
         """
-    def set(self, txn: YTransaction, key: str, value: Any):
+    def set(self, txn: YTransaction, key: str, value: Any) -> Optional[Union[YText, YArray, YMap]]:
         """
         Sets a given `key`-`value` entry within this instance of `YMap`. If another entry was
         already stored under given `key`, it will be overridden with new `value`.
+
+        Returns:
+            The `value` itself if it's a preliminary shared type integrated by the insertion, so that it can
+            be edited further. `None` otherwise.
+
+        Raises:
+            ValueError: If `value` is (or contains) a non-finite float (`nan`, `inf` or `-inf`).
+            OverflowError: If `value` is (or contains) an int outside of the 64-bit signed range.
+        """
+    def set_many(
+        self,
+        txn: YTransaction,
+        items: Union[Iterable[Tuple[str, Any]], Dict[str, Any]],
+    ):
+        """
+        Sets all entries of `items` within this instance of `YMap`, overriding existing entries stored
+        under the same keys. Values are converted in a single pass before any of them is inserted, so an
+        invalid value leaves the map unchanged. This is faster than calling `set` for each entry.
+
+        Args:
+            txn: A transaction to perform the insertions within.
+            items: A dict or an iterable of `(key, value)` pairs.
+
+        Raises:
+            TypeError: If `items` isn't formatted as `(str, value)` pairs or contains a value which can't
+                be stored in a `YMap`.
         """
     def update(
-        self, txn: YTransaction, items: Union[Iterable[Tuple[str, Any]], Dict[str, Any]]
+        self,
+        txn: YTransaction,
+        items: Union[Iterable[Tuple[str, Any]], Dict[str, Any], YMap, YArray],
     ):
         """
         Updates `YMap` with the contents of items.
 
+        When `items` is another `YMap` (or a `YArray` of `[key, value]` pairs), its entries are deep-copied:
+        nested shared types are inserted as new preliminary copies. Integrated nested types are never
+        moved out of their current parent, so later edits to the source don't affect the copy.
+
         Args:
             txn: A transaction to perform the insertion updates.
             items: An iterable object that produces key value tuples to insert into the YMap
         """
+    def merge_from(self, txn: YTransaction, prelim_map: YMap):
+        """
+        Merges top-level entries of a preliminary `prelim_map` into this `YMap` instead of inserting it
+        as a nested child. Entries stored under colliding keys are overwritten. Nested preliminary types
+        are copied and integrated recursively, so `prelim_map` itself stays preliminary and can be reused.
+
+        Args:
+            txn: A transaction to perform the merge within.
+            prelim_map: A preliminary `YMap` to merge entries from.
+
+        Raises:
+            TypeError: If `prelim_map` is already integrated into a document.
+        """
+    def __ior__(
+        self, other: Union[Dict[str, Any], YMap, Iterable[Tuple[str, Any]]]
+    ) -> YMap:
+        """
+        Merges entries of `other` into this map, like `dict.__ior__`. Integrated maps are updated within
+        the document's current transaction or, if there's none, within a new one which is committed right away.
+
+        Example::
+
+            from y_py import YDoc
+
+            doc = YDoc()
+            map = doc.get_map("map")
+            with doc.begin_transaction():
+                map |= {"a": 1, "b": 2}
+        """
+    def __or__(self, other: Union[Dict[str, Any], YMap]) -> YMap:
+        """
+        Returns:
+            A new preliminary `YMap` holding entries of this map merged with the ones of `other`, the latter
+            taking precedence, like `dict.__or__`.
+        """
     def pop(self, txn: YTransaction, key: str, fallback: Optional[Any] = None) -> Any:
         """
         Removes an entry identified by a given `key` from this instance of `YMap`, if such exists.
@@ -768,14 +2134,28 @@ class YMap:
         Returns:
             The item at the key.
         """
-    def get(self, key: str, fallback: Any) -> Any | None:
+    def popitem(self, txn: YTransaction) -> Tuple[str, Any]:
+        """
+        Removes an arbitrary entry from this `YMap` instance and returns it.
+
+        Returns:
+            A `(key, value)` tuple of the removed entry.
+
+        Raises:
+            KeyError: If the map is empty.
+        """
+    def get(self, key: str, default: Any = None, materialize: bool = False) -> Any:
         """
+        Mirrors `dict.get`: never raises for missing keys. Without a `default`, a missing key can't be
+        told apart from an entry storing `None`; use `key in map` to check for that.
+
         Args:
             key: The identifier for the requested data.
-            fallback: If the key doesn't exist in the map, this fallback value will be returned.
+            default: If the key doesn't exist in the map, this value will be returned.
+            materialize: If `True`, a nested shared type is returned as a snapshot (see `YMap`).
 
         Returns:
-            Requested data or the provided fallback value.
+            Requested data or the provided default value (`None` unless specified).
         """
     def __getitem__(self, key: str) -> Any:
         """
@@ -783,15 +2163,54 @@ class YMap:
             key: The identifier for the requested data.
 
         Returns:
-            Value of an entry stored under given `key` within this instance of `YMap`. Will throw a `KeyError` if the provided key is unassigned.
+            Value of an entry stored under given `key` within this instance of `YMap`, which is `None` for entries
+            explicitly set to `None`. Will throw a `KeyError` if the provided key is unassigned.
+        """
+    def get_typed(self, key: str, expected_kind: ValueKind) -> Any:
+        """
+        Retrieves an entry, checking that it holds a value of the expected kind first.
+
+        Args:
+            key: The identifier for the requested data.
+            expected_kind: The kind of value (as reported by `type_of`) the entry should hold.
+
+        Returns:
+            Value of an entry stored under given `key`. Will throw a `KeyError` if the provided key is unassigned,
+            a `TypeError` if the entry holds a value of a different kind and a `ValueError` if `expected_kind` is unknown.
+        """
+    def type_of(self, key: str) -> ValueKind:
+        """
+        Inspects the kind of an entry without converting it into a Python object.
+
+        Args:
+            key: The identifier for the requested data.
+
+        Returns:
+            A tag describing the entry: `"map"`, `"array"`, `"text"`, one of the XML types,
+            `"doc"`, or `"primitive"` for any other value.
+
+        Raises:
+            KeyError: If the provided key is unassigned.
         """
     def __iter__(self) -> Iterator[str]:
         """
         Returns:
             An iterator that traverses all keys of the `YMap` in an unspecified order.
         """
-    def items(self) -> YMapItemsView:
+    def items(
+        self,
+        txn: Optional[YTransaction] = None,
+        sorted: bool = False,
+        materialize: bool = False,
+    ) -> YMapItemsView:
         """
+        Args:
+            txn: An optional transaction to read the map through. Entries changed within this
+                transaction are visible in the view. Once it's committed, using the view or its
+                iterators raises a `TransactionError`.
+            sorted: If `True`, entries are yielded in the order of their keys.
+            materialize: If `True`, nested shared types are yielded as snapshots (see `YMap`).
+
         Returns:
             A view that can be used to iterate over all entries stored within this instance of `YMap`. Order of entry is not specified.
 
@@ -805,37 +2224,87 @@ class YMap:
             with doc.begin_transaction() as txn:
                 map.set(txn, 'key1', 'value1')
                 map.set(txn, 'key2', true)
-            for (key, value) in map.items()):
-                print(key, value)
+                for (key, value) in map.items(txn):
+                    print(key, value)
         """
-    def keys(self) -> YMapKeysView:
+    def keys(
+        self, txn: Optional[YTransaction] = None, sorted: bool = False
+    ) -> YMapKeysView:
         """
+        Args:
+            txn: An optional transaction to read the map through.
+            sorted: If `True`, keys are yielded in sorted order.
+
         Returns:
-            A view of all key identifiers in the YMap. The order of keys is not stable.
+            A view of all key identifiers in the YMap. The order of keys is not stable, unless `sorted` is set.
         """
-    def values(self) -> YMapValuesView:
+    def values(
+        self,
+        txn: Optional[YTransaction] = None,
+        sorted: bool = False,
+        materialize: bool = False,
+    ) -> YMapValuesView:
         """
+        Args:
+            txn: An optional transaction to read the map through.
+            sorted: If `True`, values are yielded in the order of their keys.
+            materialize: If `True`, nested shared types are yielded as snapshots (see `YMap`).
+
         Returns:
-            A view of all values in the YMap. The order of values is not stable.
+            A view of all values in the YMap. The order of values is not stable, unless `sorted` is set.
         """
-    def observe(self, f: Callable[[YMapEvent]]) -> SubscriptionId:
+    def observe(
+        self,
+        f: Callable[[YMapEvent]],
+        keys: Optional[List[str]] = None,
+        shallow_keys: bool = False,
+        weak: bool = False,
+    ) -> SubscriptionId:
         """
         Assigns a callback function to listen to YMap updates.
 
         Args:
             f: Callback function that runs when the map object receives an update.
+            keys: If given, the callback only runs for updates which changed at least one of these
+                keys, and the `keys` of the events it receives only describe changes of these keys.
+            shallow_keys: If `True`, the callback only runs for updates which added or removed entries,
+                and the `keys` of the events it receives only describe these changes. Values replaced
+                under existing keys are ignored.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             A reference to the callback subscription. Delete this observer in order to erase the associated callback function.
         """
-    def observe_deep(self, f: Callable[[List[Event]]]) -> SubscriptionId:
+    def observe_deep(
+        self, f: Callable[[List[Event]]], weak: bool = False
+    ) -> SubscriptionId:
         """
         Assigns a callback function to listen to YMap and child element updates.
 
         Args:
             f: Callback function that runs when the map object or any of its tracked elements receive an update.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             A reference to the callback subscription. Delete this observer in order to erase the associated callback function.
         """
+    def observe_changes(
+        self, f: Callable[[Changes]], weak: bool = False
+    ) -> SubscriptionId:
+        """
+        Assigns a callback function to listen to the aggregated updates of the YMap and its child elements.
+        Unlike `observe_deep`, the callback receives a single dictionary per transaction, keyed by the
+        `/`-separated path of each changed shared type relative to this YMap (`""` for the YMap itself).
+        Each value describes the changes with a `delta` and/or `keys` entry, in the same format as
+        the corresponding event type.
+
+        Args:
+            f: Callback function that runs when the map object or its children receive an update.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
+        Returns:
+            An identifier associated with the callback subscription.
+        """
     def unobserve(self, subscription_id: SubscriptionId):
         """
         Cancels the observer callback associated with the `subscripton_id`.
@@ -887,6 +2356,10 @@ class YMapEvent:
     keys: Dict[str, YMapEventKeyChange]
     """A list of modifications to the YMap by key. 
     Includes the type of modification along with the before and after state."""
+    origin: Optional[str]
+    """The origin of the transaction which has made the changes, `None` if it has no origin."""
+    kind: Literal["map"]
+    """The kind of the `target` shared type, as reported by `type_of`."""
     def path(self) -> List[Union[int, str]]:
         """
         Returns:
@@ -910,6 +2383,10 @@ class YXmlElementEvent:
     target: YXmlElement
     keys: Dict[str, EntryChange]
     delta: List[Dict]
+    origin: Optional[str]
+    """The origin of the transaction which has made the changes, `None` if it has no origin."""
+    kind: Literal["xml_element", "xml_fragment"]
+    """The kind of the `target` shared type, as reported by `type_of`."""
     def path(self) -> List[Union[int, str]]:
         """
         Returns a current shared type instance, that current event changes refer to.
@@ -937,6 +2414,23 @@ class YXmlElement:
     next_sibling: Optional[Xml]
     prev_sibling: Optional[Xml]
     parent: Optional[YXmlElement]
+    def is_alive(self) -> bool:
+        """
+        Returns:
+            `False` if this `YXmlElement` has been removed from its document, either directly or along with
+            one of its ancestors. Removed types can no longer be modified.
+        """
+    def is_deleted(self) -> bool:
+        """
+        Returns:
+            `True` if this `YXmlElement` has been removed from its document. See `is_alive`.
+        """
+    def id(self) -> Optional[Union[str, Tuple[int, int]]]:
+        """
+        Returns:
+            An identifier of this `YXmlElement` which is the same on every peer: the name of a root type, or a
+            `(client, clock)` pair identifying the update which has created a nested one.
+        """
     def __len__(self) -> int:
         """
         Returns a number of child XML nodes stored within this `YXMlElement` instance.
@@ -967,6 +2461,18 @@ class YXmlElement:
         """
         Appends a new instance of `YXmlText` as the last child of this XML node and returns it.
         """
+    def insert_xml_fragment(self, txn: YTransaction, index: int) -> YXmlFragment:
+        """
+        Inserts a new, empty instance of `YXmlFragment` as a child of this XML node and returns it.
+        Fragments group other XML nodes without introducing a tag of their own.
+
+        Raises:
+            IndexError: If `index` is greater than the number of children of this node.
+        """
+    def push_xml_fragment(self, txn: YTransaction) -> YXmlFragment:
+        """
+        Appends a new, empty instance of `YXmlFragment` as the last child of this XML node and returns it.
+        """
     def __str__(self) -> str:
         """
         Returns:
@@ -977,6 +2483,19 @@ class YXmlElement:
         Returns:
             A string representation wrapped in YXmlElement
         """
+    def to_xml(self, indent: int = 2, sort_attributes: bool = True) -> str:
+        """
+        Renders a stable, pretty-printed XML representation of this node, suitable for snapshot testing.
+        Special characters (`&`, `<`, `>` and `"`) in text and attribute values are escaped as XML entities.
+
+        Args:
+            indent: Number of spaces used to indent each nesting level. Each child node is placed on its
+                own line, unless `indent` is `0`, which produces a compact output.
+            sort_attributes: If true, attributes are rendered in the order of their names.
+
+        Returns:
+            An XML representation of this node.
+        """
     def set_attribute(self, txn: YTransaction, name: str, value: str):
         """
         Sets a `name` and `value` as new attribute for this XML node. If an attribute with the same
@@ -991,33 +2510,51 @@ class YXmlElement:
         """
         Removes an attribute from this XML node, given its `name`.
         """
-    def attributes(self) -> YXmlAttributes:
+    def attributes(
+        self, as_dict: bool = False, sort: bool = False
+    ) -> Union[List[Tuple[str, str]], Dict[str, str]]:
         """
-        Returns an iterator that enables to traverse over all attributes of this XML node in
-        unspecified order.
+        Returns the attributes of this XML node as a list of `(name, value)` tuples, or as a dict if
+        `as_dict` is `True`. Attributes are stored in unspecified order, unless `sort` is `True` in
+        which case they're ordered by their names. Attribute values are always strings.
         """
     def tree_walker(self) -> YXmlTreeWalker:
         """
         Returns an iterator that enables a deep traversal of this XML node - starting from first
         child over this XML node successors using depth-first strategy.
         """
-    def observe(self, f: Callable[[YXmlElementEvent]]) -> SubscriptionId:
+    def descendant_count(self, depth: Optional[int] = None) -> int:
+        """
+        Returns:
+            The number of all XML nodes nested within this XML element, as visited by `tree_walker`.
+            If `depth` is given, only nodes up to that many levels below this element are counted,
+            e.g. `depth=1` counts direct children only.
+        """
+    def observe(
+        self, f: Callable[[YXmlElementEvent]], weak: bool = False
+    ) -> SubscriptionId:
         """
         Subscribes to all operations happening over this instance of `YXmlElement`. All changes are
         batched and eventually triggered during transaction commit phase.
 
         Args:
             f: A callback function that receives update events.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             A `SubscriptionId` that can be used to cancel the observer callback.
         """
-    def observe_deep(self, f: Callable[[List[Event]]]) -> SubscriptionId:
+    def observe_deep(
+        self, f: Callable[[List[Event]]], weak: bool = False
+    ) -> SubscriptionId:
         """
         Subscribes to all operations happening over this instance of `YXmlElement` and its children. All changes are
         batched and eventually triggered during transaction commit phase.
 
         Args:
             f: A callback function that receives update events from the Xml element and its children.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             A `SubscriptionId` that can be used to cancel the observer callback.
         """
@@ -1037,6 +2574,23 @@ class YXmlFragment:
     first_child: Optional[Xml]
     parent: Optional[YXmlElement]
 
+    def is_alive(self) -> bool:
+        """
+        Returns:
+            `False` if this `YXmlFragment` has been removed from its document, either directly or along with
+            one of its ancestors. Removed types can no longer be modified.
+        """
+    def is_deleted(self) -> bool:
+        """
+        Returns:
+            `True` if this `YXmlFragment` has been removed from its document. See `is_alive`.
+        """
+    def id(self) -> Optional[Union[str, Tuple[int, int]]]:
+        """
+        Returns:
+            An identifier of this `YXmlFragment` which is the same on every peer: the name of a root type, or a
+            `(client, clock)` pair identifying the update which has created a nested one.
+        """
     def __len__(self) -> int:
         """
         Returns a number of child XML nodes stored within this `YXmlFragment` instance.
@@ -1086,23 +2640,31 @@ class YXmlFragment:
         Returns an iterator that enables a deep traversal of this XML fragment - starting from first
         child over this XML fragment successors using depth-first strategy.
         """
-    def observe(self, f: Callable[[YXmlElementEvent]]) -> SubscriptionId:
+    def observe(
+        self, f: Callable[[YXmlElementEvent]], weak: bool = False
+    ) -> SubscriptionId:
         """
         Subscribes to all operations happening over this instance of `YXmlFragment`. All changes are
         batched and eventually triggered during transaction commit phase.
 
         Args:
             f: A callback function that receives update events.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             A `SubscriptionId` that can be used to cancel the observer callback.
         """
-    def observe_deep(self, f: Callable[[List[Event]]]) -> SubscriptionId:
+    def observe_deep(
+        self, f: Callable[[List[Event]]], weak: bool = False
+    ) -> SubscriptionId:
         """
         Subscribes to all operations happening over this instance of `YXmlFragment` and its children. All changes are
         batched and eventually triggered during transaction commit phase.
 
         Args:
             f: A callback function that receives update events from the Xml fragment and its children.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             A `SubscriptionId` that can be used to cancel the observer callback.
         """
@@ -1118,6 +2680,23 @@ class YXmlText:
     next_sibling: Optional[Xml]
     prev_sibling: Optional[Xml]
     parent: Optional[YXmlElement]
+    def is_alive(self) -> bool:
+        """
+        Returns:
+            `False` if this `YXmlText` has been removed from its document, either directly or along with
+            one of its ancestors. Removed types can no longer be modified.
+        """
+    def is_deleted(self) -> bool:
+        """
+        Returns:
+            `True` if this `YXmlText` has been removed from its document. See `is_alive`.
+        """
+    def id(self) -> Optional[Union[str, Tuple[int, int]]]:
+        """
+        Returns:
+            An identifier of this `YXmlText` which is the same on every peer: the name of a root type, or a
+            `(client, clock)` pair identifying the update which has created a nested one.
+        """
     def __len__():
         """
         Returns:
@@ -1126,6 +2705,11 @@ class YXmlText:
     def insert(self, txn: YTransaction, index: int, chunk: str):
         """
         Inserts a given `chunk` of text into this `YXmlText` instance, starting at a given `index`.
+        The `index` is counted in UTF-8 bytes. Negative indexes are counted from the end of the text,
+        like with `list.insert`.
+
+        Raises:
+            IndexError: If `index` is out of range for the current text length.
         """
     def push(self, txn: YTransaction, chunk: str):
         """
@@ -1161,13 +2745,18 @@ class YXmlText:
         """
         Removes an attribute from this XML node, given its `name`.
         """
-    def attributes(self) -> YXmlAttributes:
+    def attributes(
+        self, as_dict: bool = False, sort: bool = False
+    ) -> Union[List[Tuple[str, str]], Dict[str, str]]:
         """
         Returns:
-            An iterator that enables to traverse over all attributes of this XML node in
-        unspecified order.
+            The attributes of this XML node as a list of `(name, value)` tuples, or as a dict if
+        `as_dict` is `True`. Attributes are stored in unspecified order, unless `sort` is `True` in
+        which case they're ordered by their names. Attribute values are always strings.
         """
-    def observe(self, f: Callable[[YXmlTextEvent]]) -> SubscriptionId:
+    def observe(
+        self, f: Callable[[YXmlTextEvent]], weak: bool = False
+    ) -> SubscriptionId:
         """
         Subscribes to all operations happening over this instance of `YXmlText`. All changes are
         batched and eventually triggered during transaction commit phase.
@@ -1175,10 +2764,14 @@ class YXmlText:
         Args:
             f: A callback function that receives update events.
             deep: Determines whether observer is triggered by changes to elements in the YXmlText.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             A `SubscriptionId` that can be used to cancel the observer callback.
         """
-    def observe_deep(self, f: Callable[[List[Event]]]) -> SubscriptionId:
+    def observe_deep(
+        self, f: Callable[[List[Event]]], weak: bool = False
+    ) -> SubscriptionId:
         """
         Subscribes to all operations happening over this instance of `YXmlText` and its children. All changes are
         batched and eventually triggered during transaction commit phase.
@@ -1186,6 +2779,8 @@ class YXmlText:
         Args:
             f: A callback function that receives update events of this element and its descendants.
             deep: Determines whether observer is triggered by changes to elements in the YXmlText.
+            weak: If `True`, only a weak reference to `f` is kept, so that observing doesn't keep it alive.
+                Once `f` is garbage collected, the callback is unsubscribed on the next update.
         Returns:
             A `SubscriptionId` that can be used to cancel the observer callback.
         """
@@ -1201,6 +2796,10 @@ class YXmlTextEvent:
     target: YXmlText
     keys: List[EntryChange]
     delta: List[YTextDelta]
+    origin: Optional[str]
+    """The origin of the transaction which has made the changes, `None` if it has no origin."""
+    kind: Literal["xml_text"]
+    """The kind of the `target` shared type, as reported by `type_of`."""
     def path(self) -> List[Union[int, str]]:
         """
         Returns a current shared type instance, that current event changes refer to.